@@ -0,0 +1,23 @@
+//! 子命令实现
+
+mod clean;
+mod convert;
+mod errors;
+mod fetch;
+mod header;
+mod pack;
+mod resume;
+mod schema;
+mod validate;
+mod watch;
+
+pub use clean::clean;
+pub use convert::convert;
+pub use errors::errors;
+pub use fetch::{fetch, fetch_stories};
+pub use header::{HeaderSource, header};
+pub use pack::pack;
+pub use resume::resume;
+pub use schema::schema;
+pub use validate::validate;
+pub use watch::watch;
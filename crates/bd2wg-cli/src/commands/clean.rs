@@ -0,0 +1,62 @@
+//! clean: 删除 bd2wg 生成的文件
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use bd2wg::{models::manifest::MANIFEST_FILE, services::extractor::clean_project};
+use console::style;
+use serde::Serialize;
+
+use crate::{
+    report::{Outcome, json_output},
+    tr,
+    utils::find_game_dir,
+};
+
+/// 清理报告
+#[derive(Debug, Serialize)]
+struct CleanReport {
+    /// 清理的游戏目录
+    root: PathBuf,
+    removed: Vec<String>,
+    /// 生成后被修改而保留的文件
+    kept: Vec<String>,
+}
+
+/// 按清单删除生成的场景和资源, 保留用户修改过的文件
+///
+/// 可以指定工程目录或其游戏目录.
+pub fn clean(project: &Path) -> Result<Outcome> {
+    let Some(root) = find_game_dir(project, MANIFEST_FILE) else {
+        bail!(tr!("no {} found in {}", MANIFEST_FILE, project.display()));
+    };
+
+    let report = clean_project(&root)?;
+
+    if json_output() {
+        let report = CleanReport {
+            root,
+            removed: report.removed,
+            kept: report.kept,
+        };
+        println!("{}", serde_json::to_string(&report)?);
+        return Ok(Outcome::Success);
+    }
+
+    for path in &report.kept {
+        println!(
+            "{} {path} {}",
+            style(tr!("kept")).yellow(),
+            tr!("(modified)")
+        );
+    }
+    println!(
+        "{}",
+        tr!(
+            "{} files removed, {} kept.",
+            report.removed.len(),
+            report.kept.len()
+        )
+    );
+    Ok(Outcome::Success)
+}
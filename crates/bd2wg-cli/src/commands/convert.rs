@@ -0,0 +1,290 @@
+//! convert: 批量转换多个故事到同一工程
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, bail};
+use bd2wg::{
+    models::config::{PipelineOptions, ProjectConfig},
+    services::pipeline::FullPipeline,
+    traits::{
+        handle::Handle,
+        pipeline::{
+            BatchPipeline, FullPipeline as _, PipelineStage, StoryStatus, StoryTask,
+            TranspileResult,
+        },
+    },
+};
+use console::style;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+use crate::{
+    config::{Overrides, ProjectFile, read_config},
+    interrupt,
+    report::{Outcome, Report, StoryReport, json_output},
+    tr,
+    utils::*,
+};
+
+/// 以批量管线转换全部故事, 结束后展示各故事的汇总表并下载资源
+pub fn convert(
+    stories: &[String],
+    outdir: &Path,
+    config: Option<&Path>,
+    project: bool,
+    dry_run: bool,
+    estimate_size: bool,
+    overrides: Overrides,
+) -> Result<Outcome> {
+    let stories = expand_stories(stories)?;
+    if stories.is_empty() {
+        bail!(tr!("no story matched"));
+    }
+
+    let mut options: PipelineOptions = match config {
+        Some(path) => read_config(path)?,
+        None => PipelineOptions::default(),
+    };
+    ProjectFile::load(outdir)?
+        .merge(overrides)
+        .apply(&mut options)?;
+    if project && options.extractor.project.is_none() {
+        options.extractor.project = Some(ProjectConfig::default());
+    }
+    options.extractor.dry_run |= dry_run;
+    options.extractor.estimate_size |= estimate_size;
+
+    let total = stories.len();
+    let mut pipe = FullPipeline::batch(stories, outdir, options);
+    match json_output() {
+        true => interrupt::wait_while(&mut *pipe, transpiling, |_| {}),
+        false => wait(&mut pipe, total),
+    }
+    match dry_run || pipe.state().stage != PipelineStage::Download {
+        true => interrupt::wait(&mut *pipe, |_| {}),
+        false => crate::wait_download(&mut *pipe, |pipe| (pipe.state().download, pipe.tasks())),
+    }
+
+    let tasks = pipe.tasks();
+    let (results, download) = pipe.join();
+
+    let mut report = Report {
+        stories: (results.iter())
+            .map(|(story, result)| StoryReport::new(Some(story.clone()), result))
+            .collect(),
+        download: None,
+    };
+
+    if !json_output() {
+        show_results(results);
+    }
+
+    let mut start_failed = false;
+    if !dry_run {
+        match download {
+            Ok(download) => report.download = Some(crate::show_download_result(download, &tasks)),
+            Err(e) => {
+                start_failed = true;
+                if !json_output() {
+                    println!("{}\n{e}", tr!("failed to start download, error:"));
+                }
+            }
+        }
+    }
+
+    report.emit();
+    report.save(outdir)?;
+    Ok(match start_failed {
+        true => Outcome::Errors,
+        false => report.outcome(),
+    })
+}
+
+/// 批量转译的完整管线
+type BatchFullPipeline = FullPipeline<dyn BatchPipeline + Send>;
+
+/// 是否处于转译阶段
+fn transpiling(pipe: &BatchFullPipeline) -> bool {
+    pipe.state().stage == PipelineStage::Transpile
+}
+
+/// 展示进度并等待批量转译结束
+///
+/// 总进度条之下, 每个正在转译的故事一个进度条 (按场景写入计), 结束后移除, 便于发现卡住的故事.
+fn wait(pipe: &mut BatchFullPipeline, total: usize) {
+    println!("{}", tr!("transpiling {} stories...", total));
+
+    let multi = MultiProgress::new();
+    let overall = multi.add(ProgressBar::new(total as u64));
+    overall.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    let style = ProgressStyle::default_bar()
+        .template("  [{elapsed}] [{bar:20.cyan/blue}] {pos}/{len} {wide_msg}")
+        .unwrap()
+        .progress_chars("#>-");
+    let mut bars: HashMap<usize, ProgressBar> = HashMap::new();
+
+    interrupt::wait_while(pipe, transpiling, |pipe| {
+        let stories = pipe.state().transpile;
+        overall.set_position(finished(&stories) as u64);
+
+        for (index, task) in stories.into_iter().enumerate() {
+            match task.status {
+                StoryStatus::Queued => {}
+                StoryStatus::Running(state) => {
+                    let bar = bars.entry(index).or_insert_with(|| {
+                        let name = task.story.file_name().unwrap_or_default();
+                        let bar = multi.add(ProgressBar::new(0).with_style(style.clone()));
+                        bar.set_message(name.to_string_lossy().into_owned());
+                        bar
+                    });
+                    let written = state.written.len()
+                        + state.conflicts.len()
+                        + state.kept.len()
+                        + state.unchanged;
+                    bar.set_length(state.scene as u64);
+                    bar.set_position(written as u64);
+                }
+                StoryStatus::Done { .. } => {
+                    if let Some(bar) = bars.remove(&index) {
+                        bar.finish_and_clear();
+                        multi.remove(&bar);
+                    }
+                }
+            }
+        }
+    });
+
+    for bar in bars.into_values() {
+        bar.finish_and_clear();
+    }
+    overall.finish_and_clear();
+}
+
+/// 已完成的故事数
+fn finished(stories: &[StoryTask]) -> usize {
+    (stories.iter())
+        .filter(|task| matches!(task.status, StoryStatus::Done { .. }))
+        .count()
+}
+
+/// 展示汇总表, 以及各故事的错误和警告
+fn show_results(results: Vec<(PathBuf, TranspileResult)>) {
+    show_summary(&results);
+
+    for (story, result) in results {
+        if result.errors.is_empty() && result.warnings.is_empty() && result.state.kept.is_empty() {
+            continue;
+        }
+
+        println!("\n{}:", story.display());
+        for path in &result.state.kept {
+            println!("{}", tr!("kept existing file: {}", path));
+        }
+        try_show_errors(result.errors);
+        try_show_warnings(result.warnings);
+    }
+    println!();
+}
+
+/// 展开参数中的通配符 (仅限文件名部分), 供不展开通配符的终端使用
+fn expand_stories(patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut stories = Vec::new();
+
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.exists() || !name.contains(['*', '?']) {
+            stories.push(path.to_path_buf());
+            continue;
+        }
+
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut matched: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|file| wildcard_match(&name, &file.to_string_lossy()))
+            })
+            .collect();
+        matched.sort();
+        stories.extend(matched);
+    }
+
+    Ok(stories)
+}
+
+/// 匹配 `*` 和 `?` 通配符
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<_>, Vec<_>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut star = None; // 最近的 * 及其匹配起点
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp + 1;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// 展示各故事的汇总表
+fn show_summary(results: &[(PathBuf, TranspileResult)]) {
+    let names: Vec<_> = results
+        .iter()
+        .map(|(story, _)| story.file_name().unwrap_or_default().to_string_lossy())
+        .collect();
+    let width = names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+
+    println!(
+        "{:<width$}  {:>6}  {:>7}  {:>9}  {:>4}  {:>6}  {:>8}",
+        "story", "scenes", "actions", "unchanged", "kept", "errors", "warnings"
+    );
+    for (name, (_, result)) in names.iter().zip(results) {
+        let errors = format!("{:>6}", result.errors.len());
+        let errors = match result.errors.is_empty() {
+            true => style(errors),
+            false => style(errors).red(),
+        };
+
+        println!(
+            "{name:<width$}  {:>6}  {:>7}  {:>9}  {:>4}  {errors}  {:>8}",
+            result.state.scene,
+            result.state.action,
+            result.state.unchanged,
+            result.state.kept.len(),
+            result.warnings.len()
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! errors: 查看上次转换保存的错误
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Result, bail};
+use console::style;
+use serde::Serialize;
+
+use crate::{
+    report::{ErrorEntry, Outcome, REPORT_FILE, Report, json_output},
+    tr,
+    utils::find_game_dir,
+};
+
+/// 带来源的错误条目
+#[derive(Debug, Serialize)]
+struct SourcedError<'a> {
+    /// 故事文件名, 下载阶段的错误为 `download`
+    source: String,
+    #[serde(flatten)]
+    entry: &'a ErrorEntry,
+}
+
+/// 读取游戏目录下的运行报告, 按类别分组展示错误及修复建议
+///
+/// `kinds` 和 `resources` 非空时只展示匹配的错误.
+pub fn errors(project: &Path, kinds: &[String], resources: &[String]) -> Result<Outcome> {
+    let Some(root) = find_game_dir(project, REPORT_FILE) else {
+        bail!(tr!("no {} found in {}", REPORT_FILE, project.display()));
+    };
+    let report = Report::load(&root)?;
+
+    let stories = report.stories.iter().flat_map(|story| {
+        let source = match &story.story {
+            Some(path) => path.file_name().unwrap_or_default().to_string_lossy(),
+            None => "transpile".into(),
+        };
+        story.errors.iter().map(move |entry| SourcedError {
+            source: source.to_string(),
+            entry,
+        })
+    });
+    let download = report.download.iter().flat_map(|download| {
+        download.errors.iter().map(|entry| SourcedError {
+            source: String::from("download"),
+            entry,
+        })
+    });
+
+    let matches = |filter: &[String], value: Option<&str>| {
+        filter.is_empty() || value.is_some_and(|value| filter.iter().any(|f| f == value))
+    };
+    let errors: Vec<_> = stories
+        .chain(download)
+        .filter(|error| matches(kinds, Some(&error.entry.kind)))
+        .filter(|error| matches(resources, error.entry.resource.as_deref()))
+        .collect();
+
+    if json_output() {
+        println!("{}", serde_json::to_string(&errors)?);
+        return Ok(Outcome::Success);
+    }
+
+    if errors.is_empty() {
+        println!("{}", tr!("no error."));
+        return Ok(Outcome::Success);
+    }
+
+    // 按类别分组
+    let mut groups: BTreeMap<&str, Vec<&SourcedError>> = BTreeMap::new();
+    for error in &errors {
+        groups.entry(&error.entry.kind).or_default().push(error);
+    }
+
+    for (kind, group) in &groups {
+        println!("{} ({})", style(kind).red().bold(), group.len());
+        if let Some(hint) = suggestion(kind) {
+            println!("  {} {hint}", style(tr!("hint:")).cyan());
+        }
+        for error in group {
+            let resource = error.entry.resource.as_deref().unwrap_or("-");
+            println!(
+                "  [{resource}] {}: {}",
+                style(&error.source).dim(),
+                error.entry.message
+            );
+        }
+        println!();
+    }
+
+    // 按资源类型统计
+    let mut by_resource: BTreeMap<&str, usize> = BTreeMap::new();
+    for error in &errors {
+        *by_resource
+            .entry(error.entry.resource.as_deref().unwrap_or("-"))
+            .or_default() += 1;
+    }
+    let counts: Vec<_> = (by_resource.iter())
+        .map(|(resource, count)| format!("{resource} {count}"))
+        .collect();
+    println!("{} {}", tr!("by resource:"), counts.join(", "));
+
+    Ok(Outcome::Success)
+}
+
+/// 各类错误的修复建议
+fn suggestion(kind: &str) -> Option<&'static str> {
+    Some(match kind {
+        "download" => {
+            tr!("check the network or proxy, then run `bd2wg-cli resume` to retry failed resources")
+        }
+        "resolve" => {
+            tr!("check --region, add a model redirect, or upload the resource and use its url")
+        }
+        "reference" => tr!(
+            "the referenced resource is missing; fix the download errors or place the file manually"
+        ),
+        "transpile" => tr!("a figure was used before being loaded; check the layout actions"),
+        "validate" => tr!(
+            "a generated scene could not be parsed back, usually an escaping problem; please report it"
+        ),
+        "file" => tr!("check that the story is valid JSON and the output directory is writable"),
+        "api" => tr!("check the story id and the network"),
+        "header" => tr!("check the header file given by --header"),
+        "config" => tr!("check the scene template in the pipeline config; it needs ${n}"),
+        "cancelled" => tr!("the run was interrupted; run `bd2wg-cli resume` to continue"),
+        "aborted" => tr!("too many errors; fix the earlier errors or relax the error policy"),
+        "panicked" => tr!("internal error; please report it with a log file (--log-file)"),
+        _ => return None,
+    })
+}
@@ -0,0 +1,128 @@
+//! fetch: 从 Bestdori 获取社区故事或活动剧情脚本
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use bd2wg::{
+    models::config::ConvertOptions,
+    services::{
+        bestdori_api::parse_story_id,
+        fetcher::{DEFAULT_STORY_TEMPLATE, FetchedStory, Fetcher},
+    },
+};
+
+use serde::Serialize;
+
+use crate::{
+    config::{Overrides, ProjectFile},
+    report::{Outcome, json_output},
+    tr,
+};
+
+/// 获取报告
+#[derive(Debug, Serialize)]
+struct FetchReport {
+    /// 故事 id, 活动剧情为 `event<活动 id>-<话数>`
+    id: String,
+    output: PathBuf,
+    title: Option<String>,
+    /// 活动名称 (仅活动剧情)
+    event: Option<String>,
+    /// 对话最多的角色所属的乐队
+    band: Option<String>,
+}
+
+/// 获取故事脚本并保存, 保存路径按模板以故事的标题, 活动和乐队命名
+///
+/// 默认保存为当前目录下的 `story-<id>.json`, 指定 `convert` 时随后将其转换到该输出目录.
+pub fn fetch(
+    story: &str,
+    output: Option<&str>,
+    convert: Option<&Path>,
+    overrides: Overrides,
+) -> Result<Outcome> {
+    // 转换时沿用输出目录下的工程配置
+    let fetched = fetch_story(&fetcher(convert, overrides.clone())?, story)?;
+    let output = fetched.save(Path::new(""), output.unwrap_or(DEFAULT_STORY_TEMPLATE))?;
+    if !json_output() {
+        println!("{}", tr!("saved to {}", output.display()));
+    }
+
+    match convert {
+        None if json_output() => {
+            let report = FetchReport {
+                id: fetched.id.to_string(),
+                output,
+                title: fetched.info.title,
+                event: fetched.info.event,
+                band: fetched.info.band,
+            };
+            println!("{}", serde_json::to_string(&report)?);
+            Ok(Outcome::Success)
+        }
+        Some(outdir) => super::convert(
+            &[output.to_string_lossy().into_owned()],
+            outdir,
+            None,
+            false,
+            false,
+            false,
+            overrides,
+        ),
+        None => Ok(Outcome::Success),
+    }
+}
+
+/// 获取多个故事, 保存到临时目录供转换读取, 返回保存路径
+pub fn fetch_stories(
+    stories: &[String],
+    outdir: &Path,
+    overrides: &Overrides,
+) -> Result<Vec<String>> {
+    if stories.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let fetcher = fetcher(Some(outdir), overrides.clone())?;
+    let dir = env::temp_dir().join("bd2wg-stories");
+    stories
+        .iter()
+        .map(|story| {
+            let path = fetch_story(&fetcher, story)?.save(&dir, DEFAULT_STORY_TEMPLATE)?;
+            Ok(path.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// 按 id 或页面链接获取单个故事
+pub fn fetch_story(fetcher: &Fetcher, story: &str) -> Result<FetchedStory> {
+    let id =
+        parse_story_id(story).ok_or_else(|| anyhow!(tr!("invalid story id or url: {}", story)))?;
+
+    if !json_output() {
+        println!("{}", tr!("fetching story {}...", id));
+    }
+    let fetched = fetcher.fetch(id)?;
+    if !json_output()
+        && let Some(title) = fetched.info.display_title()
+    {
+        println!("{}", tr!("fetched {}", title));
+    }
+
+    Ok(fetched)
+}
+
+/// 以输出目录下的工程配置和命令行参数创建获取器
+pub fn fetcher(outdir: Option<&Path>, overrides: Overrides) -> Result<Fetcher> {
+    let file = match outdir {
+        Some(outdir) => ProjectFile::load(outdir)?,
+        None => ProjectFile::default(),
+    }
+    .merge(overrides);
+    let mut options = ConvertOptions::default();
+    file.apply_convert(&mut options)?;
+    options.fetcher()
+}
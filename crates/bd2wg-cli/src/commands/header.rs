@@ -0,0 +1,77 @@
+//! header: 从浏览器复制的请求生成请求头文件
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use bd2wg::utils::{header_from_curl, header_from_har, new_header_from_json};
+use serde::Serialize;
+
+use crate::{
+    report::{Outcome, json_output},
+    tr,
+};
+
+/// 默认保存路径
+const DEFAULT_HEADER_FILE: &str = "header.json";
+
+/// 请求来源
+#[derive(Debug, Clone, Copy)]
+pub enum HeaderSource<'a> {
+    /// 浏览器 "复制为 cURL (bash)" 得到的命令
+    Curl,
+    /// 浏览器导出的 HAR 文件, 可指定所选请求的链接
+    Har(Option<&'a str>),
+}
+
+/// 生成报告
+#[derive(Debug, Serialize)]
+struct HeaderReport {
+    output: PathBuf,
+    headers: BTreeMap<String, String>,
+}
+
+/// 读取 cURL 命令或 HAR 文件 (`-` 为标准输入), 提取请求头并保存为下载器使用的请求头文件
+pub fn header(source: HeaderSource, file: &Path, output: Option<&Path>) -> Result<Outcome> {
+    let bytes = match file == Path::new("-") {
+        true => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+        false => fs::read(file)?,
+    };
+
+    let headers = match source {
+        HeaderSource::Curl => header_from_curl(&String::from_utf8_lossy(&bytes))?,
+        HeaderSource::Har(url) => header_from_har(&bytes, url)?,
+    };
+    // 确认下载器可以读取
+    let json = serde_json::to_value(&headers)?;
+    new_header_from_json(&json)?;
+
+    let output = output.unwrap_or(Path::new(DEFAULT_HEADER_FILE));
+    fs::write(output, serde_json::to_string_pretty(&json)?)?;
+
+    if json_output() {
+        let report = HeaderReport {
+            output: output.to_path_buf(),
+            headers,
+        };
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        for name in headers.keys() {
+            println!("  {name}");
+        }
+        println!(
+            "{}",
+            tr!("{} headers saved to {}", headers.len(), output.display())
+        );
+    }
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,86 @@
+//! pack: 将工程打包为可分享的压缩包
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, bail};
+use bd2wg::services::extractor::{audit_project, export_zip, is_work_file};
+use serde::Serialize;
+
+use crate::{
+    config::CONFIG_FILE,
+    report::{ErrorEntry, Outcome, REPORT_FILE, json_output},
+    tr,
+    utils::{find_game_dir, try_show_errors},
+};
+
+/// WebGAL 入口场景
+const START_SCENE: &str = "scene/start.txt";
+
+/// 打包报告
+#[derive(Debug, Serialize)]
+struct PackReport {
+    /// 未打包时为空
+    archive: Option<PathBuf>,
+    errors: Vec<ErrorEntry>,
+}
+
+/// 检查工程后将游戏目录打包为 zip, 默认保存为当前目录下的 `<工程名>.zip`
+///
+/// 场景无法解析或引用的资源不存在时不打包, 除非指定 `force`.
+/// 指定 `strip` 时不包含 bd2wg 的清单, 断点, 报告等工作文件.
+pub fn pack(project: &Path, output: Option<&Path>, strip: bool, force: bool) -> Result<Outcome> {
+    let Some(root) = find_game_dir(project, "scene") else {
+        bail!(tr!("no {} found in {}", "scene", project.display()));
+    };
+    if !root.join(START_SCENE).exists() {
+        bail!(tr!("no {} found in {}", START_SCENE, root.display()));
+    }
+
+    let errors = audit_project(&root)?;
+    let archive = match errors.is_empty() || force {
+        true => {
+            let path = match output {
+                Some(path) => path.to_path_buf(),
+                None => default_archive(project)?,
+            };
+            export_zip(&root, &path, |name| {
+                strip
+                    && (is_work_file(name)
+                        || name == Path::new(REPORT_FILE)
+                        || name == Path::new(CONFIG_FILE))
+            })?;
+            Some(path)
+        }
+        false => None,
+    };
+
+    if json_output() {
+        let report = PackReport {
+            archive: archive.clone(),
+            errors: errors.iter().map(ErrorEntry::from).collect(),
+        };
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        try_show_errors(&errors);
+        match &archive {
+            Some(path) => println!("{}", tr!("saved to {}", path.display())),
+            None => println!("{}", tr!("not packed, use --force to pack anyway.")),
+        }
+    }
+
+    Ok(Outcome::from_errors(!errors.is_empty()))
+}
+
+/// 以工程目录名命名的压缩包
+fn default_archive(project: &Path) -> Result<PathBuf> {
+    let project = fs::canonicalize(project)?;
+    let name = project.file_name().unwrap_or_default().to_string_lossy();
+    let name = match name.is_empty() {
+        true => "game",
+        false => &name,
+    };
+    Ok(PathBuf::from(format!("{name}.zip")))
+}
@@ -0,0 +1,67 @@
+//! resume: 从断点继续中断的转换
+
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use bd2wg::{
+    models::{
+        checkpoint::{CHECKPOINT_FILE, Checkpoint, CheckpointStage},
+        config::PipelineOptions,
+    },
+    services::pipeline::{DownloadPipeline, FullPipeline, TranspilePipeline},
+};
+use reqwest::header::HeaderMap;
+
+use crate::{
+    config::ProjectFile,
+    report::{Outcome, Report, StoryReport, json_output},
+    tr,
+    utils::find_game_dir,
+};
+
+/// 读取断点, 跳过已完成的场景和资源继续执行
+pub fn resume(project: &Path) -> Result<Outcome> {
+    let Some(root) = find_game_dir(project, CHECKPOINT_FILE) else {
+        bail!(tr!("no {} found in {}", CHECKPOINT_FILE, project.display()));
+    };
+    let Some(checkpoint) = Checkpoint::load(&root)? else {
+        bail!(tr!("no {} found in {}", CHECKPOINT_FILE, root.display()));
+    };
+    let header = project_header(project)?;
+
+    let mut report = Report::default();
+    match checkpoint.stage {
+        CheckpointStage::Transpile => {
+            let story = checkpoint.story.clone();
+            let dry_run = checkpoint.options.extractor.dry_run;
+            let pipe = TranspilePipeline::resume(checkpoint, &root, header);
+
+            let (story_report, download) =
+                crate::convert_story(FullPipeline::with_pipeline(pipe), dry_run);
+            report.stories.push(StoryReport {
+                story: Some(story),
+                ..story_report
+            });
+            report.download = download;
+        }
+        CheckpointStage::Download => {
+            if !json_output() {
+                println!("{}", tr!("{} resources pending.", checkpoint.pending.len()));
+            }
+            report.download = Some(crate::download(DownloadPipeline::resume(
+                checkpoint, &root, header,
+            )?));
+        }
+    }
+
+    report.emit();
+    report.save(&root)?;
+    Ok(report.outcome())
+}
+
+/// 断点不记录请求头, 沿用工程配置中的请求头 (未配置时采用默认请求头)
+fn project_header(project: &Path) -> Result<HeaderMap> {
+    let mut options = PipelineOptions::default();
+    ProjectFile::load(project)?.apply(&mut options)?;
+    options.header_map()
+}
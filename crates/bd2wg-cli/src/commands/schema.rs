@@ -0,0 +1,58 @@
+//! schema: 输出配置文件的 JSON Schema
+
+use anyhow::{Result, anyhow};
+use bd2wg::models::config::{CONFIG_SCHEMA_NAMES, config_schema};
+use schemars::{Schema, schema_for};
+
+use crate::{
+    config::ProjectFile,
+    report::{Outcome, Report},
+    tr,
+};
+
+/// 命令行工具自身的文件: 工程配置 `bd2wg.toml` 和运行报告
+const CLI_SCHEMA_NAMES: &[&str] = &["config", "report"];
+
+fn cli_schema(name: &str) -> Option<Schema> {
+    Some(match name {
+        "config" => schema_for!(ProjectFile),
+        "report" => schema_for!(Report),
+        _ => return None,
+    })
+}
+
+/// 输出指定配置文件的 JSON Schema
+pub fn schema(name: &str) -> Result<Outcome> {
+    let schema = cli_schema(name)
+        .or_else(|| config_schema(name))
+        .ok_or_else(|| {
+            anyhow!(tr!(
+                "unknown schema: {}, available: {}",
+                name,
+                schema_names().join(", ")
+            ))
+        })?;
+
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(Outcome::Success)
+}
+
+/// 全部可用的名称
+fn schema_names() -> Vec<&'static str> {
+    CLI_SCHEMA_NAMES
+        .iter()
+        .chain(CONFIG_SCHEMA_NAMES)
+        .copied()
+        .collect()
+}
+
+#[test]
+#[cfg(test)]
+fn test_schema_names() {
+    for name in schema_names() {
+        assert!(
+            cli_schema(name).or_else(|| config_schema(name)).is_some(),
+            "{name}"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! validate: 检查故事脚本, 不写入任何文件
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use bd2wg::{Options, models::bestdori::Story, models::config::PipelineOptions, validate as check};
+use console::style;
+use serde::Serialize;
+
+use crate::{
+    config::{Overrides, ProjectFile, read_config},
+    report::{Outcome, json_output},
+    tr,
+    utils::*,
+};
+
+/// 校验报告
+#[derive(Debug, Serialize)]
+struct ValidateReport {
+    /// 故事脚本路径或故事 id
+    story: String,
+    scenes: usize,
+    actions: usize,
+    /// 未知指令类型及其数量
+    unknown: BTreeMap<String, usize>,
+    resources: usize,
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+/// 解析并转译故事脚本, 报告未知指令, 无法解析的资源和统计信息
+///
+/// 指定 `story_id` 时经 Bestdori API 获取脚本, 不保存. 指定输出目录时读取其中的工程配置.
+pub fn validate(
+    story: Option<&Path>,
+    story_id: Option<&str>,
+    outdir: Option<&Path>,
+    config: Option<&Path>,
+    overrides: Overrides,
+) -> Result<Outcome> {
+    let mut options: PipelineOptions = match config {
+        Some(path) => read_config(path)?,
+        None => PipelineOptions::default(),
+    };
+    match outdir {
+        Some(outdir) => ProjectFile::load(outdir)?,
+        None => ProjectFile::default(),
+    }
+    .merge(overrides.clone())
+    .apply(&mut options)?;
+
+    let (story, parsed) = match (story, story_id) {
+        (_, Some(id)) => {
+            let fetched =
+                super::fetch::fetch_story(&super::fetch::fetcher(outdir, overrides)?, id)?;
+            (fetched.id.to_string(), fetched.story)
+        }
+        (Some(path), None) => {
+            let story = path.display().to_string();
+            let bytes = fs::read(path).with_context(|| story.clone())?;
+            let parsed = Story::from_bytes(&bytes).with_context(|| story.clone())?;
+            (story, parsed)
+        }
+        (None, None) => unreachable!("clap requires a story or --story-id"),
+    };
+    let validation = check(
+        &parsed,
+        &Options {
+            resolver: options.resolver,
+            transpiler: options.transpiler,
+        },
+    );
+    let errors = validation.errors.len();
+
+    if json_output() {
+        let report = ValidateReport {
+            story: story.clone(),
+            scenes: validation.scenes,
+            actions: validation.actions.total(),
+            unknown: validation.unknown,
+            resources: validation.resources,
+            errors: validation.errors.iter().map(ToString::to_string).collect(),
+            warnings: validation
+                .warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        };
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        println!(
+            "{}",
+            tr!(
+                "{}: {} scenes, {} actions, {} resources",
+                story,
+                validation.scenes,
+                validation.actions.total(),
+                validation.resources
+            )
+        );
+        println!("{}", validation.actions);
+        println!("{}", validation.stats);
+
+        if !validation.unknown.is_empty() {
+            let unknown: Vec<_> = (validation.unknown.iter())
+                .map(|(kind, count)| match kind.is_empty() {
+                    true => format!("<missing> ({count})"),
+                    false => format!("{kind} ({count})"),
+                })
+                .collect();
+            println!(
+                "{} {}",
+                style(tr!("unknown action types:")).yellow(),
+                unknown.join(", ")
+            );
+        }
+
+        println!();
+        try_show_errors(validation.errors);
+        try_show_warnings(validation.warnings);
+    }
+
+    Ok(Outcome::from_errors(errors != 0))
+}
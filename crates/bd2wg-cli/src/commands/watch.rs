@@ -0,0 +1,88 @@
+//! watch: 监听故事文件变更并重新转换
+
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use bd2wg::{
+    models::config::PipelineOptions,
+    services::{pipeline::FullPipeline, watch::FileWatcher},
+};
+use console::style;
+
+use crate::{
+    config::{CONFIG_FILE, Overrides, ProjectFile, read_config},
+    report::{Outcome, Report, StoryReport, json_output},
+    tr,
+};
+
+/// 转换一次, 之后每当故事脚本, 管线配置或工程配置变更时重新转换
+///
+/// 已存在的资源不再下载. 工程配置中新增的引用文件在重新启动后才会监听.
+/// 启用 JSON 输出时每次转换输出一行报告.
+pub fn watch(
+    story: &Path,
+    outdir: &Path,
+    config: Option<&Path>,
+    overrides: Overrides,
+) -> Result<Outcome> {
+    fs::create_dir_all(outdir)?;
+
+    let project = outdir.join(CONFIG_FILE);
+    let referenced = ProjectFile::load(outdir)?.merge(overrides.clone());
+    let watcher = FileWatcher::new(
+        [story, project.as_path()]
+            .into_iter()
+            .chain(config)
+            .chain(referenced.files()),
+    )?;
+
+    loop {
+        match convert(story, outdir, config, overrides.clone()) {
+            Ok(report) => {
+                report.emit();
+                if let Err(e) = report.save(outdir) {
+                    eprintln!("{} {e:#}", style(tr!("failed")).red());
+                }
+            }
+            Err(e) => eprintln!("{} {e:#}", style(tr!("failed")).red()),
+        }
+
+        if !json_output() {
+            println!("\n{}", tr!("watching {} for changes...", story.display()));
+        }
+        for path in watcher.wait()? {
+            if !json_output() {
+                println!("{} {}", style(tr!("changed")).cyan(), path.display());
+            }
+        }
+    }
+}
+
+/// 读取配置并执行一次转换
+fn convert(
+    story: &Path,
+    outdir: &Path,
+    config: Option<&Path>,
+    overrides: Overrides,
+) -> Result<Report> {
+    let mut options: PipelineOptions = match config {
+        Some(path) => read_config(path)?,
+        None => PipelineOptions::default(),
+    };
+    ProjectFile::load(outdir)?
+        .merge(overrides)
+        .apply(&mut options)?;
+    options.extractor.skip_existing = true;
+
+    let dry_run = options.extractor.dry_run;
+    let pipe = FullPipeline::new(story, outdir, options);
+    let (report, download) = crate::convert_story(pipe, dry_run);
+
+    Ok(Report {
+        stories: vec![StoryReport {
+            story: Some(story.to_path_buf()),
+            ..report
+        }],
+        download,
+    })
+}
@@ -0,0 +1,263 @@
+//! 工程配置文件
+//!
+//! 输出目录下的 `bd2wg.toml` 记录重复转换时的默认选项, 命令行参数优先于配置文件.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use bd2wg::{
+    models::{
+        bestdori::Region,
+        config::{ConvertOptions, ModelRedirect, NamingStrategy, OverwritePolicy, PipelineOptions},
+    },
+    utils::new_header_from_bytes,
+};
+use clap::Args;
+use schemars::JsonSchema;
+use serde::{Deserialize, de::DeserializeOwned};
+
+/// 工程配置文件名
+pub const CONFIG_FILE: &str = "bd2wg.toml";
+
+/// 工程配置
+///
+/// 相对路径相对于配置文件所在目录.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectFile {
+    /// 请求头文件 (JSON)
+    pub header: Option<PathBuf>,
+    /// 模型重定向文件 (JSON, 服装名 -> 重定向规则), 靠后的文件优先
+    pub redirects: Vec<PathBuf>,
+    pub region: Option<Region>,
+    pub naming: Option<NamingStrategy>,
+    /// 下载并发数
+    pub concurrency: Option<usize>,
+    /// 场景文件已存在且内容不同时的处理
+    pub overwrite: Option<OverwritePolicy>,
+    /// Bestdori API 响应缓存目录
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// 覆盖工程配置的命令行参数
+#[derive(Debug, Clone, Default, Args)]
+pub struct Overrides {
+    /// 请求头文件 (JSON)
+    #[arg(long)]
+    pub header: Option<PathBuf>,
+    /// 追加模型重定向文件 (JSON), 可多次指定
+    #[arg(long = "redirect")]
+    pub redirects: Vec<PathBuf>,
+    /// 数据包资源所在的服务器区域 (jp, en, tw, cn, kr)
+    #[arg(long, value_parser = parse_enum::<Region>)]
+    pub region: Option<Region>,
+    /// 上传资源的文件命名策略 (flatten, readable)
+    #[arg(long, value_parser = parse_enum::<NamingStrategy>)]
+    pub naming: Option<NamingStrategy>,
+    /// 下载并发数
+    #[arg(long)]
+    pub concurrency: Option<usize>,
+    /// 保留内容不同的已有场景文件, 不覆盖
+    #[arg(long)]
+    pub no_overwrite: bool,
+    /// Bestdori API 响应缓存目录
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl ProjectFile {
+    /// 读取输出目录下的配置文件, 不存在时返回默认配置
+    pub fn load(outdir: &Path) -> Result<Self> {
+        let path = outdir.join(CONFIG_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| path.display().to_string()),
+        };
+
+        let mut file: Self = toml::from_str(&text).with_context(|| path.display().to_string())?;
+        file.header = file.header.map(|header| outdir.join(header));
+        file.cache_dir = file.cache_dir.map(|dir| outdir.join(dir));
+        for redirect in &mut file.redirects {
+            *redirect = outdir.join(&*redirect);
+        }
+
+        Ok(file)
+    }
+
+    /// 以命令行参数覆盖, 重定向文件追加在后
+    pub fn merge(mut self, overrides: Overrides) -> Self {
+        self.redirects.extend(overrides.redirects);
+
+        Self {
+            header: overrides.header.or(self.header),
+            region: overrides.region.or(self.region),
+            naming: overrides.naming.or(self.naming),
+            concurrency: overrides.concurrency.or(self.concurrency),
+            overwrite: (overrides.no_overwrite)
+                .then_some(OverwritePolicy::Keep)
+                .or(self.overwrite),
+            cache_dir: overrides.cache_dir.or(self.cache_dir),
+            ..self
+        }
+    }
+
+    /// 引用的外部文件
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.header
+            .iter()
+            .chain(&self.redirects)
+            .map(PathBuf::as_path)
+    }
+
+    /// 应用到管线选项
+    pub fn apply(&self, options: &mut PipelineOptions) -> Result<()> {
+        if let Some(path) = &self.header {
+            let bytes = fs::read(path).with_context(|| path.display().to_string())?;
+            options.header = PipelineOptions::from(new_header_from_bytes(&bytes)?).header;
+        }
+
+        for path in &self.redirects {
+            let redirects: HashMap<String, ModelRedirect> = read_json(path)?;
+            options.resolver.model_redirects.extend(redirects);
+        }
+
+        if let Some(region) = self.region {
+            options.resolver.region = region;
+        }
+        if let Some(naming) = self.naming {
+            options.resolver.naming = naming;
+        }
+        if let Some(concurrency) = self.concurrency {
+            options.download.concurrency = concurrency;
+        }
+        if let Some(overwrite) = self.overwrite {
+            options.extractor.overwrite = overwrite;
+        }
+
+        Ok(())
+    }
+
+    /// 应用到转换选项
+    pub fn apply_convert(&self, options: &mut ConvertOptions) -> Result<()> {
+        self.apply(&mut options.pipeline)?;
+
+        if let Some(dir) = &self.cache_dir {
+            options.api.cache_dir = Some(dir.clone());
+        }
+
+        Ok(())
+    }
+}
+
+/// 读取管线配置, 扩展名为 `.toml` 时按 TOML 解析, 否则按 JSON 解析
+pub fn read_config<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        let text = fs::read_to_string(path).with_context(|| path.display().to_string())?;
+        return toml::from_str(&text).with_context(|| path.display().to_string());
+    }
+
+    read_json(path)
+}
+
+/// 读取 JSON 文件
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let bytes = fs::read(path).with_context(|| path.display().to_string())?;
+    serde_json::from_slice(&bytes).with_context(|| path.display().to_string())
+}
+
+/// 按配置文件中的名称解析枚举参数
+fn parse_enum<T: DeserializeOwned>(s: &str) -> std::result::Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|e| e.to_string())
+}
+
+#[test]
+#[cfg(test)]
+fn test_merge_overrides() {
+    let file = ProjectFile {
+        header: Some(PathBuf::from("out/header.json")),
+        redirects: vec![PathBuf::from("out/a.json")],
+        region: Some(Region::En),
+        concurrency: Some(4),
+        ..Default::default()
+    };
+    let overrides = Overrides {
+        redirects: vec![PathBuf::from("b.json")],
+        region: Some(Region::Tw),
+        no_overwrite: true,
+        ..Default::default()
+    };
+
+    let merged = file.merge(overrides);
+    assert_eq!(merged.header, Some(PathBuf::from("out/header.json")));
+    assert_eq!(
+        merged.redirects,
+        [PathBuf::from("out/a.json"), PathBuf::from("b.json")]
+    );
+    assert_eq!(merged.region, Some(Region::Tw));
+    assert_eq!(merged.concurrency, Some(4));
+    assert_eq!(merged.overwrite, Some(OverwritePolicy::Keep));
+
+    // 不读取外部文件
+    let merged = ProjectFile {
+        header: None,
+        redirects: Vec::new(),
+        ..merged
+    };
+    let mut options = PipelineOptions::default();
+    merged.apply(&mut options).unwrap();
+    assert_eq!(options.resolver.region, Region::Tw);
+    assert_eq!(options.download.concurrency, 4);
+    assert_eq!(options.extractor.overwrite, OverwritePolicy::Keep);
+
+    // 缓存目录应用到转换选项
+    let merged = merged.merge(Overrides {
+        cache_dir: Some(PathBuf::from("cache")),
+        ..Default::default()
+    });
+    let mut options = ConvertOptions::default();
+    merged.apply_convert(&mut options).unwrap();
+    assert_eq!(options.api.cache_dir, Some(PathBuf::from("cache")));
+    assert_eq!(options.pipeline.resolver.region, Region::Tw);
+}
+
+#[test]
+#[cfg(test)]
+fn test_load_project_file() {
+    let dir = std::env::temp_dir().join(format!("bd2wg-config-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    // 不存在时为默认配置
+    assert!(ProjectFile::load(&dir).unwrap().header.is_none());
+
+    fs::write(
+        dir.join(CONFIG_FILE),
+        "header = \"header.json\"\nredirects = [\"redirects.json\"]\noverwrite = \"merge\"\ncache_dir = \"cache\"\n",
+    )
+    .unwrap();
+    let file = ProjectFile::load(&dir).unwrap();
+    assert_eq!(file.header, Some(dir.join("header.json")));
+    assert_eq!(file.cache_dir, Some(dir.join("cache")));
+    assert_eq!(file.redirects, [dir.join("redirects.json")]);
+    assert_eq!(file.overwrite, Some(OverwritePolicy::Merge));
+
+    // 未知字段报错
+    fs::write(dir.join(CONFIG_FILE), "unknown = 1\n").unwrap();
+    assert!(ProjectFile::load(&dir).is_err());
+
+    // 管线配置按扩展名解析
+    let toml = dir.join("pipeline.toml");
+    fs::write(&toml, "a = 1\n").unwrap();
+    let json = dir.join("pipeline.json");
+    fs::write(&json, r#"{"a": 1}"#).unwrap();
+    for path in [toml, json] {
+        let config: HashMap<String, u32> = read_config(&path).unwrap();
+        assert_eq!(config["a"], 1);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,237 @@
+//! 界面文本本地化
+//!
+//! 以英文文本为键查找译文, 缺少译文时使用英文. 语言由 `--lang` 指定, 否则按系统区域设置选择.
+
+use std::{
+    env,
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use clap::ValueEnum;
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[repr(u8)]
+pub enum Lang {
+    En,
+    Zh,
+    Ja,
+}
+
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+/// 设置界面语言, 未指定时按系统区域设置选择
+pub fn set_lang(lang: Option<Lang>) {
+    let lang = lang.unwrap_or_else(system_lang);
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// 当前界面语言
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Zh,
+        2 => Lang::Ja,
+        _ => Lang::En,
+    }
+}
+
+/// 读取 `LC_ALL`, `LC_MESSAGES`, `LANG` 中首个非空的区域设置
+fn system_lang() -> Lang {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match locale.get(..2) {
+        Some("zh") => Lang::Zh,
+        Some("ja") => Lang::Ja,
+        _ => Lang::En,
+    }
+}
+
+/// 译文表: (英文, 中文, 日文)
+///
+/// `{}` 依次替换为参数, `{n}` 替换为第 n 个参数.
+#[rustfmt::skip]
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // 交互模式
+    ("script (path, id or url)", "脚本 (路径, id 或链接)", "スクリプト (パス, ID または URL)"),
+    ("outdir", "输出目录", "出力ディレクトリ"),
+    ("create WebGAL project (y/N)", "创建 WebGAL 工程 (y/N)", "WebGAL プロジェクトを作成 (y/N)"),
+    ("download resources (Y/n)", "下载资源 (Y/n)", "リソースをダウンロード (Y/n)"),
+    ("press any key to continue...", "按回车键继续...", "Enter キーで続行..."),
+    ("fetching story {}...", "正在获取故事 {}...", "ストーリー {} を取得中..."),
+    ("fetched {}", "已获取 {}", "{} を取得しました"),
+    ("failed to fetch story, error:", "获取故事失败, 错误:", "ストーリーの取得に失敗しました. エラー:"),
+    ("failed to save report, error:", "保存报告失败, 错误:", "レポートの保存に失敗しました. エラー:"),
+    ("failed to load {}, error:", "读取 {} 失败, 错误:", "{} の読み込みに失敗しました. エラー:"),
+    ("failed to resolve:", "解析失败:", "解決に失敗しました:"),
+    ("failed to download:", "下载失败:", "ダウンロードに失敗しました:"),
+    ("replacement url or local file (r: retry, empty: skip)", "替代链接或本地文件 (r: 重试, 留空: 跳过)", "代替 URL またはローカルファイル (r: 再試行, 空欄: スキップ)"),
+    ("replacement url or local file (empty: skip)", "替代链接或本地文件 (留空: 跳过)", "代替 URL またはローカルファイル (空欄: スキップ)"),
+    ("file not found: {}", "文件不存在: {}", "ファイルが見つかりません: {}"),
+    // 转译与下载
+    ("transpiling...", "正在转译...", "変換中..."),
+    ("transpiling {} stories...", "正在转译 {} 个故事...", "{} 件のストーリーを変換中..."),
+    ("translation completed, result: ", "转译完成, 结果: ", "変換完了, 結果: "),
+    ("{} scenes ({} unchanged), {} actions, ", "{} 个场景 ({} 个未变更), {} 条指令, ", "シーン {} 件 (未変更 {} 件), アクション {} 件, "),
+    ("merge conflict: {0}, new content saved as {0}.new", "合并冲突: {0}, 新内容已另存为 {0}.new", "マージ競合: {0}, 新しい内容は {0}.new に保存しました"),
+    ("kept existing file: {}", "保留已有文件: {}", "既存のファイルを保持しました: {}"),
+    ("dry run, {} resources to download:", "试运行, 需下载 {} 个资源:", "試行モード, ダウンロードするリソース {} 件:"),
+    ("failed to start download, error:", "启动下载失败, 错误:", "ダウンロードを開始できませんでした. エラー:"),
+    ("downloading...", "正在下载...", "ダウンロード中..."),
+    ("download completed, result: ", "下载完成, 结果: ", "ダウンロード完了, 結果: "),
+    ("{} success, ", "{} 个成功, ", "成功 {} 件, "),
+    ("no error.", "没有错误.", "エラーなし."),
+    ("{} errors: ", "{} 个错误: ", "エラー {} 件: "),
+    ("no warning.", "没有警告.", "警告なし."),
+    ("{} warnings: ", "{} 个警告: ", "警告 {} 件: "),
+    ("interrupted, cancelling... (press Ctrl-C again to exit immediately)", "已中断, 正在取消... (再次按 Ctrl-C 立即退出)", "中断しました. キャンセル中... (もう一度 Ctrl-C で即時終了)"),
+    // 终端界面
+    ("transpile", "转译", "変換"),
+    ("download", "下载", "ダウンロード"),
+    ("tasks ({} active, {} queued)", "任务 ({} 个进行中, {} 个排队)", "タスク (実行中 {} 件, 待機中 {} 件)"),
+    ("... +{} more", "... 还有 {} 个", "... 他 {} 件"),
+    ("errors ({})", "错误 ({})", "エラー ({})"),
+    ("failed", "失败", "失敗"),
+    // 子命令
+    ("no {} found in {}", "{1} 中没有 {0}", "{1} に {0} が見つかりません"),
+    ("no story matched", "没有匹配的故事", "一致するストーリーがありません"),
+    ("invalid story id or url: {}", "无效的故事 id 或链接: {}", "無効なストーリー ID または URL: {}"),
+    ("unknown schema: {}, available: {}", "未知的配置名称: {}, 可选: {}", "不明なスキーマ: {}, 使用可能: {}"),
+    ("saved to {}", "已保存到 {}", "{} に保存しました"),
+    ("{} headers saved to {}", "已将 {} 个请求头保存到 {}", "{} 件のヘッダーを {} に保存しました"),
+    ("not packed, use --force to pack anyway.", "未打包, 使用 --force 强制打包.", "パッケージ化していません. --force で強制的に実行できます."),
+    ("kept", "保留", "保持"),
+    ("(modified)", "(已修改)", "(変更あり)"),
+    ("{} files removed, {} kept.", "已删除 {} 个文件, 保留 {} 个.", "{} 件のファイルを削除, {} 件を保持."),
+    ("{} resources pending.", "{} 个资源待下载.", "未完了のリソース {} 件."),
+    ("{}: {} scenes, {} actions, {} resources", "{}: {} 个场景, {} 条指令, {} 个资源", "{}: シーン {} 件, アクション {} 件, リソース {} 件"),
+    ("unknown action types:", "未知指令类型:", "不明なアクション種別:"),
+    ("watching {} for changes...", "正在监听 {} 的变更...", "{} の変更を監視中..."),
+    ("changed", "已变更", "変更"),
+    ("Error", "错误", "エラー"),
+    ("bd2wg-cli {} is available (current {}): {}", "bd2wg-cli {} 已发布 (当前 {}): {}", "bd2wg-cli {} が公開されています (現在 {}): {}"),
+    ("Bestdori asset URLs change over time, outdated versions may fail to download resources.", "Bestdori 资源链接的格式时有变化, 旧版本可能无法正确下载资源.", "Bestdori のリソース URL は変わることがあり, 古いバージョンではリソースをダウンロードできない場合があります."),
+    // 错误报告
+    ("hint:", "建议:", "ヒント:"),
+    ("by resource:", "按资源类型:", "リソース種別:"),
+    ("check the network or proxy, then run `bd2wg-cli resume` to retry failed resources", "检查网络或代理, 之后运行 `bd2wg-cli resume` 重试失败的资源", "ネットワークまたはプロキシを確認し, `bd2wg-cli resume` で失敗したリソースを再試行してください"),
+    ("check --region, add a model redirect, or upload the resource and use its url", "检查 --region, 添加模型重定向, 或上传资源后使用其链接", "--region を確認するか, モデルのリダイレクトを追加するか, リソースをアップロードしてその URL を使用してください"),
+    ("the referenced resource is missing; fix the download errors or place the file manually", "引用的资源不存在, 请先解决下载错误或手动放置文件", "参照されたリソースがありません. ダウンロードエラーを解決するか, ファイルを手動で配置してください"),
+    ("a figure was used before being loaded; check the layout actions", "立绘在加载前被使用, 请检查布局指令", "立ち絵が読み込み前に使用されました. レイアウトアクションを確認してください"),
+    ("a generated scene could not be parsed back, usually an escaping problem; please report it", "生成的场景无法解析, 通常是转义问题, 请反馈", "生成したシーンを解析できません. 多くはエスケープの問題です. 報告してください"),
+    ("check that the story is valid JSON and the output directory is writable", "检查故事是否为有效的 JSON, 以及输出目录是否可写", "ストーリーが有効な JSON か, 出力ディレクトリが書き込み可能か確認してください"),
+    ("check the story id and the network", "检查故事 id 和网络", "ストーリー ID とネットワークを確認してください"),
+    ("check the header file given by --header", "检查 --header 指定的请求头文件", "--header で指定したヘッダーファイルを確認してください"),
+    ("check the scene template in the pipeline config; it needs ${n}", "检查管线配置中的场景文件名模板, 其中需要包含 ${n}", "パイプライン設定のシーンファイル名テンプレートを確認してください (${n} が必要です)"),
+    ("the run was interrupted; run `bd2wg-cli resume` to continue", "运行被中断, 运行 `bd2wg-cli resume` 继续", "実行が中断されました. `bd2wg-cli resume` で再開してください"),
+    ("too many errors; fix the earlier errors or relax the error policy", "错误过多, 请先解决之前的错误或放宽错误策略", "エラーが多すぎます. 先のエラーを解決するか, エラーポリシーを緩めてください"),
+    ("internal error; please report it with a log file (--log-file)", "内部错误, 请附上日志文件 (--log-file) 反馈", "内部エラーです. ログファイル (--log-file) を添えて報告してください"),
+];
+
+/// 查找当前语言的译文
+pub fn translate(text: &'static str) -> &'static str {
+    let lang = lang();
+    if lang == Lang::En {
+        return text;
+    }
+
+    match MESSAGES.iter().find(|(en, ..)| *en == text) {
+        Some((_, zh, _)) if lang == Lang::Zh => zh,
+        Some((_, _, ja)) => ja,
+        None => text,
+    }
+}
+
+/// 以参数填充模板中的 `{}` 和 `{n}`
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut next = 0;
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+
+        let index = match &rest[start + 1..start + len] {
+            "" => {
+                next += 1;
+                next - 1
+            }
+            n => match n.parse() {
+                Ok(n) => n,
+                Err(_) => {
+                    out.push_str(&rest[..=start + len]);
+                    rest = &rest[start + len + 1..];
+                    continue;
+                }
+            },
+        };
+
+        out.push_str(&rest[..start]);
+        if let Some(arg) = args.get(index) {
+            out.push_str(&arg.to_string());
+        }
+        rest = &rest[start + len + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// 翻译界面文本, 带参数时按模板填充
+#[macro_export]
+macro_rules! tr {
+    ($text:literal) => {
+        $crate::i18n::translate($text)
+    };
+
+    ($text:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::fill(
+            $crate::i18n::translate($text),
+            &[$(&$arg as &dyn std::fmt::Display),+],
+        )
+    };
+}
+
+#[test]
+#[cfg(test)]
+fn test_fill() {
+    assert_eq!(fill("{} and {}", &[&1, &"two"]), "1 and two");
+    assert_eq!(
+        fill("{0}, saved as {0}.new", &[&"a.txt"]),
+        "a.txt, saved as a.txt.new"
+    );
+    // 非数字的花括号原样保留
+    assert_eq!(fill("needs ${n}: {}", &[&3]), "needs ${n}: 3");
+    assert_eq!(fill("missing {}", &[]), "missing ");
+    assert_eq!(fill("open {", &[&1]), "open {");
+}
+
+#[test]
+#[cfg(test)]
+fn test_messages() {
+    // 各译文引用的参数与英文一致, 顺序可以不同
+    let args = |text: &str| {
+        let filled = fill(text, &[&"\u{1}0", &"\u{1}1", &"\u{1}2", &"\u{1}3"]);
+        (0..4)
+            .filter(|i| filled.contains(&format!("\u{1}{i}")))
+            .collect::<Vec<_>>()
+    };
+
+    for (i, (en, zh, ja)) in MESSAGES.iter().enumerate() {
+        assert!(
+            !MESSAGES[..i].iter().any(|(other, ..)| other == en),
+            "duplicate message: {en}"
+        );
+        assert_eq!(args(en), args(zh), "{en}");
+        assert_eq!(args(en), args(ja), "{en}");
+    }
+
+    assert_eq!(translate("no such message"), "no such message");
+}
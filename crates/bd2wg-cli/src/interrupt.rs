@@ -0,0 +1,79 @@
+//! Ctrl-C 处理
+//!
+//! 等待管线期间, 第一次 Ctrl-C 取消管线并照常输出已完成部分的结果, 再次 Ctrl-C 立即退出.
+//! 其余时候 Ctrl-C 立即退出.
+
+use std::{
+    io::stdout,
+    process,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread::sleep,
+};
+
+use bd2wg::traits::handle::Handle;
+use ratatui::crossterm::{cursor::Show, execute};
+
+use crate::{STATE_UPDATE_BACKOFF, tr};
+
+/// 被 Ctrl-C 中断时的退出码
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// 正在等待的管线数
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// 注册 Ctrl-C 处理
+pub fn install() {
+    let _ = ctrlc::set_handler(|| {
+        if WAITING.load(Ordering::Relaxed) == 0 || INTERRUPTED.swap(true, Ordering::Relaxed) {
+            let _ = execute!(stdout(), Show); // 终端界面可能隐藏了光标
+            process::exit(INTERRUPTED_EXIT_CODE);
+        }
+        eprintln!(
+            "\n{}",
+            tr!("interrupted, cancelling... (press Ctrl-C again to exit immediately)")
+        );
+    });
+}
+
+/// 是否已被 Ctrl-C 中断
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// 已被中断时退出
+pub fn exit_if_interrupted() {
+    if interrupted() {
+        process::exit(INTERRUPTED_EXIT_CODE);
+    }
+}
+
+/// 等待管线结束, 每次检查状态前调用 `tick`
+///
+/// 被中断时取消管线, 之后继续等待其保存断点并结束.
+pub fn wait<H: Handle + ?Sized>(pipe: &mut H, tick: impl FnMut(&H)) {
+    wait_while(pipe, |_| true, tick);
+}
+
+/// 在 `active` 成立且管线未结束时等待, 用于等待完整管线的某一阶段
+pub fn wait_while<H: Handle + ?Sized>(
+    pipe: &mut H,
+    active: impl Fn(&H) -> bool,
+    mut tick: impl FnMut(&H),
+) {
+    WAITING.fetch_add(1, Ordering::Relaxed);
+
+    let mut cancelled = false;
+    while !pipe.is_finished() && active(pipe) {
+        if !cancelled && interrupted() {
+            pipe.cancel();
+            cancelled = true;
+        }
+
+        tick(pipe);
+        sleep(STATE_UPDATE_BACKOFF);
+    }
+
+    WAITING.fetch_sub(1, Ordering::Relaxed);
+}
@@ -0,0 +1,65 @@
+//! 日志输出
+//!
+//! 终端日志写入标准错误, 日志文件记录完整诊断信息.
+
+use std::{fs::File, io, path::PathBuf, sync::Mutex};
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Args};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+
+/// 日志参数
+#[derive(Debug, Clone, Default, Args)]
+pub struct LogArgs {
+    /// 终端仅输出错误日志
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// 终端输出更详细的日志, 可重复 (-v, -vv, -vvv)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// 将完整诊断日志写入文件
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+}
+
+impl LogArgs {
+    /// 终端日志级别
+    fn level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::ERROR;
+        }
+
+        match self.verbose {
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        }
+    }
+
+    /// 初始化日志订阅器, 日志文件至少记录 debug 级别
+    pub fn init(&self) -> Result<()> {
+        let terminal = fmt::layer()
+            .with_writer(io::stderr)
+            .with_target(false)
+            .with_filter(self.level());
+
+        let file = match &self.log_file {
+            Some(path) => {
+                let file = File::create(path).with_context(|| path.display().to_string())?;
+                let layer = fmt::layer()
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .with_filter(self.level().max(LevelFilter::DEBUG));
+                Some(layer)
+            }
+            None => None,
+        };
+
+        tracing_subscriber::registry()
+            .with(terminal)
+            .with(file)
+            .try_init()?;
+        Ok(())
+    }
+}
@@ -1,118 +1,632 @@
 //! bd2wg 命令行终端
 
+mod commands;
+mod config;
+mod i18n;
+mod interrupt;
+mod logging;
+mod recover;
+mod report;
+mod tui;
+mod update;
 mod utils;
 
-use std::{thread::sleep, time::Duration};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::Duration,
+};
 
 use bd2wg::{
-    services::pipeline::TranspilePipeline,
+    models::config::{ExtractorConfig, PipelineOptions, ProjectConfig},
+    services::{
+        bestdori_api::{BestdoriApi, parse_story_id},
+        pipeline::FullPipeline,
+    },
     traits::{
         handle::Handle,
-        pipeline::{DownloadResult, DownloadState, TranspileResult, TranspileState},
+        pipeline::{
+            DownloadPipeline, DownloadResult, DownloadState, FullPipeline as _, PipelineStage,
+            ResourceStatus, ResourceTask, TranspileResult, TranspileState,
+        },
     },
     utils::*,
 };
+use clap::{Parser, Subcommand};
 use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::utils::*;
+use crate::{config::*, i18n::*, logging::LogArgs, report::*, tui::*, utils::*};
 
 const GIT_REPOSITORY: &str = "https://github.com/fltLi/bd2wg";
 
 /// 状态更新间隔
-const STATE_UPDATE_BACKOFF: Duration = Duration::from_millis(100);
+pub(crate) const STATE_UPDATE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// bd2wg 命令行终端
+///
+/// 不带子命令时进入交互模式.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// 不展示交互界面, 结束后以 JSON 输出运行报告
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// 不使用终端界面, 以纯文本和进度条输出
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// 界面语言, 默认按系统区域设置选择
+    #[arg(long, global = true, value_enum)]
+    lang: Option<Lang>,
+
+    /// 启动时不检查新版本
+    #[arg(long, global = true)]
+    offline: bool,
+
+    #[command(flatten)]
+    log: LogArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 输出配置文件的 JSON Schema
+    Schema {
+        /// 配置名称 (config, report, resolver, transpiler, api, extractor, project, download,
+        /// pipeline, convert, manifest, checkpoint, redirects, characters, header)
+        name: String,
+    },
+    /// 删除 bd2wg 生成的场景和资源, 保留用户编写或修改过的文件
+    Clean {
+        /// 工程目录或游戏目录
+        project: PathBuf,
+    },
+    /// 从断点继续中断的转换
+    Resume {
+        /// 工程目录或游戏目录
+        project: PathBuf,
+    },
+    /// 查看上次运行保存的错误报告, 按类别分组并给出修复建议
+    Errors {
+        /// 工程目录或游戏目录
+        project: PathBuf,
+        /// 只展示此类错误, 如 download, resolve, reference
+        #[arg(long = "kind", value_name = "KIND")]
+        kinds: Vec<String>,
+        /// 只展示此类资源的错误, 如 figure, bgm, vocal
+        #[arg(long = "resource", value_name = "RESOURCE")]
+        resources: Vec<String>,
+    },
+    /// 检查工程并打包游戏目录为 zip, 供分享给其他 WebGAL 用户
+    Pack {
+        /// 工程目录或游戏目录
+        project: PathBuf,
+        /// 压缩包路径, 默认为 <工程名>.zip
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 不包含 bd2wg 的清单, 断点, 报告等工作文件
+        #[arg(long)]
+        strip: bool,
+        /// 检查发现错误时仍然打包
+        #[arg(long)]
+        force: bool,
+    },
+    /// 从浏览器复制的请求生成请求头文件 (header.json)
+    Header {
+        #[command(subcommand)]
+        source: HeaderCommand,
+    },
+    /// 批量转换多个故事, 以脚本文件名为标识合并到同一工程
+    Convert {
+        /// 故事脚本路径, 文件名部分支持 * 和 ? 通配符
+        #[arg(required_unless_present = "story_id")]
+        stories: Vec<String>,
+        /// 经 Bestdori API 获取的故事 id 或页面链接, 可多次指定
+        #[arg(long, value_name = "ID")]
+        story_id: Vec<String>,
+        /// 输出目录
+        #[arg(short, long)]
+        outdir: PathBuf,
+        /// 管线配置文件 (JSON 或 .toml), 工程默认选项见 bd2wg.toml
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// 生成 WebGAL 工程骨架
+        #[arg(long)]
+        project: bool,
+        /// 试运行: 写入场景但跳过下载
+        #[arg(long)]
+        dry_run: bool,
+        /// 转译后探测预计下载大小
+        #[arg(long)]
+        estimate_size: bool,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+    /// 从 Bestdori 获取社区故事或活动剧情脚本并保存为 JSON
+    Fetch {
+        /// 故事 id 或故事页面链接, 活动剧情为 event:<活动 id>:<话数>
+        story: String,
+        /// 保存路径模板, 可用变量 ${id}, ${title}, ${event}, ${band}, 默认为 story-${id}.json
+        #[arg(short, long)]
+        output: Option<String>,
+        /// 获取后转换到此输出目录
+        #[arg(long, value_name = "OUTDIR")]
+        convert: Option<PathBuf>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+    /// 检查故事脚本的未知指令, 无法解析的资源和统计信息, 不写入任何文件
+    Validate {
+        /// 故事脚本路径
+        #[arg(required_unless_present = "story_id", conflicts_with = "story_id")]
+        story: Option<PathBuf>,
+        /// 经 Bestdori API 获取的故事 id 或页面链接
+        #[arg(long, value_name = "ID")]
+        story_id: Option<String>,
+        /// 读取此输出目录下的工程配置
+        #[arg(short, long)]
+        outdir: Option<PathBuf>,
+        /// 管线配置文件 (JSON 或 .toml), 工程默认选项见 bd2wg.toml
+        #[arg(long)]
+        config: Option<PathBuf>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+    /// 监听故事文件, 变更时重新转换并下载新增资源
+    Watch {
+        /// 故事脚本路径
+        story: PathBuf,
+        /// 输出目录
+        outdir: PathBuf,
+        /// 管线配置文件 (JSON 或 .toml), 变更时同样重新转换
+        #[arg(long)]
+        config: Option<PathBuf>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+}
+
+/// 请求头来源
+#[derive(Subcommand)]
+enum HeaderCommand {
+    /// 读取浏览器开发者工具中 "复制为 cURL (bash)" 得到的命令
+    FromCurl {
+        /// 保存命令的文件, `-` 为标准输入
+        file: PathBuf,
+        /// 保存路径, 默认为 header.json
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 读取浏览器开发者工具导出的 HAR 文件
+    FromHar {
+        /// HAR 文件, `-` 为标准输入
+        file: PathBuf,
+        /// 选取链接包含此文本的请求, 默认优先选取 Bestdori 的请求
+        #[arg(long)]
+        url: Option<String>,
+        /// 保存路径, 默认为 header.json
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
 
 /// 单次工作
 fn run() {
     println!();
 
-    let story = readln! {"script"};
-    let outdir = readln! {"outdir"};
+    let story = readln! {"{}", tr!("script (path, id or url)")};
+    let outdir = readln! {"{}", tr!("outdir")};
 
-    // 转译
+    // 非本地文件时按 id 获取
+    let story = match parse_story_id(&story) {
+        Some(id) if !Path::new(&story).exists() => {
+            println!("{}", tr!("fetching story {}...", id));
+            flush! {};
 
-    println!("transpiling...");
-    flush! {};
+            let path = env::temp_dir().join(format!("bd2wg-story-{id}.json"));
+            if let Err(e) = BestdoriApi::new(default_header().unwrap())
+                .and_then(|api| api.story_to(id, Default::default(), &path))
+            {
+                println!("{}\n{e}", tr!("failed to fetch story, error:"));
+                flush! {};
+                return;
+            }
+            path
+        }
+        _ => PathBuf::from(story),
+    };
+    let project = readln! {"{}", tr!("create WebGAL project (y/N)")};
+    let dry_run = readln! {"{}", tr!("download resources (Y/n)")}
+        .trim()
+        .eq_ignore_ascii_case("n");
 
-    let pipe = TranspilePipeline::new(story, outdir, default_header().unwrap());
+    let config = ExtractorConfig {
+        project: project
+            .trim()
+            .eq_ignore_ascii_case("y")
+            .then(ProjectConfig::default),
+        checkpoint: true,
+        dry_run,
+        ..Default::default()
+    };
 
-    let (
-        TranspileResult {
-            state: TranspileState { scene, action },
-            errors,
-        },
-        pipe,
-    ) = pipe.join(); // 转译很快, 直接阻塞等待即可.
+    // 读取输出目录下的工程配置
+    let mut options = PipelineOptions {
+        extractor: config,
+        recover: Some(Arc::new(recover::Prompter)),
+        ..default_header().unwrap().into()
+    };
+    if let Err(e) = ProjectFile::load(Path::new(&outdir)).and_then(|file| file.apply(&mut options))
+    {
+        println!("{}\n{e:#}", tr!("failed to load {}, error:", CONFIG_FILE));
+        flush! {};
+        return;
+    }
+
+    let pipe = FullPipeline::new(&story, &outdir, options);
+
+    let (story_report, download) = convert_story(pipe, dry_run);
+    let report = Report {
+        stories: vec![StoryReport {
+            story: Some(story),
+            ..story_report
+        }],
+        download,
+    };
+    if let Err(e) = report.save(Path::new(&outdir)) {
+        println!("{}\n{e:#}", tr!("failed to save report, error:"));
+    }
+
+    pause! {};
+}
+
+/// 等待完整管线结束并展示结果, 返回转译报告和下载报告
+///
+/// 试运行时列出所需资源, 不返回下载报告.
+fn convert_story(
+    mut pipe: Box<FullPipeline>,
+    dry_run: bool,
+) -> (StoryReport, Option<DownloadReport>) {
+    let transpiling = |pipe: &FullPipeline| pipe.state().stage == PipelineStage::Transpile;
+    if !(tui_output()
+        && Dashboard::transpile(&mut *pipe, transpiling, |pipe| {
+            (!transpiling(pipe) as usize, 1)
+        })
+        .is_ok())
+    {
+        if !json_output() {
+            println!("{}", tr!("transpiling..."));
+            flush! {};
+        }
+        interrupt::wait_while(&mut *pipe, transpiling, |_| {});
+    }
+
+    match dry_run || pipe.state().stage != PipelineStage::Download {
+        true => interrupt::wait(&mut *pipe, |_| {}),
+        false => wait_download(&mut *pipe, |pipe| (pipe.state().download, pipe.tasks())),
+    }
+
+    let tasks = pipe.tasks();
+    let (result, download) = pipe.join();
+    let report = StoryReport::new(None, &result);
+
+    if !json_output() {
+        show_transpile_result(result, dry_run);
+    }
+
+    if dry_run {
+        return (report, None);
+    }
+
+    match download {
+        Ok(download) => (report, Some(show_download_result(download, &tasks))),
+        Err(e) => {
+            if !json_output() {
+                println!("{}\n{e}", tr!("failed to start download, error:"));
+                flush! {};
+            }
+            (report, None)
+        }
+    }
+}
+
+/// 展示转译结果, 试运行时列出所需资源
+fn show_transpile_result(result: TranspileResult, dry_run: bool) {
+    let TranspileResult {
+        state:
+            TranspileState {
+                scene,
+                action,
+                actions,
+                unchanged,
+                conflicts,
+                kept,
+                timings,
+                ..
+            },
+        errors,
+        warnings,
+        stats,
+        resources,
+    } = result;
 
-    println!("translation completed, result: ");
-    print!("{scene} scenes, {action} actions, ");
+    println!("{}", tr!("translation completed, result: "));
+    print!(
+        "{}",
+        tr!(
+            "{} scenes ({} unchanged), {} actions, ",
+            scene,
+            unchanged,
+            action
+        )
+    );
     try_show_errors(errors);
+    try_show_warnings(warnings);
+    println!("{actions}");
+    println!("{stats}");
+    println!("{timings}");
+
+    for path in conflicts {
+        println!(
+            "{}",
+            tr!("merge conflict: {0}, new content saved as {0}.new", path)
+        );
+    }
+    for path in kept {
+        println!("{}", tr!("kept existing file: {}", path));
+    }
 
     println!();
     flush! {};
 
-    // 下载
-
-    let pipe = match pipe {
-        Ok(v) => v,
-        Err(e) => {
-            println!("failed to start download, error:\n{e}");
-            flush! {};
-            return;
+    if dry_run {
+        println!(
+            "{}",
+            tr!("dry run, {} resources to download:", resources.len())
+        );
+        for res in resources {
+            println!("{}/{} <- {}", res.kind.dir(), res.path, res.url);
         }
-    };
+        flush! {};
+    }
+}
+
+/// 等待下载完成并展示结果, 返回下载报告
+fn download(mut pipe: Box<dyn DownloadPipeline>) -> DownloadReport {
+    wait_download(&mut *pipe, |pipe| (pipe.state(), pipe.tasks()));
+    let tasks = pipe.tasks();
+    show_download_result(pipe.join(), &tasks)
+}
+
+/// 展示下载进度直至管线结束, `view` 返回下载状态和任务列表
+fn wait_download<H: Handle + ?Sized>(
+    pipe: &mut H,
+    view: impl Fn(&H) -> (DownloadState, Vec<ResourceTask>),
+) {
+    if json_output() {
+        interrupt::wait(pipe, |_| {});
+    } else if !(tui_output() && Dashboard::download(pipe, &view).is_ok()) {
+        wait_download_plain(pipe, view);
+    }
+}
+
+/// 展示下载结果, 返回下载报告
+fn show_download_result(result: DownloadResult, tasks: &[ResourceTask]) -> DownloadReport {
+    let report = DownloadReport::new(&result, tasks);
+    if json_output() {
+        return report;
+    }
+
+    let DownloadResult {
+        state: DownloadState {
+            success, timings, ..
+        },
+        errors,
+    } = result;
 
-    println!("downloading...");
+    println!("{}", tr!("download completed, result: "));
+    print!("{}", tr!("{} success, ", success));
+    try_show_errors(errors);
+    println!("{timings}");
+
+    report
+}
+
+/// 以进度条展示下载进度直至结束
+fn wait_download_plain<H: Handle + ?Sized>(
+    pipe: &mut H,
+    view: impl Fn(&H) -> (DownloadState, Vec<ResourceTask>),
+) {
+    println!("{}", tr!("downloading..."));
     flush! {};
 
     // 初始化 indicatif 进度条
     let pb = ProgressBar::new(0);
     pb.set_style(
         ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len}")
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}",
+            )
             .unwrap()
             .progress_chars("#>-"),
     );
 
-    // 等待下载完成
-    while !pipe.is_finished() {
-        let DownloadState {
-            success,
-            failed,
-            total,
-        } = pipe.state();
+    // 等待下载完成, 询问失败资源期间暂停刷新
+    interrupt::wait(pipe, |pipe| {
+        if recover::prompting() {
+            return;
+        }
+
+        let (
+            DownloadState {
+                success,
+                failed,
+                total,
+                ..
+            },
+            tasks,
+        ) = view(pipe);
 
         // 使用进度条呈现 done / total
         pb.set_length(total as u64);
         pb.set_position((success + failed) as u64);
+        pb.set_message(in_flight(&tasks));
+    });
 
-        sleep(STATE_UPDATE_BACKOFF);
-    }
-
-    let DownloadResult {
-        state: DownloadState {
+    let (
+        DownloadState {
             success,
             failed,
             total,
+            ..
         },
-        errors,
-    } = pipe.join();
-
+        _,
+    ) = view(pipe);
     pb.set_length(total as u64);
     pb.set_position((success + failed) as u64);
+    pb.set_message("");
     pb.finish();
+}
 
-    println!("download completed, result: ");
-    print!("{} success, ", success);
-    try_show_errors(errors);
+/// 概括正在下载的资源
+fn in_flight(tasks: &[ResourceTask]) -> String {
+    let mut active = tasks.iter().filter_map(|task| match &task.status {
+        ResourceStatus::Downloading(progress) => Some((&task.path, progress.ratio())),
+        _ => None,
+    });
 
-    pause! {};
+    let Some((path, ratio)) = active.next() else {
+        return String::new();
+    };
+    let first = match ratio {
+        Some(ratio) => format!("{path} ({:.0}%)", ratio * 100.0),
+        None => path.clone(),
+    };
+
+    match active.count() {
+        0 => first,
+        more => format!("{first} +{more}"),
+    }
 }
 
-fn main() {
+/// 交互模式
+fn interactive() -> ! {
     println!("bd2wg-cli\n{GIT_REPOSITORY}");
     flush! {};
 
+    // 询问失败资源时会打断进度展示, 不使用终端界面
+    set_plain_output(true);
+
     loop {
         run();
+        interrupt::exit_if_interrupted();
+        update::notify();
+    }
+}
+
+/// 执行子命令, 不带子命令时进入交互模式
+fn execute(command: Option<Command>) -> anyhow::Result<Outcome> {
+    match command {
+        None => interactive(),
+        Some(Command::Schema { name }) => commands::schema(&name),
+        Some(Command::Clean { project }) => commands::clean(&project),
+        Some(Command::Resume { project }) => commands::resume(&project),
+        Some(Command::Errors {
+            project,
+            kinds,
+            resources,
+        }) => commands::errors(&project, &kinds, &resources),
+        Some(Command::Pack {
+            project,
+            output,
+            strip,
+            force,
+        }) => commands::pack(&project, output.as_deref(), strip, force),
+        Some(Command::Header { source }) => match source {
+            HeaderCommand::FromCurl { file, output } => {
+                commands::header(commands::HeaderSource::Curl, &file, output.as_deref())
+            }
+            HeaderCommand::FromHar { file, url, output } => commands::header(
+                commands::HeaderSource::Har(url.as_deref()),
+                &file,
+                output.as_deref(),
+            ),
+        },
+        Some(Command::Convert {
+            mut stories,
+            story_id,
+            outdir,
+            config,
+            project,
+            dry_run,
+            estimate_size,
+            overrides,
+        }) => commands::fetch_stories(&story_id, &outdir, &overrides).and_then(|fetched| {
+            stories.extend(fetched);
+            commands::convert(
+                &stories,
+                &outdir,
+                config.as_deref(),
+                project,
+                dry_run,
+                estimate_size,
+                overrides,
+            )
+        }),
+        Some(Command::Fetch {
+            story,
+            output,
+            convert,
+            overrides,
+        }) => commands::fetch(&story, output.as_deref(), convert.as_deref(), overrides),
+        Some(Command::Validate {
+            story,
+            story_id,
+            outdir,
+            config,
+            overrides,
+        }) => commands::validate(
+            story.as_deref(),
+            story_id.as_deref(),
+            outdir.as_deref(),
+            config.as_deref(),
+            overrides,
+        ),
+        Some(Command::Watch {
+            story,
+            outdir,
+            config,
+            overrides,
+        }) => commands::watch(&story, &outdir, config.as_deref(), overrides),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    set_json_output(cli.json);
+    set_plain_output(cli.plain);
+    set_lang(cli.lang);
+
+    let result = cli.log.init().and_then(|_| {
+        interrupt::install();
+        if !cli.offline {
+            update::spawn();
+        }
+        execute(cli.command)
+    });
+
+    interrupt::exit_if_interrupted();
+    update::notify();
+    match result {
+        Ok(outcome) => outcome.into(),
+        Err(e) => {
+            eprintln!("{}: {e:?}", tr!("Error"));
+            ExitCode::from(FAILURE_EXIT_CODE)
+        }
     }
 }
@@ -0,0 +1,90 @@
+//! 交互模式下恢复失败的资源
+//!
+//! 资源解析或下载失败时询问替代链接或本地文件, 下载失败时还可以重试.
+//! 询问在管线线程中进行, 期间暂停进度展示.
+
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use bd2wg::{
+    Error,
+    error::ResolveError,
+    traits::{
+        pipeline::ResourceTask,
+        recover::{Recover, Recovery, Replacement},
+    },
+};
+use console::style;
+
+use crate::{flush, readln, tr};
+
+static PROMPTING: AtomicBool = AtomicBool::new(false);
+
+/// 串行询问, 避免多个管线线程同时读取输入
+static PROMPT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 是否正在询问, 此时不应刷新进度
+pub fn prompting() -> bool {
+    PROMPTING.load(Ordering::Relaxed)
+}
+
+/// 在终端询问失败资源的恢复方式
+#[derive(Debug)]
+pub struct Prompter;
+
+impl Prompter {
+    /// 展示失败原因并读取替代资源, 允许重试时输入 `r` 重试, 留空跳过
+    fn ask(&self, reason: &str, retry: bool) -> Recovery {
+        let _lock = PROMPT_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        PROMPTING.store(true, Ordering::Relaxed);
+        println!("\n{reason}");
+
+        let hint = match retry {
+            true => tr!("replacement url or local file (r: retry, empty: skip)"),
+            false => tr!("replacement url or local file (empty: skip)"),
+        };
+        let recovery = loop {
+            match readln! {"{}", hint}.trim() {
+                "" => break Recovery::Skip,
+                "r" if retry => break Recovery::Retry,
+                url if url.starts_with("http://") || url.starts_with("https://") => {
+                    break Recovery::Replace(Replacement::Url(url.to_string()));
+                }
+                path => match fs::canonicalize(path) {
+                    Ok(path) if path.is_file() => {
+                        break Recovery::Replace(Replacement::File(path));
+                    }
+                    _ => println!("{}", tr!("file not found: {}", Path::new(path).display())),
+                },
+            }
+        };
+
+        flush! {};
+        PROMPTING.store(false, Ordering::Relaxed);
+        recovery
+    }
+}
+
+impl Recover for Prompter {
+    fn resolve(&self, error: &ResolveError) -> Option<Replacement> {
+        let reason = format!("{} {error}", style(tr!("failed to resolve:")).red());
+        match self.ask(&reason, false) {
+            Recovery::Replace(replacement) => Some(replacement),
+            _ => None,
+        }
+    }
+
+    fn download(&self, task: &ResourceTask, errors: &[Error]) -> Recovery {
+        let mut reason = format!("{} {}", style(tr!("failed to download:")).red(), task.path);
+        for error in errors {
+            reason += &format!("\n  {error}");
+        }
+        self.ask(&reason, true)
+    }
+}
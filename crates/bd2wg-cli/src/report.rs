@@ -0,0 +1,215 @@
+//! 机器可读的运行报告
+//!
+//! 启用 `--json` 时不展示交互界面, 结束后向标准输出打印报告.
+//! 转换结束后报告同时保存到游戏目录, 供 `errors` 子命令查看.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Context, Result};
+use bd2wg::{
+    Error,
+    traits::pipeline::{DownloadResult, ResourceStatus, ResourceTask, TranspileResult},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// 保存在游戏目录下的运行报告文件名
+pub const REPORT_FILE: &str = "bd2wg-report.json";
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// 启用 JSON 输出
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// 是否启用 JSON 输出
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// 启动失败或读写文件出错时的退出码
+pub const FAILURE_EXIT_CODE: u8 = 2;
+
+/// 子命令运行结果, 决定进程退出码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// 退出码 0
+    Success,
+    /// 运行结束但存在错误, 退出码 1
+    Errors,
+}
+
+impl Outcome {
+    pub fn from_errors(errors: bool) -> Self {
+        match errors {
+            true => Self::Errors,
+            false => Self::Success,
+        }
+    }
+}
+
+impl From<Outcome> for ExitCode {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Success => ExitCode::SUCCESS,
+            Outcome::Errors => ExitCode::FAILURE,
+        }
+    }
+}
+
+/// 运行报告
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Report {
+    pub stories: Vec<StoryReport>,
+    /// 未启动下载时为空
+    pub download: Option<DownloadReport>,
+}
+
+impl Report {
+    /// 按报告中的错误和下载失败数得出运行结果
+    pub fn outcome(&self) -> Outcome {
+        let stories = self.stories.iter().any(|story| !story.errors.is_empty());
+        let download = (self.download.as_ref())
+            .is_some_and(|download| download.failed != 0 || !download.errors.is_empty());
+        Outcome::from_errors(stories || download)
+    }
+
+    /// 启用 JSON 输出时打印报告
+    pub fn emit(&self) {
+        if json_output() {
+            println!("{}", serde_json::to_string(self).unwrap());
+        }
+    }
+
+    /// 保存到游戏目录
+    pub fn save(&self, root: &Path) -> Result<()> {
+        let path = root.join(REPORT_FILE);
+        fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| path.display().to_string())
+    }
+
+    /// 读取游戏目录下保存的报告
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(REPORT_FILE);
+        let bytes = fs::read(&path).with_context(|| path.display().to_string())?;
+        serde_json::from_slice(&bytes).with_context(|| path.display().to_string())
+    }
+}
+
+/// 错误条目
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorEntry {
+    /// 错误类别, 如 `download`, `resolve`, `reference`
+    pub kind: String,
+    /// 相关资源的目录, 如 `bgm`, `figure`
+    pub resource: Option<String>,
+    pub message: String,
+}
+
+impl From<&Error> for ErrorEntry {
+    fn from(error: &Error) -> Self {
+        Self {
+            kind: error.kind_name().to_string(),
+            resource: error.resource_type().map(|kind| kind.dir().to_string()),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// 单个故事的转译报告
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StoryReport {
+    pub story: Option<PathBuf>,
+    pub scenes: usize,
+    pub actions: usize,
+    pub unchanged: usize,
+    pub errors: Vec<ErrorEntry>,
+    pub warnings: Vec<String>,
+    /// 写入或合并的场景文件
+    pub files: Vec<String>,
+    /// 合并冲突的场景文件
+    pub conflicts: Vec<String>,
+    /// 按配置保留, 未覆盖的场景文件
+    pub kept: Vec<String>,
+    /// 需要下载的资源
+    pub resources: Vec<String>,
+}
+
+impl StoryReport {
+    pub fn new(story: Option<PathBuf>, result: &TranspileResult) -> Self {
+        Self {
+            story,
+            scenes: result.state.scene,
+            actions: result.state.action,
+            unchanged: result.state.unchanged,
+            errors: result.errors.iter().map(ErrorEntry::from).collect(),
+            warnings: result.warnings.iter().map(ToString::to_string).collect(),
+            files: result.state.written.clone(),
+            conflicts: result.state.conflicts.clone(),
+            kept: result.state.kept.clone(),
+            resources: (result.resources.iter())
+                .map(|res| format!("{}/{}", res.kind.dir(), res.path))
+                .collect(),
+        }
+    }
+}
+
+/// 下载报告
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct DownloadReport {
+    pub success: usize,
+    pub failed: usize,
+    pub total: usize,
+    pub errors: Vec<ErrorEntry>,
+    /// 下载成功的资源
+    pub files: Vec<String>,
+}
+
+impl DownloadReport {
+    pub fn new(result: &DownloadResult, tasks: &[ResourceTask]) -> Self {
+        Self {
+            success: result.state.success,
+            failed: result.state.failed,
+            total: result.state.total,
+            errors: result.errors.iter().map(ErrorEntry::from).collect(),
+            files: tasks
+                .iter()
+                .filter(|task| task.status == ResourceStatus::Done)
+                .map(|task| task.path.clone())
+                .collect(),
+        }
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_report_outcome() {
+    let mut report = Report {
+        stories: vec![StoryReport::default()],
+        download: Some(DownloadReport::default()),
+    };
+    assert_eq!(report.outcome(), Outcome::Success);
+
+    report.download.as_mut().unwrap().failed = 1;
+    assert_eq!(report.outcome(), Outcome::Errors);
+
+    report.download = None;
+    report.stories[0].errors.push(ErrorEntry {
+        kind: String::from("resolve"),
+        resource: Some(String::from("figure")),
+        message: String::from("unresolved"),
+    });
+    assert_eq!(report.outcome(), Outcome::Errors);
+
+    // 保存的报告可被 resume 和 clean 读回
+    let json = serde_json::to_vec(&report).unwrap();
+    let report: Report = serde_json::from_slice(&json).unwrap();
+    assert_eq!(report.stories[0].errors[0].kind, "resolve");
+    assert_eq!(report.outcome(), Outcome::Errors);
+}
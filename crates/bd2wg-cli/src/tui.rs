@@ -0,0 +1,258 @@
+//! 终端界面
+//!
+//! 在终端底部绘制各阶段面板: 转译进度, 下载任务列表及速度, 错误日志.
+//! 结束后保留最后一帧, 之后照常输出结果. 指定 `--plain` 时改用单个进度条.
+
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal, Stdout, stdout},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use bd2wg::traits::{
+    handle::Handle,
+    pipeline::{DownloadState, ResourceStatus, ResourceTask},
+};
+use indicatif::HumanBytes;
+use ratatui::{
+    Terminal, TerminalOptions, Viewport,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Gauge, Paragraph},
+};
+
+use crate::{interrupt, report::json_output, tr};
+
+/// 下载面板高度
+const DOWNLOAD_HEIGHT: u16 = 18;
+
+/// 下载任务列表的行数
+const TASK_ROWS: usize = 6;
+
+static PLAIN_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// 启用纯文本输出
+pub fn set_plain_output(enabled: bool) {
+    PLAIN_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// 是否使用终端界面, 仅在标准输出为终端时启用
+pub fn tui_output() -> bool {
+    !PLAIN_OUTPUT.load(Ordering::Relaxed) && !json_output() && stdout().is_terminal()
+}
+
+/// 下载速度估计
+struct Speed {
+    done: u64,
+    at: Instant,
+    /// 字节每秒
+    rate: f64,
+}
+
+/// 终端面板
+pub struct Dashboard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    /// 错误日志
+    log: Vec<Line<'static>>,
+    /// 已记录失败的资源
+    failed: Vec<bool>,
+    speeds: HashMap<usize, Speed>,
+}
+
+impl Dashboard {
+    fn new(height: u16) -> io::Result<Self> {
+        let terminal = Terminal::with_options(
+            CrosstermBackend::new(stdout()),
+            TerminalOptions {
+                viewport: Viewport::Inline(height),
+            },
+        )?;
+
+        Ok(Self {
+            terminal,
+            log: Vec::new(),
+            failed: Vec::new(),
+            speeds: HashMap::new(),
+        })
+    }
+
+    /// 在 `active` 成立时展示转译进度, `progress` 返回已完成和总故事数
+    pub fn transpile<H: Handle + ?Sized>(
+        pipe: &mut H,
+        active: impl Fn(&H) -> bool,
+        progress: impl Fn(&H) -> (usize, usize),
+    ) -> io::Result<()> {
+        let mut dashboard = Self::new(3)?;
+        let mut draw = |pipe: &H| {
+            let (done, total) = progress(pipe);
+            dashboard
+                .terminal
+                .draw(|frame| render_gauge(frame.area(), frame, tr!("transpile"), done, total))
+                .map(drop)
+        };
+
+        // 绘制失败时仍等待管线结束
+        let mut result = Ok(());
+        interrupt::wait_while(pipe, active, |pipe| {
+            if result.is_ok() {
+                result = draw(pipe);
+            }
+        });
+        result.and_then(|_| draw(pipe))?;
+        dashboard.close()
+    }
+
+    /// 展示下载进度, 任务列表及错误日志直至结束, `view` 返回下载状态和任务列表
+    pub fn download<H: Handle + ?Sized>(
+        pipe: &mut H,
+        view: impl Fn(&H) -> (DownloadState, Vec<ResourceTask>),
+    ) -> io::Result<()> {
+        let mut dashboard = Self::new(DOWNLOAD_HEIGHT)?;
+
+        let mut result = Ok(());
+        interrupt::wait(pipe, |pipe| {
+            if result.is_ok() {
+                result = dashboard.draw_download(view(pipe));
+            }
+        });
+        result.and_then(|_| dashboard.draw_download(view(pipe)))?;
+        dashboard.close()
+    }
+
+    /// 绘制下载面板
+    fn draw_download(
+        &mut self,
+        (state, tasks): (DownloadState, Vec<ResourceTask>),
+    ) -> io::Result<()> {
+        self.update(&tasks);
+
+        let Self {
+            terminal,
+            log,
+            speeds,
+            ..
+        } = self;
+        terminal
+            .draw(|frame| {
+                let [gauge, list, errors] = Layout::vertical([
+                    Constraint::Length(3),
+                    Constraint::Length(TASK_ROWS as u16 + 2),
+                    Constraint::Min(3),
+                ])
+                .areas(frame.area());
+
+                let done = state.success + state.failed;
+                render_gauge(gauge, frame, tr!("download"), done, state.total);
+                frame.render_widget(task_list(&tasks, speeds), list);
+                frame.render_widget(error_log(log, errors.height), errors);
+            })
+            .map(drop)
+    }
+
+    /// 更新下载速度, 记录新失败的资源
+    fn update(&mut self, tasks: &[ResourceTask]) {
+        self.failed.resize(tasks.len(), false);
+        let now = Instant::now();
+
+        for (index, task) in tasks.iter().enumerate() {
+            match &task.status {
+                ResourceStatus::Downloading(progress) => {
+                    let speed = self.speeds.entry(index).or_insert(Speed {
+                        done: progress.done,
+                        at: now,
+                        rate: 0.0,
+                    });
+                    let elapsed = now.duration_since(speed.at).as_secs_f64();
+                    if elapsed >= 0.5 {
+                        speed.rate = progress.done.saturating_sub(speed.done) as f64 / elapsed;
+                        (speed.done, speed.at) = (progress.done, now);
+                    }
+                }
+                ResourceStatus::Failed(message) if !self.failed[index] => {
+                    self.failed[index] = true;
+                    self.speeds.remove(&index);
+                    self.log.push(Line::from(vec![
+                        format!("{} ", tr!("failed")).red(),
+                        task.path.clone().into(),
+                        format!(": {message}").into(),
+                    ]));
+                }
+                _ => {
+                    self.speeds.remove(&index);
+                }
+            }
+        }
+    }
+
+    /// 保留最后一帧, 光标移至面板下方
+    fn close(mut self) -> io::Result<()> {
+        let area = self.terminal.get_frame().area();
+        self.terminal
+            .set_cursor_position(Position::new(0, area.bottom().saturating_sub(1)))?;
+        self.terminal.show_cursor()?;
+        println!();
+        Ok(())
+    }
+}
+
+/// 绘制进度条
+fn render_gauge(area: Rect, frame: &mut ratatui::Frame, title: &str, done: usize, total: usize) {
+    let ratio = match total {
+        0 => 0.0,
+        total => (done as f64 / total as f64).min(1.0),
+    };
+
+    let gauge = Gauge::default()
+        .block(Block::bordered().title(title))
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(ratio)
+        .label(format!("{done}/{total}"));
+    frame.render_widget(gauge, area);
+}
+
+/// 正在下载的资源, 含进度和速度
+fn task_list<'a>(tasks: &'a [ResourceTask], speeds: &HashMap<usize, Speed>) -> Paragraph<'a> {
+    let queued = (tasks.iter())
+        .filter(|task| task.status == ResourceStatus::Queued)
+        .count();
+    let active: Vec<_> = (tasks.iter().enumerate())
+        .filter_map(|(index, task)| match &task.status {
+            ResourceStatus::Downloading(progress) => Some((index, task, progress)),
+            _ => None,
+        })
+        .collect();
+
+    let mut lines: Vec<_> = (active.iter().take(TASK_ROWS))
+        .map(|(index, task, progress)| {
+            let ratio = match progress.ratio() {
+                Some(ratio) => format!("{:>4.0}%", ratio * 100.0),
+                None => format!("{:>8}", HumanBytes(progress.done).to_string()),
+            };
+            let rate = speeds.get(index).map_or(0.0, |speed| speed.rate);
+
+            Line::from(vec![
+                ratio.cyan(),
+                format!(" {:>12} ", format!("{}/s", HumanBytes(rate as u64))).green(),
+                task.path.as_str().into(),
+            ])
+        })
+        .collect();
+    if active.len() > TASK_ROWS {
+        lines[TASK_ROWS - 1] = Line::from(tr!("... +{} more", active.len() - TASK_ROWS + 1));
+    }
+
+    let title = tr!("tasks ({} active, {} queued)", active.len(), queued);
+    Paragraph::new(lines).block(Block::bordered().title(title))
+}
+
+/// 错误日志, 只展示最新的几行
+fn error_log(log: &[Line<'static>], height: u16) -> Paragraph<'static> {
+    let rows = height.saturating_sub(2) as usize;
+    let lines = log[log.len().saturating_sub(rows)..].to_vec();
+
+    let title = tr!("errors ({})", log.len());
+    Paragraph::new(lines).block(Block::bordered().title(title))
+}
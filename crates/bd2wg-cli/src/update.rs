@@ -0,0 +1,123 @@
+//! 新版本检查
+//!
+//! 启动时在后台查询 GitHub 上的最新发布, 存在新版本时提示更新.
+//! Bestdori 资源链接的格式时有变化, 旧版本可能无法正确下载资源. 指定 `--offline` 时不检查.
+
+use std::{
+    sync::{Mutex, PoisonError},
+    thread::{self, JoinHandle, sleep},
+    time::{Duration, Instant},
+};
+
+use reqwest::{blocking::Client, header::ACCEPT};
+use serde::Deserialize;
+
+use crate::tr;
+
+/// GitHub 最新发布 API
+const LATEST_RELEASE_API: &str = "https://api.github.com/repos/fltLi/bd2wg/releases/latest";
+
+/// 检查请求的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 提示前等待检查结果的最长时间
+const NOTIFY_WAIT: Duration = Duration::from_millis(500);
+
+static CHECK: Mutex<Option<JoinHandle<Option<Release>>>> = Mutex::new(None);
+
+/// GitHub 发布
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+}
+
+/// 在后台启动检查
+pub fn spawn() {
+    let handle = thread::spawn(newer_release);
+    *CHECK.lock().unwrap_or_else(PoisonError::into_inner) = Some(handle);
+}
+
+/// 检查已完成且存在新版本时提示, 只提示一次
+///
+/// 检查尚未完成时稍作等待, 仍未完成则留待下次调用.
+pub fn notify() {
+    let mut check = CHECK.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(handle) = check.take() else {
+        return;
+    };
+
+    let deadline = Instant::now() + NOTIFY_WAIT;
+    while !handle.is_finished() && Instant::now() < deadline {
+        sleep(Duration::from_millis(50));
+    }
+    if !handle.is_finished() {
+        *check = Some(handle);
+        return;
+    }
+
+    if let Ok(Some(release)) = handle.join() {
+        tracing::warn!(
+            "{}",
+            tr!(
+                "bd2wg-cli {} is available (current {}): {}",
+                release.tag_name,
+                env!("CARGO_PKG_VERSION"),
+                release.html_url
+            )
+        );
+        tracing::warn!(
+            "{}",
+            tr!(
+                "Bestdori asset URLs change over time, outdated versions may fail to download resources."
+            )
+        );
+    }
+}
+
+/// 查询最新发布, 比当前版本新时返回
+///
+/// 网络不可用等任何失败均忽略.
+fn newer_release() -> Option<Release> {
+    let client = Client::builder()
+        .timeout(CHECK_TIMEOUT)
+        .user_agent(concat!("bd2wg-cli/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .ok()?;
+    let text = client
+        .get(LATEST_RELEASE_API)
+        .header(ACCEPT, "application/vnd.github+json")
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .and_then(|resp| resp.text())
+        .ok()?;
+    let release: Release = serde_json::from_str(&text).ok()?;
+
+    (parse_version(&release.tag_name)? > parse_version(env!("CARGO_PKG_VERSION"))?)
+        .then_some(release)
+}
+
+/// 解析 `v1.2.3` 或 `1.2.3-beta` 形式的版本号, 忽略预发布后缀
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let core = version.split(['-', '+']).next()?;
+
+    let mut parts = core.split('.').map(|part| part.parse().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+#[test]
+#[cfg(test)]
+fn test_parse_version() {
+    assert_eq!(parse_version("v1.2.3"), Some((1, 2, 3)));
+    assert_eq!(parse_version("1.2.3-beta.1"), Some((1, 2, 3)));
+    assert_eq!(parse_version("1.2.3+build"), Some((1, 2, 3)));
+    assert_eq!(parse_version(" v2 "), Some((2, 0, 0)));
+    assert_eq!(parse_version("1.10"), Some((1, 10, 0)));
+    assert_eq!(parse_version("latest"), None);
+    assert_eq!(parse_version("1.x.0"), None);
+    assert!(parse_version("1.10.0") > parse_version("1.9.9"));
+}
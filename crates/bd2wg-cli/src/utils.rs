@@ -1,6 +1,11 @@
 //! 命令行辅助工具
 
-use bd2wg::Error;
+use std::path::{Path, PathBuf};
+
+use bd2wg::{Error, Warning, services::extractor::WEBGAL_GAME_DIR};
+use console::style;
+
+use crate::tr;
 
 #[macro_export]
 macro_rules! flush {
@@ -34,7 +39,7 @@ macro_rules! readln {
 #[macro_export]
 macro_rules! pause {
     () => {{
-        let _ = readln! {"press any key to continue...\n"};
+        let _ = readln! {"{}\n", $crate::tr!("press any key to continue...")};
     }};
 }
 
@@ -43,14 +48,38 @@ pub fn try_show_errors(errs: impl AsRef<[Error]>) {
     let errs = errs.as_ref();
 
     if errs.is_empty() {
-        println!("no error.");
+        println!("{}", tr!("no error."));
     } else {
-        println!("{} errors: ", errs.len());
+        println!("{}", tr!("{} errors: ", style(errs.len()).red()));
 
         for (k, err) in errs.iter().enumerate() {
-            println!("{}. {}.", k + 1, err);
+            println!("{}. {}.", style(k + 1).red(), err);
         }
     }
 
     flush!()
 }
+
+/// 展示警告
+pub fn try_show_warnings(warns: impl AsRef<[Warning]>) {
+    let warns = warns.as_ref();
+
+    if warns.is_empty() {
+        println!("{}", tr!("no warning."));
+    } else {
+        println!("{}", tr!("{} warnings: ", style(warns.len()).yellow()));
+
+        for (k, warn) in warns.iter().enumerate() {
+            println!("{}. {}.", style(k + 1).yellow(), warn);
+        }
+    }
+
+    flush!()
+}
+
+/// 查找包含指定文件的游戏目录, 可以指定工程目录或其游戏目录
+pub fn find_game_dir(project: &Path, file: &str) -> Option<PathBuf> {
+    [project.join(WEBGAL_GAME_DIR), project.to_path_buf()]
+        .into_iter()
+        .find(|root| root.join(file).exists())
+}
@@ -0,0 +1,98 @@
+//! bd2wg 测试数据
+//!
+//! 手工编写的 Bestdori 故事脚本及其预期统计, 以及 Bestdori API 的响应, 供测试, 基准
+//! (`crates/bd2wg/benches`) 和文档示例 (如 `Transpiler`) 使用.
+//!
+//! 脚本按 Bestdori 的格式合成, 并非真实故事的脱敏副本: 链接均指向 example.com,
+//! 人物台词为原创内容. 添加脱敏的真实脚本时应在对应常量的文档中注明来源.
+
+/// 转译预期统计 (默认配置)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expected {
+    /// Bestdori 指令数 (含初始 bgm / background)
+    pub actions: usize,
+    /// 对话数
+    pub talks: usize,
+    /// 生成的 WebGAL 场景数 (含 start.txt)
+    pub scenes: usize,
+    /// 需要下载的资源数
+    pub resources: usize,
+    /// 转译错误数
+    pub errors: usize,
+}
+
+/// 测试故事
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub json: &'static [u8],
+    /// 预期统计, 为空表示脚本应解析失败
+    pub expected: Option<Expected>,
+}
+
+/// 双人短对话, 覆盖登场, 退场, 音效和转场
+pub const SHORT: Fixture = Fixture {
+    name: "short",
+    json: include_bytes!("../stories/short.json"),
+    expected: Some(Expected {
+        actions: 12,
+        talks: 3,
+        scenes: 2,
+        resources: 5,
+        errors: 0,
+    }),
+};
+
+/// 长篇对话, 覆盖大量指令和 bgm 切换
+pub const LONG: Fixture = Fixture {
+    name: "long",
+    json: include_bytes!("../stories/long.json"),
+    expected: Some(Expected {
+        actions: 130,
+        talks: 120,
+        scenes: 2,
+        resources: 8,
+        errors: 0,
+    }),
+};
+
+/// 多章节故事, 覆盖字幕, 卡面, 移动和上传资源
+pub const BRANCHY: Fixture = Fixture {
+    name: "branchy",
+    json: include_bytes!("../stories/branchy.json"),
+    expected: Some(Expected {
+        actions: 18,
+        talks: 4,
+        scenes: 5,
+        resources: 6,
+        errors: 0,
+    }),
+};
+
+/// 缺少必要字段的脚本
+pub const MALFORMED: Fixture = Fixture {
+    name: "malformed",
+    json: include_bytes!("../stories/malformed.json"),
+    expected: None,
+};
+
+/// 全部测试故事
+pub const ALL: &[Fixture] = &[SHORT, LONG, BRANCHY, MALFORMED];
+
+/// Bestdori API 响应
+///
+/// 按接口的响应结构编写, 保留客户端未使用的字段以检验反序列化的容错; 文本为占位内容.
+pub mod api {
+    /// `events/all.5.json`
+    pub const EVENTS: &[u8] = include_bytes!("../api/events.json");
+    /// `bands/all.1.json`
+    pub const BANDS: &[u8] = include_bytes!("../api/bands.json");
+    /// `characters/all.2.json`
+    pub const CHARACTERS: &[u8] = include_bytes!("../api/characters.json");
+    /// `post/details?id=`, 社区故事帖子
+    pub const POST: &[u8] = include_bytes!("../api/post.json");
+    /// `events/1.json`, 含活动剧情列表
+    pub const EVENT_DETAIL: &[u8] = include_bytes!("../api/event-detail.json");
+    /// 活动 1 第 1 话的游戏剧情脚本 `Scenarioevent1-01.asset`
+    pub const SCENARIO: &[u8] = include_bytes!("../api/scenario.json");
+}
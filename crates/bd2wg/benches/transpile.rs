@@ -0,0 +1,51 @@
+//! 转译基准
+//!
+//! 以 bd2wg-fixtures 中的故事衡量解析, 转译和渲染场景的耗时, 运行 `cargo bench -p bd2wg`.
+
+use bd2wg::{
+    models::bestdori,
+    services::{resolver::Resolver, transpiler::Transpiler},
+    traits::transpile::Transpile,
+};
+use bd2wg_fixtures::{Fixture, LONG};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// 可解析的故事
+fn fixtures() -> impl Iterator<Item = Fixture> {
+    bd2wg_fixtures::ALL
+        .iter()
+        .copied()
+        .filter(|fixture| fixture.expected.is_some())
+}
+
+fn parse(c: &mut Criterion) {
+    for fixture in fixtures() {
+        c.bench_function(&format!("parse/{}", fixture.name), |b| {
+            b.iter(|| bestdori::Story::from_bytes(fixture.json).unwrap())
+        });
+    }
+}
+
+fn transpile(c: &mut Criterion) {
+    for fixture in fixtures() {
+        let story = bestdori::Story::from_bytes(fixture.json).unwrap();
+        c.bench_function(&format!("transpile/{}", fixture.name), |b| {
+            b.iter(|| Transpiler::<Resolver>::default().transpile(&story))
+        });
+    }
+}
+
+fn render(c: &mut Criterion) {
+    let story = bestdori::Story::from_bytes(LONG.json).unwrap();
+    let result = Transpiler::<Resolver>::default().transpile(&story);
+    c.bench_function("render/long", |b| {
+        b.iter(|| {
+            (result.story.iter())
+                .map(|scene| scene.to_string().len())
+                .sum::<usize>()
+        })
+    });
+}
+
+criterion_group!(benches, parse, transpile, render);
+criterion_main!(benches);
@@ -1,10 +1,20 @@
 //! bd2wg 错误处理
 
-use std::{io, path::PathBuf};
+use std::{
+    any::Any,
+    io,
+    path::{Component, Path, PathBuf},
+};
 
 use thiserror::Error;
 
-use crate::{models::bestdori, traits::resolve::ResourceType};
+use crate::{
+    models::{
+        bestdori,
+        webgal::{self, ParseError},
+    },
+    traits::resolve::ResourceType,
+};
 
 /// bd2wg 返回类型
 pub type Result<T> = std::result::Result<T, Error>;
@@ -20,6 +30,94 @@ pub enum Error {
 
     #[error("Transpile failed: {0}")]
     Transpile(#[from] TranspileError),
+
+    #[error("API request failed: {0}")]
+    Api(#[from] ApiError),
+
+    #[error("Validation failed: {0}")]
+    Validate(#[from] ValidateError),
+
+    #[error("Broken reference: {0}")]
+    Reference(#[from] ReferenceError),
+
+    #[error("Invalid header: {0}")]
+    Header(anyhow::Error),
+
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("Aborted after {0} errors")]
+    Aborted(usize),
+
+    #[error("Worker thread panicked: {0}")]
+    Panicked(String),
+}
+
+impl Error {
+    /// 由线程 panic 的负载创建错误
+    pub fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        Self::Panicked(panic_message(payload))
+    }
+
+    /// 错误类别名称, 资源解析失败单独归为 `resolve`
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Self::File(_) => "file",
+            Self::Download(_) => "download",
+            Self::Transpile(TranspileError {
+                error: TranspileErrorKind::Resolve(_),
+                ..
+            }) => "resolve",
+            Self::Transpile(_) => "transpile",
+            Self::Api(_) => "api",
+            Self::Validate(_) => "validate",
+            Self::Reference(_) => "reference",
+            Self::Header(_) => "header",
+            Self::Config(_) => "config",
+            Self::Cancelled => "cancelled",
+            Self::Aborted(_) => "aborted",
+            Self::Panicked(_) => "panicked",
+        }
+    }
+
+    /// 相关资源的类型, 无法确定时为 None
+    ///
+    /// 下载错误按保存路径中最靠后的资源目录判断.
+    pub fn resource_type(&self) -> Option<webgal::ResourceType> {
+        match self {
+            Self::Download(e) => e
+                .path
+                .components()
+                .rev()
+                .find_map(|component| match component {
+                    Component::Normal(dir) => webgal::ResourceType::from_dir(dir.to_str()?),
+                    _ => None,
+                }),
+            Self::Reference(e) => Path::new(&e.path)
+                .components()
+                .next()
+                .and_then(|dir| webgal::ResourceType::from_dir(dir.as_os_str().to_str()?)),
+            Self::Transpile(e) => match &e.error {
+                TranspileErrorKind::UninitFigure(_) => Some(webgal::ResourceType::Figure),
+                TranspileErrorKind::Resolve(e) => Some(e.kind.webgal()),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// 取出 panic 负载中的消息
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(
+            || String::from("unknown panic"),
+            |message| message.to_string(),
+        ),
+    }
 }
 
 /// 文件操作错误
@@ -85,6 +183,40 @@ pub enum DownloadErrorKind {
 
     #[error("File write failed: {0}")]
     Io(#[from] io::Error),
+
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+/// Bestdori API 请求错误
+#[derive(Debug, Error)]
+#[error("Bestdori API request failed: {url}: {error}")]
+pub struct ApiError {
+    pub url: String,
+    #[source]
+    pub error: DownloadErrorKind,
+}
+
+/// 场景校验错误
+///
+/// 生成的场景无法被重新解析, 通常意味着转义问题.
+#[derive(Debug, Error)]
+#[error("{scene}:{line}: {error}")]
+pub struct ValidateError {
+    pub scene: String,
+    pub line: usize,
+    #[source]
+    pub error: ParseError,
+}
+
+/// 场景引用的资源不存在
+#[derive(Debug, Error)]
+#[error("{scene}:{line}: {path} not found")]
+pub struct ReferenceError {
+    pub scene: String,
+    pub line: usize,
+    /// 相对游戏目录的路径
+    pub path: String,
 }
 
 /// 解析错误
@@ -106,12 +238,62 @@ pub struct TranspileError {
 
 #[derive(Debug, Error)]
 pub enum TranspileErrorKind {
-    #[error("Unknown command")]
-    Unknown,
-
     #[error("Uninitialized figure model called: {0}")]
     UninitFigure(u8),
 
     #[error("Resource resolve failed: {0}")]
     Resolve(#[from] ResolveError),
 }
+
+/// 转译警告
+///
+/// 指令已降级处理, 转译结果可能与原脚本有出入.
+#[derive(Debug, Error)]
+#[error("{kind}, action={action:?}")]
+pub struct Warning {
+    pub action: Box<bestdori::Action>,
+    pub kind: WarningKind,
+}
+
+#[derive(Debug, Error)]
+pub enum WarningKind {
+    #[error("Unknown command skipped")]
+    Unknown,
+
+    #[error("Text too long, split into {0} lines")]
+    LongText(usize),
+
+    #[error("Text contains {0} special characters")]
+    SpecialChar(usize),
+
+    #[error("Unsupported screen effect: {0}")]
+    UnsupportedEffect(String),
+
+    #[error("Costume {costume} not found, fell back to {fallback}")]
+    CostumeFallback { costume: String, fallback: String },
+}
+
+#[test]
+#[cfg(test)]
+fn test_error_classification() {
+    let download = Error::from(DownloadError::with_context(
+        "https://example.com/a.mp3",
+        "/tmp/project/game/bgm/a.mp3",
+        DownloadErrorKind::Cancelled,
+    ));
+    assert_eq!(download.kind_name(), "download");
+    assert_eq!(download.resource_type(), Some(webgal::ResourceType::Bgm));
+
+    let reference = Error::from(ReferenceError {
+        scene: String::from("scene/start.txt"),
+        line: 1,
+        path: String::from("figure/model/model.json"),
+    });
+    assert_eq!(reference.kind_name(), "reference");
+    assert_eq!(
+        reference.resource_type(),
+        Some(webgal::ResourceType::Figure)
+    );
+
+    assert_eq!(Error::Cancelled.resource_type(), None);
+}
@@ -10,3 +10,4 @@ pub mod traits;
 pub mod utils;
 
 pub use error::*;
+pub use services::convert::{Conversion, Options, Validation, convert, validate};
@@ -1,6 +1,9 @@
 //! bd2wg 数据模型
-//! 
+//!
 //! 脚本, 配置等数据模型的定义及相关 serde derive.
 
 pub mod bestdori;
+pub mod checkpoint;
+pub mod config;
+pub mod manifest;
 pub mod webgal;
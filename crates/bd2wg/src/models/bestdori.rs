@@ -1,11 +1,17 @@
 //! Bestdori 数据模型
 
 pub mod action;
+pub mod character;
 pub mod live2d;
+pub mod meta;
 pub mod resource;
+pub mod scenario;
 pub mod story;
 
 pub use action::*;
+pub use character::*;
 pub use live2d::*;
+pub use meta::*;
 pub use resource::*;
+pub use scenario::*;
 pub use story::*;
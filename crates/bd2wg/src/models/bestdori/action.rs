@@ -1,13 +1,14 @@
 //! Bestdori 脚本指令
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use serde_json::Value;
 
 use crate::models::webgal::FigureSide;
 
 use super::*;
 
 /// Bestdori 脚本指令
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Action {
     Talk(TalkAction),
@@ -15,8 +16,54 @@ pub enum Action {
     Effect(EffectAction),
     Layout(LayoutAction),
     Motion(MotionAction),
-    #[serde(other)]
-    Unknown,
+    Wait(WaitAction),
+    /// 未知指令, 保留原始 json
+    #[serde(untagged)]
+    Unknown(Value),
+}
+
+/// 已知指令的反序列化辅助
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum KnownAction {
+    Talk(TalkAction),
+    Sound(SoundAction),
+    Effect(EffectAction),
+    Layout(LayoutAction),
+    Motion(MotionAction),
+    #[serde(alias = "blank")]
+    Wait(WaitAction),
+}
+
+const KNOWN_ACTION_TYPES: &[&str] = &[
+    "talk", "sound", "effect", "layout", "motion", "wait", "blank",
+];
+
+impl<'de> Deserialize<'de> for Action {
+    /// 未知类型的指令保留为原始 json, 已知类型的指令仍严格解析
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+
+        let known = value
+            .get("type")
+            .and_then(Value::as_str)
+            .is_some_and(|kind| KNOWN_ACTION_TYPES.contains(&kind));
+
+        if !known {
+            return Ok(Self::Unknown(value));
+        }
+
+        Ok(
+            match KnownAction::deserialize(value).map_err(D::Error::custom)? {
+                KnownAction::Talk(a) => Self::Talk(a),
+                KnownAction::Sound(a) => Self::Sound(a),
+                KnownAction::Effect(a) => Self::Effect(a),
+                KnownAction::Layout(a) => Self::Layout(a),
+                KnownAction::Motion(a) => Self::Motion(a),
+                KnownAction::Wait(a) => Self::Wait(a),
+            },
+        )
+    }
 }
 
 impl Action {
@@ -27,7 +74,34 @@ impl Action {
             Self::Effect(a) => a.wait,
             Self::Layout(a) => a.wait,
             Self::Motion(a) => a.wait,
-            Self::Unknown => false,
+            Self::Wait(a) => a.wait,
+            Self::Unknown(v) => v.get("wait").and_then(Value::as_bool).unwrap_or(false),
+        }
+    }
+
+    /// 指令类型名称
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Self::Talk(_) => "talk",
+            Self::Sound(_) => "sound",
+            Self::Effect(_) => "effect",
+            Self::Layout(_) => "layout",
+            Self::Motion(_) => "motion",
+            Self::Wait(_) => "wait",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+
+    /// 执行前的延迟 (秒)
+    pub fn delay(&self) -> f32 {
+        match self {
+            Self::Talk(a) => a.delay,
+            Self::Sound(a) => a.delay,
+            Self::Effect(a) => a.delay,
+            Self::Layout(a) => a.motion.delay,
+            Self::Motion(a) => a.motion.delay,
+            Self::Wait(a) => a.delay,
+            Self::Unknown(_) => 0.,
         }
     }
 }
@@ -41,9 +115,23 @@ pub struct TalkAction {
     pub text: String,
     pub motions: Vec<Motion>,
     pub characters: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub voices: Vec<Voice>,
+}
+
+/// 对话语音
+///
+/// 推测的格式, 尚未与真实的 Bestdori 脚本核对; 仅在启用 `talk_voices` 时转译.
+/// 由游戏剧情脚本转换得到的活动剧情同样采用此格式.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Voice {
+    pub voice: Resource,
+    #[serde(default)]
+    pub volume: Option<f32>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SoundAction {
     pub wait: bool,
     pub delay: f32,
@@ -51,6 +139,15 @@ pub struct SoundAction {
     pub bgm: Option<Resource>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub se: Option<Resource>,
+    /// bgm 音量 (0-1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bgm_volume: Option<f32>,
+    /// se 音量 (0-1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub se_volume: Option<f32>,
+    /// 循环播放 se
+    #[serde(default)]
+    pub se_loop: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -71,6 +168,36 @@ pub enum Effect {
     BlackOut,
     WhiteIn,
     WhiteOut,
+    Snow,
+    Rain,
+    Sakura,
+    #[serde(alias = "shakeScreen", alias = "shakeWindow")]
+    Shake,
+    /// 停止全部画面特效
+    StopEffect,
+}
+
+impl Effect {
+    /// 画面特效名称 (若为画面特效)
+    pub fn screen_effect(&self) -> Option<&'static str> {
+        match self {
+            Self::Snow => Some("snow"),
+            Self::Rain => Some("rain"),
+            Self::Sakura => Some("sakura"),
+            _ => None,
+        }
+    }
+
+    /// 转场名称 (若为黑 / 白转场)
+    pub fn transition(&self) -> Option<&'static str> {
+        match self {
+            Self::BlackIn => Some("black_in"),
+            Self::BlackOut => Some("black_out"),
+            Self::WhiteIn => Some("white_in"),
+            Self::WhiteOut => Some("white_out"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -142,3 +269,25 @@ pub struct MotionAction {
     #[serde(flatten)]
     pub motion: Motion,
 }
+
+/// 停顿
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WaitAction {
+    #[serde(default)]
+    pub wait: bool,
+    #[serde(default)]
+    pub delay: f32,
+}
+
+#[test]
+#[cfg(test)]
+fn test_unknown_action() {
+    let json = serde_json::json!({"type": "shakeScreen", "wait": true, "power": 3});
+    let action: Action = serde_json::from_value(json.clone()).unwrap();
+
+    assert!(matches!(&action, Action::Unknown(v) if *v == json));
+    assert!(action.is_wait());
+
+    // 已知类型仍严格解析
+    assert!(serde_json::from_value::<Action>(serde_json::json!({"type": "talk"})).is_err());
+}
@@ -0,0 +1,68 @@
+//! Bestdori 角色表
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+/// 内置角色表
+const CHARACTERS_JSON: &[u8] = include_bytes!("../../../assets/characters.json");
+
+/// 角色信息
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Character {
+    /// 游戏内显示名
+    pub name: String,
+    /// 罗马音, 用于生成路径
+    pub romaji: String,
+    /// 登场时的默认动作
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<String>,
+    /// 登场时的默认表情
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+}
+
+/// 角色 id 到角色信息的映射
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct CharacterTable(pub HashMap<u8, Character>);
+
+impl CharacterTable {
+    pub fn from_slice(bytes: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(bytes)
+    }
+
+    /// 内置角色表
+    pub fn builtin() -> &'static Self {
+        static TABLE: OnceLock<CharacterTable> = OnceLock::new();
+        TABLE.get_or_init(|| Self::from_slice(CHARACTERS_JSON).unwrap())
+    }
+
+    pub fn get(&self, id: u8) -> Option<&Character> {
+        self.0.get(&id)
+    }
+
+    /// 将 `036_casual-2023` 形式的服装名改写为 `tomori_casual-2023`
+    ///
+    /// 无法识别角色时返回 None.
+    pub fn friendly_costume(&self, costume: &str) -> Option<String> {
+        let (id, rest) = costume.split_once('_')?;
+        let character = self.get(id.parse().ok()?)?;
+        Some(format!("{}_{rest}", character.romaji))
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_friendly_costume() {
+    let table = CharacterTable::builtin();
+
+    assert_eq!(
+        table.friendly_costume("039_casual-2023").as_deref(),
+        Some("soyo_casual-2023")
+    );
+    assert_eq!(table.friendly_costume("999_casual"), None);
+    assert_eq!(table.friendly_costume("general"), None);
+}
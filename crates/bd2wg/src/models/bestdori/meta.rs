@@ -0,0 +1,101 @@
+//! Bestdori 元数据
+
+use serde::{Deserialize, Serialize};
+
+/// Bestdori 服务器区域
+///
+/// 元数据中的多语言字段按此顺序排列.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Region {
+    #[default]
+    Jp,
+    En,
+    Tw,
+    Cn,
+    Kr,
+}
+
+impl Region {
+    /// 多语言字段中的下标
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// 资源路径中的区域名
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Jp => "jp",
+            Self::En => "en",
+            Self::Tw => "tw",
+            Self::Cn => "cn",
+            Self::Kr => "kr",
+        }
+    }
+}
+
+/// 多语言文本, 未上线的区域为 null
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Localized(pub Vec<Option<String>>);
+
+impl Localized {
+    /// 获取指定区域的文本
+    pub fn get(&self, region: Region) -> Option<&str> {
+        self.0.get(region.index())?.as_deref()
+    }
+
+    /// 获取指定区域的文本, 不存在时回退到任意已有区域
+    pub fn get_or_any(&self, region: Region) -> Option<&str> {
+        self.get(region)
+            .or_else(|| self.0.iter().flatten().next().map(String::as_str))
+    }
+}
+
+/// 活动元数据
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventMeta {
+    pub event_name: Localized,
+    #[serde(default)]
+    pub event_type: Option<String>,
+}
+
+/// 活动详情, 含活动剧情列表
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventDetail {
+    pub event_name: Localized,
+    #[serde(default)]
+    pub stories: Vec<EventStory>,
+}
+
+/// 活动剧情
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventStory {
+    pub scenario_id: String,
+    #[serde(default)]
+    pub title: Localized,
+    #[serde(default)]
+    pub synopsis: Localized,
+}
+
+/// 乐队元数据
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandMeta {
+    pub band_name: Localized,
+}
+
+/// 角色元数据
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterMeta {
+    pub character_name: Localized,
+    #[serde(default)]
+    pub first_name: Localized,
+    #[serde(default)]
+    pub band_id: Option<u32>,
+}
@@ -5,6 +5,11 @@ use serde::{Deserialize, Serialize};
 /// Bestdori 资源入口链接
 pub const BESTDORI_ASSET_URL_ROOT: &str = "https://bestdori.com/assets/jp/";
 
+/// 指定区域的 Bestdori 资源入口链接
+pub fn asset_url_root(region: super::Region) -> String {
+    format!("https://bestdori.com/assets/{}/", region.as_str())
+}
+
 pub const BESTDORI_ASSET_URL_BGM: &str = "https://bestdori.com/assets/jp/sound/scenario/bgm/";
 pub const BESTDORI_ASSET_URL_SE: &str = "https://bestdori.com/res/CommonSE/";
 
@@ -45,6 +50,16 @@ pub struct Resource {
     pub path: ResourcePath,
 }
 
+impl Resource {
+    /// 是否为空资源 (文件名或链接为空), 用于表示停止播放
+    pub fn is_empty(&self) -> bool {
+        match &self.path {
+            ResourcePath::Url { url } => url.trim().is_empty(),
+            ResourcePath::File { file, .. } => file.trim().is_empty(),
+        }
+    }
+}
+
 #[test]
 #[cfg(test)]
 fn test_resource_serialize() {
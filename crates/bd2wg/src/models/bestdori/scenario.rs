@@ -0,0 +1,355 @@
+//! 游戏剧情脚本
+//!
+//! 游戏数据包中的剧情 (如活动剧情) 与 Bestdori 编辑器脚本格式不同,
+//! 此处将其转换为编辑器脚本指令. 枚举取值参照 Bestdori 故事查看器, 未知取值保留为未知指令.
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::*;
+
+/// 游戏剧情脚本, 对应 `Scenario*.asset` 的 `Base` 字段
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Scenario {
+    pub first_background: String,
+    pub first_background_bundle_name: String,
+    pub first_bgm: String,
+    pub snippets: Vec<Snippet>,
+    pub talk_data: Vec<TalkData>,
+    pub layout_data: Vec<LayoutData>,
+    pub special_effect_data: Vec<SpecialEffectData>,
+    pub sound_data: Vec<SoundData>,
+}
+
+/// 指令片段, 按类型引用对应数据表中的一项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Snippet {
+    pub action_type: u8,
+    pub progress_type: u8,
+    pub reference_index: usize,
+    pub delay: f32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TalkData {
+    pub talk_characters: Vec<TalkCharacter>,
+    pub window_display_name: String,
+    pub body: String,
+    pub motions: Vec<TalkMotion>,
+    pub voices: Vec<TalkVoice>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TalkCharacter {
+    pub character_id: u8,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TalkMotion {
+    pub character_id: u8,
+    pub motion_name: String,
+    pub expression_name: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TalkVoice {
+    pub character_id: u8,
+    pub voice_id: String,
+    pub volume: f32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LayoutData {
+    #[serde(rename = "type")]
+    pub kind: u8,
+    pub side_from: u8,
+    pub side_from_offset_x: i16,
+    pub side_to: u8,
+    pub side_to_offset_x: i16,
+    pub character_id: u8,
+    pub costume_type: String,
+    pub motion_name: String,
+    pub expression_name: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpecialEffectData {
+    pub effect_type: u8,
+    pub string_val: String,
+    pub string_val_sub: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SoundData {
+    pub bgm: String,
+    pub se: String,
+    pub volume: f32,
+    pub se_bundle_name: String,
+}
+
+/// 数据包内的资源, bundle 为空时不指定
+fn bundle_resource(file: &str, bundle: &str) -> Resource {
+    Resource {
+        kind: ResourceType::Bandori,
+        path: ResourcePath::File {
+            file: file.to_string(),
+            bundle: (!bundle.is_empty()).then(|| bundle.to_string()),
+        },
+    }
+}
+
+fn layout_side(side: u8) -> LayoutSideType {
+    match side {
+        1 => LayoutSideType::LeftOver,
+        2 => LayoutSideType::LeftInside,
+        4 => LayoutSideType::RightInside,
+        5 => LayoutSideType::RightOver,
+        _ => LayoutSideType::Center,
+    }
+}
+
+impl Scenario {
+    /// 转换为编辑器脚本 JSON
+    ///
+    /// 语音位于数据包 `voice_bundle` 中, 如 `sound/voice/scenario/eventstory1_0`.
+    pub fn to_story_value(&self, voice_bundle: &str, meta: &StoryMeta) -> Value {
+        let mut story = json!({ "actions": self.actions(voice_bundle) });
+
+        if !self.first_bgm.is_empty() {
+            story["bgm"] = json!(bundle_resource(&self.first_bgm, ""));
+        }
+        if !self.first_background.is_empty() {
+            story["background"] = json!(bundle_resource(
+                &self.first_background,
+                &self.first_background_bundle_name
+            ));
+        }
+        if let Some(title) = &meta.title {
+            story["title"] = json!(title);
+        }
+        if let Some(description) = &meta.description {
+            story["summary"] = json!(description);
+        }
+
+        story
+    }
+
+    /// 转换为编辑器脚本指令, 引用越界的片段被忽略
+    pub fn actions(&self, voice_bundle: &str) -> Vec<Action> {
+        self.snippets
+            .iter()
+            .filter_map(|snippet| self.action(snippet, voice_bundle))
+            .collect()
+    }
+
+    fn action(&self, snippet: &Snippet, voice_bundle: &str) -> Option<Action> {
+        let Snippet {
+            action_type,
+            progress_type,
+            reference_index: index,
+            delay,
+        } = *snippet;
+        let wait = progress_type == 0;
+
+        Some(match action_type {
+            // 对话
+            1 => {
+                let talk = self.talk_data.get(index)?;
+                Action::Talk(TalkAction {
+                    wait,
+                    delay,
+                    name: talk.window_display_name.clone(),
+                    text: talk.body.clone(),
+                    motions: talk
+                        .motions
+                        .iter()
+                        .map(|m| Motion {
+                            delay: 0.,
+                            character: m.character_id,
+                            motion: m.motion_name.clone(),
+                            expression: m.expression_name.clone(),
+                        })
+                        .collect(),
+                    characters: talk
+                        .talk_characters
+                        .iter()
+                        .map(|c| c.character_id)
+                        .collect(),
+                    voices: talk
+                        .voices
+                        .iter()
+                        .filter(|v| !v.voice_id.is_empty())
+                        .map(|v| Voice {
+                            voice: bundle_resource(&v.voice_id, voice_bundle),
+                            volume: Some(v.volume),
+                        })
+                        .collect(),
+                })
+            }
+
+            // 立绘布局
+            2 | 4 => {
+                let layout = self.layout_data.get(index)?;
+                let motion = Motion {
+                    delay,
+                    character: layout.character_id,
+                    motion: layout.motion_name.clone(),
+                    expression: layout.expression_name.clone(),
+                };
+                let kind = match layout.kind {
+                    _ if action_type == 4 => None,
+                    1 => Some(LayoutType::Move),
+                    2 => Some(LayoutType::Appear),
+                    3 => Some(LayoutType::Hide),
+                    kind => {
+                        return Some(Action::Unknown(
+                            json!({ "type": "scenarioLayout", "layoutType": kind, "wait": wait }),
+                        ));
+                    }
+                };
+
+                match kind {
+                    Some(kind) => Action::Layout(LayoutAction {
+                        wait,
+                        kind,
+                        model: layout.costume_type.clone(),
+                        motion,
+                        side: LayoutSide {
+                            from: layout_side(layout.side_from),
+                            to: layout_side(layout.side_to),
+                            from_x: layout.side_from_offset_x,
+                            to_x: layout.side_to_offset_x,
+                        },
+                    }),
+                    None => Action::Motion(MotionAction {
+                        wait,
+                        model: layout.costume_type.clone(),
+                        motion,
+                    }),
+                }
+            }
+
+            // 特效
+            6 => {
+                let data = self.special_effect_data.get(index)?;
+                let effect = match data.effect_type {
+                    1 => Effect::BlackIn,
+                    2 => Effect::BlackOut,
+                    3 => Effect::WhiteIn,
+                    4 => Effect::WhiteOut,
+                    5 | 6 => Effect::Shake,
+                    7 => Effect::ChangeBackground {
+                        image: bundle_resource(&data.string_val, &data.string_val_sub),
+                    },
+                    8 => Effect::Telop {
+                        text: data.string_val.clone(),
+                    },
+                    kind => {
+                        return Some(Action::Unknown(
+                            json!({ "type": "scenarioEffect", "effectType": kind, "wait": wait }),
+                        ));
+                    }
+                };
+                Action::Effect(EffectAction {
+                    wait,
+                    delay,
+                    effect,
+                })
+            }
+
+            // 音频
+            7 => {
+                let data = self.sound_data.get(index)?;
+                let bgm = (!data.bgm.is_empty()).then(|| bundle_resource(&data.bgm, ""));
+                let se = (!data.se.is_empty()).then(|| match data.se_bundle_name.is_empty() {
+                    true => Resource {
+                        kind: ResourceType::Common,
+                        path: ResourcePath::File {
+                            file: data.se.clone(),
+                            bundle: None,
+                        },
+                    },
+                    false => bundle_resource(&data.se, &data.se_bundle_name),
+                });
+                if bgm.is_none() && se.is_none() {
+                    return None;
+                }
+
+                Action::Sound(SoundAction {
+                    wait,
+                    delay,
+                    bgm_volume: bgm.as_ref().map(|_| data.volume),
+                    se_volume: se.as_ref().map(|_| data.volume),
+                    bgm,
+                    se,
+                    se_loop: false,
+                })
+            }
+
+            kind => Action::Unknown(
+                json!({ "type": "scenarioAction", "actionType": kind, "wait": wait }),
+            ),
+        })
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_scenario_to_story() {
+    let scenario: Scenario = serde_json::from_value(json!({
+        "firstBackground": "bg00012",
+        "firstBackgroundBundleName": "bg/scenario0",
+        "firstBgm": "bgm024",
+        "snippets": [
+            {"actionType": 2, "progressType": 1, "referenceIndex": 0, "delay": 0.0},
+            {"actionType": 1, "progressType": 0, "referenceIndex": 0, "delay": 0.0},
+            {"actionType": 6, "progressType": 0, "referenceIndex": 0, "delay": 0.5},
+            {"actionType": 1, "progressType": 0, "referenceIndex": 9, "delay": 0.0}
+        ],
+        "talkData": [{
+            "talkCharacters": [{"characterId": 1}],
+            "windowDisplayName": "香澄",
+            "body": "キラキラドキドキ！",
+            "motions": [{"characterId": 1, "motionName": "smile01", "expressionName": "smile01"}],
+            "voices": [{"characterId": 1, "voiceId": "event1-01-001", "volume": 1.0}]
+        }],
+        "layoutData": [{
+            "type": 2, "sideFrom": 3, "sideTo": 3, "characterId": 1,
+            "costumeType": "001_casual-2023", "motionName": "idle01", "expressionName": "default"
+        }],
+        "specialEffectData": [{"effectType": 99}]
+    }))
+    .unwrap();
+
+    let value = scenario.to_story_value(
+        "sound/voice/scenario/eventstory1_0",
+        &StoryMeta {
+            title: Some(String::from("第1話")),
+            description: None,
+        },
+    );
+    let story = Story::from_bytes(&serde_json::to_vec(&value).unwrap()).unwrap();
+
+    // 初始 bgm, 背景 + 布局, 对话, 未知特效; 越界的对话被忽略
+    assert_eq!(story.actions.len(), 5);
+    assert_eq!(story.meta().title.as_deref(), Some("第1話"));
+    assert!(matches!(
+        &story.actions[2],
+        Action::Layout(LayoutAction { kind: LayoutType::Appear, model, .. }) if model == "001_casual-2023"
+    ));
+    assert!(matches!(
+        &story.actions[3],
+        Action::Talk(TalkAction { voices, wait: true, .. })
+            if voices[0].voice == bundle_resource("event1-01-001", "sound/voice/scenario/eventstory1_0")
+    ));
+    assert!(matches!(&story.actions[4], Action::Unknown(v) if v["effectType"] == 99));
+}
@@ -2,23 +2,55 @@
 
 use serde::Deserialize;
 
-use crate::impl_iter_for_tuple;
-
 use super::*;
 
 /// Bestdori 故事脚本
 ///
-/// 请使用 Self::from_slice 方法经由中间结构体反序列化.
-pub struct Story(pub Vec<Action>);
+/// 请使用 Self::from_bytes 方法经由中间结构体反序列化, 或以 Self::new 直接构造.
+pub struct Story {
+    pub actions: Vec<Action>,
+    prelude: usize, // 推入的初始 bgm, background 指令数
+    meta: StoryMeta,
+}
 
-impl_iter_for_tuple! {Story, Action}
+/// 故事元数据
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct StoryMeta {
+    pub title: Option<String>,
+    #[serde(alias = "summary")]
+    pub description: Option<String>,
+}
 
 impl Story {
+    /// 由指令序列构造, 不含推入的初始指令
+    pub fn new(actions: Vec<Action>, meta: StoryMeta) -> Self {
+        Self {
+            actions,
+            prelude: 0,
+            meta,
+        }
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> serde_json::Result<Self> {
         let helper: StoryHelper = serde_json::from_slice(bytes)?;
         Ok(helper.into())
     }
 
+    /// 脚本元数据
+    pub fn meta(&self) -> &StoryMeta {
+        &self.meta
+    }
+
+    /// 指令在原始脚本 actions 中的下标, 初始 bgm, background 指令返回 None
+    pub fn source_index(&self, index: usize) -> Option<usize> {
+        index.checked_sub(self.prelude)
+    }
+
+    /// 枚举指令
+    pub fn iter(&self) -> impl Iterator<Item = &Action> {
+        self.actions.iter()
+    }
+
     /// 迭代, 每次提供下一项的 wait
     pub fn iter_with_wait(&self) -> impl Iterator<Item = (&Action, bool)> {
         self.iter().zip(
@@ -32,6 +64,8 @@ impl Story {
 
 #[derive(Debug, Clone, Deserialize)]
 struct StoryHelper {
+    #[serde(flatten)]
+    meta: StoryMeta,
     bgm: Option<Resource>,
     background: Option<Resource>,
     actions: Vec<Action>,
@@ -40,6 +74,7 @@ struct StoryHelper {
 impl From<StoryHelper> for Story {
     fn from(val: StoryHelper) -> Self {
         let StoryHelper {
+            meta,
             bgm,
             background,
             mut actions,
@@ -54,6 +89,9 @@ impl From<StoryHelper> for Story {
                 delay: 0.,
                 bgm: Some(res),
                 se: None,
+                bgm_volume: None,
+                se_volume: None,
+                se_loop: false,
             }));
         }
 
@@ -65,7 +103,21 @@ impl From<StoryHelper> for Story {
             }));
         }
 
+        let prelude = story.len();
         story.append(&mut actions);
-        Self(story)
+        Self {
+            actions: story,
+            prelude,
+            meta,
+        }
+    }
+}
+
+impl IntoIterator for Story {
+    type Item = Action;
+    type IntoIter = std::vec::IntoIter<Action>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.actions.into_iter()
     }
 }
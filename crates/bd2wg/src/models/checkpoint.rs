@@ -0,0 +1,95 @@
+//! 断点记录
+//!
+//! 记录管线进度, 中断后可跳过已完成的工作继续执行.
+
+use std::{
+    io,
+    path::{self, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{config::PipelineOptions, webgal::Resource},
+    services::output::FsSink,
+    traits::output::OutputSink,
+};
+
+/// 断点文件名, 位于游戏目录下
+pub const CHECKPOINT_FILE: &str = "bd2wg-checkpoint.json";
+
+/// 中断时所处的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum CheckpointStage {
+    /// 正在写入场景, 需重新转译
+    Transpile,
+    /// 场景已写入, 仅需下载剩余资源
+    Download,
+}
+
+/// 断点记录
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Checkpoint {
+    /// Bestdori 脚本的绝对路径, 以便在其他工作目录下继续
+    pub story: PathBuf,
+    /// 管线选项, 不含请求头
+    pub options: PipelineOptions,
+    pub stage: CheckpointStage,
+    /// 尚未下载成功的资源
+    #[serde(default)]
+    pub pending: Vec<Resource>,
+}
+
+impl Checkpoint {
+    /// 转译阶段的断点, 管线选项不含请求头
+    ///
+    /// 脚本路径转为绝对路径, 失败时保留原路径.
+    pub fn new(story: &Path, options: &PipelineOptions) -> Self {
+        Self {
+            story: path::absolute(story).unwrap_or_else(|_| story.to_path_buf()),
+            options: PipelineOptions {
+                header: Default::default(),
+                ..options.clone()
+            },
+            stage: CheckpointStage::Transpile,
+            pending: Vec::new(),
+        }
+    }
+
+    /// 读取游戏目录下的断点
+    pub fn load(root: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        Self::load_from(&FsSink, root)
+    }
+
+    /// 从输出目标读取断点
+    pub fn load_from(sink: &dyn OutputSink, root: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        sink.read(&root.as_ref().join(CHECKPOINT_FILE))?
+            .map(|bytes| serde_json::from_slice(&bytes).map_err(io::Error::other))
+            .transpose()
+    }
+
+    /// 写入输出目标
+    pub fn save_to(&self, sink: &dyn OutputSink, root: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        sink.write(&root.as_ref().join(CHECKPOINT_FILE), &json)
+    }
+
+    /// 完成后删除断点
+    pub fn remove_from(sink: &dyn OutputSink, root: impl AsRef<Path>) -> io::Result<()> {
+        sink.remove(&root.as_ref().join(CHECKPOINT_FILE))
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_checkpoint_absolute_story() {
+    let checkpoint = Checkpoint::new(Path::new("story.json"), &PipelineOptions::default());
+
+    assert!(checkpoint.story.is_absolute());
+    assert!(checkpoint.story.ends_with("story.json"));
+    assert!(checkpoint.options.header.is_empty());
+    assert_eq!(checkpoint.stage, CheckpointStage::Transpile);
+}
@@ -0,0 +1,802 @@
+//! bd2wg 配置
+
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+use reqwest::header::HeaderMap;
+
+use crate::traits::{hook::SceneHook, recover::Recover};
+
+use super::{
+    bestdori::{CharacterTable, Region},
+    webgal::{EngineVersion, START_SCENE_PATH},
+};
+
+/// 元数据缓存的默认有效期 (秒)
+pub const DEFAULT_API_CACHE_TTL: u64 = 24 * 60 * 60;
+
+/// Bestdori API 默认最小请求间隔 (毫秒)
+pub const DEFAULT_API_REQUEST_INTERVAL: u64 = 1000;
+
+/// 默认下载线程数
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 4;
+
+/// 单个下载任务的默认最大尝试次数
+pub const DEFAULT_DOWNLOAD_RETRIES: usize = 3;
+
+/// 单个下载任务的默认时间限制 (秒), 重试时倍增
+pub const DEFAULT_DOWNLOAD_TIMEOUT: u64 = 24;
+
+/// 人物登场时注入的默认动作
+pub const DEFAULT_FIGURE_MOTION: &str = "idle01";
+
+/// 资源文件命名策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum NamingStrategy {
+    /// 将完整 url 转写为文件名
+    #[default]
+    Flatten,
+    /// 保留原始文件名, 追加短哈希以保证唯一
+    Readable,
+}
+
+/// 对话中 emoji / 控制字符的处理方式
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SpecialCharPolicy {
+    /// 保留原样
+    #[default]
+    Keep,
+    /// 直接移除
+    Strip,
+    /// 替换为指定文本
+    Replace(String),
+}
+
+/// 默认场景文件名模板
+pub const DEFAULT_SCENE_TEMPLATE: &str = "scene-${n}.txt";
+
+/// 人物移动动画的默认时长 (毫秒)
+pub const DEFAULT_MOVE_DURATION: u32 = 500;
+
+/// 高亮说话人时, 其余在场人物的默认亮度
+pub const DEFAULT_DIMMED_BRIGHTNESS: f32 = 0.6;
+
+/// 默认画面特效映射, Bestdori 特效 -> WebGAL pixi 特效
+pub const DEFAULT_PIXI_EFFECTS: &[(&str, &str)] = &[
+    ("snow", "snow"),
+    ("rain", "rain"),
+    ("sakura", "cherryBlossoms"),
+];
+
+/// 转场指令没有延迟时的默认时长 (毫秒)
+pub const DEFAULT_TRANSITION_DURATION: u32 = 500;
+
+/// 转场时临时切换的纯色背景
+///
+/// 白色背景不存在时由管线生成.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct TransitionBackdrop {
+    /// 黑色背景, 为空时清除背景 (舞台默认为黑色)
+    pub black: Option<String>,
+    /// 白色背景
+    pub white: String,
+}
+
+impl Default for TransitionBackdrop {
+    fn default() -> Self {
+        Self {
+            black: None,
+            white: String::from("white.png"),
+        }
+    }
+}
+
+/// 对话中多个动作的执行顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MotionOrder {
+    /// 按原顺序在对话前立即执行
+    #[default]
+    Immediate,
+    /// 按延迟排序后在对话前执行
+    Sorted,
+    /// 按延迟排序, 并在动作间插入等待
+    Interleave,
+}
+
+/// 字幕 (telop) 的呈现方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TelopStyle {
+    /// 黑屏独白 (`intro`)
+    Intro,
+    /// 单选项分支, 并切换到新场景
+    #[default]
+    Choose,
+    /// 无说话人的旁白
+    Subtitle,
+}
+
+/// 故事结束时的处理
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StoryEnd {
+    /// 不做处理
+    #[default]
+    None,
+    /// 结束游戏并返回标题 (`end`)
+    End,
+    /// 切换到指定场景
+    ChangeScene(String),
+}
+
+/// 对话文本的正则替换规则
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TextReplacement {
+    pub pattern: String,
+    /// 替换文本, 支持 `$1` 形式的捕获组引用
+    pub replace: String,
+}
+
+/// 人物变换修正
+///
+/// Bestdori 与 WebGAL 的模型缩放 / 锚点不一致, 部分角色可能显示不全.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct FigureTransform {
+    /// 缩放比例
+    pub scale: Option<f32>,
+    /// 纵向偏移
+    pub y: Option<i16>,
+}
+
+/// 场景拆分策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SceneSplit {
+    /// 在字幕处拆分
+    #[default]
+    Telop,
+    /// 每 N 条 Bestdori 指令拆分
+    Every(usize),
+    /// 在黑屏 / 白屏入场处拆分
+    Chapter,
+    /// 不拆分
+    ///
+    /// 以单选项分支呈现的字幕仍会切换场景.
+    Single,
+}
+
+/// BGM 数据包名称的大小写规则
+///
+/// Bestdori 上 BGM 数据包名称通常为文件名首字母小写, 少数数据包例外.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BundleCase {
+    /// 保持文件名原样
+    Keep,
+    /// 首个字母变为小写
+    #[default]
+    FirstLower,
+    /// 正则替换, `replace` 支持 `$1` 形式的捕获组引用
+    Regex { pattern: String, replace: String },
+}
+
+/// 模型重定向
+///
+/// 将服装替换为另一服装, 并转换动作 / 表情名称.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ModelRedirect {
+    /// 目标服装, 为空时沿用原服装
+    pub costume: Option<String>,
+    /// 动作名称映射
+    pub motions: HashMap<String, String>,
+    /// 表情名称映射
+    pub expressions: HashMap<String, String>,
+}
+
+/// 资源解析器配置
+#[derive(Debug, Clone, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct ResolverConfig {
+    /// 上传资源的文件命名策略
+    pub naming: NamingStrategy,
+    /// 由 BGM 文件名推断数据包名称时的大小写规则
+    pub bgm_bundle_case: BundleCase,
+    /// 以角色名代替模型路径中的角色 id
+    pub friendly_model_path: bool,
+    /// 静态立绘服装, 服装名 -> 图片链接
+    ///
+    /// 服装名本身为图片链接时自动视为静态立绘.
+    pub sprites: HashMap<String, String>,
+    /// 服装不存在时回退到角色默认服装, 管线以 HEAD 请求探测 (试运行时不探测)
+    pub costume_fallback: bool,
+    /// 角色默认服装, 角色 id -> 服装名, 未配置时采用 `NNN_general`
+    pub default_costumes: HashMap<u8, String>,
+    /// 模型重定向, 服装名 -> 重定向规则
+    pub model_redirects: HashMap<String, ModelRedirect>,
+    /// 角色表, 为空时采用内置角色表, 转译器经由解析器使用同一角色表
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub characters: Option<CharacterTable>,
+    /// 数据包资源所在的服务器区域
+    pub region: Region,
+    /// 数据包资源下载失败时依次尝试的其他区域
+    pub retry_regions: Vec<Region>,
+    /// 资源下载失败时尝试其他扩展名
+    pub guess_extension: bool,
+}
+
+impl ResolverConfig {
+    /// 获取角色表
+    pub fn characters(&self) -> &CharacterTable {
+        self.characters
+            .as_ref()
+            .unwrap_or_else(|| CharacterTable::builtin())
+    }
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            naming: NamingStrategy::default(),
+            bgm_bundle_case: BundleCase::default(),
+            friendly_model_path: false,
+            sprites: HashMap::new(),
+            costume_fallback: true,
+            default_costumes: HashMap::new(),
+            model_redirects: HashMap::new(),
+            characters: None,
+            region: Region::default(),
+            retry_regions: Vec::new(),
+            guess_extension: false,
+        }
+    }
+}
+
+/// 转译器配置
+#[derive(Debug, Clone, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default, build_fn(validate = "Self::validate"))]
+#[serde(default)]
+pub struct TranspilerConfig {
+    /// 单条对话的最大字符数, 超出时拆分为多条连续对话, 为空时不拆分
+    pub max_text_length: Option<usize>,
+    /// 说话人为空时根据角色 id 填充
+    pub fill_speaker_name: bool,
+    /// 说话人名称映射, 原名称 -> 显示名称
+    pub speaker_names: HashMap<String, String>,
+    /// 说话人小头像, 角色 id -> figure 目录下的图片路径, 为空时不生成
+    pub mini_avatars: HashMap<u8, String>,
+    /// 为对话添加语音 (`-vocal`), 实验性
+    ///
+    /// 对话的 `voices` 字段为推测的格式, 尚未与真实的 Bestdori 脚本核对, 故默认关闭.
+    pub talk_voices: bool,
+    /// 对话中 emoji / 控制字符的处理方式
+    pub special_chars: SpecialCharPolicy,
+    /// 转换注音与角色名标记
+    pub markup: bool,
+    /// 将换行转换为 WebGAL 换行, 并按此宽度 (字符数) 折行
+    pub line_width: Option<usize>,
+    /// 对话文本的正则替换规则, 依次执行 (无效的正则将被忽略)
+    pub text_replacements: Vec<TextReplacement>,
+    /// 人物登场时动作为空, 注入默认动作 (优先采用角色表中的预设)
+    pub inject_default_motion: bool,
+    /// 注入的默认动作
+    pub default_motion: String,
+    /// 将指令的 delay 字段转译为等待 (wait 指令始终转译)
+    pub honor_delay: bool,
+    /// 对话中多个动作的执行顺序
+    pub motion_order: MotionOrder,
+    /// 以变换动画呈现人物移动 (同侧移动时), 时长取自 delay 字段
+    pub animate_move: bool,
+    /// delay 为 0 时移动动画的时长 (毫秒)
+    pub move_duration: u32,
+    /// 高亮全部说话人 (多人同时说话时不止首个角色), 其余在场人物调暗
+    pub highlight_speakers: bool,
+    /// 未说话人物的亮度 (0-1)
+    pub dimmed_brightness: f32,
+    /// 字幕的呈现方式
+    pub telop_style: TelopStyle,
+    /// 场景拆分策略
+    pub scene_split: SceneSplit,
+    /// 为人物输出层级 (`-zIndex`), 同一位置上后到者在上, 需要 4.5 及以上版本
+    pub figure_layering: bool,
+    /// 全部人物的变换修正
+    pub figure_transform: FigureTransform,
+    /// 各角色的变换修正, 角色 id -> 修正, 优先于全局修正
+    pub character_transforms: HashMap<u8, FigureTransform>,
+    /// 停止 bgm 时的淡出时长 (毫秒)
+    pub bgm_fade: Option<u32>,
+    /// 故事结束时停止 bgm
+    pub stop_bgm_at_end: bool,
+    /// 故事结束时的处理
+    pub story_end: StoryEnd,
+    /// 在每条指令转译结果前插入注释, 标明其在原始脚本中的下标和类型
+    pub trace_comments: bool,
+    /// 将未知指令的原始 json 写为注释 (仍记录警告)
+    pub unknown_as_comment: bool,
+    /// 画面特效映射, Bestdori 特效 -> WebGAL pixi 特效
+    pub pixi_effects: HashMap<String, String>,
+    /// 转场动画映射, Bestdori 转场 (如 `black_in`) -> WebGAL 动画, 映射的转场不再按颜色处理
+    pub transition_animations: HashMap<String, String>,
+    /// 转场时临时切换的纯色背景
+    ///
+    /// 为空时黑色转场渐隐 / 渐显整个舞台, 白色转场切换到默认的白色背景.
+    pub transition_backdrop: Option<TransitionBackdrop>,
+    /// 场景文件名模板 (不含初始场景), 可用变量: `${story}` 故事标识, `${n}` 场景序号
+    pub scene_template: String,
+    /// 故事标识, 用于场景文件名模板
+    pub story_slug: String,
+    /// 目标 WebGAL 版本, 场景按其语法渲染: 不支持的参数被省略, 不支持的命令改为注释
+    pub engine: EngineVersion,
+}
+
+impl TranspilerConfig {
+    /// 检查场景文件名模板: 必须包含 `${n}`, 且不能生成初始场景 `start.txt`
+    pub fn check_scene_template(&self) -> Result<(), String> {
+        check_scene_template(&self.scene_template, &self.story_slug)
+    }
+}
+
+impl TranspilerConfigBuilder {
+    fn validate(&self) -> Result<(), String> {
+        let default = TranspilerConfig::default();
+        check_scene_template(
+            self.scene_template
+                .as_ref()
+                .unwrap_or(&default.scene_template),
+            self.story_slug.as_ref().unwrap_or(&default.story_slug),
+        )
+    }
+}
+
+fn check_scene_template(template: &str, slug: &str) -> Result<(), String> {
+    if !template.contains("${n}") {
+        return Err(format!(
+            "scene template `{template}` does not contain `${{n}}`"
+        ));
+    }
+
+    let name = crate::utils::render_template(template, &[("story", slug), ("n", "1")]);
+    if name.trim_start_matches("./") == START_SCENE_PATH {
+        return Err(format!(
+            "scene template `{template}` produces `{START_SCENE_PATH}`"
+        ));
+    }
+
+    Ok(())
+}
+
+impl Default for TranspilerConfig {
+    fn default() -> Self {
+        Self {
+            max_text_length: None,
+            fill_speaker_name: true,
+            speaker_names: HashMap::new(),
+            mini_avatars: HashMap::new(),
+            talk_voices: false,
+            special_chars: SpecialCharPolicy::default(),
+            markup: false,
+            line_width: None,
+            text_replacements: Vec::new(),
+            inject_default_motion: false,
+            default_motion: DEFAULT_FIGURE_MOTION.to_string(),
+            honor_delay: false,
+            motion_order: MotionOrder::default(),
+            animate_move: true,
+            move_duration: DEFAULT_MOVE_DURATION,
+            highlight_speakers: false,
+            dimmed_brightness: DEFAULT_DIMMED_BRIGHTNESS,
+            telop_style: TelopStyle::default(),
+            scene_split: SceneSplit::default(),
+            figure_layering: true,
+            figure_transform: FigureTransform::default(),
+            character_transforms: HashMap::new(),
+            bgm_fade: None,
+            stop_bgm_at_end: false,
+            story_end: StoryEnd::default(),
+            trace_comments: false,
+            unknown_as_comment: false,
+            pixi_effects: DEFAULT_PIXI_EFFECTS
+                .iter()
+                .map(|&(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            transition_animations: HashMap::new(),
+            transition_backdrop: None,
+            scene_template: DEFAULT_SCENE_TEMPLATE.to_string(),
+            story_slug: String::from("story"),
+            engine: EngineVersion::default(),
+        }
+    }
+}
+
+/// Bestdori API 客户端配置
+#[derive(Debug, Clone, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// 响应缓存目录, 为空时不缓存
+    pub cache_dir: Option<PathBuf>,
+    /// 缓存有效期 (秒)
+    pub cache_ttl: u64,
+    /// 相邻两次请求的最小间隔 (毫秒)
+    pub request_interval: u64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            cache_dir: None,
+            cache_ttl: DEFAULT_API_CACHE_TTL,
+            request_interval: DEFAULT_API_REQUEST_INTERVAL,
+        }
+    }
+}
+
+/// WebGAL 工程配置, 用于生成完整工程骨架
+#[derive(Debug, Clone, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// 游戏标题, 为空时采用故事标题
+    pub title: Option<String>,
+    /// 游戏简介, 为空时采用故事简介
+    pub description: Option<String>,
+    /// 存档隔离用的游戏标识, 为空时由标题生成
+    pub key: Option<String>,
+    /// 以故事的初始背景和 bgm 作为标题画面
+    pub title_from_story: bool,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            description: None,
+            key: None,
+            title_from_story: true,
+        }
+    }
+}
+
+/// 场景文件已存在且内容不同时的处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// 覆盖
+    #[default]
+    Overwrite,
+    /// 保留已有文件 (可能经过手动修改)
+    Keep,
+    /// 以上次生成的副本为基准三方合并, 冲突时另存为 `.new` 文件
+    Merge,
+}
+
+/// 换行符
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Newline {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// 文本文件格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct TextFormat {
+    pub newline: Newline,
+    /// 写入 UTF-8 BOM
+    pub bom: bool,
+}
+
+/// 输出目标
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExportTarget {
+    /// 直接写入输出目录
+    #[default]
+    Directory,
+    /// 写入 zip 而非输出目录, 条目位于 `game/` 下
+    Zip(PathBuf),
+}
+
+/// 输出配置
+#[derive(Debug, Clone, Default, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct ExtractorConfig {
+    /// 场景文件冲突的处理, 内容相同的文件总是跳过
+    pub overwrite: OverwritePolicy,
+    /// 生成完整工程骨架, 为空时直接写入输出目录
+    pub project: Option<ProjectConfig>,
+    /// 合并到已有工程, 与工程骨架同时使用时合并到骨架中
+    pub merge: Option<MergeConfig>,
+    /// 重新解析写入的场景, 报告无法解析的行
+    pub validate: bool,
+    /// 输出目标
+    pub export: ExportTarget,
+    /// 场景和文本文件的换行符与编码
+    pub text_format: TextFormat,
+    /// 记录断点, 中断后可继续执行
+    pub checkpoint: bool,
+    /// 试运行: 写入场景但跳过下载, 所需资源见转译结果
+    pub dry_run: bool,
+    /// 转译后以 HEAD 请求探测预计下载大小 (不含 Live2D 模型), 见解析统计
+    pub estimate_size: bool,
+    /// 跳过输出目录中已存在的资源, 仅下载新增资源
+    pub skip_existing: bool,
+    /// 场景写入前依次执行的外部命令
+    pub post_process: Vec<HookCommand>,
+}
+
+/// 场景后处理命令
+///
+/// 经标准输入传入场景文本, 从标准输出读取结果, 场景路径见环境变量 `BD2WG_SCENE`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HookCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// 合并到已有工程的配置
+#[derive(Debug, Clone, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct MergeConfig {
+    /// 故事标识, 作为场景文件名前缀
+    pub slug: String,
+    /// 追加选项的索引场景
+    pub index: String,
+    /// 索引场景中的选项文本, 为空时采用故事标题或标识
+    pub label: Option<String>,
+    /// 覆盖已存在的资源
+    pub force: bool,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            slug: String::from("story"),
+            index: String::from("start.txt"),
+            label: None,
+            force: false,
+        }
+    }
+}
+
+/// 下载配置
+#[derive(Debug, Clone, Copy, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct DownloadConfig {
+    /// 下载线程数
+    pub concurrency: usize,
+    /// 单个任务最大尝试次数
+    pub retries: usize,
+    /// 单个任务时间限制 (秒), 重试时倍增
+    pub timeout: u64,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+            retries: DEFAULT_DOWNLOAD_RETRIES,
+            timeout: DEFAULT_DOWNLOAD_TIMEOUT,
+        }
+    }
+}
+
+/// 错误处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// 收集全部错误并继续执行
+    #[default]
+    Collect,
+    /// 出现第一个错误时中止
+    FailFast,
+    /// 错误数达到上限时中止
+    Limit(usize),
+}
+
+impl ErrorPolicy {
+    /// 已发生 count 个错误时是否中止
+    pub fn should_abort(self, count: usize) -> bool {
+        match self {
+            Self::Collect => false,
+            Self::FailFast => count > 0,
+            Self::Limit(limit) => count >= limit.max(1),
+        }
+    }
+}
+
+/// 丢弃未结束的管线时的处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DropPolicy {
+    /// 中断执行
+    #[default]
+    Cancel,
+    /// 分离, 在后台继续运行至结束
+    Detach,
+}
+
+/// 管线选项, 汇总转译管线和下载管线的全部配置
+#[derive(Debug, Clone, Default, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct PipelineOptions {
+    /// 请求头, 为空时采用默认请求头 (若启用)
+    pub header: HashMap<String, String>,
+    pub resolver: ResolverConfig,
+    pub transpiler: TranspilerConfig,
+    pub extractor: ExtractorConfig,
+    pub download: DownloadConfig,
+    /// 错误处理策略, 中止时不再启动后续阶段
+    pub error_policy: ErrorPolicy,
+    /// 批量转译的并行数, 为 0 时采用可用的 CPU 数
+    pub parallelism: usize,
+    /// 丢弃未结束的管线时中断或分离
+    pub drop_policy: DropPolicy,
+    /// 场景写入前依次调用的钩子, 在外部命令之后执行, 不参与序列化
+    #[serde(skip)]
+    pub hooks: Vec<Arc<dyn SceneHook>>,
+    /// 资源解析或下载失败时询问替代资源, 为空时直接记录错误, 不参与序列化
+    #[serde(skip)]
+    pub recover: Option<Arc<dyn Recover>>,
+}
+
+impl PipelineOptions {
+    /// 构建请求头
+    pub fn header_map(&self) -> anyhow::Result<HeaderMap> {
+        #[cfg(feature = "default_header")]
+        if self.header.is_empty() {
+            return crate::utils::default_header();
+        }
+
+        crate::utils::new_header_from_json(&serde_json::to_value(&self.header)?)
+    }
+}
+
+/// 转换选项, 在管线选项之外包含获取脚本和元数据所用的 API 客户端配置
+///
+/// 管线选项的字段平铺在顶层, 故管线配置文件可直接作为转换选项读取.
+#[derive(Debug, Clone, Default, Builder, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[builder(default)]
+#[serde(default)]
+pub struct ConvertOptions {
+    #[serde(flatten)]
+    pub pipeline: PipelineOptions,
+    /// Bestdori API 客户端配置, 含响应缓存位置
+    pub api: ApiConfig,
+}
+
+impl ConvertOptions {
+    /// 以管线选项中的请求头和 API 配置创建客户端
+    #[cfg(feature = "online-meta")]
+    pub fn api_client(&self) -> anyhow::Result<crate::services::bestdori_api::BestdoriApi> {
+        Ok(crate::services::bestdori_api::BestdoriApi::with_config(
+            self.pipeline.header_map()?,
+            self.api.clone(),
+        )?)
+    }
+
+    /// 以 API 客户端创建故事获取器, 采用解析器配置的区域
+    #[cfg(feature = "online-meta")]
+    pub fn fetcher(&self) -> anyhow::Result<crate::services::fetcher::Fetcher> {
+        Ok(crate::services::fetcher::Fetcher::new(
+            self.api_client()?,
+            self.pipeline.resolver.region,
+        ))
+    }
+}
+
+impl From<HeaderMap> for PipelineOptions {
+    fn from(header: HeaderMap) -> Self {
+        Self {
+            header: header
+                .iter()
+                .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// 生成配置文件的 JSON Schema
+///
+/// 可用名称: resolver, transpiler, api, extractor, project, download, pipeline, convert,
+/// manifest, checkpoint, redirects, characters, header.
+#[cfg(feature = "schema")]
+pub fn config_schema(name: &str) -> Option<schemars::Schema> {
+    use schemars::schema_for;
+
+    Some(match name {
+        "resolver" => schema_for!(ResolverConfig),
+        "transpiler" => schema_for!(TranspilerConfig),
+        "api" => schema_for!(ApiConfig),
+        "extractor" => schema_for!(ExtractorConfig),
+        "project" => schema_for!(ProjectConfig),
+        "download" => schema_for!(DownloadConfig),
+        "pipeline" => schema_for!(PipelineOptions),
+        "convert" => schema_for!(ConvertOptions),
+        "manifest" => schema_for!(super::manifest::Manifest),
+        "checkpoint" => schema_for!(super::checkpoint::Checkpoint),
+        "redirects" => schema_for!(HashMap<String, ModelRedirect>),
+        "characters" => schema_for!(CharacterTable),
+        "header" => schema_for!(HashMap<String, String>),
+        _ => return None,
+    })
+}
+
+/// 可生成 JSON Schema 的配置名称
+#[cfg(feature = "schema")]
+pub const CONFIG_SCHEMA_NAMES: &[&str] = &[
+    "resolver",
+    "transpiler",
+    "api",
+    "extractor",
+    "project",
+    "download",
+    "pipeline",
+    "convert",
+    "manifest",
+    "checkpoint",
+    "redirects",
+    "characters",
+    "header",
+];
+
+#[test]
+#[cfg(test)]
+fn test_convert_options() {
+    // 管线配置的字段平铺在顶层
+    let options: ConvertOptions = serde_json::from_str(
+        r#"{"parallelism": 2, "resolver": {"region": "en"}, "api": {"cache_dir": "cache"}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(options.pipeline.parallelism, 2);
+    assert_eq!(
+        options.pipeline.resolver.region,
+        super::bestdori::Region::En
+    );
+    assert_eq!(options.api.cache_dir, Some(PathBuf::from("cache")));
+    assert_eq!(options.api.cache_ttl, DEFAULT_API_CACHE_TTL);
+}
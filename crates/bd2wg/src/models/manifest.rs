@@ -0,0 +1,115 @@
+//! 工程清单
+//!
+//! 记录 bd2wg 生成的场景和下载的资源, 供校验, 清理和更新使用.
+
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{services::output::FsSink, traits::output::OutputSink};
+
+/// 清单文件名, 位于游戏目录下
+pub const MANIFEST_FILE: &str = "bd2wg-manifest.json";
+
+/// 清单格式版本
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// 清单条目
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ManifestEntry {
+    /// 相对游戏目录的路径
+    pub path: String,
+    /// 写入时的文件内容哈希, 为空时清理不会删除该文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// 来源链接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// 工程清单
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct Manifest {
+    pub version: u32,
+    pub scenes: Vec<ManifestEntry>,
+    pub assets: Vec<ManifestEntry>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            scenes: Vec::new(),
+            assets: Vec::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// 读取游戏目录下的清单, 不存在时返回空清单
+    pub fn load(root: impl AsRef<Path>) -> io::Result<Self> {
+        Self::load_from(&FsSink, root)
+    }
+
+    /// 从输出目标读取清单
+    pub fn load_from(sink: &dyn OutputSink, root: impl AsRef<Path>) -> io::Result<Self> {
+        match sink.read(&root.as_ref().join(MANIFEST_FILE))? {
+            Some(bytes) => serde_json::from_slice(&bytes).map_err(io::Error::other),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// 写入游戏目录
+    pub fn save(&self, root: impl AsRef<Path>) -> io::Result<()> {
+        self.save_to(&FsSink, root)
+    }
+
+    /// 写入输出目标
+    pub fn save_to(&self, sink: &dyn OutputSink, root: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        sink.write(&root.as_ref().join(MANIFEST_FILE), &json)
+    }
+
+    /// 记录场景, 同路径条目将被替换
+    pub fn insert_scene(&mut self, entry: ManifestEntry) {
+        upsert(&mut self.scenes, entry);
+    }
+
+    /// 记录资源, 同路径条目将被替换
+    pub fn insert_asset(&mut self, entry: ManifestEntry) {
+        upsert(&mut self.assets, entry);
+    }
+}
+
+fn upsert(entries: &mut Vec<ManifestEntry>, entry: ManifestEntry) {
+    match entries.iter_mut().find(|e| e.path == entry.path) {
+        Some(old) => *old = entry,
+        None => entries.push(entry),
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_manifest_upsert() {
+    let entry = |path: &str, hash: &str| ManifestEntry {
+        path: path.to_string(),
+        hash: Some(hash.to_string()),
+        url: None,
+    };
+
+    let mut manifest = Manifest::default();
+    manifest.insert_scene(entry("scene/start.txt", "a"));
+    manifest.insert_scene(entry("scene/scene-1.txt", "b"));
+    manifest.insert_scene(entry("scene/start.txt", "c"));
+
+    assert_eq!(
+        manifest.scenes,
+        vec![
+            entry("scene/start.txt", "c"),
+            entry("scene/scene-1.txt", "b")
+        ]
+    );
+}
@@ -2,10 +2,12 @@
 
 pub mod action;
 pub mod live2d;
+pub mod parse;
 pub mod resource;
 pub mod story;
 
 pub use action::*;
 pub use live2d::*;
+pub use parse::*;
 pub use resource::*;
 pub use story::*;
@@ -1,15 +1,105 @@
 //! WebGAL 脚本指令
 
 use std::{
-    fmt::{self, Display},
+    fmt::{self, Display, Write},
     ops::Deref,
+    str::FromStr,
 };
 
 use derive_builder::Builder;
-use serde::Serialize;
-use webgal_derive::{ActionCustom, Actionable};
+use serde::{Deserialize, Serialize};
+use webgal_derive::{ActionCustom, Actionable, escape};
 
-use crate::impl_display_for_serde;
+use crate::{impl_display_for_serde, impl_fromstr_for_serde};
+
+/// 目标 WebGAL 版本, 决定可用的指令和参数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EngineVersion {
+    /// 4.4 之前, 不支持变换动画和连接对话
+    #[serde(rename = "legacy")]
+    Legacy,
+    #[serde(rename = "4.4")]
+    V4_4,
+    #[default]
+    #[serde(rename = "4.5")]
+    V4_5,
+}
+
+impl EngineVersion {
+    /// 是否支持 `setTransform`, 不支持时移动不以变换动画呈现
+    pub fn has_transform(self) -> bool {
+        self.supports_command("setTransform")
+    }
+
+    /// 是否支持命令, 见 `COMMANDS_SINCE`
+    pub fn supports_command(self, head: &str) -> bool {
+        COMMANDS_SINCE
+            .iter()
+            .all(|&(command, since)| command != head || self >= since)
+    }
+
+    /// 是否支持命令的参数, 见 `ARGS_SINCE`
+    pub fn supports_arg(self, head: &str, key: &str) -> bool {
+        ARGS_SINCE.iter().all(|&(command, arg, since)| {
+            arg != key || command.is_some_and(|command| command != head) || self >= since
+        })
+    }
+
+    /// 按目标版本改写渲染后的命令行
+    ///
+    /// 指令总是生成全部参数, 目标版本不支持的命令和参数仅在此处理.
+    ///
+    /// 不支持的命令改为注释, 不支持的参数被省略; 4.4 之前以 `-file.mp3` 的形式指定语音.
+    /// 无法切分的行 (如注释) 原样返回.
+    pub fn rewrite(self, line: &str) -> String {
+        let Ok(parsed) = webgal_derive::split_line(line) else {
+            return line.to_string();
+        };
+
+        if !self.supports_command(parsed.head) {
+            return format!(";{line}");
+        }
+
+        let mut changed = false;
+        let command = line.trim().trim_end_matches(';').split(" -").next();
+        let mut out = String::from(command.unwrap_or_default());
+
+        for (key, value) in parsed.args.all() {
+            let unsupported = !self.supports_arg(parsed.head, key);
+            changed |= unsupported;
+
+            match (key, value) {
+                _ if unsupported => {}
+                ("vocal", Some(file)) if self < Self::V4_4 => {
+                    changed = true;
+                    let _ = write!(out, " -{file}");
+                }
+                (key, Some(value)) => {
+                    let _ = write!(out, " -{key}={value}");
+                }
+                (key, None) => {
+                    let _ = write!(out, " -{key}");
+                }
+            }
+        }
+
+        match changed {
+            true => out + ";",
+            false => line.to_string(),
+        }
+    }
+}
+
+/// 命令及其最低支持版本
+const COMMANDS_SINCE: &[(&str, EngineVersion)] = &[("setTransform", EngineVersion::V4_4)];
+
+/// 参数及其最低支持版本, 每个参数一项, 命令名为空时匹配任意命令 (如对话)
+const ARGS_SINCE: &[(Option<&str>, &str, EngineVersion)] = &[
+    (None, "concat", EngineVersion::V4_4),
+    (Some("changeFigure"), "zIndex", EngineVersion::V4_5),
+    (Some("changeBg"), "duration", EngineVersion::V4_5),
+];
 
 /// WebGAL 命令
 pub struct Action(pub Box<dyn Actionable + Send + Sync + 'static>);
@@ -20,19 +110,23 @@ impl Display for Action {
     }
 }
 
-/// 渲染指令迭代器
-pub fn display_action_iter<I, A>(iter: I, f: &mut fmt::Formatter<'_>) -> fmt::Result
+/// 按目标版本渲染指令迭代器
+pub fn display_action_iter<I, A>(
+    iter: I,
+    engine: EngineVersion,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result
 where
     I: Iterator<Item = A>,
     A: Deref<Target = Action>,
 {
     for action in iter {
-        writeln!(f, "{}", action.deref())?;
+        writeln!(f, "{}", engine.rewrite(&action.deref().to_string()))?;
     }
     Ok(())
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub enum FigureSide {
     Left,
     #[default]
@@ -40,28 +134,99 @@ pub enum FigureSide {
     Right,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+impl Display for FigureSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Left => "left",
+            Self::Center => "center",
+            Self::Right => "right",
+        })
+    }
+}
+
+impl FromStr for FigureSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Self::Left),
+            "center" => Ok(Self::Center),
+            "right" => Ok(Self::Right),
+            _ => Err(format!("unknown figure side: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Position {
     pub x: i16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<i16>,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Scale {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Transform {
-    pub position: Position,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<Scale>,
+    /// 透明度 (0-1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<f32>,
+    /// 亮度 (1 为原始亮度)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<f32>,
 }
 
 impl Transform {
     pub fn new_with_x(x: i16) -> Self {
         Self {
-            position: Position { x },
+            position: Some(Position { x, y: None }),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_with_alpha(alpha: f32) -> Self {
+        Self {
+            alpha: Some(alpha),
+            ..Default::default()
         }
     }
 }
 
 impl_display_for_serde! {Transform}
+impl_fromstr_for_serde! {Transform}
 
 // ---------------- model ----------------
 
+/// 注释
+///
+/// 注释不需要参数, 故直接实现 Display.
+#[derive(Debug, Clone)]
+pub struct CommentAction {
+    pub text: String,
+}
+
+impl Display for CommentAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ";{}", self.text.replace('\n', " "))
+    }
+}
+
+impl Actionable for CommentAction {}
+
+impl From<CommentAction> for Action {
+    fn from(value: CommentAction) -> Self {
+        Action(Box::new(value))
+    }
+}
+
 /// 调用场景
 #[derive(Debug, Clone, Actionable)]
 #[action(head = "callScene", main = "single")]
@@ -70,6 +235,25 @@ pub struct CallSceneAction {
     pub file: String,
 }
 
+/// 切换场景
+#[derive(Debug, Clone, Actionable)]
+#[action(head = "changeScene", main = "single")]
+pub struct ChangeSceneAction {
+    #[action(main)]
+    pub file: String,
+}
+
+/// 结束游戏, 返回标题
+#[derive(Debug, Clone, Actionable)]
+#[action(custom)]
+pub struct EndAction {}
+
+impl ActionCustom for EndAction {
+    fn get_head(&self) -> String {
+        String::from("end")
+    }
+}
+
 /// 分支选择
 #[derive(Debug, Clone, Actionable)]
 #[action(head = "choose", custom)]
@@ -80,50 +264,61 @@ pub struct ChooseAction {
 
 impl ActionCustom for ChooseAction {
     fn get_main(&self) -> String {
-        format!("{}:{}", self.text, self.file)
+        format!("{}:{}", escape(&self.text), escape(&self.file))
     }
 }
 
+/// 黑屏独白
+#[derive(Debug, Clone, Actionable)]
+#[action(head = "intro", main = "list")]
+pub struct IntroAction {
+    #[action(main)]
+    pub lines: Vec<String>,
+    /// 独白结束后保持黑屏
+    #[action(arg = "tag")]
+    pub hold: bool,
+}
+
 /// 普通对话
 #[derive(Debug, Clone, Actionable)]
 #[action(main = "single", custom)]
 pub struct SayAction {
     pub name: String,
-    #[action(main)]
+    /// `|` 为换行, 命令名以首个 `:` 分隔, 二者均无需转义
+    #[action(main, keep = ":|")]
     pub text: String,
     #[action(arg = "tag", rename = "notend")]
     pub next: bool,
+    #[action(arg = "tag")]
+    pub concat: bool,
+    /// 说话的角色, WebGAL 只接受一个 figureId, 多人同时说话时取首个角色
     #[action(arg = "pair", nullable, rename = "figureId", tie = "id")]
     pub character: Option<u8>,
+    /// 语音文件
+    #[action(arg = "pair", nullable)]
+    pub vocal: Option<String>,
 }
 
 impl ActionCustom for SayAction {
     fn get_head(&self) -> String {
-        self.name.clone() + ":"
+        escape(&self.name) + ":"
     }
 }
 
 /// 文本显示
 #[derive(Debug, Clone, Actionable)]
-#[action(head = "setTextbox", custom)]
+#[action(head = "setTextbox", main = "single")]
 pub struct SetTextboxAction {
+    #[action(main, bool = "on|hide")]
     pub visible: bool,
-}
-
-impl ActionCustom for SetTextboxAction {
-    fn get_main(&self) -> String {
-        if self.visible {
-            String::from("on")
-        } else {
-            String::from("hide")
-        }
-    }
+    #[action(arg = "tag")]
+    pub next: bool,
 }
 
 /// 切换立绘
 #[derive(Debug, Clone, Default, Builder, Actionable)]
 #[builder(default)]
-#[action(head = "changeFigure", main = "single", custom)]
+#[action(head = "changeFigure", main = "single")]
 pub struct ChangeFigureAction {
     #[action(main, nullable, none)]
     pub model: Option<String>,
@@ -131,13 +326,18 @@ pub struct ChangeFigureAction {
     pub id: u8,
     #[action(arg = "tag")]
     pub next: bool,
-    pub side: FigureSide,
-    #[action(arg = "pair", nullable)]
+    #[action(arg = "pair", nullable, raw)]
     pub transform: Option<Transform>,
     #[action(arg = "pair", nullable)]
     pub motion: Option<String>,
     #[action(arg = "pair", nullable)]
     pub expression: Option<String>,
+    /// 层级, 数值大者在上
+    #[action(arg = "pair", nullable, rename = "zIndex")]
+    pub z_index: Option<u32>,
+    /// 居中时不输出
+    #[action(arg = "value", when = "self.side != FigureSide::Center")]
+    pub side: FigureSide,
 }
 
 impl ChangeFigureAction {
@@ -150,21 +350,11 @@ impl ChangeFigureAction {
     }
 }
 
-impl ActionCustom for ChangeFigureAction {
-    fn get_other_args(&self) -> Option<Vec<(String, Option<String>)>> {
-        match self.side {
-            FigureSide::Center => None,
-            FigureSide::Left => Some(vec![(String::from("left"), None)]),
-            FigureSide::Right => Some(vec![(String::from("right"), None)]),
-        }
-    }
-}
-
 /// 设置效果
 #[derive(Debug, Clone, Actionable)]
 #[action(head = "setEffect", main = "single")]
 pub struct SetEffectAction {
-    #[action(main)]
+    #[action(main, raw)]
     pub transform: Transform,
     #[action(arg = "pair")]
     pub target: u8,
@@ -172,12 +362,23 @@ pub struct SetEffectAction {
     pub next: bool,
 }
 
+/// 文本框小头像
+#[derive(Debug, Clone, Actionable)]
+#[action(head = "miniAvatar", main = "single")]
+pub struct MiniAvatarAction {
+    #[action(main, nullable, none)]
+    pub image: Option<String>,
+}
+
 /// 切换背景
 #[derive(Debug, Clone, Default, Actionable)]
 #[action(head = "changeBg", main = "single")]
 pub struct ChangeBgAction {
     #[action(main, nullable, none)]
     pub image: Option<String>,
+    /// 切换时长 (毫秒)
+    #[action(arg = "pair", nullable)]
+    pub duration: Option<u32>,
     #[action(arg = "tag")]
     pub next: bool,
 }
@@ -188,6 +389,23 @@ pub struct ChangeBgAction {
 pub struct BgmAction {
     #[action(main, nullable, none)]
     pub sound: Option<String>,
+    /// 淡入 / 淡出时长 (毫秒)
+    #[action(arg = "pair", nullable)]
+    pub enter: Option<u32>,
+    /// 音量 (0-100)
+    #[action(arg = "pair", nullable)]
+    pub volume: Option<u8>,
+}
+
+impl BgmAction {
+    /// 停止 bgm, 可选淡出
+    pub fn new_stop(fade: Option<u32>) -> Self {
+        Self {
+            sound: None,
+            enter: fade,
+            volume: None,
+        }
+    }
 }
 
 /// 效果声音
@@ -196,23 +414,179 @@ pub struct BgmAction {
 pub struct PlayEffectAction {
     #[action(main, nullable, none)]
     pub sound: Option<String>,
+    /// 音量 (0-100)
+    #[action(arg = "pair", nullable)]
+    pub volume: Option<u8>,
+    /// 设置 id 时循环播放, 直到以同一 id 停止
+    #[action(arg = "pair", nullable)]
+    pub id: Option<String>,
+}
+
+/// 画面特效
+#[derive(Debug, Clone, Actionable)]
+pub enum PixiAction {
+    /// 添加特效
+    #[action(head = "pixiPerform", main = "single")]
+    Perform {
+        #[action(main)]
+        effect: String,
+    },
+    /// 清除全部特效
+    #[action(head = "pixiInit")]
+    Init,
+}
+
+/// 动画
+#[derive(Debug, Clone, Actionable)]
+#[action(main = "single")]
+pub enum AnimateAction {
+    /// 变换动画
+    #[action(head = "setTransform")]
+    Transform {
+        #[action(main, raw)]
+        transform: Transform,
+        #[action(arg = "pair")]
+        target: String,
+        /// 动画时长 (毫秒)
+        #[action(arg = "pair")]
+        duration: u32,
+        #[action(arg = "tag")]
+        next: bool,
+    },
+    /// 预设动画
+    #[action(head = "setAnimation")]
+    Animation {
+        #[action(main)]
+        animation: String,
+        #[action(arg = "pair")]
+        target: String,
+        #[action(arg = "tag")]
+        next: bool,
+    },
+}
+
+/// 已合并为枚举的旧版指令, 保留以兼容, 序列化委托给对应的枚举变体
+#[allow(deprecated)]
+mod legacy {
+    use super::*;
+
+    /// 变换动画
+    #[deprecated(note = "use AnimateAction::Transform")]
+    #[derive(Debug, Clone)]
+    pub struct SetTransformAction {
+        pub transform: Transform,
+        pub target: String,
+        /// 动画时长 (毫秒)
+        pub duration: u32,
+        pub next: bool,
+    }
+
+    /// 设置动画
+    #[deprecated(note = "use AnimateAction::Animation")]
+    #[derive(Debug, Clone)]
+    pub struct SetAnimation {
+        pub animation: String,
+        pub target: String,
+        pub next: bool,
+    }
+
+    /// 添加画面特效
+    #[deprecated(note = "use PixiAction::Perform")]
+    #[derive(Debug, Clone)]
+    pub struct PixiPerformAction {
+        pub effect: String,
+    }
+
+    /// 清除画面特效
+    #[deprecated(note = "use PixiAction::Init")]
+    #[derive(Debug, Clone)]
+    pub struct PixiInitAction {}
+
+    impl From<SetTransformAction> for AnimateAction {
+        fn from(value: SetTransformAction) -> Self {
+            Self::Transform {
+                transform: value.transform,
+                target: value.target,
+                duration: value.duration,
+                next: value.next,
+            }
+        }
+    }
+
+    impl From<SetAnimation> for AnimateAction {
+        fn from(value: SetAnimation) -> Self {
+            Self::Animation {
+                animation: value.animation,
+                target: value.target,
+                next: value.next,
+            }
+        }
+    }
+
+    impl From<PixiPerformAction> for PixiAction {
+        fn from(value: PixiPerformAction) -> Self {
+            Self::Perform {
+                effect: value.effect,
+            }
+        }
+    }
+
+    impl From<PixiInitAction> for PixiAction {
+        fn from(_: PixiInitAction) -> Self {
+            Self::Init
+        }
+    }
+
+    macro_rules! delegate_to_enum {
+        ($($legacy:ident => $target:ident),* $(,)?) => {$(
+            impl Display for $legacy {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    $target::from(self.clone()).fmt(f)
+                }
+            }
+
+            impl Actionable for $legacy {}
+
+            impl From<$legacy> for Action {
+                fn from(value: $legacy) -> Self {
+                    $target::from(value).into()
+                }
+            }
+        )*};
+    }
+
+    delegate_to_enum! {
+        SetTransformAction => AnimateAction,
+        SetAnimation => AnimateAction,
+        PixiPerformAction => PixiAction,
+        PixiInitAction => PixiAction,
+    }
 }
 
-/// 设置动画
+#[allow(deprecated)]
+pub use legacy::{PixiInitAction, PixiPerformAction, SetAnimation, SetTransformAction};
+
+/// 等待
 #[derive(Debug, Clone, Actionable)]
-#[action(head = "setAnimation", main = "single")]
-pub struct SetAnimation {
+#[action(head = "wait", main = "single")]
+pub struct WaitAction {
+    /// 等待时长 (毫秒)
     #[action(main)]
-    pub animation: String,
-    #[action(arg = "pair")]
-    pub target: String,
-    #[action(arg = "tag")]
-    pub next: bool,
+    pub time: u32,
 }
 
 #[test]
 #[cfg(test)]
 fn test_action_serialize() {
+    assert_eq!(
+        SetTextboxAction {
+            visible: false,
+            next: true,
+        }
+        .to_string(),
+        r#"setTextbox:hide -next;"#
+    );
+
     assert_eq!(
         ChooseAction {
             file: String::from("start.txt"),
@@ -227,10 +601,12 @@ fn test_action_serialize() {
             name: String::from("Soyo"),
             text: String::from("ごきげんよう~"),
             next: true,
+            concat: false,
             character: Some(39),
+            vocal: Some(String::from("scenario0-001.mp3")),
         }
         .to_string(),
-        r#"Soyo:ごきげんよう~ -notend -id -figureId=39;"#
+        r#"Soyo:ごきげんよう~ -notend -id -figureId=39 -vocal=scenario0-001.mp3;"#
     );
 
     assert_eq!(
@@ -239,33 +615,447 @@ fn test_action_serialize() {
             id: 36,
             next: false,
             side: FigureSide::Left,
-            transform: Some(Transform {
-                position: Position { x: 0 },
-            }),
+            transform: Some(Transform::new_with_x(0)),
             motion: Some(String::from("angry01")),
             expression: Some(String::from("angry01")),
+            z_index: Some(2),
         }
         .to_string(),
-        r#"changeFigure:036_casual-2023 -id=36 -transform={"position":{"x":0}} -motion=angry01 -expression=angry01 -left;"#
+        r#"changeFigure:036_casual-2023 -id=36 -transform={"position":{"x":0}} -motion=angry01 -expression=angry01 -zIndex=2 -left;"#
     );
 
     assert_eq!(
         ChangeBgAction {
             image: None,
+            duration: None,
             next: false,
         }
         .to_string(),
         r#"changeBg:none;"#
     );
 
+    assert_eq!(
+        ChangeBgAction {
+            image: Some(String::from("white.png")),
+            duration: Some(1500),
+            next: true,
+        }
+        .to_string(),
+        r#"changeBg:white.png -duration=1500 -next;"#
+    );
+
     assert_eq!(
         BgmAction {
             sound: Some(String::from("01. ショパン「雨だれ」.flac")),
+            enter: None,
+            volume: None,
         }
         .to_string(),
         r#"bgm:01. ショパン「雨だれ」.flac;"#
     );
 
+    assert_eq!(
+        BgmAction::new_stop(Some(3000)).to_string(),
+        r#"bgm:none -enter=3000;"#
+    );
+
+    assert_eq!(
+        AnimateAction::Animation {
+            animation: String::from("rgbFilm"),
+            target: String::from("bg-main"),
+            next: true,
+        }
+        .to_string(),
+        r#"setAnimation:rgbFilm -target=bg-main -next;"#
+    );
+
+    assert_eq!(
+        SayAction {
+            name: String::from("A:B"),
+            text: String::from("so - what; 10:30 | -ok"),
+            next: false,
+            concat: false,
+            character: None,
+            vocal: None,
+        }
+        .to_string(),
+        r#"A：B:so － what； 10:30 | －ok;"#
+    );
+
+    assert_eq!(WaitAction { time: 500 }.to_string(), r#"wait:500;"#);
+
+    assert_eq!(
+        IntroAction {
+            lines: vec![String::from("三日後"), String::from("放課後")],
+            hold: false,
+        }
+        .to_string(),
+        r#"intro:三日後|放課後;"#
+    );
+
+    assert_eq!(
+        PixiAction::Perform {
+            effect: String::from("snow"),
+        }
+        .to_string(),
+        r#"pixiPerform:snow;"#
+    );
+
+    assert_eq!(PixiAction::Init.to_string(), r#"pixiInit;"#);
+
+    assert_eq!(
+        AnimateAction::Transform {
+            transform: Transform::new_with_x(-120),
+            target: String::from("39"),
+            duration: 500,
+            next: false,
+        }
+        .to_string(),
+        r#"setTransform:{"position":{"x":-120}} -target=39 -duration=500;"#
+    );
+
+    assert_eq!(
+        AnimateAction::Transform {
+            transform: Transform::new_with_alpha(0.),
+            target: String::from("stage-main"),
+            duration: 800,
+            next: true,
+        }
+        .to_string(),
+        r#"setTransform:{"alpha":0.0} -target=stage-main -duration=800 -next;"#
+    );
+
+    assert_eq!(
+        CommentAction {
+            text: String::from("index=42 type=talk"),
+        }
+        .to_string(),
+        r#";index=42 type=talk"#
+    );
+
+    assert_eq!(
+        PlayEffectAction {
+            sound: Some(String::from("rain.mp3")),
+            volume: Some(60),
+            id: Some(String::from("se")),
+        }
+        .to_string(),
+        r#"playEffect:rain.mp3 -volume=60 -id=se;"#
+    );
+
+    assert_eq!(EndAction {}.to_string(), r#"end;"#);
+}
+
+#[test]
+#[cfg(test)]
+fn test_action_parse() {
+    use webgal_derive::ParseError;
+
+    fn round_trip<T>(action: T)
+    where
+        T: Display + std::str::FromStr<Err = ParseError>,
+    {
+        let line = action.to_string();
+        assert_eq!(line.parse::<T>().unwrap().to_string(), line);
+    }
+
+    round_trip(BgmAction::new_stop(Some(3000)));
+    round_trip(SetTextboxAction {
+        visible: true,
+        next: false,
+    });
+    round_trip(ChangeBgAction {
+        image: Some(String::from("white.png")),
+        duration: Some(1500),
+        next: true,
+    });
+    round_trip(IntroAction {
+        lines: vec![String::from("三日後"), String::from("放課後")],
+        hold: true,
+    });
+    round_trip(AnimateAction::Transform {
+        transform: Transform::new_with_x(-120),
+        target: String::from("39"),
+        duration: 500,
+        next: false,
+    });
+    round_trip(ChangeFigureAction {
+        model: Some(String::from("036_casual-2023")),
+        id: 36,
+        side: FigureSide::Right,
+        ..Default::default()
+    });
+    round_trip(ChangeFigureAction::new_hide(36, true));
+    round_trip(PlayEffectAction {
+        sound: None,
+        volume: Some(60),
+        id: Some(String::from("se")),
+    });
+    round_trip(PixiAction::Init);
+    round_trip(PixiAction::Perform {
+        effect: String::from("snow"),
+    });
+
+    /// 重复参数
+    #[derive(Debug, Clone, Actionable)]
+    #[action(head = "playEffect", main = "single")]
+    struct Repeated {
+        #[action(main)]
+        sound: String,
+        #[action(arg = "pair", rename = "id")]
+        ids: Vec<u8>,
+        #[action(arg = "pair", rename = "loop", bool = "on|off")]
+        looped: bool,
+        #[action(arg = "value")]
+        flags: Vec<String>,
+    }
+
+    let line = "playEffect:rain.mp3 -id=1 -id=2 -loop=off -slow -fade;";
+    let repeated: Repeated = line.parse().unwrap();
+    assert_eq!(repeated.ids, [1, 2]);
+    assert!(!repeated.looped);
+    assert_eq!(repeated.flags, ["slow", "fade"]);
+    assert_eq!(repeated.to_string(), line);
+
+    /// 自定义分隔符
+    #[derive(Debug, Clone, Actionable)]
+    #[action(head = "intro", main = "list", sep = ",")]
+    struct Listed {
+        #[action(main)]
+        items: Vec<u32>,
+    }
+
+    let listed: Listed = "intro:1,2,3;".parse().unwrap();
+    assert_eq!(listed.items, [1, 2, 3]);
+    assert_eq!(listed.to_string(), "intro:1,2,3;");
+
+    /// 浮点数精度
+    #[derive(Debug, Clone, Actionable)]
+    #[action(head = "setVolume", main = "single")]
+    struct Formatted {
+        #[action(main, format = "{:.2}")]
+        volume: f32,
+        #[action(arg = "pair", nullable, format = "{:.1}")]
+        fade: Option<f64>,
+    }
+
+    let formatted = Formatted {
+        volume: 0.1 + 0.2,
+        fade: Some(1.25),
+    };
+    assert_eq!(formatted.to_string(), "setVolume:0.30 -fade=1.2;");
+    let formatted: Formatted = "setVolume:0.30 -fade=1.2;".parse().unwrap();
+    assert_eq!(formatted.fade, Some(1.2));
+
+    // 转义有损: 分隔字符解析后仍为全角字符, 再次序列化得到相同的命令行
+    let intro = IntroAction {
+        lines: vec![String::from("A: so - what;"), String::from("x|y")],
+        hold: false,
+    };
+    let line = intro.to_string();
+    let parsed: IntroAction = line.parse().unwrap();
+    assert_eq!(parsed.lines, ["A： so － what；", "x｜y"]);
+    assert_eq!(parsed.to_string(), line);
+
+    let wait: WaitAction = "wait:500;".parse().unwrap();
+    assert_eq!(wait.time, 500);
+    assert!(
+        !"setTextbox:hide;"
+            .parse::<SetTextboxAction>()
+            .unwrap()
+            .visible
+    );
+    assert!("setTextbox:off;".parse::<SetTextboxAction>().is_err());
+    assert!(matches!(
+        "wait:soon;".parse::<WaitAction>(),
+        Err(ParseError::InvalidValue { field: "time", .. })
+    ));
+    assert_eq!(
+        "bgm:a.mp3 -loop;".parse::<BgmAction>().unwrap_err(),
+        ParseError::UnknownArg(String::from("loop"))
+    );
+    assert_eq!(
+        r#"setTransform:{"position":{"x":0}} -duration=500;"#
+            .parse::<AnimateAction>()
+            .unwrap_err(),
+        ParseError::MissingArg("target")
+    );
+    assert_eq!(
+        "changeBg:a.png;".parse::<BgmAction>().unwrap_err(),
+        ParseError::Head(String::from("changeBg"))
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_action_flatten() {
+    /// 共用的动画参数
+    #[derive(Debug, Clone, Actionable)]
+    struct Timing {
+        #[action(arg = "pair", nullable)]
+        duration: Option<u32>,
+        #[action(arg = "tag")]
+        next: bool,
+    }
+
+    #[derive(Debug, Clone, Actionable)]
+    #[action(head = "setTransform", main = "single")]
+    struct Move {
+        #[action(main, raw)]
+        transform: Transform,
+        #[action(arg = "pair")]
+        target: String,
+        #[action(flatten)]
+        timing: Timing,
+        #[action(skip)]
+        note: String,
+    }
+
+    let action = Move {
+        transform: Transform::new_with_x(0),
+        target: String::from("36"),
+        timing: Timing {
+            duration: Some(500),
+            next: true,
+        },
+        note: String::from("ignored"),
+    };
+    let line = r#"setTransform:{"position":{"x":0}} -target=36 -duration=500 -next;"#;
+    assert_eq!(action.to_string(), line);
+
+    let parsed: Move = line.parse().unwrap();
+    assert_eq!(parsed.timing.duration, Some(500));
+    assert!(parsed.timing.next);
+    assert!(parsed.note.is_empty());
+}
+
+#[test]
+#[cfg(test)]
+fn test_action_generics() {
+    #[derive(Debug, Clone, Actionable)]
+    #[action(head = "playEffect", main = "single")]
+    struct Borrowed<'a, T: Display> {
+        #[action(main)]
+        sound: &'a str,
+        #[action(arg = "pair")]
+        volume: T,
+    }
+
+    #[derive(Debug, Clone, Actionable)]
+    #[action(head = "wait", main = "single")]
+    struct Owned<T>
+    where
+        T: Display,
+    {
+        #[action(main)]
+        time: T,
+    }
+
+    let borrowed = Borrowed {
+        sound: "rain.mp3",
+        volume: 60,
+    };
+    assert_eq!(borrowed.to_string(), "playEffect:rain.mp3 -volume=60;");
+
+    let owned: Owned<u32> = "wait:500;".parse().unwrap();
+    assert_eq!(owned.time, 500);
+    let action: Action = owned.into();
+    assert_eq!(action.to_string(), "wait:500;");
+}
+
+#[test]
+#[cfg(test)]
+fn test_action_serde() {
+    #[derive(Debug, Clone, Actionable)]
+    #[action(head = "changeFigure", main = "single", serde)]
+    struct Figure {
+        #[action(main)]
+        file: String,
+        #[action(arg = "pair")]
+        id: String,
+        #[action(arg = "value")]
+        side: String,
+        #[action(arg = "tag")]
+        next: bool,
+    }
+
+    let figure = Figure {
+        file: String::from("a.json"),
+        id: String::from("36"),
+        side: String::from("left"),
+        next: true,
+    };
+    assert_eq!(
+        serde_json::to_value(&figure).unwrap(),
+        serde_json::json!({
+            "head": "changeFigure",
+            "main": "a.json",
+            "args": [
+                { "key": "id", "value": "36" },
+                { "key": "left" },
+                { "key": "next" },
+            ],
+        })
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_engine_rewrite() {
+    use super::Scene;
+
+    let say = r#"Soyo:ごきげんよう~ -concat -figureId=39 -vocal=scenario0-001.mp3;"#;
+    let figure = r#"changeFigure:036_casual-2023 -id=36 -zIndex=2 -left;"#;
+    let transform = r#"setTransform:{"alpha":0.0} -target=stage-main -duration=800;"#;
+
+    // 每个参数单独记录最低版本
+    assert!(!EngineVersion::Legacy.supports_arg("say", "concat"));
+    assert!(EngineVersion::V4_4.supports_arg("say", "concat"));
+    assert!(!EngineVersion::V4_4.supports_arg("changeFigure", "zIndex"));
+    assert!(EngineVersion::V4_4.supports_arg("changeBg", "zIndex"));
+    assert!(!EngineVersion::V4_4.supports_arg("changeBg", "duration"));
+    assert!(EngineVersion::V4_4.has_transform());
+
+    // 最新版本原样输出
+    for line in [say, figure, transform, ";comment"] {
+        assert_eq!(EngineVersion::V4_5.rewrite(line), line);
+    }
+
+    assert_eq!(
+        EngineVersion::V4_4.rewrite(figure),
+        r#"changeFigure:036_casual-2023 -id=36 -left;"#
+    );
+    assert_eq!(EngineVersion::V4_4.rewrite(say), say);
+    assert_eq!(
+        EngineVersion::Legacy.rewrite(say),
+        r#"Soyo:ごきげんよう~ -figureId=39 -scenario0-001.mp3;"#
+    );
+    assert_eq!(
+        EngineVersion::Legacy.rewrite(transform),
+        format!(";{transform}")
+    );
+
+    // 场景按其目标版本渲染
+    let scene = Scene {
+        actions: vec![
+            ChangeBgAction {
+                image: Some(String::from("bg.png")),
+                duration: Some(500),
+                next: true,
+            }
+            .into(),
+        ],
+        engine: EngineVersion::V4_4,
+        ..Scene::new("start.txt")
+    };
+    assert_eq!(scene.to_string(), "changeBg:bg.png -next;\n");
+}
+
+#[test]
+#[cfg(test)]
+#[allow(deprecated)]
+fn test_legacy_actions() {
+    // 旧版类型与枚举变体输出相同
     assert_eq!(
         SetAnimation {
             animation: String::from("rgbFilm"),
@@ -275,4 +1065,22 @@ fn test_action_serialize() {
         .to_string(),
         r#"setAnimation:rgbFilm -target=bg-main -next;"#
     );
+    assert_eq!(
+        Action::from(SetTransformAction {
+            transform: Transform::new_with_x(0),
+            target: String::from("36"),
+            duration: 500,
+            next: false,
+        })
+        .to_string(),
+        r#"setTransform:{"position":{"x":0}} -target=36 -duration=500;"#
+    );
+    assert_eq!(
+        PixiPerformAction {
+            effect: String::from("snow"),
+        }
+        .to_string(),
+        r#"pixiPerform:snow;"#
+    );
+    assert_eq!(PixiInitAction {}.to_string(), r#"pixiInit;"#);
 }
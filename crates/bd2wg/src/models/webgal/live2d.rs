@@ -107,6 +107,19 @@ impl Model {
 
         (model, res)
     }
+
+    /// 配置引用的全部模型文件, 相对模型目录
+    pub fn files(&self) -> Vec<String> {
+        let motions = self.motions.iter().flat_map(|(_, motions)| motions);
+
+        [&self.model, &self.physics]
+            .into_iter()
+            .chain(&self.textures)
+            .chain(motions.map(|motion| &motion.file))
+            .chain(self.expressions.iter().map(|expression| &expression.file))
+            .cloned()
+            .collect()
+    }
 }
 
 impl Default for Model {
@@ -0,0 +1,115 @@
+//! WebGAL 脚本解析
+//!
+//! 序列化的逆过程, 仅用于校验生成的场景, 不还原为具体指令类型. 需要还原时使用派生的 FromStr.
+
+pub use webgal_derive::ParseError;
+use webgal_derive::split_line;
+
+/// 没有 `:` 的指令
+const BARE_COMMANDS: &[&str] = &["end", "pixiInit"];
+
+/// 解析后的单行指令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedLine {
+    /// 指令名, 对话为说话人
+    pub head: String,
+    pub main: String,
+    pub args: Vec<(String, Option<String>)>,
+}
+
+/// 解析单行, 空行和注释返回 None
+///
+/// 与派生的 FromStr 共用 [`split_line`], 另外要求除 [`BARE_COMMANDS`] 外的指令带有 `:`.
+pub fn parse_line(line: &str) -> Result<Option<ParsedLine>, ParseError> {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with(';') {
+        return Ok(None);
+    }
+
+    let parsed = split_line(line)?;
+
+    let command = line.split(" -").next().unwrap_or_default();
+    if !command.contains(':') && !BARE_COMMANDS.contains(&parsed.head) {
+        let command = command.trim_end_matches(';');
+        return Err(ParseError::MissingColon(command.to_string()));
+    }
+
+    Ok(Some(ParsedLine {
+        head: parsed.head.to_string(),
+        main: parsed.main.to_string(),
+        args: parsed
+            .args
+            .all()
+            .map(|(key, value)| (key.to_string(), value.map(str::to_string)))
+            .collect(),
+    }))
+}
+
+/// 校验场景文本, 返回出错的行号 (从 1 开始) 和错误
+pub fn validate_scene(text: &str) -> Vec<(usize, ParseError)> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(k, line)| parse_line(line).err().map(|e| (k + 1, e)))
+        .collect()
+}
+
+/// 引用资源的指令及其资源目录
+const REFERENCE_DIRS: &[(&str, &str)] = &[
+    ("changeFigure", "figure"),
+    ("miniAvatar", "figure"),
+    ("changeBg", "background"),
+    ("bgm", "bgm"),
+    ("playEffect", "vocal"),
+];
+
+/// 收集场景引用的资源, 返回行号 (从 1 开始) 和相对游戏目录的路径
+///
+/// 无法解析的行将被忽略.
+pub fn scene_references(text: &str) -> Vec<(usize, String)> {
+    let mut refs = Vec::new();
+
+    for (k, line) in text.lines().enumerate() {
+        let Ok(Some(line)) = parse_line(line) else {
+            continue;
+        };
+
+        if let Some((_, dir)) = REFERENCE_DIRS.iter().find(|(head, _)| *head == line.head)
+            && !line.main.is_empty()
+            && line.main != "none"
+        {
+            refs.push((k + 1, format!("{dir}/{}", line.main)));
+        }
+
+        // 对话语音
+        if let Some((_, Some(vocal))) = line.args.iter().find(|(key, _)| key == "vocal") {
+            refs.push((k + 1, format!("vocal/{vocal}")));
+        }
+    }
+
+    refs
+}
+
+#[test]
+#[cfg(test)]
+fn test_parse_line() {
+    use super::*;
+
+    let line = ChangeFigureAction::new_hide(36, true).to_string();
+    let parsed = parse_line(&line).unwrap().unwrap();
+    assert_eq!(parsed.head, "changeFigure");
+    assert_eq!(parsed.main, "none");
+    assert!(parsed.args.contains(&(String::from("next"), None)));
+
+    assert_eq!(
+        parse_line(&EndAction {}.to_string()).unwrap().unwrap().head,
+        "end"
+    );
+    assert_eq!(parse_line(";comment"), Ok(None));
+    assert_eq!(parse_line("A:a;b;"), Err(ParseError::StraySemicolon));
+    assert_eq!(parse_line("A:text"), Err(ParseError::Unterminated));
+    assert_eq!(
+        parse_line("A:so - what -next;"),
+        Err(ParseError::MalformedArg(String::from(" what")))
+    );
+}
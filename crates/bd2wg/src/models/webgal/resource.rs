@@ -2,30 +2,69 @@
 
 use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
 use strum_macros::{AsRefStr, Display};
 
 use crate::traits::asset::Asset;
 
+/// 本地文件链接前缀
+pub const LOCAL_URL_SCHEME: &str = "file://";
+
 /// WebGAL 资源类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, Display, Deserialize, Serialize)]
 #[strum(serialize_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ResourceType {
     Background,
     Bgm,
     Vocal,
     Figure,
+    /// 静态立绘 (非 Live2D)
+    Sprite,
+}
+
+impl ResourceType {
+    /// 资源所在的 WebGAL 目录
+    pub fn dir(&self) -> &'static str {
+        match self {
+            Self::Figure | Self::Sprite => "figure",
+            Self::Background => "background",
+            Self::Bgm => "bgm",
+            Self::Vocal => "vocal",
+        }
+    }
+
+    /// 由 WebGAL 目录名得到资源类型, 立绘目录视为 Live2D 模型
+    pub fn from_dir(dir: &str) -> Option<Self> {
+        Some(match dir {
+            "figure" => Self::Figure,
+            "background" => Self::Background,
+            "bgm" => Self::Bgm,
+            "vocal" => Self::Vocal,
+            _ => return None,
+        })
+    }
 }
 
 /// WebGAL 资源
 ///
 /// 作为 Resolver 的解析结果, Downloader 的接收类型.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Resource {
     pub kind: ResourceType,
     pub url: String,
     pub path: String,
 }
 
+impl Resource {
+    /// 链接指向的本地文件, 由用户提供替代资源时使用
+    pub fn local_file(&self) -> Option<&Path> {
+        self.url.strip_prefix(LOCAL_URL_SCHEME).map(Path::new)
+    }
+}
+
 impl Asset for Resource {
     fn relative_path(&self) -> String {
         match self.kind {
@@ -35,6 +74,7 @@ impl Asset for Resource {
     }
 
     fn absolute_path(&self, root: impl AsRef<Path>) -> PathBuf {
-        root.as_ref().join(format!("{}/{}", self.kind, self.path))
+        root.as_ref()
+            .join(format!("{}/{}", self.kind.dir(), self.path))
     }
 }
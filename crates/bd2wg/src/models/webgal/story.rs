@@ -7,9 +7,10 @@ use std::{
 
 use crate::{impl_iter_for_tuple, models::webgal::display_action_iter, traits::asset::Asset};
 
-use super::Action;
+use super::{Action, EngineVersion};
 
-const START_SCENE_PATH: &str = "start.txt";
+/// 初始场景路径
+pub const START_SCENE_PATH: &str = "start.txt";
 
 /// WebGAL 故事脚本
 pub struct Story(pub Vec<Scene>);
@@ -28,7 +29,7 @@ impl_iter_for_tuple! {Story, Scene}
 
 impl Display for Scene {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        display_action_iter(self.actions.iter(), f)
+        display_action_iter(self.actions.iter(), self.engine, f)
     }
 }
 
@@ -37,6 +38,8 @@ impl Display for Scene {
 pub struct Scene {
     pub path: String,
     pub actions: Vec<Action>,
+    /// 目标 WebGAL 版本, 渲染时据此改写命令
+    pub engine: EngineVersion,
 }
 
 impl Scene {
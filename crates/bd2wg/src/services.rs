@@ -1,6 +1,18 @@
 //! bd2wg 业务实现
 
+#[cfg(feature = "online-meta")]
+pub mod bestdori_api;
+pub mod convert;
 pub mod downloader;
+pub mod extractor;
+#[cfg(feature = "online-meta")]
+pub mod fetcher;
+pub mod filter;
+pub mod hook;
+pub mod merge;
+pub mod output;
 pub mod pipeline;
 pub mod resolver;
 pub mod transpiler;
+#[cfg(feature = "watch")]
+pub mod watch;
@@ -0,0 +1,590 @@
+//! Bestdori 元数据查询
+//!
+//! 通过 Bestdori 公开的 JSON API 查询活动, 乐队和角色信息, 并按 id 获取社区故事和活动剧情.
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread::sleep,
+    time::{Duration, Instant, SystemTime},
+};
+
+use reqwest::{blocking::Client, header::HeaderMap};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+
+use crate::{
+    error::*,
+    models::{
+        bestdori::{
+            self, BandMeta, CharacterMeta, EventDetail, EventMeta, Region, Scenario, StoryMeta,
+            asset_url_root,
+        },
+        config::ApiConfig,
+    },
+    safe_unwrap_lock,
+    utils::{create_and_write, new_client_with_header, render_template},
+};
+
+/// Bestdori API 入口链接
+pub const BESTDORI_API_URL_ROOT: &str = "https://bestdori.com/api/";
+
+const BESTDORI_API_EVENTS: &str = "events/all.5.json";
+const BESTDORI_API_BANDS: &str = "bands/all.1.json";
+const BESTDORI_API_CHARACTERS: &str = "characters/all.2.json";
+const BESTDORI_API_POST: &str = "post/details?id=";
+
+/// Bestdori 社区故事页面路径
+const BESTDORI_STORY_PAGE: &str = "/community/stories/";
+/// Bestdori 故事查看器中活动剧情的页面路径
+const BESTDORI_EVENT_STORY_PAGE: &str = "/storyviewer/event/";
+
+/// 故事 id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoryId {
+    /// 社区故事帖子
+    Post(u64),
+    /// 活动剧情, 话数从 1 开始
+    Event { event: u32, episode: usize },
+}
+
+impl fmt::Display for StoryId {
+    /// 用作默认文件名
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Post(id) => write!(f, "{id}"),
+            Self::Event { event, episode } => write!(f, "event{event}-{episode}"),
+        }
+    }
+}
+
+/// 故事的标题和所属活动, 乐队, 用于命名模板和报告
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoryInfo {
+    /// 故事标题, 活动剧情为该话的标题
+    pub title: Option<String>,
+    /// 活动名称 (仅活动剧情)
+    pub event: Option<String>,
+    /// 对话最多的角色所属的乐队
+    pub band: Option<String>,
+}
+
+impl StoryInfo {
+    /// 渲染命名模板, 可用变量: `${id}`, `${title}`, `${event}`, `${band}`, 缺失的变量为空
+    pub fn render(&self, template: &str, id: StoryId) -> String {
+        render_template(
+            template,
+            &[
+                ("id", &id.to_string()),
+                ("title", self.title.as_deref().unwrap_or_default()),
+                ("event", self.event.as_deref().unwrap_or_default()),
+                ("band", self.band.as_deref().unwrap_or_default()),
+            ],
+        )
+    }
+
+    /// 报告中展示的标题, 活动剧情前置活动名称
+    pub fn display_title(&self) -> Option<String> {
+        match (&self.event, &self.title) {
+            (Some(event), Some(title)) => Some(format!("{event} - {title}")),
+            (event, title) => title.clone().or_else(|| event.clone()),
+        }
+    }
+}
+
+/// 从 id 或页面链接解析故事 id
+///
+/// 社区故事如 `1234`, `https://bestdori.com/community/stories/1234/title`;
+/// 活动剧情如 `event:12:3`, `https://bestdori.com/tool/storyviewer/event/jp/12/3`.
+pub fn parse_story_id(input: &str) -> Option<StoryId> {
+    let input = input.trim();
+    let event = |event: &str, episode: &str| {
+        Some(StoryId::Event {
+            event: event.parse().ok()?,
+            episode: episode.parse().ok().filter(|&n| n > 0)?,
+        })
+    };
+
+    if let Some(rest) = input.strip_prefix("event:") {
+        let (id, episode) = rest.split_once(':')?;
+        return event(id, episode);
+    }
+
+    if let Some((_, rest)) = input.split_once(BESTDORI_EVENT_STORY_PAGE) {
+        let mut parts = rest.split(['/', '?', '#']).skip(1);
+        return event(parts.next()?, parts.next()?);
+    }
+
+    match input.split_once(BESTDORI_STORY_PAGE) {
+        Some((_, rest)) => rest.split(['/', '?', '#']).next()?.parse().ok(),
+        None => input.parse().ok(),
+    }
+    .map(StoryId::Post)
+}
+
+#[derive(Deserialize)]
+struct PostResponse {
+    post: Post,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Post {
+    title: Option<String>,
+    summary: Option<String>,
+    story_source: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct ScenarioAsset {
+    #[serde(rename = "Base")]
+    base: Scenario,
+}
+
+/// Bestdori API 客户端
+///
+/// 响应缓存在磁盘上, 并限制请求频率, 避免批量转换时频繁请求.
+pub struct BestdoriApi {
+    client: Client,
+    config: ApiConfig,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl BestdoriApi {
+    /// 根据请求头创建客户端
+    pub fn new(header: HeaderMap) -> Result<Self> {
+        Self::with_config(header, ApiConfig::default())
+    }
+
+    /// 根据请求头和配置创建客户端
+    pub fn with_config(header: HeaderMap, config: ApiConfig) -> Result<Self> {
+        Ok(Self {
+            client: new_client_with_header(header).map_err(|e| ApiError {
+                url: BESTDORI_API_URL_ROOT.to_string(),
+                error: e.into(),
+            })?,
+            config,
+            last_request: Mutex::new(None),
+        })
+    }
+
+    /// API 对应的缓存文件, 路径分隔符和查询字符串中的字符替换为 `_`
+    fn cache_path(&self, api: &str) -> Option<PathBuf> {
+        self.config
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join(api.replace(['/', '\\', '?', '=', '&', ':'], "_")))
+    }
+
+    /// 读取未过期的缓存
+    fn read_cache(&self, api: &str) -> Option<Vec<u8>> {
+        let path = self.cache_path(api)?;
+        let age = SystemTime::now()
+            .duration_since(fs::metadata(&path).ok()?.modified().ok()?)
+            .ok()?;
+
+        if age > Duration::from_secs(self.config.cache_ttl) {
+            return None;
+        }
+
+        fs::read(path).ok()
+    }
+
+    /// 等待至满足最小请求间隔
+    fn throttle(&self) {
+        let mut last = safe_unwrap_lock!(self.last_request.lock());
+        let interval = Duration::from_millis(self.config.request_interval);
+
+        if let Some(elapsed) = last.map(|t| t.elapsed())
+            && elapsed < interval
+        {
+            sleep(interval - elapsed);
+        }
+
+        *last = Some(Instant::now());
+    }
+
+    /// 请求 API 原始响应 (优先读取缓存)
+    fn get_bytes(&self, api: &str) -> Result<Vec<u8>> {
+        self.get_url_bytes(format!("{BESTDORI_API_URL_ROOT}{api}"), api)
+    }
+
+    /// 请求原始响应, 以 key 作为缓存文件名 (优先读取缓存)
+    fn get_url_bytes(&self, url: String, key: &str) -> Result<Vec<u8>> {
+        if let Some(bytes) = self.read_cache(key) {
+            return Ok(bytes);
+        }
+
+        self.throttle();
+
+        let bytes = self
+            .client
+            .get(&url)
+            .send()
+            .and_then(|resp| resp.error_for_status())
+            .and_then(|resp| resp.bytes())
+            .map_err(|e| ApiError {
+                url,
+                error: e.into(),
+            })?;
+
+        // 缓存写入失败不影响结果
+        if let Some(path) = self.cache_path(key) {
+            let _ = create_and_write(&bytes, &path);
+        }
+
+        Ok(bytes.to_vec())
+    }
+
+    /// 请求并解析 API
+    fn get<T: DeserializeOwned>(&self, api: &str) -> Result<T> {
+        let bytes = self.get_bytes(api)?;
+
+        Ok(serde_json::from_slice(&bytes).map_err(|e| ApiError {
+            url: format!("{BESTDORI_API_URL_ROOT}{api}"),
+            error: e.into(),
+        })?)
+    }
+
+    /// 获取故事脚本 JSON, 格式与编辑器导出的脚本相同
+    ///
+    /// 社区故事的帖子标题和简介并入脚本元数据; 活动剧情由游戏剧情脚本转换而来,
+    /// 标题和简介取自指定区域 (缺失时任意区域).
+    pub fn story_bytes(&self, id: StoryId, region: Region) -> Result<Vec<u8>> {
+        let story = match id {
+            StoryId::Post(id) => self.post_story(id)?,
+            StoryId::Event { event, episode } => self.event_story(event, episode, region)?,
+        };
+
+        Ok(serde_json::to_vec(&story).map_err(|e| ApiError {
+            url: id.to_string(),
+            error: e.into(),
+        })?)
+    }
+
+    /// 获取并解析故事脚本
+    pub fn story(&self, id: StoryId, region: Region) -> Result<bestdori::Story> {
+        let bytes = self.story_bytes(id, region)?;
+        Ok(bestdori::Story::from_bytes(&bytes).map_err(FileError::from)?)
+    }
+
+    /// 获取故事脚本并保存, 供管线读取
+    pub fn story_to(&self, id: StoryId, region: Region, path: impl AsRef<Path>) -> Result<()> {
+        let bytes = self.story_bytes(id, region)?;
+        Ok(create_and_write(bytes, path.as_ref()).map_err(FileError::from)?)
+    }
+
+    /// 社区故事帖子中的脚本
+    fn post_story(&self, id: u64) -> Result<Value> {
+        let Post {
+            title,
+            summary,
+            story_source: mut story,
+        } = self
+            .get::<PostResponse>(&format!("{BESTDORI_API_POST}{id}"))?
+            .post;
+
+        for (key, value) in [("title", title), ("summary", summary)] {
+            if let Some(value) = value {
+                story.entry(key).or_insert(Value::String(value));
+            }
+        }
+
+        Ok(Value::Object(story))
+    }
+
+    /// 活动剧情转换得到的脚本
+    fn event_story(&self, event: u32, episode: usize, region: Region) -> Result<Value> {
+        let api = format!("events/{event}.json");
+        let detail: EventDetail = self.get(&api)?;
+        let story = episode
+            .checked_sub(1)
+            .and_then(|i| detail.stories.get(i))
+            .ok_or_else(|| ApiError {
+                url: format!("{BESTDORI_API_URL_ROOT}{api}"),
+                error: io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("event {event} has no episode {episode}"),
+                )
+                .into(),
+            })?;
+
+        let key = format!(
+            "scenario/eventstory/event{event}_rip/Scenario{}.asset",
+            story.scenario_id
+        );
+        let url = format!("{}{key}", asset_url_root(region));
+        let bytes = self.get_url_bytes(url.clone(), &key)?;
+        let scenario = serde_json::from_slice::<ScenarioAsset>(&bytes)
+            .map_err(|e| ApiError {
+                url,
+                error: e.into(),
+            })?
+            .base;
+
+        let meta = StoryMeta {
+            title: story.title.get_or_any(region).map(str::to_string),
+            description: story.synopsis.get_or_any(region).map(str::to_string),
+        };
+        let voice_bundle = format!("sound/voice/scenario/eventstory{event}_{}", episode - 1);
+
+        Ok(scenario.to_story_value(&voice_bundle, &meta))
+    }
+
+    /// 全部活动
+    pub fn events(&self) -> Result<HashMap<u32, EventMeta>> {
+        self.get(BESTDORI_API_EVENTS)
+    }
+
+    /// 全部乐队
+    pub fn bands(&self) -> Result<HashMap<u32, BandMeta>> {
+        self.get(BESTDORI_API_BANDS)
+    }
+
+    /// 全部角色, 含 id 超出剧情角色范围的路人角色
+    pub fn characters(&self) -> Result<HashMap<u32, CharacterMeta>> {
+        self.get(BESTDORI_API_CHARACTERS)
+    }
+
+    /// 查询活动名称
+    pub fn event_name(&self, id: u32, region: Region) -> Result<Option<String>> {
+        Ok(self
+            .events()?
+            .get(&id)
+            .and_then(|event| event.event_name.get_or_any(region))
+            .map(str::to_string))
+    }
+
+    /// 查询乐队名称
+    pub fn band_name(&self, id: u32, region: Region) -> Result<Option<String>> {
+        Ok(self
+            .bands()?
+            .get(&id)
+            .and_then(|band| band.band_name.get_or_any(region))
+            .map(str::to_string))
+    }
+
+    /// 查询故事的活动和乐队, 标题取自脚本元数据
+    pub fn story_info(
+        &self,
+        id: StoryId,
+        story: &bestdori::Story,
+        region: Region,
+    ) -> Result<StoryInfo> {
+        let event = match id {
+            StoryId::Event { event, .. } => self.event_name(event, region)?,
+            StoryId::Post(_) => None,
+        };
+
+        // 按对话次数统计角色所属的乐队
+        let characters = self.characters()?;
+        let mut bands: HashMap<u32, usize> = HashMap::new();
+        for action in &story.actions {
+            if let bestdori::Action::Talk(talk) = action {
+                for id in &talk.characters {
+                    if let Some(band) = characters.get(&(*id as u32)).and_then(|c| c.band_id) {
+                        *bands.entry(band).or_default() += 1;
+                    }
+                }
+            }
+        }
+        let band = match bands
+            .into_iter()
+            .max_by_key(|&(id, n)| (n, std::cmp::Reverse(id)))
+        {
+            Some((band, _)) => self.band_name(band, region)?,
+            None => None,
+        };
+
+        Ok(StoryInfo {
+            title: story.meta().title.clone(),
+            event,
+            band,
+        })
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_parse_story_id() {
+    assert_eq!(parse_story_id(" 1234 "), Some(StoryId::Post(1234)));
+    assert_eq!(
+        parse_story_id("https://bestdori.com/community/stories/1234/MyGO"),
+        Some(StoryId::Post(1234))
+    );
+    assert_eq!(
+        parse_story_id("https://bestdori.com/community/stories/1234?p=1"),
+        Some(StoryId::Post(1234))
+    );
+    assert_eq!(
+        parse_story_id("event:12:3"),
+        Some(StoryId::Event {
+            event: 12,
+            episode: 3
+        })
+    );
+    assert_eq!(
+        parse_story_id("https://bestdori.com/tool/storyviewer/event/jp/12/3"),
+        Some(StoryId::Event {
+            event: 12,
+            episode: 3
+        })
+    );
+    assert_eq!(parse_story_id("event:12:0"), None);
+    assert_eq!(parse_story_id("story.json"), None);
+}
+
+#[test]
+#[cfg(test)]
+fn test_api_cache() {
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-api-cache-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("bands_all.1.json"),
+        r#"{"1": {"bandName": ["Poppin'Party"]}}"#,
+    )
+    .unwrap();
+
+    let api = BestdoriApi::with_config(
+        HeaderMap::new(),
+        ApiConfig {
+            cache_dir: Some(dir.clone()),
+            request_interval: 50,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // 查询字符串不出现在缓存文件名中
+    assert_eq!(
+        api.cache_path("post/details?id=1234").unwrap(),
+        dir.join("post_details_id_1234")
+    );
+
+    // 命中缓存时不发出请求
+    assert_eq!(
+        api.band_name(1, Region::Jp).unwrap().as_deref(),
+        Some("Poppin'Party")
+    );
+
+    // 相邻请求至少间隔 request_interval
+    let start = Instant::now();
+    api.throttle();
+    api.throttle();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_recorded_responses() {
+    use bd2wg_fixtures::api;
+
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-api-recorded-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let client = BestdoriApi::with_config(
+        HeaderMap::new(),
+        ApiConfig {
+            cache_dir: Some(dir.clone()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // 以缓存提供响应
+    for (key, bytes) in [
+        (BESTDORI_API_EVENTS, api::EVENTS),
+        (BESTDORI_API_BANDS, api::BANDS),
+        (BESTDORI_API_CHARACTERS, api::CHARACTERS),
+        ("post/details?id=1234", api::POST),
+        ("events/1.json", api::EVENT_DETAIL),
+        (
+            "scenario/eventstory/event1_rip/Scenarioevent1-01.asset",
+            api::SCENARIO,
+        ),
+    ] {
+        fs::write(client.cache_path(key).unwrap(), bytes).unwrap();
+    }
+
+    assert_eq!(
+        client.event_name(1, Region::En).unwrap().as_deref(),
+        Some("The First Event")
+    );
+    // 未上线的区域回退到任意区域
+    assert_eq!(
+        client.event_name(2, Region::En).unwrap().as_deref(),
+        Some("二番目のイベント")
+    );
+    assert_eq!(client.event_name(3, Region::Jp).unwrap(), None);
+    assert_eq!(
+        client.band_name(2, Region::Kr).unwrap().as_deref(),
+        Some("Afterglow")
+    );
+
+    let characters = client.characters().unwrap();
+    assert_eq!(characters[&1].band_id, Some(1));
+    assert_eq!(characters[&1].first_name.get(Region::Jp), Some("香澄"));
+    assert_eq!(characters[&601].band_id, None);
+
+    // 帖子标题和简介并入脚本元数据
+    let story = client.story(StoryId::Post(1234), Region::Jp).unwrap();
+    assert_eq!(story.meta().title.as_deref(), Some("はじめての物語"));
+    assert_eq!(story.actions.len(), 1);
+
+    // 乐队取自对话角色, 帖子不属于活动
+    let info = client
+        .story_info(StoryId::Post(1234), &story, Region::Jp)
+        .unwrap();
+    assert_eq!(info.band.as_deref(), Some("Poppin'Party"));
+    assert_eq!(info.event, None);
+    assert_eq!(info.display_title().as_deref(), Some("はじめての物語"));
+
+    // 活动剧情由游戏剧情脚本转换, 标题取自活动详情
+    let story = client
+        .story(
+            StoryId::Event {
+                event: 1,
+                episode: 1,
+            },
+            Region::En,
+        )
+        .unwrap();
+    assert_eq!(story.meta().title.as_deref(), Some("The Beginning"));
+    assert_eq!(
+        story.meta().description.as_deref(),
+        Some("The story begins.")
+    );
+    assert!(story.actions.iter().any(
+        |a| matches!(a, bestdori::Action::Effect(e) if matches!(e.effect, bestdori::Effect::BlackOut))
+    ));
+
+    let id = StoryId::Event {
+        event: 1,
+        episode: 1,
+    };
+    let info = client.story_info(id, &story, Region::En).unwrap();
+    assert_eq!(
+        info.display_title().as_deref(),
+        Some("The First Event - The Beginning")
+    );
+    assert_eq!(
+        info.render("${band}/${id}.json", id),
+        "Poppin'Party/event1-1.json"
+    );
+    assert!(
+        client
+            .story(
+                StoryId::Event {
+                    event: 1,
+                    episode: 2
+                },
+                Region::En
+            )
+            .is_err()
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,154 @@
+//! 同步转译入口
+//!
+//! 不创建线程, 不读写文件, 适用于在其他程序, 测试或 WASM 中直接调用.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde_json::Value;
+
+use crate::{
+    error::*,
+    models::{
+        bestdori,
+        config::{ResolverConfig, TranspilerConfig},
+        webgal::{self, Resource},
+    },
+    services::{resolver::Resolver, transpiler::Transpiler},
+    traits::{
+        resolve::ResolveStats,
+        transpile::{ActionStats, Transpile},
+    },
+};
+
+/// 转译选项
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub resolver: ResolverConfig,
+    pub transpiler: TranspilerConfig,
+}
+
+/// 转译结果
+pub struct Conversion {
+    pub story: webgal::Story,
+    /// 需要下载的资源
+    pub resources: Vec<Resource>,
+    pub actions: ActionStats,
+    pub stats: ResolveStats,
+    pub errors: Vec<Error>,
+    pub warnings: Vec<Warning>,
+}
+
+/// 转译 Bestdori 脚本, 返回 WebGAL 脚本, 所需资源和收集到的错误与警告
+pub fn convert(story: &bestdori::Story, opts: &Options) -> Conversion {
+    let result = Transpiler::with_config(
+        Resolver::with_config(opts.resolver.clone()),
+        opts.transpiler.clone(),
+    )
+    .transpile(story);
+
+    Conversion {
+        story: result.story,
+        resources: result
+            .resources
+            .into_iter()
+            .map(Arc::unwrap_or_clone)
+            .collect(),
+        actions: result.actions,
+        stats: result.stats,
+        errors: result.errors,
+        warnings: result.warnings,
+    }
+}
+
+/// 校验结果
+#[derive(Debug, Default)]
+pub struct Validation {
+    pub scenes: usize,
+    pub actions: ActionStats,
+    /// 未知指令类型及其数量, 缺少类型字段时记为空字符串
+    pub unknown: BTreeMap<String, usize>,
+    /// 需要下载的资源数
+    pub resources: usize,
+    pub stats: ResolveStats,
+    pub errors: Vec<Error>,
+    pub warnings: Vec<Warning>,
+}
+
+/// 校验 Bestdori 脚本: 转译但不输出, 统计指令, 未知指令类型和无法解析的资源
+pub fn validate(story: &bestdori::Story, opts: &Options) -> Validation {
+    let result = Transpiler::with_config(
+        Resolver::with_config(opts.resolver.clone()),
+        opts.transpiler.clone(),
+    )
+    .transpile(story);
+
+    let mut unknown = BTreeMap::new();
+    for action in story.iter() {
+        if let bestdori::Action::Unknown(raw) = action {
+            let kind = raw.get("type").and_then(Value::as_str).unwrap_or_default();
+            *unknown.entry(kind.to_string()).or_default() += 1;
+        }
+    }
+
+    Validation {
+        scenes: result.story.len().0,
+        actions: result.actions,
+        unknown,
+        resources: result.resources.len(),
+        stats: result.stats,
+        errors: result.errors,
+        warnings: result.warnings,
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_validate() {
+    let json = br#"{"actions": [
+        {"type": "talk", "wait": true, "delay": 0, "name": "", "body": "hi", "motions": [], "voices": [], "characters": []},
+        {"type": "telop", "text": "?"},
+        {"type": "telop"},
+        {"kind": "none"}
+    ]}"#;
+    let story = bestdori::Story::from_bytes(json).unwrap();
+    let validation = validate(&story, &Options::default());
+
+    assert_eq!(validation.actions.talk, 1);
+    assert_eq!(validation.actions.skipped, 3);
+    assert_eq!(validation.unknown.get("telop"), Some(&2));
+    assert_eq!(validation.unknown.get(""), Some(&1));
+}
+
+#[test]
+#[cfg(test)]
+fn test_convert() {
+    let story = bestdori::Story::from_bytes(bd2wg_fixtures::BRANCHY.json).unwrap();
+    let conversion = convert(&story, &Options::default());
+
+    let paths: Vec<_> = conversion
+        .story
+        .iter()
+        .map(|scene| scene.path.as_str())
+        .collect();
+    assert_eq!(paths.len(), 5);
+    assert_eq!(paths[..3], ["start.txt", "scene-1.txt", "scene-2.txt"]);
+    assert!(
+        conversion.story.0[1]
+            .to_string()
+            .contains("choose:第一話:scene-2.txt;")
+    );
+    assert!(
+        conversion.story.0[2]
+            .to_string()
+            .contains("ましろ:ここは……どこ？")
+    );
+
+    assert_eq!(conversion.resources.len(), 6);
+    assert!(conversion.resources.iter().any(|res| {
+        res.url == "https://example.com/uploads/sample/classroom.png"
+            && res.kind == webgal::ResourceType::Background
+            && res.path.contains("classroom")
+    }));
+    assert_eq!(conversion.actions.talk, 4);
+    assert!(conversion.errors.is_empty(), "{:?}", conversion.errors);
+}
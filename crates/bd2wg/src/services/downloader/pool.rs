@@ -4,10 +4,11 @@
 
 use std::{
     collections::VecDeque,
+    io::{self, Read},
     mem,
     sync::{
         Arc,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc::{Receiver, Sender, channel},
     },
     thread::{JoinHandle, sleep, spawn},
@@ -21,20 +22,17 @@ use reqwest::{
     header::HeaderMap,
 };
 
-use crate::{error::*, impl_drop_for_handle, traits::handle::Handle, utils::*};
+use crate::{
+    error::*,
+    impl_drop_for_handle,
+    models::config::DownloadConfig,
+    traits::{download::Progress, handle::Handle},
+    utils::*,
+};
 
 /// 下载池返回类型
 pub type PoolResult<T> = std::result::Result<T, DownloadErrorKind>;
 
-/// 下载器工作线程计数
-const CLIENT_COUNT: usize = 4;
-
-/// 单个下载任务时间限制
-const TASK_TIMEOUT: Duration = Duration::from_secs(24);
-
-/// 单个下载任务最大重试次数
-const TASK_MAX_RETRIES: usize = 3;
-
 /// 客户端重启所需的连续失败次数
 const CLIENT_RESTART_FAILURE_THRESHOLD: usize = 5;
 
@@ -44,27 +42,76 @@ const CLIENT_RESTART_BACKOFF: Duration = Duration::from_secs(8);
 /// 客户端连续重启在全部失败情况下的次数限制
 const CLIENT_RESTART_LIMIT: usize = 3;
 
+/// 读取响应体的分块大小
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 下载进度计数
+///
+/// 由工作线程更新, 任务句柄读取.
+#[derive(Debug, Default)]
+pub struct ProgressCounter {
+    started: AtomicBool,
+    done: AtomicU64,
+    total: AtomicU64, // 0 表示未知
+}
+
+impl ProgressCounter {
+    /// 开始 (或重试时重新开始) 计数
+    pub fn start(&self, total: Option<u64>) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total.unwrap_or(0), Ordering::Relaxed);
+        self.started.store(true, Ordering::Relaxed);
+    }
+
+    /// 增加已完成量
+    pub fn advance(&self, n: u64) {
+        self.done.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// 当前进度
+    pub fn snapshot(&self) -> Progress {
+        let total = self.total.load(Ordering::Relaxed);
+
+        Progress {
+            started: self.started.load(Ordering::Relaxed),
+            done: self.done.load(Ordering::Relaxed),
+            total: (total != 0).then_some(total),
+        }
+    }
+}
+
 /// 下载命令
 struct DownloadCommand {
     url: String,
     cancel: Arc<AtomicBool>,
+    progress: Arc<ProgressCounter>,
     sender: Sender<PoolResult<Bytes>>,
 }
 
 /// 下载任务句柄
 pub struct DownloadHandle {
     cancel: Arc<AtomicBool>,
+    progress: Arc<ProgressCounter>,
     receiver: Receiver<PoolResult<Bytes>>,
 }
 
+impl DownloadHandle {
+    /// 当前进度
+    pub fn progress(&self) -> Progress {
+        self.progress.snapshot()
+    }
+}
+
 impl Handle for DownloadHandle {
     type Result = PoolResult<Bytes>;
 
     /// 等待并获取下载结果
     ///
-    /// panic: 下载池 / 句柄被调用 cancel.
+    /// 任务被取消时返回 Cancelled.
     fn join(self: Box<Self>) -> Self::Result {
-        self.receiver.recv().unwrap() // 下载池不应崩溃
+        self.receiver
+            .recv()
+            .unwrap_or(Err(DownloadErrorKind::Cancelled))
     }
 
     fn cancel(&mut self) {
@@ -81,15 +128,21 @@ impl_drop_for_handle! {DownloadHandle}
 /// 创建下载任务, 获取命令和句柄
 fn new_download_task(url: &str) -> (DownloadCommand, Box<DownloadHandle>) {
     let cancel = Arc::new(AtomicBool::new(false));
+    let progress = Arc::new(ProgressCounter::default());
     let (sender, receiver) = channel();
 
     (
         DownloadCommand {
             url: url.to_string(),
             cancel: cancel.clone(),
+            progress: progress.clone(),
             sender,
         },
-        Box::new(DownloadHandle { cancel, receiver }),
+        Box::new(DownloadHandle {
+            cancel,
+            progress,
+            receiver,
+        }),
     )
 }
 
@@ -98,6 +151,7 @@ struct DownloadTask {
     count: usize,
     url: String,
     cancel: Arc<AtomicBool>,
+    progress: Arc<ProgressCounter>,
     sender: Sender<PoolResult<Bytes>>,
 }
 
@@ -106,6 +160,7 @@ impl DownloadTask {
         let DownloadCommand {
             url,
             cancel,
+            progress,
             sender,
         } = command;
 
@@ -113,6 +168,7 @@ impl DownloadTask {
             count: 0,
             url,
             cancel,
+            progress,
             sender,
         }
     }
@@ -139,6 +195,7 @@ struct DownloadPoolWorker {
     successes_since_restart: usize, // 自上次重启以来成功的任务数
 
     header: Arc<HeaderMap>, // 保存请求头以支持重新创建 Client
+    config: DownloadConfig,
     client: Client,
     cancel: Arc<AtomicBool>,
     receiver: MultiReceiver<DownloadCommand>,
@@ -149,6 +206,7 @@ impl DownloadPoolWorker {
     /// 创建 (但不运行) 下载池内部管理
     fn new(
         header: Arc<HeaderMap>,
+        config: DownloadConfig,
         cancel: Arc<AtomicBool>,
         receiver: MultiReceiver<DownloadCommand>,
     ) -> PoolResult<Self> {
@@ -159,6 +217,7 @@ impl DownloadPoolWorker {
             restart_count: 0,
             successes_since_restart: 0,
             header,
+            config,
             client,
             cancel: cancel.clone(),
             receiver,
@@ -166,13 +225,19 @@ impl DownloadPoolWorker {
         })
     }
 
-    /// 退出全部下载任务
+    /// 退出全部下载任务, 包括尚未接收的任务
     fn cancel(&mut self) {
         drop(mem::take(&mut self.tasks));
+
+        while let Ok(cmd) = self.receiver.try_recv() {
+            drop(DownloadTask::new(cmd));
+        }
     }
 
     /// 接收并启动一些下载任务
-    fn receive(&mut self) {
+    ///
+    /// 下载池已关闭且没有剩余任务时返回 false.
+    fn receive(&mut self) -> bool {
         if !self.tasks.is_empty() {
             // 有任务时, 非阻塞获取并加入一个任务
             if let Ok(cmd) = self.receiver.try_recv() {
@@ -180,9 +245,13 @@ impl DownloadPoolWorker {
             }
         } else if let Ok(cmd) = self.receiver.recv() {
             // 没有任务时, 阻塞等待下一个任务
-            // 当 Sender 丢弃时, 忽略错误, run() 将进入下一轮开头的退出检查分支
             self.tasks.push_back(DownloadTask::new(cmd));
+        } else {
+            // Sender 已丢弃
+            return false;
         }
+
+        true
     }
 
     // ---------------- task: begin ----------------
@@ -194,7 +263,8 @@ impl DownloadPoolWorker {
             return;
         }
         // 尝试下载 (阻塞)
-        let timeout = TASK_TIMEOUT.mul_f32((1 << (self.restart_count + task.count)) as f32); // 分段重试
+        let timeout = Duration::from_secs(self.config.timeout)
+            .mul_f32((1 << (self.restart_count + task.count)) as f32); // 分段重试
         let res = self.client.get(&task.url).timeout(timeout).send();
 
         // 处理响应
@@ -212,6 +282,10 @@ impl DownloadPoolWorker {
             self.successes_since_restart = 0;
 
             // 等待一段时间再尝试重建 client
+            tracing::info!(
+                restart = self.restart_count,
+                "too many failures, restarting client"
+            );
             sleep(CLIENT_RESTART_BACKOFF);
             if let Ok(client) = new_client_with_header((*self.header).clone()) {
                 self.client = client;
@@ -233,6 +307,27 @@ impl DownloadPoolWorker {
         }
     }
 
+    /// 分块读取响应体并更新进度
+    fn read_body(task: &DownloadTask, resp: &mut Response) -> io::Result<Bytes> {
+        task.progress.start(resp.content_length());
+
+        let mut body = Vec::new();
+        let mut chunk = vec![0; BODY_CHUNK_SIZE];
+        loop {
+            let n = match resp.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            body.extend_from_slice(&chunk[..n]);
+            task.progress.advance(n as u64);
+        }
+
+        Ok(Bytes::from(body))
+    }
+
     /// 处理成功返回的 Response
     fn handle_response_ok(
         &mut self,
@@ -240,19 +335,20 @@ impl DownloadPoolWorker {
         resp: reqwest::blocking::Response,
     ) {
         match resp.error_for_status() {
-            Ok(resp) => {
-                match resp.bytes() {
+            Ok(mut resp) => {
+                // 检查 Content-Encoding, 在 reqwest 未自动解压的情况下提供回退解码
+                #[cfg(feature = "wider_compression")]
+                let encoding = resp
+                    .headers()
+                    .get(reqwest::header::CONTENT_ENCODING)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("")
+                    .to_lowercase();
+
+                match Self::read_body(&task, &mut resp) {
                     Ok(bytes) => {
                         #[cfg(feature = "wider_compression")]
                         {
-                            // 检查 Content-Encoding, 在 reqwest 未自动解压的情况下提供回退解码
-                            let encoding = resp
-                                .headers()
-                                .get(reqwest::header::CONTENT_ENCODING)
-                                .and_then(|v| v.to_str().ok())
-                                .unwrap_or("")
-                                .to_lowercase();
-
                             match maybe_decompress_bytes(&bytes, &encoding) {
                                 Ok(out) => self.handle_success(task, Bytes::from(out)),
                                 Err(e) => task.send(Err(DownloadErrorKind::Io(e))),
@@ -280,22 +376,28 @@ impl DownloadPoolWorker {
     }
 
     /// 请求成功但读取 body 出错
-    fn handle_body_error(&mut self, task: DownloadTask, err: reqwest::Error) {
-        self.increment_failure_and_maybe_retry(task, err);
+    fn handle_body_error(&mut self, task: DownloadTask, err: io::Error) {
+        self.increment_failure_and_maybe_retry(task, DownloadErrorKind::Io(err));
     }
 
     /// 请求发起阶段出错 (包含超时)
     fn handle_request_error(&mut self, task: DownloadTask, err: reqwest::Error) {
-        self.increment_failure_and_maybe_retry(task, err);
+        self.increment_failure_and_maybe_retry(task, DownloadErrorKind::Reqwest(err));
     }
 
     /// 增加失败计数并决定是重试还是结束任务
-    fn increment_failure_and_maybe_retry(&mut self, mut task: DownloadTask, err: reqwest::Error) {
+    fn increment_failure_and_maybe_retry(
+        &mut self,
+        mut task: DownloadTask,
+        err: DownloadErrorKind,
+    ) {
         task.count += 1;
         self.count += 1;
-        if task.count >= TASK_MAX_RETRIES || self.restart_count >= CLIENT_RESTART_LIMIT {
-            task.send(Err(DownloadErrorKind::Reqwest(err)));
+        if task.count >= self.config.retries || self.restart_count >= CLIENT_RESTART_LIMIT {
+            tracing::debug!(url = task.url, attempts = task.count, "download failed");
+            task.send(Err(err));
         } else {
+            tracing::debug!(url = task.url, attempt = task.count, "retry download");
             self.tasks.push_back(task);
         }
     }
@@ -307,6 +409,7 @@ impl DownloadPoolWorker {
     /// 保证下载循环不会崩溃, 进而保证下载任务和下载池句柄的有效性.
     ///
     /// 每次循环时, 检查下载池和下载任务的退出信号, 然后尝试处理最早的任务.
+    /// 下载池关闭 (Sender 丢弃) 后处理完剩余任务再退出.
     ///
     /// 错误处理:
     /// 1. 下载任务超时 / 出错时, 先推入队尾重新尝试.
@@ -322,7 +425,9 @@ impl DownloadPoolWorker {
             }
 
             // 接收任务
-            self.receive();
+            if !self.receive() {
+                break;
+            }
 
             // 处理任务
             if let Some(task) = self.tasks.pop_front() {
@@ -341,22 +446,26 @@ impl DownloadPoolWorker {
 #[derive(Debug)]
 pub struct DownloadPool {
     cancel: Arc<AtomicBool>,
-    sender: MultiSender<DownloadCommand>,
+    sender: Option<MultiSender<DownloadCommand>>, // 关闭时丢弃
     handles: Vec<JoinHandle<()>>,
 }
 
 impl DownloadPool {
-    /// 根据请求头启动下载池
-    pub fn new(header: HeaderMap) -> PoolResult<Box<Self>> {
+    /// 根据请求头和下载配置启动下载池
+    pub fn with_config(header: HeaderMap, config: DownloadConfig) -> PoolResult<Box<Self>> {
         let header = Arc::new(header);
         let cancel = Arc::new(AtomicBool::new(false));
         let (sender, receiver) = unbounded();
 
         // 同时启动多个工作线程
-        let handles = (0..CLIENT_COUNT)
+        let handles = (0..config.concurrency.max(1))
             .map(|_| {
-                let worker =
-                    DownloadPoolWorker::new(header.clone(), cancel.clone(), receiver.clone())?;
+                let worker = DownloadPoolWorker::new(
+                    header.clone(),
+                    config,
+                    cancel.clone(),
+                    receiver.clone(),
+                )?;
                 Ok(spawn(move || worker.run()))
             })
             .collect::<PoolResult<_>>()?;
@@ -364,7 +473,7 @@ impl DownloadPool {
         Ok(Box::new(Self {
             handles,
             cancel,
-            sender,
+            sender: Some(sender),
         }))
     }
 
@@ -372,13 +481,20 @@ impl DownloadPool {
     ///
     /// 非阻塞地在子线程启动下载任务, 返回任务句柄.
     ///
-    /// panic: 下载池被调用 cancel.
+    /// 下载池已关闭时, 任务直接以 Cancelled 结束.
     pub fn download(&mut self, url: &str) -> Box<DownloadHandle> {
-        #[cfg(debug_assertions)]
-        dbg!(url);
+        tracing::debug!(url, "queue download");
 
         let (cmd, handle) = new_download_task(url);
-        self.sender.send(cmd).unwrap();
+        let rejected = match &self.sender {
+            Some(sender) => sender.send(cmd).err().map(|e| e.into_inner()),
+            None => Some(cmd),
+        };
+
+        if let Some(cmd) = rejected {
+            drop(DownloadTask::new(cmd));
+        }
+
         handle
     }
 }
@@ -386,24 +502,37 @@ impl DownloadPool {
 impl Handle for DownloadPool {
     type Result = ();
 
-    /// 等待下载任务完成
-    ///
-    /// panic: 下载池被调用 cancel.
+    /// 关闭下载池, 等待剩余下载任务完成
     fn join(mut self: Box<Self>) -> Self::Result {
+        self.sender = None;
+
         for handle in mem::take(&mut self.handles) {
-            handle.join().unwrap(); // 下载池不应崩溃
+            let _ = handle.join(); // 工作线程 panic 时其任务已随之结束
         }
     }
 
     fn cancel(&mut self) {
         self.cancel.store(true, Ordering::Relaxed);
-        self.handles.clear();
+        self.sender = None; // 唤醒等待任务的工作线程
     }
 
-    /// 询问下载任务是否均已完成
+    /// 询问工作线程是否均已退出
     fn is_finished(&self) -> bool {
-        self.handles.iter().any(|handle| handle.is_finished())
+        self.handles.iter().all(|handle| handle.is_finished())
     }
 }
 
 impl_drop_for_handle! {DownloadPool}
+
+#[test]
+#[cfg(test)]
+fn test_cancelled_pool() {
+    let mut pool = DownloadPool::with_config(HeaderMap::new(), DownloadConfig::default()).unwrap();
+    pool.cancel();
+
+    let handle = pool.download("https://bestdori.com/");
+    assert!(handle.is_finished());
+    assert!(matches!(handle.join(), Err(DownloadErrorKind::Cancelled)));
+
+    pool.join();
+}
@@ -1,6 +1,7 @@
 //! Bestdori 下载器
 
 use std::{
+    fs,
     path::{Path, PathBuf},
     sync::{
         Arc, Mutex,
@@ -14,26 +15,42 @@ use reqwest::header::HeaderMap;
 
 use crate::{
     error::*,
-    false_or_panic, impl_drop_for_handle,
+    false_or_return, impl_drop_for_handle,
     models::{
         bestdori,
+        config::DownloadConfig,
         webgal::{self, Resource, ResourceType, default_model_config_path},
     },
-    traits::{asset::Asset, download::Download, handle::Handle},
-    utils::*,
+    safe_unwrap_lock,
+    services::output::FsSink,
+    traits::{
+        asset::Asset,
+        download::{Download, DownloadTask, Progress},
+        handle::Handle,
+        output::OutputSink,
+    },
 };
 
-use super::pool::{DownloadHandle, DownloadPool};
+use super::pool::{DownloadHandle, DownloadPool, ProgressCounter};
 
 type DownloadResult = std::result::Result<(), Vec<Error>>;
 
 /// Downloader join(): Live2d 任务结束状态检查间隔时间
 const DOWNLOAD_JOIN_CHECK_BACKOFF: Duration = Duration::from_secs(1);
 
+/// 生成下载错误, 取消时返回 Cancelled
+fn download_error(url: &str, path: &Path, error: DownloadErrorKind) -> Error {
+    match error {
+        DownloadErrorKind::Cancelled => Error::Cancelled,
+        error => Error::Download(DownloadError::with_context(url, path, error)),
+    }
+}
+
 /// 常规下载任务句柄
 struct CommonDownloadHandle {
     url: String,
     path: PathBuf,
+    sink: Arc<dyn OutputSink>,
     handle: Option<Box<DownloadHandle>>,
 }
 
@@ -41,25 +58,21 @@ impl Handle for CommonDownloadHandle {
     type Result = DownloadResult;
 
     /// 等待下载任务完成
-    ///
-    /// panic: 下载器 / 句柄被调用 cancel.
     fn join(mut self: Box<Self>) -> Self::Result {
         self.handle
             .take()
             .unwrap()
             .join()
-            .and_then(|bytes| create_and_write(&bytes, &self.path).map_err(DownloadErrorKind::Io))
-            .map_err(|e| {
-                vec![Error::Download(DownloadError {
-                    url: self.url.clone(),
-                    path: self.path.clone(),
-                    error: e,
-                })]
+            .and_then(|bytes| {
+                self.sink
+                    .write(&self.path, &bytes)
+                    .map_err(DownloadErrorKind::Io)
             })
+            .map_err(|e| vec![download_error(&self.url, &self.path, e)])
     }
 
     fn cancel(&mut self) {
-        if let Some(mut handle) = self.handle.take() {
+        if let Some(handle) = &mut self.handle {
             handle.cancel();
         }
     }
@@ -73,10 +86,52 @@ impl Handle for CommonDownloadHandle {
 
 impl_drop_for_handle! {CommonDownloadHandle}
 
+impl DownloadTask for CommonDownloadHandle {
+    fn progress(&self) -> Progress {
+        self.handle
+            .as_ref()
+            .map(|handle| handle.progress())
+            .unwrap_or_default()
+    }
+}
+
+/// 本地文件复制任务句柄, 创建时即已完成
+struct LocalFileHandle {
+    result: Option<DownloadResult>,
+}
+
+impl Handle for LocalFileHandle {
+    type Result = DownloadResult;
+
+    fn join(mut self: Box<Self>) -> Self::Result {
+        self.result.take().unwrap()
+    }
+
+    fn cancel(&mut self) {}
+
+    fn is_finished(&self) -> bool {
+        true
+    }
+}
+
+impl_drop_for_handle! {LocalFileHandle}
+
+impl DownloadTask for LocalFileHandle {
+    fn progress(&self) -> Progress {
+        Progress {
+            started: true,
+            done: 1,
+            total: Some(1),
+        }
+    }
+}
+
 struct Live2dDownloadWorker {
     url: String,
     path: PathBuf, // Live2D 资源根目录
+    sink: Arc<dyn OutputSink>,
     cancel: Arc<AtomicBool>,
+    progress: Arc<ProgressCounter>, // 以模型文件计
     count: Arc<AtomicUsize>,
     pool: Arc<Mutex<Box<DownloadPool>>>,
 }
@@ -86,10 +141,12 @@ impl Live2dDownloadWorker {
     fn new(
         url: &str,
         path: &Path,
+        sink: Arc<dyn OutputSink>,
         count: Arc<AtomicUsize>,
         pool: Arc<Mutex<Box<DownloadPool>>>,
-    ) -> (Self, Arc<AtomicBool>) {
+    ) -> (Self, Arc<AtomicBool>, Arc<ProgressCounter>) {
         let cancel = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(ProgressCounter::default());
 
         count.fetch_add(1, Ordering::Relaxed);
 
@@ -97,27 +154,24 @@ impl Live2dDownloadWorker {
             Self {
                 url: url.to_string(),
                 path: path.to_path_buf(),
+                sink,
                 cancel: cancel.clone(),
+                progress: progress.clone(),
                 count,
                 pool,
             },
             cancel,
+            progress,
         )
     }
 
     /// (阻塞) 执行主循环
     fn run(self) -> DownloadResult {
         // 生成下载错误
-        let download_error = |error| {
-            Error::Download(DownloadError {
-                url: self.url.clone(),
-                path: self.path.clone(),
-                error,
-            })
-        };
+        let download_error = |error| download_error(&self.url, &self.path, error);
 
         // 获取 Live2D 配置
-        let handle = self.pool.lock().unwrap().download(&self.url);
+        let handle = safe_unwrap_lock!(self.pool.lock()).download(&self.url);
         let resource = handle
             .join()
             .map_err(download_error)
@@ -130,13 +184,15 @@ impl Live2dDownloadWorker {
                 let (model, res) = webgal::Model::from_bestdori_model(model);
 
                 // 写入配置文件
-                create_and_write(
-                    &serde_json::to_vec_pretty(&model).map_err(|e| download_error(e.into()))?,
-                    Path::new(&default_model_config_path(&self.path.to_string_lossy())),
-                )
-                .map_err(|e| download_error(e.into()))?;
+                self.sink
+                    .write(
+                        Path::new(&default_model_config_path(&self.path.to_string_lossy())),
+                        &serde_json::to_vec_pretty(&model).map_err(|e| download_error(e.into()))?,
+                    )
+                    .map_err(|e| download_error(e.into()))?;
 
                 // 合成完整路径
+                self.progress.start(Some(res.len() as u64));
                 Ok(res
                     .into_iter()
                     .map(|(url, path)| (url, self.path.join(path))))
@@ -146,24 +202,23 @@ impl Live2dDownloadWorker {
         // 启动下载
         let handles = resource
             .into_iter()
-            .map(|(url, path)| (self.pool.lock().unwrap().download(&url), path));
-
-        // 等待并处理下载结果
-        let errors: Vec<_> = handles
-            .into_iter()
-            .filter_map(|(handle, path)| {
-                false_or_panic! {self.cancel}
-
-                handle
-                    .join()
-                    .map_err(download_error)
-                    .and_then(|bytes| {
-                        // 写入本地文件
-                        create_and_write(&bytes, &path).map_err(|err| download_error(err.into()))
-                    })
-                    .err() // 保留失败错误
-            })
-            .collect();
+            .map(|(url, path)| (safe_unwrap_lock!(self.pool.lock()).download(&url), path));
+
+        // 等待并处理下载结果, 保留失败错误
+        let mut errors = Vec::new();
+        for (handle, path) in handles {
+            false_or_return! {self.cancel, Err(vec![Error::Cancelled])}
+
+            if let Err(e) = handle.join().map_err(download_error).and_then(|bytes| {
+                // 写入本地文件
+                self.sink
+                    .write(&path, &bytes)
+                    .map_err(|err| download_error(err.into()))
+            }) {
+                errors.push(e);
+            }
+            self.progress.advance(1);
+        }
 
         if errors.is_empty() {
             Ok(())
@@ -184,6 +239,7 @@ impl Drop for Live2dDownloadWorker {
 /// Live2D 下载任务句柄
 struct Live2dDownloadHandle {
     cancel: Arc<AtomicBool>,
+    progress: Arc<ProgressCounter>,
     handle: Option<JoinHandle<DownloadResult>>,
 }
 
@@ -192,14 +248,16 @@ impl Live2dDownloadHandle {
     fn new(
         url: &str,
         path: &Path,
+        sink: Arc<dyn OutputSink>,
         count: Arc<AtomicUsize>,
         pool: Arc<Mutex<Box<DownloadPool>>>,
     ) -> Box<Self> {
-        let (worker, cancel) = Live2dDownloadWorker::new(url, path, count, pool);
+        let (worker, cancel, progress) = Live2dDownloadWorker::new(url, path, sink, count, pool);
         let handle = thread::spawn(move || worker.run());
 
         Box::new(Self {
             cancel,
+            progress,
             handle: Some(handle),
         })
     }
@@ -209,12 +267,15 @@ impl Handle for Live2dDownloadHandle {
     type Result = DownloadResult;
 
     fn join(mut self: Box<Self>) -> Self::Result {
-        self.handle.take().unwrap().join().unwrap()
+        self.handle
+            .take()
+            .unwrap()
+            .join()
+            .unwrap_or_else(|e| Err(vec![Error::from_panic(e)]))
     }
 
     fn cancel(&mut self) {
         self.cancel.store(true, Ordering::Relaxed);
-        self.handle = None;
     }
 
     fn is_finished(&self) -> bool {
@@ -224,11 +285,20 @@ impl Handle for Live2dDownloadHandle {
 
 impl_drop_for_handle! {Live2dDownloadHandle}
 
+impl DownloadTask for Live2dDownloadHandle {
+    /// 获取模型配置后开始计数
+    fn progress(&self) -> Progress {
+        self.progress.snapshot()
+    }
+}
+
 /// Bestdori 下载器
 ///
-/// 根据不同的资源类型下载对应资源
+/// 根据不同的资源类型下载对应资源. 写入路径取自解析器生成的资源,
+/// 因此与解析时的命名策略一致, 替换链接或本地文件时也不会改变.
 pub struct Downloader {
     root: PathBuf,
+    sink: Arc<dyn OutputSink>,
     count: Arc<AtomicUsize>, // Live2D 任务计数
     pool: Option<Arc<Mutex<Box<DownloadPool>>>>,
 }
@@ -236,11 +306,31 @@ pub struct Downloader {
 impl Downloader {
     /// 在指定目录创建下载器
     pub fn new(root: impl AsRef<Path>, header: HeaderMap) -> Result<Self> {
+        Self::with_sink(root, header, Arc::new(FsSink))
+    }
+
+    /// 创建写入指定输出目标的下载器
+    pub fn with_sink(
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+        sink: Arc<dyn OutputSink>,
+    ) -> Result<Self> {
+        Self::with_config(root, header, sink, DownloadConfig::default())
+    }
+
+    /// 按下载配置创建写入指定输出目标的下载器
+    pub fn with_config(
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+        sink: Arc<dyn OutputSink>,
+        config: DownloadConfig,
+    ) -> Result<Self> {
         Ok(Self {
             root: root.as_ref().to_path_buf(),
+            sink,
             count: Arc::new(AtomicUsize::new(0)),
             pool: Some(Arc::new(Mutex::new(
-                DownloadPool::new(header).map_err(DownloadError::from)?,
+                DownloadPool::with_config(header, config).map_err(DownloadError::from)?,
             ))),
         })
     }
@@ -248,21 +338,28 @@ impl Downloader {
     /// 下载普通资源
     fn download_normal(&mut self, res: &Resource) -> Box<CommonDownloadHandle> {
         let path = res.absolute_path(&self.root);
-        let handle = self
-            .pool
-            .as_ref()
-            .unwrap()
-            .lock()
-            .unwrap()
-            .download(&res.url);
+        let handle = safe_unwrap_lock!(self.pool.as_ref().unwrap().lock()).download(&res.url);
 
         Box::new(CommonDownloadHandle {
             url: res.url.clone(),
             path,
+            sink: self.sink.clone(),
             handle: Some(handle),
         })
     }
 
+    /// 复制用户提供的本地文件
+    fn copy_local(&self, res: &Resource, file: &Path) -> Box<LocalFileHandle> {
+        let path = res.absolute_path(&self.root);
+        let result = fs::read(file)
+            .and_then(|bytes| self.sink.write(&path, &bytes))
+            .map_err(|e| vec![download_error(&res.url, &path, DownloadErrorKind::Io(e))]);
+
+        Box::new(LocalFileHandle {
+            result: Some(result),
+        })
+    }
+
     /// 下载 Live2D 模型
     ///
     /// resource.url 实际为 buildScript url.
@@ -270,6 +367,7 @@ impl Downloader {
         Live2dDownloadHandle::new(
             &res.url,
             &res.absolute_path(&self.root), // 编译器会优化掉 & + clone 吧...
+            self.sink.clone(),
             self.count.clone(),
             self.pool.as_ref().unwrap().clone(),
         )
@@ -280,8 +378,6 @@ impl Handle for Downloader {
     type Result = ();
 
     /// 等待下载任务完成并返回
-    ///
-    /// panic: 下载器被调用 cancel.
     fn join(mut self: Box<Self>) -> Self::Result {
         // 等待 Live2D 下载任务
         while self.count.load(Ordering::Relaxed) != 0 {
@@ -289,33 +385,35 @@ impl Handle for Downloader {
         }
 
         // 等待常规下载任务
-        Arc::try_unwrap(self.pool.take().unwrap())
-            .unwrap()
-            .into_inner()
-            .unwrap()
-            .join();
+        let pool = self.pool.take().unwrap();
+        match Arc::try_unwrap(pool) {
+            Ok(pool) => safe_unwrap_lock!(pool.into_inner()).join(),
+            // Live2D 任务线程尚未释放下载池时, 关闭后等待其自行退出
+            Err(pool) => safe_unwrap_lock!(pool.lock()).cancel(),
+        }
     }
 
     fn cancel(&mut self) {
-        // 子线程中的 Live2dDownloadHandle 会自然 panic.
-        if let Some(pool) = self.pool.take() {
-            pool.lock().unwrap().cancel();
+        // 子线程中的 Live2dDownloadHandle 随后获得 Cancelled 并退出.
+        if let Some(pool) = &self.pool {
+            safe_unwrap_lock!(pool.lock()).cancel();
         }
     }
 
     fn is_finished(&self) -> bool {
         self.pool
             .as_ref()
-            .is_none_or(|pool| pool.lock().unwrap().is_finished())
+            .is_none_or(|pool| safe_unwrap_lock!(pool.lock()).is_finished())
     }
 }
 
 impl Download for Downloader {
-    fn download(
-        &mut self,
-        res: impl AsRef<Resource>,
-    ) -> Box<dyn Handle<Result = std::result::Result<(), Vec<Error>>>> {
+    fn download(&mut self, res: impl AsRef<Resource>) -> Box<dyn DownloadTask> {
         let res = res.as_ref();
+        if let Some(file) = res.local_file() {
+            return self.copy_local(res, file);
+        }
+
         match res.kind {
             ResourceType::Figure => self.download_model(res),
             _ => self.download_normal(res),
@@ -0,0 +1,808 @@
+//! 工程输出
+//!
+//! 将转译结果写入 WebGAL 游戏目录, 可选生成完整的工程骨架.
+
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{Error, ReferenceError, ValidateError},
+    models::{
+        bestdori::StoryMeta,
+        checkpoint::CHECKPOINT_FILE,
+        config::{Newline, OverwritePolicy, ProjectConfig, TextFormat},
+        manifest::{MANIFEST_FILE, Manifest},
+        webgal::{Resource, ResourceType, Scene, scene_references, validate_scene},
+    },
+    services::{merge::merge3, output::ZipSink},
+    traits::{asset::Asset, output::OutputSink},
+    utils::{hash_bytes, short_hash},
+};
+
+/// WebGAL 游戏目录名
+pub const WEBGAL_GAME_DIR: &str = "game";
+
+/// WebGAL 游戏配置文件名
+pub const WEBGAL_CONFIG_FILE: &str = "config.txt";
+
+/// 未指定标题时的游戏名
+pub const DEFAULT_GAME_NAME: &str = "bd2wg";
+
+/// 合并模式下保存生成副本的目录, 位于游戏目录下
+pub const PRISTINE_DIR: &str = ".bd2wg/pristine";
+
+/// WebGAL 游戏目录下的资源目录
+pub const WEBGAL_ASSET_DIRS: &[&str] = &[
+    "animation",
+    "background",
+    "bgm",
+    "figure",
+    "scene",
+    "tex",
+    "video",
+    "vocal",
+];
+
+/// 工程元数据, 取自故事脚本和转译结果
+#[derive(Debug, Clone, Default)]
+pub struct ProjectMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// 标题画面背景
+    pub title_img: Option<String>,
+    /// 标题画面 bgm
+    pub title_bgm: Option<String>,
+}
+
+impl ProjectMeta {
+    /// 从故事元数据和资源生成, 以最先出现的背景和 bgm 作为标题画面
+    pub fn new<'a>(meta: &StoryMeta, resources: impl IntoIterator<Item = &'a Resource>) -> Self {
+        let mut project = Self {
+            title: meta.title.clone(),
+            description: meta.description.clone(),
+            ..Self::default()
+        };
+
+        for res in resources {
+            match res.kind {
+                ResourceType::Background if project.title_img.is_none() => {
+                    project.title_img = Some(res.relative_path())
+                }
+                ResourceType::Bgm if project.title_bgm.is_none() => {
+                    project.title_bgm = Some(res.relative_path())
+                }
+                _ => {}
+            }
+        }
+
+        project
+    }
+}
+
+/// 场景写入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written,
+    /// 内容相同, 未重写
+    Unchanged,
+    /// 内容不同, 按配置保留已有文件
+    Kept,
+    /// 与用户修改合并
+    Merged,
+    /// 合并冲突, 保留已有文件并另存为 `.new` 文件
+    Conflict,
+}
+
+/// 按文本格式编码
+pub fn encode_text(text: &str, format: TextFormat) -> Vec<u8> {
+    let text = match format.newline {
+        Newline::Lf => text.to_string(),
+        Newline::Crlf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+    };
+
+    match format.bom {
+        true => ["\u{feff}", &text].concat().into_bytes(),
+        false => text.into_bytes(),
+    }
+}
+
+/// 读取文本文件, 去除 BOM, 不存在时返回空文本
+fn read_text(sink: &dyn OutputSink, path: &Path) -> io::Result<String> {
+    let bytes = sink.read(path)?.unwrap_or_default();
+    let text = String::from_utf8(bytes).map_err(io::Error::other)?;
+    Ok(text.trim_start_matches('\u{feff}').to_string())
+}
+
+/// 写入单个场景
+///
+/// 已有文件内容相同时跳过, 以保持修改时间不变.
+pub fn write_scene(
+    sink: &dyn OutputSink,
+    scene: &Scene,
+    root: &Path,
+    policy: OverwritePolicy,
+    format: TextFormat,
+) -> io::Result<WriteOutcome> {
+    write_scene_text(
+        sink,
+        root,
+        &format!("scene/{}", scene.relative_path()),
+        &encode_text(&scene.to_string(), format),
+        policy,
+    )
+}
+
+/// 写入已编码的场景内容, path 为相对游戏目录的路径
+///
+/// 已有文件内容相同时跳过, 以保持修改时间不变.
+/// 合并模式下在 [`PRISTINE_DIR`] 保存生成的副本, 作为下次合并的基准;
+/// 发生冲突时保留原基准, 以便用户处理冲突后再次合并.
+pub fn write_scene_text(
+    sink: &dyn OutputSink,
+    root: &Path,
+    path: &str,
+    text: &[u8],
+    policy: OverwritePolicy,
+) -> io::Result<WriteOutcome> {
+    let target = root.join(path);
+    let pristine = root.join(PRISTINE_DIR).join(path);
+
+    // 仅文件不存在时视为新文件, 其他读取错误直接返回, 以免覆盖无法读取的场景
+    let outcome = match (sink.read(&target)?, policy) {
+        (Some(old), _) if old == text => WriteOutcome::Unchanged,
+        (Some(_), OverwritePolicy::Keep) => return Ok(WriteOutcome::Kept),
+        (Some(old), OverwritePolicy::Merge) => {
+            match sink.read(&pristine)? {
+                // 未经修改
+                Some(base) if base == old => {
+                    sink.write(&target, text)?;
+                    WriteOutcome::Written
+                }
+                base => {
+                    let text = String::from_utf8_lossy(text);
+                    let merged = base.map(|base| {
+                        merge3(
+                            &String::from_utf8_lossy(&base),
+                            &String::from_utf8_lossy(&old),
+                            &text,
+                        )
+                    });
+
+                    match merged {
+                        Some(Ok(merged)) => {
+                            sink.write(&target, merged.as_bytes())?;
+                            WriteOutcome::Merged
+                        }
+                        // 冲突或缺少基准
+                        _ => {
+                            sink.write(&root.join(format!("{path}.new")), text.as_bytes())?;
+                            WriteOutcome::Conflict
+                        }
+                    }
+                }
+            }
+        }
+        _ => {
+            sink.write(&target, text)?;
+            WriteOutcome::Written
+        }
+    };
+
+    if policy == OverwritePolicy::Merge && outcome != WriteOutcome::Conflict {
+        sink.write(&pristine, text)?;
+    }
+
+    Ok(outcome)
+}
+
+/// 检查清单中的场景引用的资源是否存在
+pub fn audit_references(sink: &dyn OutputSink, root: &Path) -> io::Result<Vec<ReferenceError>> {
+    let manifest = Manifest::load_from(sink, root)?;
+    let mut errors = Vec::new();
+
+    for entry in &manifest.scenes {
+        let Some(bytes) = sink.read(&root.join(&entry.path))? else {
+            continue;
+        };
+        let text = String::from_utf8_lossy(&bytes);
+
+        errors.extend(
+            scene_references(&text)
+                .into_iter()
+                .filter(|(_, path)| !sink.exists(&root.join(path)))
+                .map(|(line, path)| ReferenceError {
+                    scene: entry.path.clone(),
+                    line,
+                    path,
+                }),
+        );
+    }
+
+    Ok(errors)
+}
+
+/// 是否为 bd2wg 的工作文件: 清单, 断点, 合并基准及合并冲突的 `.new` 文件
+///
+/// 参数为相对游戏目录的路径. 分享工程时无需包含这些文件.
+pub fn is_work_file(path: &Path) -> bool {
+    path == Path::new(MANIFEST_FILE)
+        || path == Path::new(CHECKPOINT_FILE)
+        || path.starts_with(Path::new(PRISTINE_DIR).parent().unwrap())
+        || path.extension().is_some_and(|ext| ext == "new")
+}
+
+/// 检查游戏目录下的全部场景: 能否解析, 引用的资源是否存在
+///
+/// 与 [`audit_references`] 不同, 不依赖清单, 同样适用于手动编写或修改过的场景.
+pub fn audit_project(root: &Path) -> io::Result<Vec<Error>> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(&path, files)?;
+            } else if path.extension().is_some_and(|ext| ext == "txt") {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut scenes = Vec::new();
+    walk(&root.join("scene"), &mut scenes)?;
+    scenes.sort();
+
+    let mut errors = Vec::new();
+    for file in scenes {
+        let text = fs::read_to_string(&file)?;
+        let scene = (file.strip_prefix(root).map_err(io::Error::other)?)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        errors.extend(validate_scene(&text).into_iter().map(|(line, error)| {
+            Error::from(ValidateError {
+                scene: scene.clone(),
+                line,
+                error,
+            })
+        }));
+        errors.extend(
+            scene_references(&text)
+                .into_iter()
+                .filter(|(_, path)| !root.join(path).exists())
+                .map(|(line, path)| {
+                    Error::from(ReferenceError {
+                        scene: scene.clone(),
+                        line,
+                        path,
+                    })
+                }),
+        );
+    }
+
+    Ok(errors)
+}
+
+/// 生成游戏配置文件内容
+///
+/// 配置项优先于故事元数据.
+pub fn project_config_text(config: &ProjectConfig, meta: &ProjectMeta) -> String {
+    let title = config
+        .title
+        .as_deref()
+        .or(meta.title.as_deref())
+        .unwrap_or(DEFAULT_GAME_NAME);
+    let key = config.key.clone().unwrap_or_else(|| short_hash(title));
+
+    let mut text = format!("Game_name:{title};\nGame_key:{key};\n");
+
+    if let Some(description) = config.description.as_ref().or(meta.description.as_ref()) {
+        // 配置值不能跨行
+        let _ = writeln!(text, "Description:{};", description.replace('\n', " "));
+    }
+
+    if config.title_from_story {
+        if let Some(img) = &meta.title_img {
+            let _ = writeln!(text, "Title_img:{img};");
+        }
+        if let Some(bgm) = &meta.title_bgm {
+            let _ = writeln!(text, "Title_bgm:{bgm};");
+        }
+    }
+
+    text
+}
+
+/// 在索引场景中追加选项, 已有指向该场景的选项时返回 None
+///
+/// 追加到首个 `choose` 指令末尾, 没有时新增一行.
+pub fn append_index_entry(text: &str, label: &str, file: &str) -> Option<String> {
+    // 选项文本不能包含分隔符
+    let label = label.replace([':', ';', '|', '\n'], " ");
+    let entry = format!("{label}:{file}");
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+
+    match lines.iter_mut().find(|line| line.starts_with("choose:")) {
+        Some(line) => {
+            if line
+                .split(['|', ';'])
+                .any(|option| option.ends_with(&format!(":{file}")))
+            {
+                return None;
+            }
+
+            let end = line.find(';').unwrap_or(line.len());
+            line.insert_str(end, &format!("|{entry}"));
+        }
+        None => lines.push(format!("choose:{entry};")),
+    }
+
+    Some(lines.join("\n") + "\n")
+}
+
+/// 目录条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// 选项文本
+    pub label: String,
+    /// 故事首个场景
+    pub file: String,
+}
+
+impl IndexEntry {
+    /// 以故事标题作为选项文本, 没有标题时使用 slug
+    pub fn new(meta: &StoryMeta, slug: &str, file: &str) -> Self {
+        Self {
+            label: meta.title.clone().unwrap_or_else(|| slug.to_string()),
+            file: file.to_string(),
+        }
+    }
+}
+
+/// 生成目录场景内容, 以一个 `choose` 列出全部故事
+pub fn index_scene_text<'a>(entries: impl IntoIterator<Item = &'a IndexEntry>) -> String {
+    entries.into_iter().fold(String::new(), |text, entry| {
+        append_index_entry(&text, &entry.label, &entry.file).unwrap_or(text)
+    })
+}
+
+/// 写入目录场景, 覆盖已有内容
+pub fn write_index_scene(
+    sink: &dyn OutputSink,
+    root: &Path,
+    index: &str,
+    entries: &[IndexEntry],
+    format: TextFormat,
+) -> io::Result<()> {
+    let path = Scene::new(index).absolute_path(root);
+    sink.write(&path, &encode_text(&index_scene_text(entries), format))
+}
+
+/// 在已有工程的索引场景中加入指向合并场景的选项
+pub fn merge_into_index(
+    sink: &dyn OutputSink,
+    root: &Path,
+    index: &str,
+    label: &str,
+    file: &str,
+    format: TextFormat,
+) -> io::Result<()> {
+    let path = Scene::new(index).absolute_path(root);
+    let text = read_text(sink, &path)?;
+
+    match append_index_entry(&text, label, file) {
+        Some(text) => sink.write(&path, &encode_text(&text, format)),
+        None => Ok(()),
+    }
+}
+
+/// 将游戏目录打包为 zip, 条目位于 `game/` 下, 跳过 `skip` 返回 true 的文件
+///
+/// `skip` 的参数为相对游戏目录的路径. 经由 [`ZipSink`] 写入, 与管线直接输出 zip 时一致.
+pub fn export_zip(root: &Path, path: &Path, skip: impl Fn(&Path) -> bool) -> io::Result<()> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(&path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(root, &mut files)?;
+
+    let sink = ZipSink::new(root, path);
+    for file in files {
+        // 输出文件位于游戏目录内时跳过自身, 并跳过合并基准
+        if path.canonicalize().ok() == file.canonicalize().ok()
+            || file.starts_with(root.join(PRISTINE_DIR))
+        {
+            continue;
+        }
+
+        let name = file.strip_prefix(root).map_err(io::Error::other)?;
+        if !skip(name) {
+            sink.write(&file, &fs::read(&file)?)?;
+        }
+    }
+
+    sink.finish()
+}
+
+/// 生成 WebGAL 工程骨架 (资源目录), 返回游戏目录
+pub fn scaffold_project(root: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let game = root.as_ref().join(WEBGAL_GAME_DIR);
+    create_asset_dirs(&game)?;
+    Ok(game)
+}
+
+/// 在游戏目录下创建资源目录
+pub fn create_asset_dirs(game: &Path) -> io::Result<()> {
+    for dir in WEBGAL_ASSET_DIRS {
+        fs::create_dir_all(game.join(dir))?;
+    }
+    Ok(())
+}
+
+/// 写入游戏配置文件, 已存在时不覆盖
+pub fn write_project_config(
+    sink: &dyn OutputSink,
+    game: impl AsRef<Path>,
+    config: &ProjectConfig,
+    meta: &ProjectMeta,
+    format: TextFormat,
+) -> io::Result<()> {
+    let path = game.as_ref().join(WEBGAL_CONFIG_FILE);
+
+    if sink.exists(&path) {
+        return Ok(());
+    }
+
+    sink.write(
+        &path,
+        &encode_text(&project_config_text(config, meta), format),
+    )
+}
+
+/// 清理结果
+#[derive(Debug, Clone, Default)]
+pub struct CleanReport {
+    /// 已删除的文件
+    pub removed: Vec<String>,
+    /// 生成后被修改而保留的文件
+    pub kept: Vec<String>,
+}
+
+/// 按清单删除 bd2wg 生成的场景和资源
+///
+/// 内容哈希与清单不符或未记录哈希的文件视为用户修改, 予以保留; 清单外的文件不受影响.
+/// 全部删除后一并删除清单.
+pub fn clean_project(root: &Path) -> io::Result<CleanReport> {
+    let mut manifest = Manifest::load(root)?;
+    let mut report = CleanReport::default();
+
+    for entries in [&mut manifest.scenes, &mut manifest.assets] {
+        entries.retain(|entry| {
+            let path = root.join(&entry.path);
+            let Ok(bytes) = fs::read(&path) else {
+                return false; // 已不存在
+            };
+
+            // 未记录哈希时无法确认为 bd2wg 生成, 与哈希不符同样保留
+            if entry
+                .hash
+                .as_ref()
+                .is_none_or(|hash| *hash != hash_bytes(&bytes))
+            {
+                report.kept.push(entry.path.clone());
+                return true;
+            }
+
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    report.removed.push(entry.path.clone());
+                    false
+                }
+                Err(_) => {
+                    report.kept.push(entry.path.clone());
+                    true
+                }
+            }
+        });
+    }
+
+    match manifest.scenes.is_empty() && manifest.assets.is_empty() {
+        true => match fs::remove_file(root.join(MANIFEST_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        },
+        false => manifest.save(root)?,
+    }
+
+    Ok(report)
+}
+
+#[test]
+#[cfg(test)]
+fn test_project_config_text() {
+    let config = crate::models::config::ProjectConfigBuilder::default()
+        .key(Some(String::from("mygo")))
+        .title_from_story(true)
+        .build()
+        .unwrap();
+    let meta = ProjectMeta {
+        title: Some(String::from("MyGO!!!!!")),
+        description: Some(String::from("迷子でもいい\n前へ進め")),
+        title_img: Some(String::from("bg00010.png")),
+        title_bgm: None,
+    };
+
+    assert_eq!(
+        project_config_text(&config, &meta),
+        "Game_name:MyGO!!!!!;\nGame_key:mygo;\nDescription:迷子でもいい 前へ進め;\nTitle_img:bg00010.png;\n"
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_append_index_entry() {
+    assert_eq!(
+        append_index_entry("", "Story A", "a-scene-1.txt").as_deref(),
+        Some("choose:Story A:a-scene-1.txt;\n")
+    );
+    assert_eq!(
+        append_index_entry("intro:...;\nchoose:A:a.txt;\n", "B", "b.txt").as_deref(),
+        Some("intro:...;\nchoose:A:a.txt|B:b.txt;\n")
+    );
+    assert_eq!(
+        append_index_entry("choose:A:a.txt|B:b.txt;", "B", "b.txt"),
+        None
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_index_scene_text() {
+    let entries = [
+        IndexEntry::new(&StoryMeta::default(), "ch1", "ch1-scene-1.txt"),
+        IndexEntry {
+            label: String::from("第二话: 迷子"),
+            file: String::from("ch2-scene-1.txt"),
+        },
+    ];
+
+    assert_eq!(
+        index_scene_text(&entries),
+        "choose:ch1:ch1-scene-1.txt|第二话  迷子:ch2-scene-1.txt;\n"
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_encode_text() {
+    let format = TextFormat {
+        newline: Newline::Crlf,
+        bom: true,
+    };
+
+    assert_eq!(
+        encode_text("a;\nb;\r\n", format),
+        "\u{feff}a;\r\nb;\r\n".as_bytes()
+    );
+    assert_eq!(encode_text("a;\n", TextFormat::default()), b"a;\n");
+}
+
+#[test]
+#[cfg(test)]
+fn test_write_scene() {
+    let sink = crate::services::output::MemorySink::default();
+    let root = Path::new("game");
+    let mut scene = Scene::new("start.txt");
+    scene
+        .actions
+        .push(crate::models::webgal::EndAction {}.into());
+
+    let write = |scene: &Scene, policy| {
+        write_scene(&sink, scene, root, policy, TextFormat::default()).unwrap()
+    };
+
+    assert_eq!(
+        write(&scene, OverwritePolicy::Overwrite),
+        WriteOutcome::Written
+    );
+    assert_eq!(
+        write(&scene, OverwritePolicy::Overwrite),
+        WriteOutcome::Unchanged
+    );
+
+    scene
+        .actions
+        .push(crate::models::webgal::EndAction {}.into());
+    assert_eq!(write(&scene, OverwritePolicy::Keep), WriteOutcome::Kept);
+    assert_eq!(
+        write(&scene, OverwritePolicy::Overwrite),
+        WriteOutcome::Written
+    );
+
+    assert_eq!(sink.into_files().len(), 1);
+
+    // 无法读取已有文件时不覆盖
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-write-scene-{}", std::process::id()));
+    fs::create_dir_all(dir.join("scene/start.txt")).unwrap();
+    let result = write_scene(
+        &crate::services::output::FsSink,
+        &scene,
+        &dir,
+        OverwritePolicy::Overwrite,
+        TextFormat::default(),
+    );
+    assert!(result.is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_write_scene_merge() {
+    use crate::services::output::MemorySink;
+
+    let sink = MemorySink::default();
+    let root = Path::new("game");
+    let write = |text: &str| {
+        write_scene_text(
+            &sink,
+            root,
+            "scene/a.txt",
+            text.as_bytes(),
+            OverwritePolicy::Merge,
+        )
+        .unwrap()
+    };
+    let read =
+        |path: &str| String::from_utf8(sink.read(&root.join(path)).unwrap().unwrap()).unwrap();
+
+    assert_eq!(write("a;\nb;\nc;\nd;\n"), WriteOutcome::Written);
+
+    // 用户修改后重新生成
+    sink.write(&root.join("scene/a.txt"), b"a;\nB;\nc;\nd;\n")
+        .unwrap();
+    assert_eq!(write("a;\nb;\nc;\nD;\n"), WriteOutcome::Merged);
+    assert_eq!(read("scene/a.txt"), "a;\nB;\nc;\nD;\n");
+
+    // 修改同一行, 保留原基准
+    assert_eq!(write("a;\nX;\nc;\nD;\n"), WriteOutcome::Conflict);
+    assert_eq!(read("scene/a.txt"), "a;\nB;\nc;\nD;\n");
+    assert_eq!(read("scene/a.txt.new"), "a;\nX;\nc;\nD;\n");
+    assert_eq!(
+        read(&format!("{PRISTINE_DIR}/scene/a.txt")),
+        "a;\nb;\nc;\nD;\n"
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_export_zip() {
+    let root = std::env::temp_dir().join(format!("bd2wg-test-export-zip-{}", std::process::id()));
+    crate::utils::create_and_write("end;", &root.join("scene/start.txt")).unwrap();
+    crate::utils::create_and_write("Game_name:test;", &root.join(WEBGAL_CONFIG_FILE)).unwrap();
+
+    let path = root.join("game.zip");
+    export_zip(&root, &path, |_| false).unwrap();
+    export_zip(&root, &path, |_| false).unwrap(); // 再次打包时跳过自身
+
+    let zip = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+    let mut names: Vec<_> = zip.file_names().collect();
+    names.sort();
+    assert_eq!(names, vec!["game/config.txt", "game/scene/start.txt"]);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_audit_project() {
+    let root = std::env::temp_dir().join(format!("bd2wg-test-audit-{}", std::process::id()));
+    let write = |path: &str, text: &str| {
+        crate::utils::create_and_write(text, &root.join(path)).unwrap();
+    };
+    write(
+        "scene/start.txt",
+        "changeBg:bg1.png;\nchangeScene:part/a.txt;\n",
+    );
+    write("scene/part/a.txt", "bgm:missing.mp3;\n:unterminated\n");
+    write("background/bg1.png", "");
+    write(MANIFEST_FILE, "{}");
+    write("scene/start.txt.new", "end;");
+
+    let errors: Vec<_> = (audit_project(&root).unwrap().iter())
+        .map(|e| e.kind_name())
+        .collect();
+    assert_eq!(errors, vec!["validate", "reference"]);
+
+    // 打包时跳过工作文件
+    let path = root.join("game.zip");
+    export_zip(&root, &path, is_work_file).unwrap();
+    let zip = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+    let mut names: Vec<_> = zip.file_names().collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "game/background/bg1.png",
+            "game/scene/part/a.txt",
+            "game/scene/start.txt"
+        ]
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_clean_project() {
+    use crate::models::manifest::ManifestEntry;
+
+    let root = std::env::temp_dir().join(format!("bd2wg-test-clean-{}", std::process::id()));
+    let entry = |path: &str, text: &str| {
+        crate::utils::create_and_write(text, &root.join(path)).unwrap();
+        ManifestEntry {
+            path: path.to_string(),
+            hash: Some(hash_bytes(text.as_bytes())),
+            url: None,
+        }
+    };
+
+    let mut manifest = Manifest::default();
+    manifest.insert_scene(entry("scene/start.txt", "end;"));
+    manifest.insert_scene(entry("scene/scene-1.txt", "end;"));
+    manifest.insert_asset(ManifestEntry {
+        hash: None,
+        ..entry("background/bg.png", "png")
+    });
+    manifest.save(&root).unwrap();
+    crate::utils::create_and_write("intro:edited;", &root.join("scene/scene-1.txt")).unwrap();
+    crate::utils::create_and_write("end;", &root.join("scene/mine.txt")).unwrap();
+
+    let report = clean_project(&root).unwrap();
+    assert_eq!(report.removed, vec!["scene/start.txt"]);
+    assert_eq!(report.kept, vec!["scene/scene-1.txt", "background/bg.png"]);
+    assert!(root.join("scene/mine.txt").exists());
+    assert!(root.join("background/bg.png").exists());
+    assert_eq!(Manifest::load(&root).unwrap().scenes.len(), 1);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_audit_references() {
+    use crate::{models::manifest::ManifestEntry, services::output::MemorySink};
+
+    let sink = MemorySink::default();
+    let root = Path::new("game");
+    let text = "changeBg:bg1.png -next;\nchangeFigure:none -id=1;\nSoyo:... -vocal=v1.mp3;\n";
+
+    sink.write(&root.join("scene/start.txt"), text.as_bytes())
+        .unwrap();
+    sink.write(&root.join("background/bg1.png"), b"").unwrap();
+
+    let mut manifest = Manifest::default();
+    manifest.insert_scene(ManifestEntry {
+        path: String::from("scene/start.txt"),
+        hash: None,
+        url: None,
+    });
+    manifest.save_to(&sink, root).unwrap();
+
+    let errors = audit_references(&sink, root).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "scene/start.txt:3: vocal/v1.mp3 not found"
+    );
+}
@@ -0,0 +1,105 @@
+//! 故事获取
+//!
+//! 按 id 经 Bestdori API 获取故事脚本, 按命名模板保存为管线可读取的文件.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::*,
+    models::bestdori::{self, Region},
+    services::bestdori_api::{BestdoriApi, StoryId, StoryInfo},
+    utils::create_and_write,
+};
+
+/// 默认的保存路径模板
+pub const DEFAULT_STORY_TEMPLATE: &str = "story-${id}.json";
+
+/// 获取到的故事
+pub struct FetchedStory {
+    pub id: StoryId,
+    /// 脚本 JSON, 格式与编辑器导出的脚本相同
+    pub bytes: Vec<u8>,
+    pub story: bestdori::Story,
+    pub info: StoryInfo,
+}
+
+impl FetchedStory {
+    /// 按命名模板保存到 dir 下, 返回保存路径
+    pub fn save(&self, dir: &Path, template: &str) -> Result<PathBuf> {
+        let path = dir.join(self.info.render(template, self.id));
+        create_and_write(&self.bytes, &path).map_err(FileError::from)?;
+        Ok(path)
+    }
+}
+
+/// 故事获取器
+pub struct Fetcher {
+    api: BestdoriApi,
+    region: Region,
+}
+
+impl Fetcher {
+    /// 以 API 客户端创建, 活动剧情和元数据采用 region 区域的文本
+    pub fn new(api: BestdoriApi, region: Region) -> Self {
+        Self { api, region }
+    }
+
+    /// 获取并解析故事, 查询其标题, 活动和乐队
+    pub fn fetch(&self, id: StoryId) -> Result<FetchedStory> {
+        let bytes = self.api.story_bytes(id, self.region)?;
+        let story = bestdori::Story::from_bytes(&bytes).map_err(FileError::from)?;
+        let info = self.api.story_info(id, &story, self.region)?;
+
+        Ok(FetchedStory {
+            id,
+            bytes,
+            story,
+            info,
+        })
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_fetch_and_save() {
+    use std::fs;
+
+    use bd2wg_fixtures::api;
+    use reqwest::header::HeaderMap;
+
+    use crate::models::config::ApiConfig;
+
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-fetcher-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, bytes) in [
+        ("post_details_id_1234", api::POST),
+        ("characters_all.2.json", api::CHARACTERS),
+        ("bands_all.1.json", api::BANDS),
+    ] {
+        fs::write(dir.join(name), bytes).unwrap();
+    }
+
+    let api = BestdoriApi::with_config(
+        HeaderMap::new(),
+        ApiConfig {
+            cache_dir: Some(dir.clone()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let fetched = Fetcher::new(api, Region::Jp)
+        .fetch(StoryId::Post(1234))
+        .unwrap();
+    assert_eq!(fetched.story.actions.len(), 1);
+
+    // 按模板命名, 保存的脚本可再次解析
+    let path = fetched.save(&dir, "${band}/${id}.json").unwrap();
+    assert_eq!(path, dir.join("Poppin'Party/1234.json"));
+    assert!(bestdori::Story::from_bytes(&fs::read(&path).unwrap()).is_ok());
+    assert_eq!(
+        fetched.save(&dir, DEFAULT_STORY_TEMPLATE).unwrap(),
+        dir.join("story-1234.json")
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
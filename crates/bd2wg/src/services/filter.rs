@@ -0,0 +1,157 @@
+//! 内置文本过滤器
+
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+
+use crate::{
+    models::{bestdori::CharacterTable, config::TextReplacement},
+    traits::filter::TextFilter,
+};
+
+/// WebGAL 对话换行符
+const WEBGAL_LINE_BREAK: char = '|';
+
+/// 标记转换
+///
+/// - `[ruby=よみ]漢字[/ruby]` 转换为 WebGAL 注音 `[漢字](よみ)`
+/// - `[chara=39]` 转换为角色名
+#[derive(Debug, Clone)]
+pub struct MarkupFilter {
+    characters: CharacterTable,
+}
+
+impl MarkupFilter {
+    pub fn new(characters: CharacterTable) -> Self {
+        Self { characters }
+    }
+
+    fn convert(&self, s: &str) -> String {
+        static RUBY: OnceLock<Regex> = OnceLock::new();
+        static CHARA: OnceLock<Regex> = OnceLock::new();
+
+        let ruby = RUBY.get_or_init(|| Regex::new(r"\[ruby=([^\]]*)\](.*?)\[/ruby\]").unwrap());
+        let chara = CHARA.get_or_init(|| Regex::new(r"\[chara[=:](\d+)\]").unwrap());
+
+        let s = ruby.replace_all(s, "[$2]($1)");
+        chara
+            .replace_all(&s, |caps: &Captures| {
+                caps[1]
+                    .parse()
+                    .ok()
+                    .and_then(|id| self.characters.get(id))
+                    .map_or_else(|| caps[0].to_string(), |c| c.name.clone())
+            })
+            .into_owned()
+    }
+}
+
+impl TextFilter for MarkupFilter {
+    fn filter_name(&self, name: &str) -> String {
+        self.convert(name)
+    }
+
+    fn filter_text(&self, text: &str) -> String {
+        self.convert(text)
+    }
+}
+
+/// 换行转换
+///
+/// 将换行符转换为 WebGAL 换行语法, 并在超过指定宽度的行中插入换行.
+#[derive(Debug, Clone)]
+pub struct LineBreakFilter {
+    width: Option<usize>,
+}
+
+impl LineBreakFilter {
+    pub fn new(width: Option<usize>) -> Self {
+        Self { width }
+    }
+}
+
+impl TextFilter for LineBreakFilter {
+    fn filter_text(&self, text: &str) -> String {
+        let width = self.width.unwrap_or(usize::MAX).max(1);
+
+        text.lines()
+            .flat_map(|line| {
+                let chars: Vec<char> = line.chars().collect();
+                chars
+                    .chunks(width)
+                    .map(|chunk| chunk.iter().collect::<String>())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .join(&WEBGAL_LINE_BREAK.to_string())
+    }
+}
+
+/// 正则替换
+#[derive(Debug, Clone, Default)]
+pub struct RegexFilter {
+    rules: Vec<(Regex, String)>,
+}
+
+impl RegexFilter {
+    /// 编译替换规则, 忽略无效的正则
+    pub fn new(rules: &[TextReplacement]) -> Self {
+        Self {
+            rules: rules
+                .iter()
+                .filter_map(|rule| Some((Regex::new(&rule.pattern).ok()?, rule.replace.clone())))
+                .collect(),
+        }
+    }
+}
+
+impl TextFilter for RegexFilter {
+    fn filter_text(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (regex, replace)| {
+                regex.replace_all(&text, replace.as_str()).into_owned()
+            })
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_text_filters() {
+    let markup = MarkupFilter::new(CharacterTable::builtin().clone());
+    assert_eq!(
+        markup.filter_text("[chara=39]の[ruby=ほし]星[/ruby]"),
+        "そよの[星](ほし)"
+    );
+
+    let line_break = LineBreakFilter::new(Some(4));
+    assert_eq!(line_break.filter_text("あいうえお\nか"), "あいうえ|お|か");
+
+    let regex = RegexFilter::new(&[
+        TextReplacement {
+            pattern: "(".to_string(),
+            replace: String::new(),
+        },
+        TextReplacement {
+            pattern: "~+".to_string(),
+            replace: "〜".to_string(),
+        },
+    ]);
+    assert_eq!(regex.filter_text("ね~~"), "ね〜");
+}
+
+#[test]
+#[cfg(test)]
+fn test_line_break_say() {
+    use crate::models::webgal::action::SayAction;
+
+    let say = SayAction {
+        name: String::from("Soyo"),
+        text: LineBreakFilter::new(Some(6)).filter_text("10:30に集合ね\n遅れないで"),
+        next: false,
+        concat: false,
+        character: None,
+        vocal: None,
+    };
+    assert_eq!(say.to_string(), "Soyo:10:30に|集合ね|遅れないで;");
+}
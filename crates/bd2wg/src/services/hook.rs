@@ -0,0 +1,97 @@
+//! 场景后处理钩子实现
+
+use std::{
+    fmt, io,
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+use crate::{error::panic_message, models::config::HookCommand, traits::hook::SceneHook};
+
+/// 场景路径的环境变量名, 传递给外部命令
+pub const SCENE_PATH_ENV: &str = "BD2WG_SCENE";
+
+/// 由闭包实现的钩子
+pub struct ClosureHook<F>(F);
+
+impl<F> ClosureHook<F>
+where
+    F: Fn(&str, String) -> String + Send + Sync,
+{
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl<F> fmt::Debug for ClosureHook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClosureHook")
+    }
+}
+
+impl<F> SceneHook for ClosureHook<F>
+where
+    F: Fn(&str, String) -> String + Send + Sync,
+{
+    fn process(&self, path: &str, text: String) -> io::Result<String> {
+        Ok((self.0)(path, text))
+    }
+}
+
+/// 外部命令: 经标准输入传入场景文本, 从标准输出读取结果
+impl SceneHook for HookCommand {
+    fn process(&self, path: &str, text: String) -> io::Result<String> {
+        tracing::debug!(program = self.program, path, "run scene hook");
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .env(SCENE_PATH_ENV, path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // 在子线程写入, 避免管道填满后互相等待
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = thread::spawn(move || stdin.write_all(text.as_bytes()));
+
+        let output = child.wait_with_output()?;
+        writer
+            .join()
+            .map_err(|e| io::Error::other(panic_message(e)))??;
+
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}: {}",
+                self.program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// 依次执行钩子
+pub fn apply_scene_hooks<'a>(
+    hooks: impl IntoIterator<Item = &'a dyn SceneHook>,
+    path: &str,
+    text: String,
+) -> io::Result<String> {
+    hooks
+        .into_iter()
+        .try_fold(text, |text, hook| hook.process(path, text))
+}
+
+#[test]
+#[cfg(test)]
+fn test_apply_scene_hooks() {
+    let prepend = ClosureHook::new(|_: &str, text: String| format!("setVar:a=1;\n{text}"));
+    let append = ClosureHook::new(|path: &str, text: String| format!("{text}; {path}\n"));
+    let hooks: [&dyn SceneHook; 2] = [&prepend, &append];
+
+    let text = apply_scene_hooks(hooks, "scene/start.txt", String::from("end;")).unwrap();
+
+    assert_eq!(text, "setVar:a=1;\nend;; scene/start.txt\n");
+}
@@ -0,0 +1,184 @@
+//! 三方合并
+//!
+//! 以上次生成的场景为基准, 合并用户修改和新生成的内容.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// 差分的最大差异行数, 超出时不再逐行合并
+const MAX_DIFF: isize = 1000;
+
+/// 按行三方合并, 存在冲突时返回冲突数
+///
+/// 双方修改了同一区域且结果不同时视为冲突. 任一方与基准差异过大时整个文件视为一处冲突.
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> Result<String, usize> {
+    let base: Vec<_> = base.split_inclusive('\n').collect();
+    let ours: Vec<_> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<_> = theirs.split_inclusive('\n').collect();
+
+    let (Some(to_ours), Some(to_theirs)) =
+        (diff_matches(&base, &ours), diff_matches(&base, &theirs))
+    else {
+        return Err(1);
+    };
+
+    // 三方共有的行作为锚点, 末尾追加哨兵
+    let anchors = (0..base.len())
+        .filter_map(|k| Some((k, to_ours[k]?, to_theirs[k]?)))
+        .chain([(base.len(), ours.len(), theirs.len())]);
+
+    let mut merged = String::new();
+    let mut conflicts = 0;
+    let (mut b, mut o, mut t) = (0, 0, 0);
+
+    for (kb, ko, kt) in anchors {
+        let (chunk_b, chunk_o, chunk_t) = (&base[b..kb], &ours[o..ko], &theirs[t..kt]);
+
+        let chunk = if chunk_o == chunk_b || chunk_o == chunk_t {
+            chunk_t
+        } else if chunk_t == chunk_b {
+            chunk_o
+        } else {
+            conflicts += 1;
+            chunk_o
+        };
+        merged.extend(chunk.iter().copied());
+
+        if kb < base.len() {
+            merged.push_str(base[kb]);
+        }
+        (b, o, t) = (kb + 1, ko + 1, kt + 1);
+    }
+
+    match conflicts {
+        0 => Ok(merged),
+        n => Err(n),
+    }
+}
+
+/// 行的哈希, 比较哈希而非整行文本
+fn hash_lines(lines: &[&str]) -> Vec<u64> {
+    lines
+        .iter()
+        .map(|line| {
+            let mut hasher = DefaultHasher::new();
+            line.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+/// Myers 差分, 返回 a 中每行匹配到的 b 中行号, 差异行数超过 [`MAX_DIFF`] 时返回 None
+///
+/// 耗时为 O((n + m) · d), 回溯记录占用 O(d²), d 为差异行数, 重新生成的场景通常只有少量差异.
+fn diff_matches(a: &[&str], b: &[&str]) -> Option<Vec<Option<usize>>> {
+    let (a, b) = (hash_lines(a), hash_lines(b));
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    let index = |k: isize| (k + offset) as usize;
+
+    // 记录每一步开始前对角线 -d..=d 上到达的最远 x, 用于回溯
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+
+    'search: for d in 0..=n + m {
+        if d > MAX_DIFF {
+            return None;
+        }
+        trace.push(v[index(-d)..=index(d)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = match k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                true => v[index(k + 1)],
+                false => v[index(k - 1)] + 1,
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            v[index(k)] = x;
+
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // 自终点回溯, 沿对角线的部分为匹配行
+    let mut matches = vec![None; a.len()];
+    let (mut x, mut y) = (n, m);
+
+    for (d, v) in trace.iter().enumerate().rev() {
+        let (d, k) = (d as isize, x - y);
+        let at = |k: isize| v[(k + d) as usize];
+        let prev_k = match k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            true => k + 1,
+            false => k - 1,
+        };
+        let prev_x = match d {
+            0 => 0,
+            _ => at(prev_k),
+        };
+        let prev_y = match d {
+            0 => 0,
+            _ => prev_x - prev_k,
+        };
+
+        while x > prev_x && y > prev_y {
+            (x, y) = (x - 1, y - 1);
+            matches[x as usize] = Some(y as usize);
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+
+    Some(matches)
+}
+
+#[test]
+#[cfg(test)]
+fn test_merge3() {
+    let base = "a;\nb;\nc;\nd;\n";
+
+    // 用户修改 b, 新生成修改 d
+    assert_eq!(
+        merge3(base, "a;\nB;\nc;\nd;\n", "a;\nb;\nc;\nD;\n").as_deref(),
+        Ok("a;\nB;\nc;\nD;\n")
+    );
+    // 双方修改相同
+    assert_eq!(
+        merge3(base, "a;\nB;\nc;\nd;\n", "a;\nB;\nc;\nd;\n").as_deref(),
+        Ok("a;\nB;\nc;\nd;\n")
+    );
+    // 双方修改同一行
+    assert_eq!(merge3(base, "a;\nB;\nc;\nd;\n", "a;\nX;\nc;\nd;\n"), Err(1));
+    // 用户插入, 新生成删除
+    assert_eq!(
+        merge3(base, "a;\nb;\nb2;\nc;\nd;\n", "b;\nc;\nd;\n").as_deref(),
+        Ok("b;\nb2;\nc;\nd;\n")
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_diff_matches() {
+    fn lines(s: &str) -> Vec<&str> {
+        s.split_inclusive('\n').collect()
+    }
+
+    assert_eq!(
+        diff_matches(&lines("a\nb\nc\n"), &lines("a\nx\nc\ny\n")).unwrap(),
+        [Some(0), None, Some(2)]
+    );
+    assert_eq!(diff_matches(&lines(""), &lines("a\n")).unwrap(), []);
+    assert_eq!(diff_matches(&lines("a\n"), &lines("")).unwrap(), [None]);
+
+    // 大量重复行
+    let long = "w;\n".repeat(2000);
+    let edited = format!("x;\n{long}");
+    let matches = diff_matches(&lines(&long), &lines(&edited)).unwrap();
+    assert_eq!(matches.iter().flatten().count(), 2000);
+
+    // 差异过大时放弃逐行合并, 整个文件视为一处冲突
+    let ours: String = (0..600).map(|i| format!("a{i};\n")).collect();
+    let theirs: String = (0..600).map(|i| format!("b{i};\n")).collect();
+    assert_eq!(diff_matches(&lines(&ours), &lines(&theirs)), None);
+    assert_eq!(merge3(&ours, &ours, &theirs), Err(1));
+}
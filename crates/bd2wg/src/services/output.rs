@@ -0,0 +1,180 @@
+//! 输出目标实现
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    models::config::ExportTarget, safe_unwrap_lock, services::extractor::WEBGAL_GAME_DIR,
+    traits::output::OutputSink, utils::create_and_write,
+};
+
+/// 写入本地文件系统
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsSink;
+
+impl OutputSink for FsSink {
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        create_and_write(bytes, path)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/// 写入内存, 用于测试或由调用方自行处理
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemorySink {
+    /// 取出全部文件
+    pub fn into_files(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        safe_unwrap_lock!(self.files.into_inner())
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        Ok(safe_unwrap_lock!(self.files.lock()).get(path).cloned())
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        safe_unwrap_lock!(self.files.lock()).insert(path.to_path_buf(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        safe_unwrap_lock!(self.files.lock()).remove(path);
+        Ok(())
+    }
+}
+
+/// 写入 zip, 条目位于 `game/` 下
+///
+/// 文件先缓存在内存中, 读取时返回缓存的内容, 同一路径重复写入时覆盖.
+/// 调用 [`OutputSink::finish`] 时写出压缩包, 之后不再接受写入.
+pub struct ZipSink {
+    root: PathBuf,
+    path: PathBuf,
+    files: Mutex<Option<BTreeMap<PathBuf, Vec<u8>>>>,
+}
+
+impl ZipSink {
+    /// 创建写入 `path` 的压缩包, root 为对应的输出目录
+    pub fn new(root: impl AsRef<Path>, path: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            files: Mutex::new(Some(BTreeMap::new())),
+        }
+    }
+
+    /// 压缩包内的条目名
+    fn entry_name(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).to_path_buf()
+    }
+}
+
+impl OutputSink for ZipSink {
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        let files = safe_unwrap_lock!(self.files.lock());
+        Ok(files
+            .as_ref()
+            .and_then(|files| files.get(&self.entry_name(path)).cloned()))
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        let mut files = safe_unwrap_lock!(self.files.lock());
+        let files = files
+            .as_mut()
+            .ok_or_else(|| io::Error::other("zip already finished"))?;
+        files.insert(self.entry_name(path), bytes.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        if let Some(files) = safe_unwrap_lock!(self.files.lock()).as_mut() {
+            files.remove(&self.entry_name(path));
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        use std::io::Write;
+
+        let Some(files) = safe_unwrap_lock!(self.files.lock()).take() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut zip = ZipWriter::new(fs::File::create(&self.path)?);
+        for (name, bytes) in files {
+            let name = Path::new(WEBGAL_GAME_DIR).join(name);
+            zip.start_file(
+                name.to_string_lossy().replace('\\', "/"),
+                SimpleFileOptions::default(),
+            )
+            .map_err(io::Error::other)?;
+            zip.write_all(&bytes)?;
+        }
+        zip.finish().map(|_| ()).map_err(io::Error::other)
+    }
+}
+
+/// 按输出配置选择输出目标
+pub fn export_sink(root: &Path, export: &ExportTarget) -> Arc<dyn OutputSink> {
+    match export {
+        ExportTarget::Directory => Arc::new(FsSink),
+        ExportTarget::Zip(path) => Arc::new(ZipSink::new(root, path)),
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_zip_sink() {
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-zip-sink-{}", std::process::id()));
+    let path = dir.join("game.zip");
+
+    let sink = ZipSink::new(&dir, &path);
+    let manifest = dir.join("bd2wg-manifest.json");
+    sink.write(&dir.join("scene/start.txt"), b"end;").unwrap();
+    sink.write(&manifest, b"{}").unwrap();
+    sink.write(&manifest, b"{\"version\":1}").unwrap(); // 重复写入时覆盖
+    assert_eq!(sink.read(&manifest).unwrap().unwrap(), b"{\"version\":1}");
+    sink.finish().unwrap();
+    assert!(sink.write(&dir.join("scene/a.txt"), b"end;").is_err());
+
+    let zip = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(
+        zip.file_names().collect::<Vec<_>>(),
+        vec!["game/bd2wg-manifest.json", "game/scene/start.txt"]
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -1,8 +1,11 @@
 //! 工作管线
 
+mod batch;
 mod download;
+mod full;
 mod transpile;
 
+pub use batch::BatchPipeline;
 pub use download::DownloadPipeline;
+pub use full::FullPipeline;
 pub use transpile::TranspilePipeline;
-
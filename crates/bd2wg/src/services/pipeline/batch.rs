@@ -0,0 +1,374 @@
+//! 批量转译管线
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use reqwest::header::HeaderMap;
+
+use crate::{
+    error::*,
+    impl_drop_for_handle,
+    models::config::{DropPolicy, ExtractorConfig, MergeConfig, PipelineOptions},
+    safe_unwrap_lock,
+    services::{output::export_sink, resolver::SharedResolver},
+    traits::{
+        handle::Handle,
+        output::OutputSink,
+        pipeline::{
+            BatchPipeline as BatchPipelineTrait, BatchState,
+            DownloadPipeline as DownloadPipelineTrait, StoryStatus, StoryTask, TranspileResult,
+            TranspileState,
+        },
+        resolve::ResolveStats,
+    },
+};
+
+use super::transpile::{
+    JobResult, TranspileJob, TranspilePipeline, aborted_count, game_root, new_resolver,
+    start_download,
+};
+
+/// 单个故事的进度, 转译中时持有其状态
+#[derive(Debug, Clone)]
+enum StoryProgress {
+    Queued,
+    Running(Arc<RwLock<TranspileState>>),
+    Done(usize),
+}
+
+/// 批量转译管线
+///
+/// 在线程池中并行转译多个故事, 以脚本文件名为标识合并到同一工程 (见 MergeConfig).
+/// 各故事共享解析器, 资源只下载一次. 批量转译不记录断点.
+pub struct BatchPipeline {
+    cancel: Arc<AtomicBool>,
+    state: Arc<RwLock<BatchState>>,
+    stories: Vec<PathBuf>,
+    progress: Arc<RwLock<Vec<StoryProgress>>>,
+    #[allow(clippy::type_complexity)]
+    handle: Option<JoinHandle<Vec<(PathBuf, TranspileState, JobResult)>>>,
+    drop_policy: DropPolicy,
+
+    root: PathBuf,
+    header: Option<Result<HeaderMap>>, // 传递给下载管线
+    options: PipelineOptions,          // 传递给下载管线
+    sink: Arc<dyn OutputSink>,         // 传递给下载管线
+}
+
+impl BatchPipeline {
+    /// 启动批量转译管线, 接受管线选项或请求头
+    ///
+    /// 配置了工程时输出到其游戏目录, 并在转译时生成工程骨架.
+    pub fn new(
+        stories: Vec<PathBuf>,
+        root: impl AsRef<Path>,
+        options: impl Into<PipelineOptions>,
+    ) -> Box<Self> {
+        let options = options.into();
+        let sink = export_sink(
+            &game_root(root.as_ref(), &options.extractor),
+            &options.extractor.export,
+        );
+        Self::start(stories, root, options, sink, true)
+    }
+
+    /// 启动写入指定输出目标的批量转译管线
+    pub fn with_sink(
+        stories: Vec<PathBuf>,
+        root: impl AsRef<Path>,
+        options: impl Into<PipelineOptions>,
+        sink: Arc<dyn OutputSink>,
+    ) -> Box<Self> {
+        Self::start(stories, root, options.into(), sink, false)
+    }
+
+    fn start(
+        stories: Vec<PathBuf>,
+        root: impl AsRef<Path>,
+        options: PipelineOptions,
+        sink: Arc<dyn OutputSink>,
+        scaffold: bool,
+    ) -> Box<Self> {
+        let root = game_root(root.as_ref(), &options.extractor);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(RwLock::new(BatchState {
+            total: stories.len(),
+            ..Default::default()
+        }));
+
+        let progress = Arc::new(RwLock::new(vec![StoryProgress::Queued; stories.len()]));
+
+        let mut pipe = Box::new(Self {
+            cancel: cancel.clone(),
+            state: state.clone(),
+            stories: stories.clone(),
+            progress: progress.clone(),
+            handle: None,
+            drop_policy: options.drop_policy,
+            root: root.clone(),
+            header: Some(options.header_map().map_err(Error::Header)),
+            options: options.clone(),
+            sink: sink.clone(),
+        });
+
+        pipe.handle = Some(thread::spawn(move || {
+            Self::run(
+                stories, &root, &options, sink, scaffold, cancel, state, progress,
+            )
+        }));
+
+        pipe
+    }
+
+    /// 分离管线, 之后丢弃时转译在后台继续运行至结束, 不再下载
+    pub fn detach(&mut self) {
+        self.drop_policy = DropPolicy::Detach;
+    }
+
+    /// 执行批量转译, 按输入顺序返回各故事的结果
+    ///
+    /// 被取消或有故事按错误策略中止时, 不再开始剩余的故事.
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        stories: Vec<PathBuf>,
+        root: &Path,
+        options: &PipelineOptions,
+        sink: Arc<dyn OutputSink>,
+        scaffold: bool,
+        cancel: Arc<AtomicBool>,
+        state: Arc<RwLock<BatchState>>,
+        progress: Arc<RwLock<Vec<StoryProgress>>>,
+    ) -> Vec<(PathBuf, TranspileState, JobResult)> {
+        let resolver = SharedResolver::new(new_resolver(options));
+        let lock = Arc::new(Mutex::new(()));
+        let next = AtomicUsize::new(0);
+        let aborted = AtomicBool::new(false);
+
+        let threads = match options.parallelism {
+            0 => thread::available_parallelism().map_or(1, usize::from),
+            n => n,
+        }
+        .clamp(1, stories.len().max(1));
+
+        let mut results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+
+                        loop {
+                            let k = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(story) = stories.get(k) else { break };
+
+                            if cancel.load(Ordering::Relaxed) || aborted.load(Ordering::Relaxed) {
+                                break;
+                            }
+
+                            let job = TranspileJob {
+                                story: story.clone(),
+                                root: root.to_path_buf(),
+                                options: story_options(options, story),
+                                resolver: resolver.clone(),
+                                scaffold,
+                                lock: lock.clone(),
+                            };
+                            let job_state: Arc<RwLock<TranspileState>> = Arc::default();
+                            safe_unwrap_lock!(progress.write())[k] =
+                                StoryProgress::Running(job_state.clone());
+                            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                                TranspilePipeline::run(
+                                    job,
+                                    sink.as_ref(),
+                                    cancel.clone(),
+                                    job_state.clone(),
+                                )
+                            }))
+                            .unwrap_or_else(|e| {
+                                let errors = vec![Error::from_panic(e)];
+                                (errors, Vec::new(), Vec::new(), ResolveStats::default())
+                            });
+
+                            if aborted_count(&result.0).is_some() {
+                                aborted.store(true, Ordering::Relaxed);
+                            }
+
+                            safe_unwrap_lock!(progress.write())[k] =
+                                StoryProgress::Done(result.0.len());
+                            safe_unwrap_lock!(state.write()).finished += 1;
+                            let job_state = safe_unwrap_lock!(job_state.read()).clone();
+                            results.push((k, story.clone(), job_state, result));
+                        }
+
+                        results
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_default()) // 任务的 panic 已被捕获
+                .collect()
+        });
+
+        results.sort_by_key(|(k, ..)| *k);
+        results
+            .into_iter()
+            .map(|(_, story, state, result)| (story, state, result))
+            .collect()
+    }
+}
+
+/// 以脚本文件名为标识, 将故事合并到工程
+fn story_options(options: &PipelineOptions, story: &Path) -> PipelineOptions {
+    let merge = options.extractor.merge.clone().unwrap_or_default();
+    let slug = story.file_stem().map_or_else(
+        || merge.slug.clone(),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+
+    PipelineOptions {
+        extractor: ExtractorConfig {
+            merge: Some(MergeConfig {
+                slug,
+                label: None,
+                ..merge
+            }),
+            checkpoint: false,
+            ..options.extractor.clone()
+        },
+        ..options.clone()
+    }
+}
+
+impl Handle for BatchPipeline {
+    type Result = (
+        Vec<(PathBuf, TranspileResult)>,
+        Result<Box<dyn DownloadPipelineTrait>>,
+    );
+
+    /// 等待批量转译结束, 并启动下载全部故事所需资源的下载管线
+    ///
+    /// 被取消或中止时不启动下载管线.
+    fn join(mut self: Box<Self>) -> Self::Result {
+        let results = match self.handle.take().unwrap().join() {
+            Ok(results) => results,
+            Err(e) => return (Vec::new(), Err(Error::from_panic(e))),
+        };
+
+        let mut aborted = None;
+        let mut res = Vec::new();
+        let results = results
+            .into_iter()
+            .map(|(story, state, (errors, warnings, story_res, stats))| {
+                aborted = aborted.or(aborted_count(&errors));
+                let resources = story_res.iter().map(|res| res.as_ref().clone()).collect();
+                res.extend(story_res);
+
+                let result = TranspileResult {
+                    state,
+                    errors,
+                    warnings,
+                    stats,
+                    resources,
+                };
+                (story, result)
+            })
+            .collect();
+
+        let pipe = start_download(
+            &self.root,
+            self.header.take().unwrap(),
+            res,
+            self.sink.clone(),
+            &self.options,
+            self.cancel.load(Ordering::Relaxed),
+            aborted,
+        );
+
+        (results, pipe)
+    }
+
+    fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+}
+
+impl_drop_for_handle! {BatchPipeline, drop_policy}
+
+impl BatchPipelineTrait for BatchPipeline {
+    fn state(&self) -> BatchState {
+        safe_unwrap_lock!(self.state.read()).clone()
+    }
+
+    fn stories(&self) -> Vec<StoryTask> {
+        let progress = safe_unwrap_lock!(self.progress.read());
+        (self.stories.iter().zip(progress.iter()))
+            .map(|(story, progress)| StoryTask {
+                story: story.clone(),
+                status: match progress {
+                    StoryProgress::Queued => StoryStatus::Queued,
+                    StoryProgress::Running(state) => {
+                        StoryStatus::Running(safe_unwrap_lock!(state.read()).clone())
+                    }
+                    StoryProgress::Done(errors) => StoryStatus::Done { errors: *errors },
+                },
+            })
+            .collect()
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_batch_pipeline() {
+    use std::fs;
+
+    use crate::services::output::MemorySink;
+
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-batch-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let stories: Vec<_> = [
+        ("short", bd2wg_fixtures::SHORT),
+        ("branchy", bd2wg_fixtures::BRANCHY),
+    ]
+    .into_iter()
+    .map(|(name, fixture)| {
+        let path = dir.join(format!("{name}.json"));
+        fs::write(&path, fixture.json).unwrap();
+        path
+    })
+    .collect();
+
+    let mut options = PipelineOptions::default();
+    options.extractor.dry_run = true;
+    options.parallelism = 2;
+
+    let sink = Arc::new(MemorySink::default());
+    let pipe = BatchPipeline::with_sink(stories.clone(), &dir, options, sink.clone());
+    while !pipe.is_finished() {
+        thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!((pipe.stories().iter()).all(|task| task.status == StoryStatus::Done { errors: 0 }));
+    let (results, pipe) = pipe.join();
+
+    assert!(pipe.is_ok());
+    assert_eq!(
+        results.iter().map(|(story, _)| story).collect::<Vec<_>>(),
+        stories.iter().collect::<Vec<_>>()
+    );
+    assert!(sink.exists(&dir.join("scene/short-scene-1.txt")));
+    assert!(sink.exists(&dir.join("scene/branchy-scene-1.txt")));
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -1,37 +1,59 @@
 //! 下载管线
 
 use std::{
+    collections::{HashMap, VecDeque},
+    io,
     path::Path,
     sync::{
         Arc, RwLock,
         atomic::{AtomicBool, Ordering},
     },
     thread::{self, JoinHandle, sleep},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use reqwest::header::HeaderMap;
 
 use crate::{
     error::*,
-    false_or_panic, impl_drop_for_handle,
-    models::webgal::Resource,
-    services::downloader::Downloader,
+    impl_drop_for_handle,
+    models::{
+        checkpoint::{Checkpoint, CheckpointStage},
+        config::{DropPolicy, ExportTarget, ExtractorConfig, PipelineOptions},
+        manifest::{Manifest, ManifestEntry},
+        webgal::{Model, Resource, ResourceType},
+    },
+    safe_unwrap_lock,
+    services::{
+        downloader::Downloader, extractor::audit_references, output::export_sink,
+        resolver::alternative_urls,
+    },
     traits::{
+        asset::Asset,
         download::Download,
         handle::Handle,
-        pipeline::{DownloadPipeline as DownloadPipelineTrait, DownloadResult, DownloadState},
+        output::OutputSink,
+        pipeline::{
+            DownloadPipeline as DownloadPipelineTrait, DownloadResult, DownloadState,
+            ResourceStatus, ResourceTask,
+        },
+        recover::Recovery,
     },
+    utils::hash_bytes,
 };
 
 /// 下载状态更新间隔
 const DOWNLOAD_STATE_UPDATE_BACKOFF: Duration = Duration::from_millis(100);
+/// 下载期间两次写入断点的最小间隔, 结束时总会写入
+const CHECKPOINT_SAVE_INTERVAL: Duration = Duration::from_secs(2);
 
 /// 下载管线
 pub struct DownloadPipeline {
     cancel: Arc<AtomicBool>,
     state: Arc<RwLock<DownloadState>>,
+    tasks: Arc<RwLock<Vec<ResourceTask>>>,
     handle: Option<JoinHandle<Vec<Error>>>,
+    drop_policy: DropPolicy,
 }
 
 impl DownloadPipeline {
@@ -41,118 +63,681 @@ impl DownloadPipeline {
         header: HeaderMap,
         res: Vec<Arc<Resource>>,
     ) -> Result<Box<Self>> {
-        let downloader = Downloader::new(root, header)?;
+        Self::with_export(root, header, res, ExportTarget::Directory)
+    }
+
+    /// 启动写入指定输出目标 (目录或 zip) 的下载管线
+    pub fn with_export(
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+        res: Vec<Arc<Resource>>,
+        export: ExportTarget,
+    ) -> Result<Box<Self>> {
+        let sink = export_sink(root.as_ref(), &export);
+        Self::with_sink(root, header, res, export, sink)
+    }
+
+    /// 跳过下载, 返回已结束的空管线
+    pub fn skip() -> Box<Self> {
+        Box::new(Self {
+            cancel: Arc::default(),
+            state: Arc::default(),
+            tasks: Arc::default(),
+            handle: Some(thread::spawn(Vec::new)),
+            drop_policy: DropPolicy::default(),
+        })
+    }
+
+    /// 从下载阶段的断点继续, 仅下载剩余资源
+    pub fn resume(
+        checkpoint: Checkpoint,
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+    ) -> Result<Box<Self>> {
+        let res = checkpoint.pending.into_iter().map(Arc::new).collect();
+        let sink = export_sink(root.as_ref(), &checkpoint.options.extractor.export);
+        Self::with_options(root, header, res, sink, checkpoint.options)
+    }
+
+    /// 启动写入指定输出目标的下载管线, 结束时调用其 [`OutputSink::finish`]
+    pub fn with_sink(
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+        res: Vec<Arc<Resource>>,
+        export: ExportTarget,
+        sink: Arc<dyn OutputSink>,
+    ) -> Result<Box<Self>> {
+        let options = PipelineOptions {
+            extractor: ExtractorConfig {
+                export,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Self::with_options(root, header, res, sink, options)
+    }
+
+    /// 按管线选项启动写入指定输出目标的下载管线
+    ///
+    /// 采用其中的下载配置, 输出目标和错误处理策略.
+    pub fn with_options(
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+        res: Vec<Arc<Resource>>,
+        sink: Arc<dyn OutputSink>,
+        options: PipelineOptions,
+    ) -> Result<Box<Self>> {
+        let downloader = Downloader::with_config(&root, header, sink.clone(), options.download)?;
 
         let cancel = Arc::new(AtomicBool::new(false));
         let state = Arc::new(RwLock::new(DownloadState {
             total: res.len(),
             ..Default::default()
         }));
+        let tasks = Arc::new(RwLock::new(
+            res.iter()
+                .map(|res| ResourceTask {
+                    path: format!("{}/{}", res.kind.dir(), res.path),
+                    url: res.url.clone(),
+                    status: ResourceStatus::Queued,
+                })
+                .collect(),
+        ));
 
         let mut pipe = Box::new(Self {
             cancel: cancel.clone(),
             state: state.clone(),
+            tasks: tasks.clone(),
             handle: None,
+            drop_policy: options.drop_policy,
         });
 
-        pipe.handle = Some(thread::spawn(move || {
-            Self::run(downloader, res, cancel, state)
-        }));
+        pipe.handle = Some({
+            let root = root.as_ref().to_path_buf();
+            thread::spawn(move || {
+                Self::run(
+                    downloader,
+                    &root,
+                    res,
+                    options,
+                    sink.as_ref(),
+                    cancel,
+                    state,
+                    tasks,
+                )
+            })
+        });
 
         Ok(pipe)
     }
 
+    /// 分离管线, 之后丢弃时下载在后台继续运行至结束
+    pub fn detach(&mut self) {
+        self.drop_policy = DropPolicy::Detach;
+    }
+
     /// 执行下载管线
+    #[allow(clippy::too_many_arguments)]
     fn run(
         mut downloader: Downloader,
+        root: &Path,
         resources: Vec<Arc<Resource>>,
+        options: PipelineOptions,
+        sink: &dyn OutputSink,
         cancel: Arc<AtomicBool>,
         state: Arc<RwLock<DownloadState>>,
+        tasks: Arc<RwLock<Vec<ResourceTask>>>,
     ) -> Vec<Error> {
         let mut errors = Vec::new();
+        tracing::info!(total = resources.len(), "download resources");
+
+        // 已转入下载阶段的断点
+        let mut checkpoint = Checkpoint::load_from(sink, root)
+            .ok()
+            .flatten()
+            .filter(|checkpoint| checkpoint.stage == CheckpointStage::Download);
 
         // 启动下载任务
+        let timer = Instant::now();
+        let mut elapsed = Duration::ZERO; // 已完成资源的耗时之和
+        let mut downloaded = Vec::new(); // 下载成功的资源
+        let mut alternatives: HashMap<usize, VecDeque<String>> = HashMap::new(); // 尚未尝试的其他链接
+        let mut checkpoint_saved = Instant::now();
+        let mut checkpoint_dirty = false; // 上次写入后是否有资源下载成功
+        let mut clock = TaskClock::default();
         let mut handles: Vec<_> = resources
             .into_iter()
-            .map(|res| downloader.download(res))
+            .enumerate()
+            .map(|(k, res)| {
+                clock.queue(k, Instant::now());
+                (k, res.clone(), downloader.download(res))
+            })
             .collect();
 
         // 状态检查
-        let mut check = || -> bool {
+        let mut check = |downloader: &mut Downloader| -> bool {
             if handles.is_empty() {
                 return false;
             }
 
-            // 检查已完成的任务
+            // 检查已完成的任务, 并更新进行中任务的进度
+            let mut list = safe_unwrap_lock!(tasks.write());
             let done: Vec<_> = handles
                 .iter()
                 .enumerate()
-                .filter_map(|(k, task)| if task.is_finished() { Some(k) } else { None })
+                .filter_map(|(k, (index, _, task))| {
+                    if task.is_finished() {
+                        return Some(k);
+                    }
+
+                    let progress = task.progress();
+                    if progress.started {
+                        clock.start(*index, Instant::now());
+                        list[*index].status = ResourceStatus::Downloading(progress);
+                    }
+                    None
+                })
                 .collect();
 
             let mut success = 0;
-            let mut failed = 0;
+            let mut failures = Vec::new();
+            let mut current = safe_unwrap_lock!(state.write());
 
             // 清理任务
             for k in done.into_iter().rev() {
-                let task = handles.swap_remove(k);
+                let (index, res, task) = handles.swap_remove(k);
+
+                // 更新耗时统计
+                let timings = &mut current.timings;
+                let took = clock.finish(index, Instant::now());
+                elapsed += took;
+                if timings
+                    .slowest
+                    .as_ref()
+                    .is_none_or(|(_, slowest)| took > *slowest)
+                {
+                    timings.slowest = Some((format!("{}/{}", res.kind.dir(), res.path), took));
+                }
 
                 match task.join() {
-                    Ok(_) => success += 1,
-                    Err(mut e) => {
-                        failed += 1;
-                        errors.append(&mut e);
+                    Ok(_) => {
+                        success += 1;
+                        list[index].status = ResourceStatus::Done;
+                        downloaded.push(res.clone());
+                        if let Some(checkpoint) = &mut checkpoint {
+                            checkpoint.pending.retain(|pending| *pending != *res);
+                        }
+                    }
+                    Err(e) => {
+                        let message = e.iter().map(Error::to_string).collect::<Vec<_>>();
+                        tracing::info!(resource = list[index].path, "{}", message.join("; "));
+                        list[index].status = ResourceStatus::Failed(message.join("; "));
+                        failures.push((index, res, e));
                     }
                 }
             }
 
-            // 更新计数
-            state.write().unwrap().success += success;
-            state.write().unwrap().failed += failed;
+            current.success += success;
+            drop(current);
+            drop(list);
+
+            // 按间隔更新断点
+            checkpoint_dirty |= success != 0;
+            if checkpoint_dirty
+                && checkpoint_saved.elapsed() >= CHECKPOINT_SAVE_INTERVAL
+                && let Some(checkpoint) = &checkpoint
+            {
+                let _ = checkpoint.save_to(sink, root);
+                checkpoint_saved = Instant::now();
+                checkpoint_dirty = false;
+            }
+
+            // 依次尝试其他链接, 之后询问失败资源的恢复方式, 重新下载或记录失败
+            for (index, res, mut e) in failures {
+                let alternative = alternatives
+                    .entry(index)
+                    .or_insert_with(|| alternative_urls(&options.resolver, &res).into())
+                    .pop_front()
+                    .filter(|_| !cancel.load(Ordering::Relaxed));
+                if let Some(url) = alternative {
+                    tracing::debug!(url, "retry with alternative url");
+                    let retry = Resource {
+                        url,
+                        ..(*res).clone()
+                    };
+
+                    let mut list = safe_unwrap_lock!(tasks.write());
+                    list[index].url = retry.url.clone();
+                    list[index].status = ResourceStatus::Queued;
+                    clock.queue(index, Instant::now());
+                    handles.push((index, res, downloader.download(Arc::new(retry))));
+                    continue;
+                }
+
+                let task = safe_unwrap_lock!(tasks.read())[index].clone();
+                let recovery = match &options.recover {
+                    Some(recover) if !cancel.load(Ordering::Relaxed) => recover.download(&task, &e),
+                    _ => Recovery::Skip,
+                };
+
+                let retry = match recovery {
+                    Recovery::Retry => (*res).clone(),
+                    Recovery::Replace(replacement) => Resource {
+                        url: replacement.into_url(),
+                        ..(*res).clone()
+                    },
+                    Recovery::Skip => {
+                        safe_unwrap_lock!(state.write()).failed += 1;
+                        errors.append(&mut e);
+                        continue;
+                    }
+                };
+
+                let mut list = safe_unwrap_lock!(tasks.write());
+                list[index].url = retry.url.clone();
+                list[index].status = ResourceStatus::Queued;
+                clock.queue(index, Instant::now());
+                handles.push((index, res, downloader.download(Arc::new(retry))));
+            }
+
+            // 更新平均耗时
+            let mut current = safe_unwrap_lock!(state.write());
+            if current.success + current.failed != 0 {
+                current.timings.average = elapsed / (current.success + current.failed) as u32;
+            }
 
             true
         };
 
-        // 监听循环
+        // 监听循环, 取消或中止后继续回收已中断的任务
         // while !check() {  // 耻辱柱!
-        while check() {
-            false_or_panic! {cancel}
+        let mut stop = None;
+        while check(&mut downloader) {
+            if stop.is_none() {
+                let failed = safe_unwrap_lock!(state.read()).failed;
+                if cancel.load(Ordering::Relaxed) {
+                    stop = Some(Error::Cancelled);
+                } else if options.error_policy.should_abort(failed) {
+                    stop = Some(Error::Aborted(failed));
+                }
+
+                if stop.is_some() {
+                    downloader.cancel();
+                }
+            }
 
             sleep(DOWNLOAD_STATE_UPDATE_BACKOFF);
         }
+        safe_unwrap_lock!(state.write()).timings.total = timer.elapsed();
+
+        // 合并各任务的取消错误
+        let stopped = stop.is_some();
+        if let Some(stop) = stop {
+            errors.retain(|e| !matches!(e, Error::Cancelled));
+            errors.push(stop);
+        }
+
+        // 全部下载成功时删除断点, 否则保留失败的资源
+        if let Some(checkpoint) = &checkpoint
+            && let Err(e) = match checkpoint.pending.is_empty() {
+                true => Checkpoint::remove_from(sink, root),
+                false => checkpoint.save_to(sink, root),
+            }
+        {
+            errors.push(Error::File(e.into()));
+        }
+
+        // 在清单中记录下载的资源
+        errors.extend(record_assets(sink, root, &downloaded));
+
+        if stopped {
+            return errors;
+        }
+
+        // 检查场景引用的资源
+        match audit_references(sink, root) {
+            Ok(broken) => errors.extend(broken.into_iter().map(Error::from)),
+            Err(e) => errors.push(Error::File(e.into())),
+        }
+
+        // 写出输出目标, 如压缩包
+        if let Err(e) = sink.finish() {
+            errors.push(Error::File(e.into()));
+        }
 
-        cancel.store(true, Ordering::Relaxed);
         errors
     }
 }
 
+/// 单个资源的计时
+///
+/// 自观察到开始传输起计, 任务在两次状态检查之间完成时自提交起计.
+#[derive(Debug, Default)]
+struct TaskClock {
+    queued: HashMap<usize, Instant>,
+    started: HashMap<usize, Instant>,
+}
+
+impl TaskClock {
+    /// 提交 (或重新提交) 资源
+    fn queue(&mut self, index: usize, at: Instant) {
+        self.queued.insert(index, at);
+        self.started.remove(&index);
+    }
+
+    /// 观察到资源开始传输, 只记录首次
+    fn start(&mut self, index: usize, at: Instant) {
+        self.started.entry(index).or_insert(at);
+    }
+
+    /// 资源结束, 返回其耗时
+    fn finish(&mut self, index: usize, at: Instant) -> Duration {
+        let queued = self.queued.remove(&index);
+        (self.started.remove(&index).or(queued))
+            .map_or(Duration::ZERO, |start| at.saturating_duration_since(start))
+    }
+}
+
+/// 在清单中记录下载的资源及写入文件的哈希, 返回无法记录的条目的错误
+///
+/// 只记录本次写入的文件, 跳过的已有文件不属于 bd2wg 生成.
+/// Live2D 模型逐个记录配置文件及其引用的模型文件.
+fn record_assets(sink: &dyn OutputSink, root: &Path, downloaded: &[Arc<Resource>]) -> Vec<Error> {
+    if downloaded.is_empty() {
+        return Vec::new();
+    }
+
+    let mut manifest = match Manifest::load_from(sink, root) {
+        Ok(manifest) => manifest,
+        Err(e) => return vec![Error::File(e.into())],
+    };
+
+    let mut errors = Vec::new();
+    for res in downloaded {
+        let files = match asset_files(sink, root, res) {
+            Ok(files) => files,
+            Err(e) => {
+                errors.push(Error::File(e.into()));
+                continue;
+            }
+        };
+
+        for path in files {
+            let hash = match sink.read(&root.join(&path)) {
+                Ok(bytes) => bytes.map(|bytes| hash_bytes(&bytes)),
+                Err(e) => {
+                    errors.push(Error::File(e.into()));
+                    continue;
+                }
+            };
+            manifest.insert_asset(ManifestEntry {
+                path,
+                hash,
+                url: Some(res.url.clone()),
+            });
+        }
+    }
+
+    if let Err(e) = manifest.save_to(sink, root) {
+        errors.push(Error::File(e.into()));
+    }
+    errors
+}
+
+/// 资源写入的文件, 相对游戏目录
+///
+/// Live2D 模型为目录, 由写入的配置文件得到其中的模型文件.
+fn asset_files(sink: &dyn OutputSink, root: &Path, res: &Resource) -> io::Result<Vec<String>> {
+    let dir = res.kind.dir();
+    if res.kind != ResourceType::Figure {
+        return Ok(vec![format!("{dir}/{}", res.path)]);
+    }
+
+    let config = format!("{dir}/{}", res.relative_path());
+    let files = match sink.read(&root.join(&config))? {
+        Some(bytes) => serde_json::from_slice::<Model>(&bytes)
+            .map_err(io::Error::other)?
+            .files(),
+        None => Vec::new(),
+    };
+
+    Ok(std::iter::once(config)
+        .chain(
+            files
+                .into_iter()
+                .map(|file| format!("{dir}/{}{file}", res.path)),
+        )
+        .collect())
+}
+
 impl Handle for DownloadPipeline {
     type Result = DownloadResult;
 
     /// 等待下载管线结束
     ///
-    /// panic: 下载管线被调用 cancel.
+    /// 被取消时保存断点, 结果中包含 Cancelled.
     fn join(mut self: Box<Self>) -> Self::Result {
-        let state = self.state.read().unwrap().clone();
-        let errors = self.handle.take().unwrap().join().unwrap();
+        let errors =
+            (self.handle.take().unwrap().join()).unwrap_or_else(|e| vec![Error::from_panic(e)]);
+        let state = safe_unwrap_lock!(self.state.read()).clone();
 
         DownloadResult { state, errors }
     }
 
     fn cancel(&mut self) {
         self.cancel.store(true, Ordering::Relaxed);
-        self.handle = None;
     }
 
     fn is_finished(&self) -> bool {
-        self.cancel.load(Ordering::Relaxed)
+        self.handle.as_ref().is_none_or(JoinHandle::is_finished)
     }
 }
 
-impl_drop_for_handle! {DownloadPipeline}
+impl_drop_for_handle! {DownloadPipeline, drop_policy}
 
 impl DownloadPipelineTrait for DownloadPipeline {
     fn state(&self) -> DownloadState {
-        self.state.read().unwrap().clone()
+        safe_unwrap_lock!(self.state.read()).clone()
+    }
+
+    fn tasks(&self) -> Vec<ResourceTask> {
+        safe_unwrap_lock!(self.tasks.read()).clone()
     }
 }
+
+#[test]
+#[cfg(test)]
+fn test_recover_download() {
+    use std::{fs, path::PathBuf};
+
+    use crate::{
+        error::ResolveError,
+        models::webgal::{LOCAL_URL_SCHEME, ResourceType},
+        services::output::MemorySink,
+        traits::recover::{Recover, Replacement},
+    };
+
+    /// 以本地文件替代失败的资源
+    #[derive(Debug)]
+    struct Local(PathBuf);
+
+    impl Recover for Local {
+        fn resolve(&self, _: &ResolveError) -> Option<Replacement> {
+            None
+        }
+
+        fn download(&self, task: &ResourceTask, _: &[Error]) -> Recovery {
+            assert!(matches!(task.status, ResourceStatus::Failed(_)));
+            Recovery::Replace(Replacement::File(self.0.clone()))
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-recover-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("bg.png");
+    fs::write(&file, b"png").unwrap();
+
+    let res = Arc::new(Resource {
+        kind: ResourceType::Background,
+        url: format!("{LOCAL_URL_SCHEME}{}", dir.join("missing.png").display()),
+        path: String::from("bg.png"),
+    });
+    let options = PipelineOptions {
+        recover: Some(Arc::new(Local(file))),
+        ..Default::default()
+    };
+
+    let sink = Arc::new(MemorySink::default());
+    let pipe =
+        DownloadPipeline::with_options(&dir, HeaderMap::new(), vec![res], sink.clone(), options)
+            .unwrap();
+    let result = pipe.join();
+
+    assert_eq!((result.state.success, result.state.failed), (1, 0));
+    assert_eq!(
+        sink.read(&dir.join("background/bg.png")).unwrap().unwrap(),
+        b"png"
+    );
+    let manifest = Manifest::load_from(&*sink, &dir).unwrap();
+    assert_eq!(manifest.assets.len(), 1);
+    assert_eq!(manifest.assets[0].hash, Some(hash_bytes(b"png")));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_readable_download() {
+    use std::fs;
+
+    use crate::{
+        models::{
+            bestdori,
+            config::{NamingStrategy, ResolverConfig},
+            webgal::LOCAL_URL_SCHEME,
+        },
+        services::{output::MemorySink, resolver::Resolver},
+        traits::resolve::{Resolve, ResourceType},
+    };
+
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-readable-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("Classroom.png");
+    fs::write(&file, b"png").unwrap();
+
+    // 解析器按命名策略生成路径, 下载器写入同一路径
+    let mut resolver = Resolver::with_config(ResolverConfig {
+        naming: NamingStrategy::Readable,
+        ..Default::default()
+    });
+    let res = bestdori::Resource {
+        kind: bestdori::ResourceType::Custom,
+        path: bestdori::ResourcePath::Url {
+            url: format!("{LOCAL_URL_SCHEME}{}", file.display()),
+        },
+    };
+    let res = resolver
+        .resolve_normal(&res, ResourceType::Image)
+        .unwrap()
+        .into_vacant()
+        .unwrap();
+    assert!(res.path.starts_with("Classroom-"), "{}", res.path);
+
+    let sink = Arc::new(MemorySink::default());
+    let pipe = DownloadPipeline::with_options(
+        &dir,
+        HeaderMap::new(),
+        vec![res.clone()],
+        sink.clone(),
+        PipelineOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(pipe.join().state.success, 1);
+    assert_eq!(
+        sink.read(&res.absolute_path(&dir)).unwrap().unwrap(),
+        b"png"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_record_model_assets() {
+    use crate::{models::webgal::default_model_config_path, services::output::MemorySink};
+
+    let root = Path::new("game");
+    let sink = MemorySink::default();
+    let resource = |kind, path: &str| {
+        Arc::new(Resource {
+            kind,
+            url: format!("https://example.com/{path}"),
+            path: path.to_string(),
+        })
+    };
+
+    let model = Model {
+        textures: vec![String::from("textures/texture_00.png")],
+        ..Default::default()
+    };
+    let config = default_model_config_path("game/figure/036_casual/");
+    sink.write(Path::new(&config), &serde_json::to_vec(&model).unwrap())
+        .unwrap();
+    sink.write(Path::new("game/figure/036_casual/model.moc"), b"moc")
+        .unwrap();
+    sink.write(Path::new("game/figure/broken/model.json"), b"{")
+        .unwrap();
+    sink.write(Path::new("game/background/bg.png"), b"png")
+        .unwrap();
+
+    // 损坏的模型配置只影响自身条目
+    let errors = record_assets(
+        &sink,
+        root,
+        &[
+            resource(ResourceType::Figure, "036_casual/"),
+            resource(ResourceType::Figure, "broken/"),
+            resource(ResourceType::Background, "bg.png"),
+        ],
+    );
+    assert_eq!(errors.len(), 1);
+
+    let manifest = Manifest::load_from(&sink, root).unwrap();
+    let assets: Vec<_> = (manifest.assets.iter())
+        .map(|entry| (entry.path.as_str(), entry.hash.is_some()))
+        .collect();
+    assert_eq!(
+        assets,
+        vec![
+            ("figure/036_casual/model.json", true),
+            ("figure/036_casual/model.moc", true),
+            ("figure/036_casual/physics.json", false),
+            ("figure/036_casual/textures/texture_00.png", false),
+            ("background/bg.png", true),
+        ]
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_task_clock() {
+    let base = Instant::now();
+    let at = |ms| base + Duration::from_millis(ms);
+    let mut clock = TaskClock::default();
+
+    // 排队时间不计入, 只记录首次开始
+    clock.queue(0, at(0));
+    clock.queue(1, at(0));
+    clock.start(0, at(400));
+    clock.start(0, at(500));
+    assert_eq!(clock.finish(0, at(600)), Duration::from_millis(200));
+
+    // 未观察到开始时自提交起计
+    assert_eq!(clock.finish(1, at(50)), Duration::from_millis(50));
+
+    // 重新提交后重新计时
+    clock.queue(0, at(1000));
+    clock.start(0, at(1100));
+    assert_eq!(clock.finish(0, at(1400)), Duration::from_millis(300));
+}
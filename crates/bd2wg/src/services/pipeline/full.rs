@@ -0,0 +1,353 @@
+//! 完整管线
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle, sleep},
+    time::Duration,
+};
+
+use crate::{
+    error::*,
+    models::config::{DropPolicy, PipelineOptions},
+    safe_unwrap_lock,
+    traits::{
+        handle::Handle,
+        pipeline::{
+            BatchPipeline as BatchPipelineTrait, DownloadResult, FullPipeline as FullPipelineTrait,
+            PipelineStage, PipelineState, ResourceTask,
+            TranspilePipeline as TranspilePipelineTrait, TranspileStage,
+        },
+    },
+};
+
+use super::{BatchPipeline, TranspilePipeline};
+
+/// 状态更新间隔
+const PIPELINE_STATE_UPDATE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// 完整管线
+///
+/// 在子线程中依次运行转译管线 (或批量转译管线) 和下载管线, 转译结束后自动开始下载.
+pub struct FullPipeline<S: TranspileStage + ?Sized = dyn TranspilePipelineTrait + Send> {
+    cancel: Arc<AtomicBool>,
+    state: Arc<RwLock<PipelineState<S::Progress>>>,
+    tasks: Arc<RwLock<Vec<ResourceTask>>>,
+    #[allow(clippy::type_complexity)]
+    handle: Option<JoinHandle<(S::Output, Result<DownloadResult>)>>,
+    drop_policy: DropPolicy,
+}
+
+impl FullPipeline {
+    /// 启动完整管线
+    pub fn new(
+        story: impl AsRef<Path>,
+        root: impl AsRef<Path>,
+        options: impl Into<PipelineOptions>,
+    ) -> Box<Self> {
+        let options = options.into();
+        let drop_policy = options.drop_policy;
+
+        let mut pipe = Self::with_pipeline(TranspilePipeline::new(story, root, options));
+        pipe.drop_policy = drop_policy;
+        pipe
+    }
+}
+
+impl FullPipeline<dyn BatchPipelineTrait + Send> {
+    /// 启动批量转译多个故事的完整管线
+    pub fn batch(
+        stories: Vec<PathBuf>,
+        root: impl AsRef<Path>,
+        options: impl Into<PipelineOptions>,
+    ) -> Box<Self> {
+        let options = options.into();
+        let drop_policy = options.drop_policy;
+
+        let mut pipe = Self::with_pipeline(BatchPipeline::new(stories, root, options));
+        pipe.drop_policy = drop_policy;
+        pipe
+    }
+}
+
+impl<S: TranspileStage + ?Sized> FullPipeline<S> {
+    /// 接管已启动的转译管线, 丢弃时中断
+    pub fn with_pipeline(pipe: Box<S>) -> Box<Self> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let state: Arc<RwLock<PipelineState<S::Progress>>> = Arc::default();
+        let tasks: Arc<RwLock<Vec<ResourceTask>>> = Arc::default();
+
+        let handle = {
+            let (cancel, state, tasks) = (cancel.clone(), state.clone(), tasks.clone());
+            thread::spawn(move || Self::run(pipe, cancel, state, tasks))
+        };
+
+        Box::new(Self {
+            cancel,
+            state,
+            tasks,
+            handle: Some(handle),
+            drop_policy: DropPolicy::default(),
+        })
+    }
+
+    /// 分离管线, 之后丢弃时转译和下载在后台继续运行至结束
+    pub fn detach(&mut self) {
+        self.drop_policy = DropPolicy::Detach;
+    }
+
+    /// 执行完整管线
+    fn run(
+        mut pipe: Box<S>,
+        cancel: Arc<AtomicBool>,
+        state: Arc<RwLock<PipelineState<S::Progress>>>,
+        tasks: Arc<RwLock<Vec<ResourceTask>>>,
+    ) -> (S::Output, Result<DownloadResult>) {
+        // 转译阶段
+        while !pipe.is_finished() {
+            if cancel.load(Ordering::Relaxed) {
+                pipe.cancel();
+            }
+            safe_unwrap_lock!(state.write()).transpile = pipe.progress();
+            sleep(PIPELINE_STATE_UPDATE_BACKOFF);
+        }
+
+        let progress = pipe.progress();
+        let (transpile, pipe) = pipe.join();
+        {
+            let mut state = safe_unwrap_lock!(state.write());
+            state.transpile = progress;
+            state.stage = PipelineStage::Download;
+        }
+
+        // 下载阶段
+        let download = pipe.map(|mut pipe| {
+            while !pipe.is_finished() {
+                if cancel.load(Ordering::Relaxed) {
+                    pipe.cancel();
+                }
+                safe_unwrap_lock!(state.write()).download = pipe.state();
+                *safe_unwrap_lock!(tasks.write()) = pipe.tasks();
+                sleep(PIPELINE_STATE_UPDATE_BACKOFF);
+            }
+
+            *safe_unwrap_lock!(tasks.write()) = pipe.tasks();
+            let download = pipe.join();
+            safe_unwrap_lock!(state.write()).download = download.state.clone();
+            download
+        });
+
+        safe_unwrap_lock!(state.write()).stage = PipelineStage::Finished;
+        (transpile, download)
+    }
+}
+
+impl<S: TranspileStage + ?Sized> Handle for FullPipeline<S> {
+    type Result = (S::Output, Result<DownloadResult>);
+
+    /// 等待转译和下载结束
+    ///
+    /// 转译阶段被取消时不启动下载, 下载结果为 Cancelled.
+    fn join(mut self: Box<Self>) -> Self::Result {
+        self.handle.take().unwrap().join().unwrap_or_else(|e| {
+            // 无法确定所处阶段, 两个结果中均报告
+            let message = panic_message(e);
+            let result = S::failed(Error::Panicked(message.clone()));
+            (result, Err(Error::Panicked(message)))
+        })
+    }
+
+    fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+}
+
+impl<S: TranspileStage + ?Sized> Drop for FullPipeline<S> {
+    fn drop(&mut self) {
+        if self.drop_policy == DropPolicy::Cancel && !self.is_finished() {
+            self.cancel();
+        }
+    }
+}
+
+impl<S: TranspileStage + ?Sized> FullPipelineTrait<S> for FullPipeline<S> {
+    fn state(&self) -> PipelineState<S::Progress> {
+        safe_unwrap_lock!(self.state.read()).clone()
+    }
+
+    fn tasks(&self) -> Vec<ResourceTask> {
+        safe_unwrap_lock!(self.tasks.read()).clone()
+    }
+}
+
+#[cfg(test)]
+mod stub {
+    use super::*;
+    use crate::traits::pipeline::{
+        DownloadPipeline, DownloadState, TranspileResult, TranspileState,
+    };
+
+    /// 在 `done` 置位或被取消后结束的管线
+    pub struct Stub {
+        pub done: Arc<AtomicBool>,
+        pub cancelled: bool,
+        pub download: Option<Arc<AtomicBool>>, // 转译阶段结束后启动的下载
+    }
+
+    impl Stub {
+        /// 以此为转译阶段启动完整管线
+        pub fn start(self) -> Box<FullPipeline> {
+            FullPipeline::with_pipeline(Box::new(self))
+        }
+    }
+
+    impl Handle for Stub {
+        type Result = (TranspileResult, Result<Box<dyn DownloadPipeline>>);
+
+        fn join(mut self: Box<Self>) -> Self::Result {
+            let result = TranspileResult {
+                state: self.state(),
+                ..Default::default()
+            };
+            let download = match (self.cancelled, self.download.take()) {
+                (true, _) => Err(Error::Cancelled),
+                (false, done) => Ok(Box::new(StubDownload(Stub {
+                    done: done.unwrap_or_default(),
+                    cancelled: false,
+                    download: None,
+                })) as Box<dyn DownloadPipeline>),
+            };
+            (result, download)
+        }
+
+        fn cancel(&mut self) {
+            self.cancelled = true;
+        }
+
+        fn is_finished(&self) -> bool {
+            self.cancelled || self.done.load(Ordering::Relaxed)
+        }
+    }
+
+    impl TranspilePipelineTrait for Stub {
+        fn state(&self) -> TranspileState {
+            TranspileState {
+                scene: 1,
+                ..Default::default()
+            }
+        }
+    }
+
+    pub struct StubDownload(pub Stub);
+
+    impl Handle for StubDownload {
+        type Result = DownloadResult;
+
+        fn join(self: Box<Self>) -> Self::Result {
+            DownloadResult {
+                state: self.state(),
+                errors: (self.0.cancelled.then_some(Error::Cancelled))
+                    .into_iter()
+                    .collect(),
+            }
+        }
+
+        fn cancel(&mut self) {
+            self.0.cancel();
+        }
+
+        fn is_finished(&self) -> bool {
+            self.0.is_finished()
+        }
+    }
+
+    impl DownloadPipeline for StubDownload {
+        fn state(&self) -> DownloadState {
+            DownloadState {
+                total: 1,
+                success: self.0.done.load(Ordering::Relaxed) as usize,
+                ..Default::default()
+            }
+        }
+
+        fn tasks(&self) -> Vec<ResourceTask> {
+            Vec::new()
+        }
+    }
+
+    /// 等待条件成立
+    pub fn wait_until(cond: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if cond() {
+                return;
+            }
+            sleep(Duration::from_millis(20));
+        }
+        panic!("timed out");
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_full_pipeline_stages() {
+    use stub::*;
+
+    let (transpiled, downloaded) = (Arc::new(AtomicBool::new(false)), Arc::default());
+    let pipe = (Stub {
+        done: transpiled.clone(),
+        cancelled: false,
+        download: Some(Arc::clone(&downloaded)),
+    })
+    .start();
+
+    // 转译结束前停留在转译阶段, 并同步转译进度
+    wait_until(|| pipe.state().transpile.scene == 1);
+    assert_eq!(pipe.state().stage, PipelineStage::Transpile);
+
+    transpiled.store(true, Ordering::Relaxed);
+    wait_until(|| pipe.state().stage == PipelineStage::Download);
+    assert_eq!(pipe.state().download.total, 1);
+
+    downloaded.store(true, Ordering::Relaxed);
+    wait_until(|| pipe.state().stage == PipelineStage::Finished);
+
+    let (transpile, download) = pipe.join();
+    assert_eq!(transpile.state.scene, 1);
+    assert_eq!(download.unwrap().state.success, 1);
+}
+
+#[test]
+#[cfg(test)]
+fn test_full_pipeline_cancel() {
+    use stub::*;
+
+    // 转译阶段取消时不启动下载
+    let mut pipe = (Stub {
+        done: Arc::default(),
+        cancelled: false,
+        download: Some(Arc::default()),
+    })
+    .start();
+    pipe.cancel();
+    let (_, download) = pipe.join();
+    assert!(matches!(download, Err(Error::Cancelled)));
+
+    // 下载阶段取消时中断下载
+    let mut pipe = (Stub {
+        done: Arc::new(AtomicBool::new(true)),
+        cancelled: false,
+        download: Some(Arc::default()),
+    })
+    .start();
+    wait_until(|| pipe.state().stage == PipelineStage::Download);
+    pipe.cancel();
+    let (_, download) = pipe.join();
+    assert!(matches!(&download.unwrap().errors[..], [Error::Cancelled]));
+}
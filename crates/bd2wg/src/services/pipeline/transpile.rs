@@ -1,50 +1,178 @@
 //! 转译管线
 
 use std::{
-    fs,
+    fs, io,
     path::{Path, PathBuf},
     sync::{
-        Arc, RwLock,
+        Arc, Mutex, RwLock,
         atomic::{AtomicBool, Ordering},
     },
     thread::{self, JoinHandle},
+    time::Instant,
 };
 
 use reqwest::header::HeaderMap;
 
 use crate::{
     error::*,
-    false_or_panic, impl_drop_for_handle,
-    models::{bestdori, webgal::Resource},
-    services::{resolver::Resolver, transpiler::Transpiler},
+    false_or_return, impl_drop_for_handle,
+    models::{
+        bestdori,
+        checkpoint::{Checkpoint, CheckpointStage},
+        config::{
+            DropPolicy, ExtractorConfig, OverwritePolicy, PipelineOptions, ProjectConfig,
+            TranspilerConfig,
+        },
+        manifest::{Manifest, ManifestEntry},
+        webgal::{Resource, validate_scene},
+    },
+    safe_unwrap_lock,
+    services::{
+        extractor::{
+            ProjectMeta, WEBGAL_GAME_DIR, WriteOutcome, create_asset_dirs, encode_text,
+            merge_into_index, write_project_config, write_scene_text,
+        },
+        hook::apply_scene_hooks,
+        output::export_sink,
+        resolver::{Resolver, SharedResolver, probe_size},
+        transpiler::Transpiler,
+    },
     traits::{
         asset::Asset,
         handle::Handle,
+        hook::SceneHook,
+        output::OutputSink,
         pipeline::{
             DownloadPipeline as DownloadPipelineTrait, TranspilePipeline as TranspilePipelineTrait,
             TranspileResult, TranspileState,
         },
+        resolve::ResolveStats,
         transpile::{self, Transpile},
     },
-    utils::*,
+    utils::{hash_bytes, new_size_probe, new_url_probe},
 };
 
 use super::DownloadPipeline;
 
+/// 场景写入线程数
+const SCENE_WRITE_THREADS: usize = 4;
+
+/// 转译任务的运行结果
+pub(super) type JobResult = (Vec<Error>, Vec<Warning>, Vec<Arc<Resource>>, ResolveStats);
+
+/// 转译任务
+pub(super) struct TranspileJob {
+    pub story: PathBuf, // Bestdori 脚本路径
+    pub root: PathBuf,
+    pub options: PipelineOptions,
+    pub resolver: SharedResolver, // 可在多个任务间共享
+    pub scaffold: bool,           // 生成工程骨架
+    pub lock: Arc<Mutex<()>>,     // 串行写入多个任务共享的文件
+}
+
+impl TranspileJob {
+    /// 创建使用独立解析器的任务
+    pub fn new(story: &Path, root: &Path, options: PipelineOptions, scaffold: bool) -> Self {
+        Self {
+            story: story.to_path_buf(),
+            root: root.to_path_buf(),
+            resolver: SharedResolver::new(new_resolver(&options)),
+            options,
+            scaffold,
+            lock: Arc::default(),
+        }
+    }
+}
+
 /// 转译管线
 pub struct TranspilePipeline {
     cancel: Arc<AtomicBool>,
     state: Arc<RwLock<TranspileState>>,
-    #[allow(clippy::type_complexity)]
-    handle: Option<JoinHandle<(Vec<Error>, Vec<Arc<Resource>>)>>,
+    handle: Option<JoinHandle<JobResult>>,
+    drop_policy: DropPolicy,
 
     root: PathBuf,
-    header: Option<HeaderMap>, // 传递给下载管线
+    header: Option<Result<HeaderMap>>, // 传递给下载管线
+    options: PipelineOptions,          // 传递给下载管线
+    sink: Arc<dyn OutputSink>,         // 传递给下载管线
 }
 
 impl TranspilePipeline {
-    /// 启动转译管线
-    pub fn new(story: impl AsRef<Path>, root: impl AsRef<Path>, header: HeaderMap) -> Box<Self> {
+    /// 启动转译管线, 接受管线选项或请求头
+    ///
+    /// 配置了工程时输出到其游戏目录, 并在转译时生成工程骨架.
+    pub fn new(
+        story: impl AsRef<Path>,
+        root: impl AsRef<Path>,
+        options: impl Into<PipelineOptions>,
+    ) -> Box<Self> {
+        let options = options.into();
+        let root = game_root(root.as_ref(), &options.extractor);
+        let sink = export_sink(&root, &options.extractor.export);
+        Self::start(story, root, options, sink, true)
+    }
+
+    /// 生成 WebGAL 工程骨架, 并启动输出到其游戏目录的转译管线
+    ///
+    /// 转译完成后写入 `config.txt`.
+    pub fn with_project(
+        story: impl AsRef<Path>,
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+        project: &ProjectConfig,
+    ) -> Box<Self> {
+        let config = ExtractorConfig {
+            project: Some(project.clone()),
+            ..Default::default()
+        };
+        Self::with_config(story, root, header, config)
+    }
+
+    /// 按输出配置启动转译管线
+    pub fn with_config(
+        story: impl AsRef<Path>,
+        root: impl AsRef<Path>,
+        header: HeaderMap,
+        config: ExtractorConfig,
+    ) -> Box<Self> {
+        let options = PipelineOptions {
+            extractor: config,
+            ..header.into()
+        };
+        Self::new(story, root, options)
+    }
+
+    /// 启动写入指定输出目标的转译管线
+    ///
+    /// 配置了工程时输出到其游戏目录, 但不生成工程骨架.
+    pub fn with_sink(
+        story: impl AsRef<Path>,
+        root: impl AsRef<Path>,
+        options: impl Into<PipelineOptions>,
+        sink: Arc<dyn OutputSink>,
+    ) -> Box<Self> {
+        let options = options.into();
+        let root = game_root(root.as_ref(), &options.extractor);
+        Self::start(story, root, options, sink, false)
+    }
+
+    /// 从转译阶段的断点继续, root 为断点所在的游戏目录
+    pub fn resume(checkpoint: Checkpoint, root: impl AsRef<Path>, header: HeaderMap) -> Box<Self> {
+        let options = PipelineOptions {
+            header: PipelineOptions::from(header).header,
+            ..checkpoint.options
+        };
+        let sink = export_sink(root.as_ref(), &options.extractor.export);
+        Self::start(checkpoint.story, root, options, sink, true)
+    }
+
+    fn start(
+        story: impl AsRef<Path>,
+        root: impl AsRef<Path>,
+        options: PipelineOptions,
+        sink: Arc<dyn OutputSink>,
+        scaffold: bool,
+    ) -> Box<Self> {
         let cancel = Arc::new(AtomicBool::new(false));
         let state: Arc<RwLock<TranspileState>> = Arc::default();
 
@@ -52,72 +180,442 @@ impl TranspilePipeline {
             cancel: cancel.clone(),
             state: state.clone(),
             handle: None,
+            drop_policy: options.drop_policy,
             root: root.as_ref().to_path_buf(),
-            header: Some(header),
+            header: Some(options.header_map().map_err(Error::Header)),
+            options: options.clone(),
+            sink: sink.clone(),
         });
 
         pipe.handle = Some({
-            let story = story.as_ref().to_path_buf();
-            let root = root.as_ref().to_path_buf();
-
-            thread::spawn(move || Self::run(&story, &root, cancel, state))
+            let job = TranspileJob::new(story.as_ref(), root.as_ref(), options, scaffold);
+            thread::spawn(move || Self::run(job, sink.as_ref(), cancel, state))
         });
 
         // Self { handle: ..., ..pipe }
         pipe
     }
 
-    /// 执行转译管线
-    fn run(
-        story: &Path, // Bestdori 脚本路径
-        root: &Path,
+    /// 分离管线, 之后丢弃时转译在后台继续运行至结束
+    ///
+    /// 下载管线在 join 时启动, 因此分离后不再下载. 需要后台完成下载时使用 FullPipeline.
+    pub fn detach(&mut self) {
+        self.drop_policy = DropPolicy::Detach;
+    }
+
+    /// 执行转译任务
+    pub(super) fn run(
+        job: TranspileJob,
+        sink: &dyn OutputSink,
         cancel: Arc<AtomicBool>,
         state: Arc<RwLock<TranspileState>>,
-    ) -> (Vec<Error>, Vec<Arc<Resource>>) {
+    ) -> JobResult {
+        let TranspileJob {
+            story,
+            root,
+            options,
+            resolver,
+            scaffold,
+            lock,
+        } = job;
+        let (story, root) = (story.as_path(), root.as_path());
+        tracing::info!(story = %story.display(), "transpile story");
+
         macro_rules! unwrap_or_into_vec {
             ($expr:expr) => {
                 match $expr {
                     Ok(v) => v,
-                    Err(e) => return (vec![Error::File(e.into())], Vec::new()),
+                    Err(e) => {
+                        return (
+                            vec![Error::File(e.into())],
+                            Vec::new(),
+                            Vec::new(),
+                            ResolveStats::default(),
+                        )
+                    }
                 }
             };
         }
 
+        // 检查场景文件名模板, 以免覆盖初始场景或重名
+        let transpiler = transpiler_config(&options);
+        if let Err(e) = transpiler.check_scene_template() {
+            return (
+                vec![Error::Config(e)],
+                Vec::new(),
+                Vec::new(),
+                ResolveStats::default(),
+            );
+        }
+
+        let config = &options.extractor;
+
+        // 生成工程骨架
+        if scaffold && config.project.is_some() {
+            unwrap_or_into_vec! {create_asset_dirs(root)}
+        }
+
+        let mut checkpoint = config.checkpoint.then(|| Checkpoint::new(story, &options));
+
         // 读取故事脚本
+        let timer = Instant::now();
         let story = unwrap_or_into_vec! {
             bestdori::Story::from_bytes(
                 &unwrap_or_into_vec! {fs::read(story)},
             )
         };
+        safe_unwrap_lock!(state.write()).timings.parse = timer.elapsed();
 
-        false_or_panic! {cancel}
+        false_or_return! {
+            cancel,
+            (vec![Error::Cancelled], Vec::new(), Vec::new(), ResolveStats::default())
+        }
+
+        let meta = story.meta().clone();
 
         // 执行转译
+        let timer = Instant::now();
         let transpile::TranspileResult {
             story,
-            resources,
+            mut resources,
             mut errors,
-        } = Transpiler::<Resolver>::default().transpile(&story);
-
-        false_or_panic! {cancel}
+            warnings,
+            mut stats,
+            actions,
+            generated,
+        } = Transpiler::with_config(resolver, transpiler).transpile(&story);
 
         {
             let (scene, action) = story.len();
-            let mut state = state.write().unwrap();
-            (state.scene, state.action) = (scene, action);
+            let mut state = safe_unwrap_lock!(state.write());
+            (state.scene, state.action, state.actions) = (scene, action, actions);
+            state.timings.transpile = timer.elapsed();
+        }
+
+        // 已取消或按错误策略中止时保留转译结果, 不再写入后续内容
+        macro_rules! return_if_stopped {
+            () => {
+                if cancel.load(Ordering::Relaxed) {
+                    errors.push(Error::Cancelled);
+                    return (errors, warnings, Vec::new(), stats);
+                }
+                if options.error_policy.should_abort(errors.len()) {
+                    errors.push(Error::Aborted(errors.len()));
+                    return (errors, warnings, Vec::new(), stats);
+                }
+            };
+        }
+
+        return_if_stopped! {}
+
+        // 在解析器之外批量探测预计下载大小
+        if config.estimate_size
+            && let Ok(header) = options.header_map()
+            && let Ok(probe) = new_size_probe(header)
+        {
+            stats.size = Some(probe_size(&resources, probe, options.download.concurrency));
+        }
+
+        // 记录断点
+        if let Some(checkpoint) = &checkpoint
+            && let Err(e) = checkpoint.save_to(sink, root)
+        {
+            errors.push(Error::File(e.into()));
+        }
+
+        // 渲染并校验场景 (合并时不写入初始场景)
+        let timer = Instant::now();
+        let skip = config.merge.is_some() as usize;
+        let hooks: Vec<&dyn SceneHook> = config
+            .post_process
+            .iter()
+            .map(|command| command as &dyn SceneHook)
+            .chain(options.hooks.iter().map(Arc::as_ref))
+            .collect();
+        let scenes: Vec<_> = story
+            .iter()
+            .skip(skip)
+            .map(|scene| {
+                let path = format!("scene/{}", scene.path);
+
+                // 后处理失败时写入原场景
+                let text = apply_scene_hooks(hooks.iter().copied(), &path, scene.to_string())
+                    .unwrap_or_else(|e| {
+                        errors.push(Error::File(e.into()));
+                        scene.to_string()
+                    });
+
+                if config.validate {
+                    errors.extend(validate_scene(&text).into_iter().map(|(line, error)| {
+                        Error::Validate(ValidateError {
+                            scene: scene.path.clone(),
+                            line,
+                            error,
+                        })
+                    }));
+                }
+
+                (path, encode_text(&text, config.text_format))
+            })
+            .collect();
+
+        return_if_stopped! {}
+
+        // 以下写入可能与其他任务共享的文件
+        let _guard = safe_unwrap_lock!(lock.lock());
+
+        // 并发写入场景, 取消后跳过剩余场景
+        let outcomes = write_scenes(sink, root, &scenes, config.overwrite, &cancel);
+        let mut recorded = Vec::new(); // 写入或内容相同的场景, 及冲突时另存的文件
+        for ((path, text), outcome) in scenes.iter().zip(outcomes) {
+            match outcome {
+                None => {}
+                Some(Ok(WriteOutcome::Written | WriteOutcome::Merged)) => {
+                    safe_unwrap_lock!(state.write()).written.push(path.clone());
+                    recorded.push((path.clone(), text.as_slice()));
+                }
+                // 记录另存的 `.new` 文件, 清理工程时一并删除
+                Some(Ok(WriteOutcome::Conflict)) => {
+                    safe_unwrap_lock!(state.write())
+                        .conflicts
+                        .push(path.clone());
+                    recorded.push((format!("{path}.new"), text.as_slice()));
+                }
+                Some(Ok(WriteOutcome::Unchanged)) => {
+                    safe_unwrap_lock!(state.write()).unchanged += 1;
+                    recorded.push((path.clone(), text.as_slice()));
+                }
+                Some(Ok(WriteOutcome::Kept)) => {
+                    safe_unwrap_lock!(state.write()).kept.push(path.clone())
+                }
+                Some(Err(e)) => errors.push(Error::File(e.into())),
+            }
         }
 
-        // 逐个写入场景
-        for scene in story.iter() {
-            false_or_panic! {cancel}
+        // 写入生成的文件, 不覆盖已有的同名文件
+        let mut generated_recorded = Vec::new();
+        for (path, bytes) in &generated {
+            let absolute = root.join(path);
+            if sink.exists(&absolute) {
+                continue;
+            }
+            match sink.write(&absolute, bytes) {
+                Ok(()) => generated_recorded.push((path.clone(), bytes.as_slice())),
+                Err(e) => errors.push(Error::File(e.into())),
+            }
+        }
+
+        return_if_stopped! {}
+
+        // 更新清单, 下载的资源在下载后记录
+        if let Err(e) = update_manifest(sink, root, &recorded, &generated_recorded) {
+            errors.push(Error::File(e.into()));
+        }
+
+        // 写入工程配置
+        if let Some(project) = &config.project {
+            let meta = ProjectMeta::new(&meta, resources.iter().map(Arc::as_ref));
+            if let Err(e) = write_project_config(sink, root, project, &meta, config.text_format) {
+                errors.push(Error::File(e.into()));
+            }
+        }
+
+        // 加入索引场景
+        if let Some(merge) = &config.merge {
+            let label = merge
+                .label
+                .as_deref()
+                .or(meta.title.as_deref())
+                .unwrap_or(&merge.slug);
+
+            if let Some(first) = story.0.get(1)
+                && let Err(e) = merge_into_index(
+                    sink,
+                    root,
+                    &merge.index,
+                    label,
+                    &first.path,
+                    config.text_format,
+                )
+            {
+                errors.push(Error::File(e.into()));
+            }
+        }
+
+        // 跳过已存在的资源
+        if config.skip_existing || config.merge.as_ref().is_some_and(|merge| !merge.force) {
+            resources.retain(|res| !sink.exists(&res.absolute_path(root)));
+        }
+
+        // 场景写入完成, 转入下载阶段
+        if let Some(checkpoint) = &mut checkpoint {
+            checkpoint.stage = CheckpointStage::Download;
+            checkpoint.pending = resources.iter().map(|res| res.as_ref().clone()).collect();
 
-            if let Err(e) = create_and_write(scene.to_string(), &scene.absolute_path(root)) {
+            if let Err(e) = checkpoint.save_to(sink, root) {
                 errors.push(Error::File(e.into()));
             }
         }
 
-        cancel.store(true, Ordering::Relaxed);
-        (errors, resources)
+        safe_unwrap_lock!(state.write()).timings.write = timer.elapsed();
+        return_if_stopped! {}
+
+        (errors, warnings, resources, stats)
+    }
+}
+
+/// 并发写入已编码的场景, 按场景顺序返回结果
+///
+/// 取消后未写入的场景结果为 None.
+fn write_scenes(
+    sink: &dyn OutputSink,
+    root: &Path,
+    scenes: &[(String, Vec<u8>)],
+    policy: OverwritePolicy,
+    cancel: &AtomicBool,
+) -> Vec<Option<io::Result<WriteOutcome>>> {
+    let threads = SCENE_WRITE_THREADS.min(scenes.len()).max(1);
+    let chunk = scenes.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = scenes
+            .chunks(chunk)
+            .map(|chunk| {
+                let handle = scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(path, text)| {
+                            (!cancel.load(Ordering::Relaxed))
+                                .then(|| write_scene_text(sink, root, path, text, policy))
+                        })
+                        .collect::<Vec<_>>()
+                });
+                (chunk.len(), handle)
+            })
+            .collect();
+
+        // 线程 panic 时, 其负责的场景均视为写入失败
+        handles
+            .into_iter()
+            .flat_map(|(len, handle)| {
+                handle.join().unwrap_or_else(|e| {
+                    let message = panic_message(e);
+                    (0..len)
+                        .map(|_| Some(Err(io::Error::other(message.clone()))))
+                        .collect()
+                })
+            })
+            .collect()
+    })
+}
+
+/// 在清单中记录写入的场景和生成的文件
+///
+/// 保留的已有文件不属于 bd2wg 生成, 不应记录.
+fn update_manifest(
+    sink: &dyn OutputSink,
+    root: &Path,
+    scenes: &[(String, &[u8])],
+    generated: &[(String, &[u8])],
+) -> io::Result<()> {
+    let mut manifest = Manifest::load_from(sink, root)?;
+
+    for (path, text) in scenes {
+        manifest.insert_scene(ManifestEntry {
+            path: path.clone(),
+            hash: Some(hash_bytes(text)),
+            url: None,
+        });
+    }
+
+    for (path, bytes) in generated {
+        manifest.insert_asset(ManifestEntry {
+            path: path.clone(),
+            hash: Some(hash_bytes(bytes)),
+            url: None,
+        });
+    }
+
+    manifest.save_to(sink, root)
+}
+
+/// 按输出配置生成转译配置
+///
+/// 合并模式下沿用配置的场景文件名模板, 模板不含故事标识时为其添加前缀.
+fn transpiler_config(options: &PipelineOptions) -> TranspilerConfig {
+    let config = options.transpiler.clone();
+    match &options.extractor.merge {
+        Some(merge) => TranspilerConfig {
+            scene_template: match config.scene_template.contains("${story}") {
+                true => config.scene_template.clone(),
+                false => format!("${{story}}-{}", config.scene_template),
+            },
+            story_slug: merge.slug.clone(),
+            ..config
+        },
+        None => config,
+    }
+}
+
+/// 按管线选项创建解析器, 设置了恢复方式时由其提供解析失败资源的替代
+///
+/// 启用服装回退时以 HEAD 请求探测服装是否存在, 试运行时不发起请求.
+pub(super) fn new_resolver(options: &PipelineOptions) -> Resolver {
+    let mut resolver = Resolver::with_config(options.resolver.clone());
+    if options.resolver.costume_fallback
+        && !options.extractor.dry_run
+        && let Ok(header) = options.header_map()
+        && let Ok(probe) = new_url_probe(header)
+    {
+        resolver = resolver.with_probe(probe);
+    }
+
+    match options.recover.clone() {
+        Some(recover) => resolver.with_prompt(move |e| Some(recover.resolve(e)?.into_url())),
+        None => resolver,
+    }
+}
+
+/// 转译结束后启动下载管线
+///
+/// 被取消或按错误策略中止时不启动, 试运行时返回已结束的空管线.
+pub(super) fn start_download(
+    root: &Path,
+    header: Result<HeaderMap>,
+    res: Vec<Arc<Resource>>,
+    sink: Arc<dyn OutputSink>,
+    options: &PipelineOptions,
+    cancelled: bool,
+    aborted: Option<usize>,
+) -> Result<Box<dyn DownloadPipelineTrait>> {
+    match (aborted, options.extractor.dry_run) {
+        _ if cancelled => Err(Error::Cancelled),
+        (Some(count), _) => Err(Error::Aborted(count)),
+        // 不下载时直接写出输出目标
+        (None, true) => match sink.finish() {
+            Ok(()) => Ok(DownloadPipeline::skip()),
+            Err(e) => Err(Error::File(e.into())),
+        },
+        (None, false) => header.and_then(|header| {
+            DownloadPipeline::with_options(root, header, res, sink, options.clone())
+                .map(|pipe| -> Box<dyn DownloadPipelineTrait> { pipe })
+        }),
+    }
+}
+
+/// 按错误策略中止时的错误数
+pub(super) fn aborted_count(errors: &[Error]) -> Option<usize> {
+    errors.iter().find_map(|e| match e {
+        Error::Aborted(count) => Some(*count),
+        _ => None,
+    })
+}
+
+/// 配置了工程时输出到其游戏目录
+pub(super) fn game_root(root: &Path, config: &ExtractorConfig) -> PathBuf {
+    match config.project {
+        Some(_) => root.join(WEBGAL_GAME_DIR),
+        None => root.to_path_buf(),
     }
 }
 
@@ -126,32 +624,124 @@ impl Handle for TranspilePipeline {
 
     /// 等待转译管线结束
     ///
-    /// panic: 转译管线被调用 cancel.
+    /// 被取消或中止时不启动下载管线.
     fn join(mut self: Box<Self>) -> Self::Result {
-        let (errors, res) = self.handle.take().unwrap().join().unwrap();
-        let state = self.state.read().unwrap().clone();
+        let (errors, warnings, res, stats) =
+            (self.handle.take().unwrap().join()).unwrap_or_else(|e| {
+                let errors = vec![Error::from_panic(e)];
+                (errors, Vec::new(), Vec::new(), ResolveStats::default())
+            });
+        let state = safe_unwrap_lock!(self.state.read()).clone();
+        let resources = res.iter().map(|res| res.as_ref().clone()).collect();
+
+        let pipe = start_download(
+            &self.root,
+            self.header.take().unwrap(),
+            res,
+            self.sink.clone(),
+            &self.options,
+            self.cancel.load(Ordering::Relaxed),
+            aborted_count(&errors),
+        );
 
         (
-            TranspileResult { state, errors },
-            DownloadPipeline::new(&self.root, self.header.take().unwrap(), res)
-                .map(|pipe| -> Box<dyn DownloadPipelineTrait> { pipe }),
+            TranspileResult {
+                state,
+                errors,
+                warnings,
+                stats,
+                resources,
+            },
+            pipe,
         )
     }
 
     fn cancel(&mut self) {
         self.cancel.store(true, Ordering::Relaxed);
-        self.handle = None;
     }
 
     fn is_finished(&self) -> bool {
-        self.cancel.load(Ordering::Relaxed)
+        self.handle.as_ref().is_none_or(JoinHandle::is_finished)
     }
 }
 
-impl_drop_for_handle! {TranspilePipeline}
+impl_drop_for_handle! {TranspilePipeline, drop_policy}
 
 impl TranspilePipelineTrait for TranspilePipeline {
     fn state(&self) -> TranspileState {
-        self.state.read().unwrap().clone()
+        safe_unwrap_lock!(self.state.read()).clone()
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_detach_transpile_pipeline() {
+    use std::{thread::sleep, time::Duration};
+
+    use crate::services::output::MemorySink;
+
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-detach-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let story = dir.join("story.json");
+    fs::write(&story, bd2wg_fixtures::SHORT.json).unwrap();
+
+    let mut options = PipelineOptions::default();
+    options.extractor.dry_run = true;
+
+    // 分离后仍写入场景
+    let sink = Arc::new(MemorySink::default());
+    let mut pipe = TranspilePipeline::with_sink(&story, &dir, options, sink.clone());
+    pipe.detach();
+    drop(pipe);
+
+    let scene = dir.join("scene/start.txt");
+    for _ in 0..50 {
+        if sink.exists(&scene) {
+            break;
+        }
+        sleep(Duration::from_millis(100));
     }
+    assert!(sink.exists(&scene));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(test)]
+fn test_merge_scene_template() {
+    let mut options = PipelineOptions::default();
+    options.extractor.merge = Some(Default::default());
+    assert_eq!(
+        transpiler_config(&options).scene_template,
+        "${story}-scene-${n}.txt"
+    );
+
+    options.transpiler.scene_template = String::from("chapter/${story}_${n}.txt");
+    assert_eq!(
+        transpiler_config(&options).scene_template,
+        "chapter/${story}_${n}.txt"
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_check_scene_template() {
+    use crate::models::config::TranspilerConfigBuilder;
+
+    let template = |template: &str| {
+        TranspilerConfigBuilder::default()
+            .scene_template(template.to_string())
+            .build()
+    };
+
+    assert!(template("${story}-${n}.txt").is_ok());
+    assert!(template("scene.txt").is_err());
+    assert!(template("start.txt").is_err());
+
+    let config = TranspilerConfig {
+        scene_template: String::from("${story}.txt"),
+        story_slug: String::from("start"),
+        ..Default::default()
+    };
+    assert!(config.check_scene_template().is_err());
 }
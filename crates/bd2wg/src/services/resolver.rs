@@ -1,31 +1,51 @@
 //! 资源解析器
 
 use std::{
-    collections::{HashMap, hash_map::Entry},
-    sync::Arc,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
 };
 
+use regex::Regex;
+
 use crate::{
     error::*,
     models::{
         bestdori::{
             self, BESTDORI_ASSET_URL_MODEL, BESTDORI_ASSET_URL_MODEL_BUILDER,
-            BESTDORI_ASSET_URL_ROOT, BESTDORI_ASSET_URL_SE,
+            BESTDORI_ASSET_URL_SE, asset_url_root,
         },
+        config::{BundleCase, ModelRedirect, NamingStrategy, ResolverConfig},
         webgal,
     },
+    safe_unwrap_lock,
     traits::resolve::*,
     utils::*,
 };
 
+/// 资源探测函数, 返回资源是否存在
+type UrlProbe = dyn Fn(&str) -> bool + Send + Sync;
+
+/// 资源链接询问函数, 重试阶段最后调用, 返回用户提供的链接
+type UrlPrompt = dyn Fn(&ResolveError) -> Option<String> + Send + Sync;
+
+/// 角色默认服装后缀
+const DEFAULT_COSTUME_SUFFIX: &str = "general";
+
 const RESOURCE_IMAGE_EXTEND: &str = ".png";
 const RESOURCE_SOUND_EXTEND: &str = ".mp3";
 
+/// 重试阶段猜测的扩展名
+const RESOURCE_IMAGE_EXTEND_GUESSES: &[&str] = &[".png", ".jpg", ".webp"];
+const RESOURCE_SOUND_EXTEND_GUESSES: &[&str] = &[".mp3", ".ogg", ".wav"];
+
 /// 根据 webgal 资源类型获取后缀名
 macro_rules! get_extend {
     ($kind:ident) => {
         match $kind {
-            webgal::ResourceType::Background => RESOURCE_IMAGE_EXTEND,
+            webgal::ResourceType::Background | webgal::ResourceType::Sprite => {
+                RESOURCE_IMAGE_EXTEND
+            }
             webgal::ResourceType::Bgm | webgal::ResourceType::Vocal => RESOURCE_SOUND_EXTEND,
             _ => return None,
         }
@@ -38,12 +58,50 @@ enum ResourceKey {
     Model(String),
 }
 
+/// 基于名称映射的模型名称转换, 未映射的名称保持不变
+#[derive(Debug, Clone, Default)]
+pub struct MapDisplayResolver {
+    motions: HashMap<String, String>,
+    expressions: HashMap<String, String>,
+}
+
+impl From<&ModelRedirect> for MapDisplayResolver {
+    fn from(value: &ModelRedirect) -> Self {
+        Self {
+            motions: value.motions.clone(),
+            expressions: value.expressions.clone(),
+        }
+    }
+}
+
+impl ModelDisplayResolver for MapDisplayResolver {
+    fn motion(&self, motion: &str) -> String {
+        self.motions
+            .get(motion)
+            .cloned()
+            .unwrap_or_else(|| motion.to_string())
+    }
+
+    fn expression(&self, expression: &str) -> String {
+        self.expressions
+            .get(expression)
+            .cloned()
+            .unwrap_or_else(|| expression.to_string())
+    }
+}
+
 /// 资源解析器
 ///
 /// 解析 Bestdori 资源, 供下载器和转译器使用.
 #[derive(Default)]
 pub struct Resolver {
+    config: ResolverConfig,
     resource: HashMap<ResourceKey, Arc<webgal::Resource>>,
+    probe: Option<Box<UrlProbe>>,
+    prompt: Option<Box<UrlPrompt>>,
+    fallbacks: HashMap<String, String>, // 服装 -> 回退服装
+    bgm_regex: Option<Regex>,           // 编译后的 BGM 数据包正则, 无效时为空
+    stats: ResolveStats,
 }
 
 impl Resolver {
@@ -52,50 +110,91 @@ impl Resolver {
         Self::default()
     }
 
+    /// 根据配置创建空的解析器
+    ///
+    /// BGM 数据包的正则规则在此编译一次.
+    pub fn with_config(config: ResolverConfig) -> Self {
+        let bgm_regex = match &config.bgm_bundle_case {
+            BundleCase::Regex { pattern, .. } => Regex::new(pattern).ok(),
+            _ => None,
+        };
+
+        Self {
+            config,
+            bgm_regex,
+            ..Self::default()
+        }
+    }
+
+    /// 设置资源探测函数, 用于检查服装是否存在
+    pub fn with_probe(mut self, probe: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.probe = Some(Box::new(probe));
+        self
+    }
+
+    /// 设置资源链接询问函数, 其他重试策略均失败时由用户提供链接
+    pub fn with_prompt(
+        mut self,
+        prompt: impl Fn(&ResolveError) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.prompt = Some(Box::new(prompt));
+        self
+    }
+
+    /// 记录新资源
+    fn record(&mut self, res: &webgal::Resource) {
+        *self.stats.unique.entry(res.kind).or_default() += 1;
+    }
+
     /// 查找已存在的元素 / 插入
     fn get_or_insert(
         &mut self,
         key: ResourceKey,
-        call: impl FnOnce() -> ResolveResult<webgal::Resource>,
+        call: impl FnOnce(&mut Self) -> ResolveResult<webgal::Resource>,
     ) -> ResolveResult<ResourceEntry> {
-        Ok(match self.resource.entry(key) {
-            // 解析并保存, 返回拷贝的指针
-            Entry::Vacant(v) => ResourceEntry::Vacant(v.insert(Arc::new(call()?)).clone()),
+        // 资源已存在, 返回共享的指针
+        if let Some(res) = self.resource.get(&key) {
+            self.stats.reused += 1;
+            return Ok(ResourceEntry::occupied(res.clone()));
+        }
 
-            // 资源已存在, 返回保存的裸指针
-            Entry::Occupied(o) => ResourceEntry::Occupied(Arc::as_ptr(o.get())),
-        })
+        // 解析并保存, 返回共享的指针
+        let res = Arc::new(call(self)?);
+        self.record(&res);
+        self.resource.insert(key, res.clone());
+        Ok(ResourceEntry::vacant(res))
     }
 
     // ---------------- resolve ----------------
 
     /// 解析资源
-    fn resolve(res: &bestdori::Resource, kind: ResourceType) -> Option<webgal::Resource> {
+    fn resolve(&self, res: &bestdori::Resource, kind: ResourceType) -> Option<webgal::Resource> {
         match kind {
-            ResourceType::Image => Self::resolve_image(res),
-            ResourceType::Bgm => Self::resolve_bgm(res),
-            ResourceType::Se => Self::resolve_se(res),
+            ResourceType::Image => self.resolve_image(res),
+            ResourceType::Bgm => self.resolve_bgm(res),
+            ResourceType::Se => self.resolve_se(res),
+            ResourceType::Voice => self.resolve_voice(res),
         }
     }
 
-    fn resolve_image(res: &bestdori::Resource) -> Option<webgal::Resource> {
+    fn resolve_image(&self, res: &bestdori::Resource) -> Option<webgal::Resource> {
         match res.kind {
             bestdori::ResourceType::Custom => {
-                Self::resolve_custom(&res.path, webgal::ResourceType::Background)
+                self.resolve_custom(&res.path, webgal::ResourceType::Background)
             }
             bestdori::ResourceType::Bandori => {
-                Self::resolve_bundle(&res.path, webgal::ResourceType::Background)
+                self.resolve_bundle(&res.path, webgal::ResourceType::Background)
             }
             _ => None,
         }
     }
 
-    fn resolve_bgm(res: &bestdori::Resource) -> Option<webgal::Resource> {
+    fn resolve_bgm(&self, res: &bestdori::Resource) -> Option<webgal::Resource> {
         match res {
             bestdori::Resource {
                 kind: bestdori::ResourceType::Custom,
                 path,
-            } => Self::resolve_custom(path, webgal::ResourceType::Bgm),
+            } => self.resolve_custom(path, webgal::ResourceType::Bgm),
 
             // 从数据包获取 bgm
             bestdori::Resource {
@@ -106,8 +205,9 @@ impl Resolver {
                 Some(webgal::Resource {
                     kind: webgal::ResourceType::Bgm,
                     url: format!(
-                        "{BESTDORI_ASSET_URL_ROOT}{}_rip/{file}",
-                        lower_first_alphabetic(&file)
+                        "{}{}_rip/{file}",
+                        self.asset_root(),
+                        self.bgm_bundle(&file)?
                     ),
                     path: file,
                 })
@@ -117,12 +217,27 @@ impl Resolver {
         }
     }
 
-    fn resolve_se(res: &bestdori::Resource) -> Option<webgal::Resource> {
+    /// 根据大小写规则由 BGM 文件名推断数据包名称
+    ///
+    /// 正则无效时返回 None.
+    fn bgm_bundle(&self, file: &str) -> Option<String> {
+        Some(match &self.config.bgm_bundle_case {
+            BundleCase::Keep => file.to_string(),
+            BundleCase::FirstLower => lower_first_alphabetic(file),
+            BundleCase::Regex { replace, .. } => self
+                .bgm_regex
+                .as_ref()?
+                .replace_all(file, replace.as_str())
+                .into_owned(),
+        })
+    }
+
+    fn resolve_se(&self, res: &bestdori::Resource) -> Option<webgal::Resource> {
         match res {
             bestdori::Resource {
                 kind: bestdori::ResourceType::Custom,
                 path,
-            } => Self::resolve_custom(path, webgal::ResourceType::Vocal),
+            } => self.resolve_custom(path, webgal::ResourceType::Vocal),
 
             // 从数据包获取 se
             bestdori::Resource {
@@ -136,7 +251,7 @@ impl Resolver {
                 let file = format!("{file}{RESOURCE_SOUND_EXTEND}");
                 Some(webgal::Resource {
                     kind: webgal::ResourceType::Vocal,
-                    url: format!("{BESTDORI_ASSET_URL_ROOT}{bundle}_rip/{file}"),
+                    url: format!("{}{bundle}_rip/{file}", self.asset_root()),
                     path: file,
                 })
             }
@@ -158,10 +273,35 @@ impl Resolver {
         }
     }
 
+    fn resolve_voice(&self, res: &bestdori::Resource) -> Option<webgal::Resource> {
+        match res.kind {
+            bestdori::ResourceType::Custom => {
+                self.resolve_custom(&res.path, webgal::ResourceType::Vocal)
+            }
+            bestdori::ResourceType::Bandori => match &res.path {
+                // 从数据包获取语音
+                bestdori::ResourcePath::File {
+                    file,
+                    bundle: Some(bundle),
+                } => {
+                    let file = format!("{file}{RESOURCE_SOUND_EXTEND}");
+                    Some(webgal::Resource {
+                        kind: webgal::ResourceType::Vocal,
+                        url: format!("{}{bundle}_rip/{file}", self.asset_root()),
+                        path: format!("{}-{file}", bundle.replace('/', "_")),
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     // ---------------- resolve ----------------
 
     /// 解析上传的资源
     fn resolve_custom(
+        &self,
         res: &bestdori::ResourcePath,
         kind: webgal::ResourceType,
     ) -> Option<webgal::Resource> {
@@ -169,14 +309,87 @@ impl Resolver {
             bestdori::ResourcePath::Url { url } => Some(webgal::Resource {
                 kind,
                 url: url.clone(),
-                path: gen_name_from_url(url, get_extend! {kind}),
+                path: self.resolve_custom_path(url, kind)?,
             }),
             _ => None,
         }
     }
 
+    /// 根据命名策略生成上传资源的路径
+    fn resolve_custom_path(&self, url: &str, kind: webgal::ResourceType) -> Option<String> {
+        Some(match self.config.naming {
+            NamingStrategy::Flatten => gen_name_from_url(url, get_extend! {kind}),
+            NamingStrategy::Readable => gen_readable_name_from_url(url, get_extend! {kind}),
+        })
+    }
+
+    /// 解析 Live2D 模型
+    fn resolve_live2d(&self, costume: &str) -> webgal::Resource {
+        let path = self
+            .config
+            .friendly_model_path
+            .then(|| self.config.characters().friendly_costume(costume))
+            .flatten()
+            .unwrap_or_else(|| costume.to_string());
+
+        webgal::Resource {
+            kind: webgal::ResourceType::Figure,
+            url: Self::model_url(costume),
+            path: format!("{path}/"),
+        }
+    }
+
+    /// 按配置区域的数据包入口链接
+    fn asset_root(&self) -> String {
+        bestdori::asset_url_root(self.config.region)
+    }
+
+    fn model_url(costume: &str) -> String {
+        format!("{BESTDORI_ASSET_URL_MODEL}{costume}_rip/{BESTDORI_ASSET_URL_MODEL_BUILDER}")
+    }
+
+    /// 获取可用的回退服装 (若服装不存在)
+    ///
+    /// 仅在设置了探测函数时生效.
+    fn fallback_costume(&self, costume: &str) -> Option<String> {
+        let probe = self
+            .probe
+            .as_ref()
+            .filter(|_| self.config.costume_fallback)?;
+
+        if probe(&Self::model_url(costume)) {
+            return None;
+        }
+
+        let id: u8 = costume.split_once('_')?.0.parse().ok()?;
+        let fallback = self
+            .config
+            .default_costumes
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| format!("{id:03}_{DEFAULT_COSTUME_SUFFIX}"));
+
+        (fallback != costume && probe(&Self::model_url(&fallback))).then_some(fallback)
+    }
+
+    /// 获取静态立绘链接 (若服装为静态立绘)
+    fn sprite_url(&self, costume: &str) -> Option<String> {
+        if let Some(url) = self.config.sprites.get(costume) {
+            return Some(url.clone());
+        }
+
+        let lower = costume.to_ascii_lowercase();
+        let is_url = lower.starts_with("http://") || lower.starts_with("https://");
+        let is_image = [".png", ".jpg", ".jpeg", ".webp"]
+            .iter()
+            .any(|ext| lower.split(['?', '#']).next().unwrap_or("").ends_with(ext));
+
+        (is_url && is_image).then(|| costume.to_string())
+    }
+
     /// 解析带完整路径的资源
     fn resolve_bundle(
+        &self,
         res: &bestdori::ResourcePath,
         kind: webgal::ResourceType,
     ) -> Option<webgal::Resource> {
@@ -186,12 +399,37 @@ impl Resolver {
                 bundle: Some(bundle),
             } => Some(webgal::Resource {
                 kind,
-                url: format!("{BESTDORI_ASSET_URL_ROOT}{bundle}_rip/{file}"),
+                url: format!("{}{bundle}_rip/{file}", self.asset_root()),
                 path: format!("{bundle}-{file}{}", get_extend! {kind}),
             }),
             _ => None,
         }
     }
+
+    // ---------------- retry ----------------
+
+    /// 重试阶段
+    ///
+    /// 解析失败时由用户提供链接. 其他区域和扩展名在下载失败后尝试, 见 [`alternative_urls`].
+    fn retry(&self, res: &bestdori::Resource, kind: ResourceType) -> Option<webgal::Resource> {
+        tracing::debug!(resource = ?res.path, "retry resolving resource");
+        self.prompt_url(res, kind)
+    }
+
+    /// 询问用户提供链接, 按上传资源处理
+    fn prompt_url(&self, res: &bestdori::Resource, kind: ResourceType) -> Option<webgal::Resource> {
+        let url = self.prompt.as_ref()?(&ResolveError {
+            kind,
+            resource: res.clone(),
+        })?;
+        let kind = kind.webgal();
+
+        Some(webgal::Resource {
+            kind,
+            path: self.resolve_custom_path(&url, kind)?,
+            url,
+        })
+    }
 }
 
 impl Resolve for Resolver {
@@ -200,8 +438,17 @@ impl Resolve for Resolver {
         res: &bestdori::Resource,
         kind: ResourceType,
     ) -> ResolveResult<ResourceEntry> {
-        self.get_or_insert(ResourceKey::Normal(res.clone(), kind), || {
-            Self::resolve(res, kind).ok_or_else(|| ResolveError {
+        self.get_or_insert(ResourceKey::Normal(res.clone(), kind), |this| {
+            if let Some(resolved) = this.resolve(res, kind) {
+                return Ok(resolved);
+            }
+
+            if let Some(retried) = this.retry(res, kind) {
+                this.stats.retried += 1;
+                return Ok(retried);
+            }
+
+            Err(ResolveError {
                 kind,
                 resource: res.clone(),
             })
@@ -209,15 +456,412 @@ impl Resolve for Resolver {
     }
 
     fn resolve_model(&mut self, costume: &str) -> ResourceEntry {
-        self.get_or_insert(ResourceKey::Model(costume.to_string()), || {
-            Ok(webgal::Resource {
-                kind: webgal::ResourceType::Figure,
-                url: format!(
-                    "{BESTDORI_ASSET_URL_MODEL}{costume}_rip/{BESTDORI_ASSET_URL_MODEL_BUILDER}"
-                ),
-                path: format!("{costume}/"),
-            })
+        let display = self.config.model_redirects.get(costume).map(
+            |redirect| -> Arc<dyn ModelDisplayResolver> {
+                Arc::new(MapDisplayResolver::from(redirect))
+            },
+        );
+
+        // 重定向到其他服装, 以目标服装为键, 使重定向到同一服装的模型共享资源
+        let target = match self.sprite_url(costume) {
+            Some(_) => None,
+            None => (self.config.model_redirects.get(costume))
+                .and_then(|redirect| redirect.costume.clone()),
+        };
+        let key = target.clone().unwrap_or_else(|| costume.to_string());
+
+        self.get_or_insert(ResourceKey::Model(key), |this| {
+            // 静态立绘
+            if let Some(url) = this.sprite_url(costume) {
+                let kind = webgal::ResourceType::Sprite;
+                return Ok(webgal::Resource {
+                    kind,
+                    path: this.resolve_custom_path(&url, kind).unwrap(),
+                    url,
+                });
+            }
+
+            if let Some(target) = &target {
+                return Ok(this.resolve_live2d(target));
+            }
+
+            // 服装不存在时回退到默认服装
+            if let Some(fallback) = this.fallback_costume(costume) {
+                let res = this.resolve_live2d(&fallback);
+                this.fallbacks.insert(costume.to_string(), fallback);
+                return Ok(res);
+            }
+
+            Ok(this.resolve_live2d(costume))
         })
         .unwrap() // :(
+        .with_display(display)
+        .with_fallback(self.fallbacks.get(costume).map(|fallback| CostumeFallback {
+            costume: costume.to_string(),
+            fallback: fallback.clone(),
+        }))
+    }
+
+    fn stats(&self) -> ResolveStats {
+        self.stats.clone()
+    }
+
+    fn characters(&self) -> bestdori::CharacterTable {
+        self.config.characters().clone()
+    }
+}
+
+/// 下载失败的资源可尝试的其他链接, 依次为其他区域和其他扩展名
+///
+/// 仅对下载失败的资源调用, 由下载本身检查链接是否可用, 无需逐个探测. 写入路径保持不变.
+pub fn alternative_urls(config: &ResolverConfig, res: &webgal::Resource) -> Vec<String> {
+    let mut urls = Vec::new();
+
+    // 其他区域
+    if let Some(rest) = res.url.strip_prefix(&asset_url_root(config.region)) {
+        urls.extend(
+            config
+                .retry_regions
+                .iter()
+                .filter(|region| **region != config.region)
+                .map(|region| format!("{}{rest}", asset_url_root(*region))),
+        );
+    }
+
+    // 其他扩展名, 替换已知的扩展名, 否则追加
+    let guesses = match res.kind {
+        _ if !config.guess_extension => &[][..],
+        webgal::ResourceType::Background | webgal::ResourceType::Sprite => {
+            RESOURCE_IMAGE_EXTEND_GUESSES
+        }
+        webgal::ResourceType::Bgm | webgal::ResourceType::Vocal => RESOURCE_SOUND_EXTEND_GUESSES,
+        _ => &[][..],
+    };
+    let stem = guesses
+        .iter()
+        .find_map(|e| res.url.strip_suffix(e))
+        .unwrap_or(&res.url);
+    urls.extend(
+        guesses
+            .iter()
+            .map(|extend| format!("{stem}{extend}"))
+            .filter(|url| *url != res.url),
+    );
+
+    urls
+}
+
+/// 线程安全的共享解析器
+///
+/// 克隆得到的解析器共享同一份解析结果, 可在多个线程的转译器间去重资源.
+/// 解析统计按克隆分别记录, 只包含经该克隆发起的解析.
+pub struct SharedResolver<R: Resolve = Resolver> {
+    inner: Arc<Mutex<R>>,
+    stats: ResolveStats,
+}
+
+impl<R: Resolve> SharedResolver<R> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(resolver)),
+            stats: ResolveStats::default(),
+        }
+    }
+
+    /// 持锁解析, 并记录本次解析的统计增量
+    fn with_inner<T>(&mut self, call: impl FnOnce(&mut R) -> T) -> T {
+        let mut inner = safe_unwrap_lock!(self.inner.lock());
+        let before = inner.stats();
+        let result = call(&mut inner);
+        self.stats.add(&inner.stats().since(&before));
+        result
+    }
+}
+
+impl<R: Resolve> Clone for SharedResolver<R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            stats: ResolveStats::default(),
+        }
+    }
+}
+
+impl<R: Resolve + Default> Default for SharedResolver<R> {
+    fn default() -> Self {
+        Self::new(R::default())
+    }
+}
+
+impl<R: Resolve> Resolve for SharedResolver<R> {
+    fn resolve_normal(
+        &mut self,
+        res: &bestdori::Resource,
+        kind: ResourceType,
+    ) -> ResolveResult<ResourceEntry> {
+        self.with_inner(|inner| inner.resolve_normal(res, kind))
+    }
+
+    fn resolve_model(&mut self, costume: &str) -> ResourceEntry {
+        self.with_inner(|inner| inner.resolve_model(costume))
+    }
+
+    fn stats(&self) -> ResolveStats {
+        self.stats.clone()
+    }
+
+    fn characters(&self) -> bestdori::CharacterTable {
+        safe_unwrap_lock!(self.inner.lock()).characters()
+    }
+}
+
+/// 以 threads 个线程并发探测资源大小, 返回总和
+///
+/// 在解析器之外调用, 以免持锁等待请求. Live2D 模型由多个文件组成, 与本地文件一样不统计大小.
+pub fn probe_size(
+    resources: &[Arc<webgal::Resource>],
+    probe: impl Fn(&str) -> Option<u64> + Sync,
+    threads: usize,
+) -> u64 {
+    let urls: Vec<&str> = resources
+        .iter()
+        .filter(|res| res.kind != webgal::ResourceType::Figure && res.local_file().is_none())
+        .map(|res| res.url.as_str())
+        .collect();
+    let chunk = urls.len().div_ceil(threads.max(1)).max(1);
+
+    let probe = &probe;
+    thread::scope(|s| {
+        let handles: Vec<_> = urls
+            .chunks(chunk)
+            .map(|urls| s.spawn(move || urls.iter().filter_map(|url| probe(url)).sum::<u64>()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap_or(0)).sum()
+    })
+}
+
+#[test]
+#[cfg(test)]
+fn test_shared_resolver_dedup() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedResolver>();
+
+    let resolver = SharedResolver::<Resolver>::default();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let mut resolver = resolver.clone();
+            std::thread::spawn(move || resolver.resolve_model("039_casual-2023").is_vacant())
+        })
+        .collect();
+
+    let vacant = handles
+        .into_iter()
+        .map(|h| h.join().unwrap())
+        .filter(|&v| v)
+        .count();
+
+    assert_eq!(vacant, 1);
+}
+
+#[test]
+#[cfg(test)]
+fn test_costume_fallback() {
+    let mut resolver = Resolver::new().with_probe(|url| !url.contains("039_missing"));
+
+    let fallback = CostumeFallback {
+        costume: "039_missing".to_string(),
+        fallback: "039_general".to_string(),
+    };
+    let res = resolver.resolve_model("039_missing");
+    assert!(res.url.contains("039_general_rip"));
+    assert_eq!(res.fallback(), Some(&fallback));
+
+    // 已解析的服装同样返回回退记录, 以便各调用方分别警告
+    let res = resolver.resolve_model("039_missing");
+    assert!(!res.is_vacant());
+    assert_eq!(res.fallback(), Some(&fallback));
+    assert!(
+        resolver
+            .resolve_model("039_casual-2023")
+            .fallback()
+            .is_none()
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_model_redirect_display() {
+    let mut config = ResolverConfig::default();
+    config.model_redirects.insert(
+        "039_missing".to_string(),
+        ModelRedirect {
+            costume: Some("039_casual-2023".to_string()),
+            motions: HashMap::from([("smile99".to_string(), "smile01".to_string())]),
+            ..Default::default()
+        },
+    );
+    let mut resolver = Resolver::with_config(config);
+
+    for _ in 0..2 {
+        let res = resolver.resolve_model("039_missing");
+        let display = res.display().unwrap();
+
+        assert!(res.url.contains("039_casual-2023_rip"));
+        assert_eq!(display.motion("smile99"), "smile01");
+        assert_eq!(display.expression("sad01"), "sad01");
     }
+
+    // 重定向目标与目标服装本身共享同一资源, 不重复下载
+    let res = resolver.resolve_model("039_casual-2023");
+    assert!(!res.is_vacant());
+    assert!(res.display().is_none());
+}
+
+#[test]
+#[cfg(test)]
+fn test_resolve_stats() {
+    let mut resolver = Resolver::new();
+    let bgm = bestdori::Resource {
+        kind: bestdori::ResourceType::Bandori,
+        path: bestdori::ResourcePath::File {
+            file: "BGM001".to_string(),
+            bundle: None,
+        },
+    };
+
+    for _ in 0..3 {
+        resolver.resolve_normal(&bgm, ResourceType::Bgm).unwrap();
+        resolver.resolve_model("039_casual-2023");
+    }
+
+    let stats = resolver.stats();
+    assert_eq!(stats.total(), 2);
+    assert_eq!(stats.reused, 4);
+    assert_eq!(stats.to_string(), "2 unique assets (4 reused)");
+
+    // 模型不统计大小
+    let resources: Vec<_> = resolver.resource.into_values().collect();
+    assert_eq!(probe_size(&resources, |_| Some(1 << 20), 4), 1 << 20);
+}
+
+#[test]
+#[cfg(test)]
+fn test_bgm_bundle_case() {
+    let bgm = bestdori::Resource {
+        kind: bestdori::ResourceType::Bandori,
+        path: bestdori::ResourcePath::File {
+            file: "BGM001".to_string(),
+            bundle: None,
+        },
+    };
+    let url = |bgm_bundle_case| {
+        let config = crate::models::config::ResolverConfigBuilder::default()
+            .bgm_bundle_case(bgm_bundle_case)
+            .build()
+            .unwrap();
+        Resolver::with_config(config)
+            .resolve_normal(&bgm, ResourceType::Bgm)
+            .map(|res| res.url.clone())
+    };
+
+    assert!(
+        url(BundleCase::FirstLower)
+            .unwrap()
+            .contains("/bGM001.mp3_rip/")
+    );
+    assert!(url(BundleCase::Keep).unwrap().contains("/BGM001.mp3_rip/"));
+    assert!(
+        url(BundleCase::Regex {
+            pattern: r"^BGM(\d+)".to_string(),
+            replace: "sound/bgm$1".to_string(),
+        })
+        .unwrap()
+        .contains("/sound/bgm001.mp3_rip/")
+    );
+    assert!(
+        url(BundleCase::Regex {
+            pattern: "(".to_string(),
+            replace: String::new(),
+        })
+        .is_err()
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_resolve_retry() {
+    let mut resolver = Resolver::new().with_prompt(|e| {
+        (e.kind == ResourceType::Image).then(|| "https://example.com/bg.png".to_string())
+    });
+
+    let bandori = |file: &str, bundle: Option<&str>| bestdori::Resource {
+        kind: bestdori::ResourceType::Bandori,
+        path: bestdori::ResourcePath::File {
+            file: file.to_string(),
+            bundle: bundle.map(str::to_string),
+        },
+    };
+
+    // 缺少数据包, 由用户提供链接
+    let image = resolver
+        .resolve_normal(&bandori("bg00001", None), ResourceType::Image)
+        .unwrap();
+    assert_eq!(image.url, "https://example.com/bg.png");
+
+    // 全部策略失败
+    assert!(
+        resolver
+            .resolve_normal(&bandori("se01", None), ResourceType::Voice)
+            .is_err()
+    );
+    assert_eq!(resolver.stats().retried, 1);
+}
+
+#[test]
+#[cfg(test)]
+fn test_alternative_urls() {
+    use crate::models::bestdori::Region;
+
+    let config = crate::models::config::ResolverConfigBuilder::default()
+        .retry_regions(vec![Region::Jp, Region::En])
+        .guess_extension(true)
+        .build()
+        .unwrap();
+    let res = |url: &str| webgal::Resource {
+        kind: webgal::ResourceType::Background,
+        url: url.to_string(),
+        path: String::from("bg.png"),
+    };
+
+    assert_eq!(
+        alternative_urls(&config, &res("https://bestdori.com/assets/jp/bg/a.png")),
+        [
+            "https://bestdori.com/assets/en/bg/a.png",
+            "https://bestdori.com/assets/jp/bg/a.jpg",
+            "https://bestdori.com/assets/jp/bg/a.webp",
+        ]
+    );
+    assert!(
+        alternative_urls(
+            &ResolverConfig::default(),
+            &res("https://example.com/a.png")
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_shared_resolver_stats() {
+    let shared = SharedResolver::<Resolver>::default();
+    let (mut a, mut b) = (shared.clone(), shared.clone());
+
+    a.resolve_model("039_casual-2023");
+    b.resolve_model("039_casual-2023");
+    b.resolve_model("036_casual-2023");
+
+    // 各克隆只统计经自身发起的解析
+    assert_eq!((a.stats().total(), a.stats().reused), (1, 0));
+    assert_eq!((b.stats().total(), b.stats().reused), (1, 1));
+    assert_eq!(shared.stats().total(), 0);
 }
@@ -1,9 +1,7 @@
 //! 脚本转译器
 
-// TODO: 处理 delay 字段.
-
 use std::{
-    collections::{HashMap, hash_map::Entry},
+    collections::{HashMap, HashSet, hash_map::Entry},
     sync::Arc,
 };
 
@@ -12,27 +10,61 @@ use derive_builder::Builder;
 use crate::{
     error::*,
     models::{
-        bestdori::{self, Motion},
+        bestdori::{self, CharacterTable, Motion},
+        config::{
+            DEFAULT_TRANSITION_DURATION, MotionOrder, SceneSplit, SpecialCharPolicy, StoryEnd,
+            TelopStyle, TransitionBackdrop, TranspilerConfig,
+        },
         webgal::{self, ChangeFigureAction, FigureSide, Resource, SayAction, Scene, Transform},
     },
     return_ok,
-    traits::{asset::Asset, resolve::*, transpile::*},
+    services::filter::{LineBreakFilter, MarkupFilter, RegexFilter},
+    traits::{asset::Asset, filter::TextFilter, resolve::*, transpile::*},
+    utils::{is_special_char, render_template, split_text},
 };
 
 type PreResult<T> = std::result::Result<T, TranspileErrorKind>;
 
+/// 循环播放 se 的 id
+const LOOP_SE_ID: &str = "se";
+
+/// 切换说话人高亮的动画时长 (毫秒)
+const HIGHLIGHT_DURATION: u32 = 200;
+
+/// 白色转场背景, 1x1 的白色 PNG
+const WHITE_BACKDROP_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53,
+    0xde, 0x00, 0x00, 0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xff, 0xff, 0x3f,
+    0x00, 0x05, 0xfe, 0x02, 0xfe, 0x0d, 0xef, 0x46, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e,
+    0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+/// 将 0-1 的音量转换为百分比
+fn to_volume(volume: f32) -> u8 {
+    (volume.clamp(0., 1.) * 100.).round() as u8
+}
+
 /// 模型上下文信息
 #[derive(Debug, Clone, Default, Builder)]
 struct Model {
     path: String,
     #[builder(default)]
+    sprite: bool,
+    #[builder(default)]
+    display: Option<Arc<dyn ModelDisplayResolver>>,
+    #[builder(default)]
     side: FigureSide,
     #[builder(default)]
+    layer: u32,
+    #[builder(default = "Transform::new_with_x(0)")]
     transform: Transform,
     #[builder(default)]
     motion: Option<String>,
     #[builder(default)]
     expression: Option<String>,
+    #[builder(default)]
+    dimmed: bool,
 }
 
 /// 上下文信息
@@ -40,25 +72,90 @@ struct Model {
 struct Context {
     background: Option<String>,
     models: HashMap<u8, Model>,
+    slots: HashMap<FigureSide, Vec<u8>>, // 舞台位置上的人物, 自下而上
+    layers: u32,                         // 已分配的人物层级数
 }
 
 /// 脚本转译器
 ///
-/// 若希望复用 Resolver, 考虑使用 Arc 包装一个实现.
+/// 若希望在多个转译器间复用 Resolver, 考虑使用 SharedResolver 包装.
+///
+/// ```
+/// use bd2wg::{
+///     models::bestdori::Story,
+///     services::{resolver::Resolver, transpiler::Transpiler},
+///     traits::transpile::Transpile,
+/// };
+/// use bd2wg_fixtures::SHORT;
+///
+/// let story = Story::from_bytes(SHORT.json).unwrap();
+/// let result = Transpiler::<Resolver>::default().transpile(&story);
+///
+/// let expected = SHORT.expected.unwrap();
+/// assert_eq!(result.story.len().0, expected.scenes);
+/// assert_eq!(result.resources.len(), expected.resources);
+/// assert!(result.story.iter().any(|scene| scene.path == "start.txt"));
+/// ```
 pub struct Transpiler<R: Resolve> {
     resolver: R,
+    config: TranspilerConfig,
     context: Context,
     scenes: Vec<Scene>,
+    scene_actions: usize,   // 当前场景已转译的指令数
+    avatar: Option<String>, // 当前小头像
+    bgm: bool,              // bgm 是否正在播放
+    filters: Vec<Box<dyn TextFilter>>,
     resources: Vec<Arc<Resource>>,
+    actions: ActionStats,
+    warnings: Vec<Warning>,
+    pending_warnings: Vec<WarningKind>, // 当前指令的警告
+    fallbacks: HashSet<String>,         // 已警告回退的服装
+    characters: CharacterTable,         // 取自解析器
+    generated: Vec<(String, Vec<u8>)>,  // 生成的文件
 }
 
 impl<R: Resolve> Transpiler<R> {
     pub fn new(resolver: R) -> Self {
+        Self::with_config(resolver, TranspilerConfig::default())
+    }
+
+    /// 根据配置创建转译器
+    pub fn with_config(resolver: R, config: TranspilerConfig) -> Self {
+        // 按配置启用内置文本过滤器
+        let mut filters: Vec<Box<dyn TextFilter>> = Vec::new();
+
+        if config.markup {
+            filters.push(Box::new(MarkupFilter::new(resolver.characters())));
+        }
+
+        if !config.text_replacements.is_empty() {
+            filters.push(Box::new(RegexFilter::new(&config.text_replacements)));
+        }
+
+        if config.line_width.is_some() {
+            filters.push(Box::new(LineBreakFilter::new(config.line_width)));
+        }
+
+        let engine = config.engine;
         let mut transpiler = Self {
-            resolver,
+            config,
             context: Context::default(),
-            scenes: vec![Scene::new_start_scene()],
+            scenes: vec![Scene {
+                engine,
+                ..Scene::new_start_scene()
+            }],
+            scene_actions: 0,
+            avatar: None,
+            bgm: false,
+            filters,
             resources: Vec::new(),
+            actions: ActionStats::default(),
+            warnings: Vec::new(),
+            pending_warnings: Vec::new(),
+            fallbacks: HashSet::new(),
+            characters: resolver.characters(),
+            generated: Vec::new(),
+            resolver,
         };
 
         transpiler.push_action_and_change_scene(
@@ -71,59 +168,57 @@ impl<R: Resolve> Transpiler<R> {
         transpiler
     }
 
+    /// 追加文本过滤器, 在内置过滤器之后执行
+    pub fn with_filter(mut self, filter: impl TextFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
     fn into_result(self, errors: Vec<Error>) -> TranspileResult {
         TranspileResult {
             story: webgal::Story(self.scenes),
             resources: self.resources,
             errors,
+            warnings: self.warnings,
+            stats: self.resolver.stats(),
+            actions: self.actions,
+            generated: self.generated,
         }
     }
 
-    /// 清空场景
-    fn clear(&mut self) -> Context {
-        // 移除人物
-        let actions: Vec<webgal::Action> = self
-            .context
-            .models
-            .keys()
-            .map(|&id| webgal::ChangeFigureAction::new_hide(id, true).into())
-            // 移除背景
-            .chain(std::iter::once(webgal::ChangeBgAction::default().into()))
-            .collect();
+    /// 隐藏全部人物, 保留上下文
+    fn hide_models(&mut self) {
+        let ids: Vec<u8> = self.context.models.keys().copied().collect();
 
-        for act in actions {
-            self.push_action(act);
+        for id in ids {
+            self.push_action(ChangeFigureAction::new_hide(id, true).into());
         }
-
-        std::mem::take(&mut self.context)
     }
 
-    /// 设置上下文
-    fn set_context(&mut self, context: Context) {
-        // 清空场景 (场景大概为空)
-        self.clear();
+    /// 按层级重新显示上下文中的人物
+    fn show_models(&mut self) {
+        let mut models: Vec<(u8, Model)> = self
+            .context
+            .models
+            .iter()
+            .map(|(&id, model)| (id, model.clone()))
+            .collect();
+        models.sort_by_key(|(id, model)| (model.layer, *id));
 
-        // 设置人物
-        for (&id, model) in &context.models {
-            self.display_model(id, model.clone(), true);
+        for (id, model) in models {
+            self.display_model(id, model, true);
         }
-
-        // 设置背景
-        self.push_action(
-            webgal::ChangeBgAction {
-                image: context.background.clone(),
-                next: false,
-            }
-            .into(),
-        );
-
-        // 设置场景
-        self.context = context;
     }
 
     /// 下一个场景的名称
     fn next_scene_name(&self) -> String {
-        format!("scene-{}.txt", self.scenes.len())
+        render_template(
+            &self.config.scene_template,
+            &[
+                ("story", &self.config.story_slug),
+                ("n", &self.scenes.len().to_string()),
+            ],
+        )
     }
 
     fn push_action(&mut self, action: webgal::Action) {
@@ -132,31 +227,133 @@ impl<R: Resolve> Transpiler<R> {
 
     fn push_action_and_change_scene(&mut self, action: webgal::Action) {
         self.push_action(action);
-        self.scenes.push(Scene::new(&self.next_scene_name()));
+        self.scenes.push(Scene {
+            engine: self.config.engine,
+            ..Scene::new(&self.next_scene_name())
+        });
+        self.scene_actions = 0;
+    }
+
+    /// 按拆分策略在指令前切换场景
+    fn maybe_split_scene(&mut self, action: &bestdori::Action) {
+        use bestdori::{Action, Effect};
+
+        let effect = match action {
+            Action::Effect(a) => Some(&a.effect),
+            _ => None,
+        };
+
+        let split = match self.config.scene_split {
+            SceneSplit::Telop => {
+                matches!(effect, Some(Effect::Telop { .. }))
+                    && self.config.telop_style != TelopStyle::Choose
+            }
+            SceneSplit::Every(n) => self.scene_actions >= n.max(1),
+            SceneSplit::Chapter => matches!(effect, Some(Effect::BlackIn | Effect::WhiteIn)),
+            SceneSplit::Single => false,
+        };
+
+        // 不拆分出空场景
+        if split && self.scenes.last().is_some_and(|s| !s.actions.is_empty()) {
+            self.push_action_and_change_scene(
+                webgal::ChangeSceneAction {
+                    file: self.next_scene_name(),
+                }
+                .into(),
+            );
+        }
+
+        self.scene_actions += 1;
     }
 
     /// 识别并记录新资源
     ///
     /// 始终在上下文使用完资源后调用以记录
     fn maybe_push_resource(&mut self, res: ResourceEntry) {
-        if let ResourceEntry::Vacant(v) = res {
-            self.resources.push(v);
+        if let Some(res) = res.into_vacant() {
+            self.resources.push(res);
+        }
+    }
+
+    /// 按配置处理 emoji / 控制字符, 返回处理结果和命中的字符数
+    fn filter_special_chars(&self, text: &str) -> (String, usize) {
+        let mut count = 0;
+        let mut out = String::with_capacity(text.len());
+
+        for c in text.chars() {
+            if !is_special_char(c) {
+                out.push(c);
+                continue;
+            }
+
+            count += 1;
+            match &self.config.special_chars {
+                SpecialCharPolicy::Keep => out.push(c),
+                SpecialCharPolicy::Strip => {}
+                SpecialCharPolicy::Replace(s) => out.push_str(s),
+            }
         }
+
+        (out, count)
     }
 
     // ---------------- transpile ----------------
 
     /// 转译单个场景
-    fn transpile(&mut self, action: &bestdori::Action, wait: bool) -> Result<()> {
+    ///
+    /// index 为指令在原始脚本中的下标, 初始 bgm, background 指令为 None.
+    fn transpile(
+        &mut self,
+        action: &bestdori::Action,
+        index: Option<usize>,
+        wait: bool,
+    ) -> Result<()> {
         use bestdori::Action;
 
-        match action {
+        self.actions.record(action);
+        self.maybe_split_scene(action);
+
+        // 标明来源
+        if self.config.trace_comments {
+            let index = index.map_or_else(|| String::from("prelude"), |k| k.to_string());
+            self.push_action(
+                webgal::CommentAction {
+                    text: format!("index={index} type={}", action.kind_name()),
+                }
+                .into(),
+            );
+        }
+
+        // 延迟转译为等待 (移动动画和转场的延迟作为时长, 停顿指令总是等待)
+        let delay = (action.delay() * 1000.).round();
+        let honor = self.config.honor_delay || matches!(action, Action::Wait(_));
+        if honor
+            && delay >= 1.
+            && !self.is_animated_move(action)
+            && !self.is_timed_transition(action)
+        {
+            self.push_action(webgal::WaitAction { time: delay as u32 }.into());
+        }
+
+        let result = match action {
             Action::Talk(a) => self.transpile_talk(a, wait),
             Action::Sound(a) => self.transpile_sound(a),
             Action::Effect(a) => self.transpile_effect(a, wait),
             Action::Layout(a) => self.transpile_layout(a, wait),
-            Action::Motion(a) => return_ok! {self.transpile_motion(a, wait)},
-            Action::Unknown => Err(TranspileErrorKind::Unknown),
+            Action::Motion(a) => self.transpile_motion(a, wait),
+            Action::Wait(_) => Ok(()),
+            Action::Unknown(raw) => {
+                if self.config.unknown_as_comment {
+                    self.push_action(
+                        webgal::CommentAction {
+                            text: raw.to_string(),
+                        }
+                        .into(),
+                    );
+                }
+                self.warn(WarningKind::Unknown);
+                Ok(())
+            }
         }
         .map_err(|e| {
             TranspileError {
@@ -164,7 +361,17 @@ impl<R: Resolve> Transpiler<R> {
                 error: e,
             }
             .into()
-        })
+        });
+
+        // 收集警告
+        for kind in std::mem::take(&mut self.pending_warnings) {
+            self.warnings.push(Warning {
+                action: Box::new(action.clone()),
+                kind,
+            });
+        }
+
+        result
     }
 
     fn transpile_talk(&mut self, action: &bestdori::TalkAction, wait: bool) -> PreResult<()> {
@@ -173,49 +380,148 @@ impl<R: Resolve> Transpiler<R> {
             text,
             motions,
             characters,
+            voices,
             ..
         } = action;
 
         let mut res = Ok(()); // 至多收集 1 个错误
 
-        // 执行动作
+        // 解析语音 (仅采用第一条, 需启用)
+        let vocal = match voices.first().filter(|_| self.config.talk_voices) {
+            Some(voice) => match self
+                .resolver
+                .resolve_normal(&voice.voice, ResourceType::Voice)
+            {
+                Ok(entry) => {
+                    let path = entry.relative_path();
+                    self.maybe_push_resource(entry);
+                    Some(path)
+                }
+                Err(e) => {
+                    res = Err(e.into());
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // 执行动作 (可按延迟排序, 并在动作间插入等待)
+        let mut motions: Vec<&Motion> = motions.iter().collect();
+        if self.config.motion_order != MotionOrder::Immediate {
+            motions.sort_by(|a, b| a.delay.total_cmp(&b.delay));
+        }
+
+        let mut elapsed = 0; // 已等待的毫秒数
         for motion in motions {
+            let at = (motion.delay.max(0.) * 1000.).round() as u32;
+            if self.config.motion_order == MotionOrder::Interleave && at > elapsed {
+                self.push_action(webgal::WaitAction { time: at - elapsed }.into());
+            }
+            elapsed = elapsed.max(at);
+
             res = res.and(self.try_display_motion(motion, true));
         }
 
+        // 说话人为空时根据角色填充, 多人同时说话时以 "・" 连接
+        let table = &self.characters;
+        let name = if name.trim().is_empty()
+            && self.config.fill_speaker_name
+            && !characters.is_empty()
+            && let Some(names) = characters
+                .iter()
+                .map(|&id| table.get(id).map(|c| c.name.as_str()))
+                .collect::<Option<Vec<_>>>()
+        {
+            names.join("・")
+        } else {
+            name.clone()
+        };
+
+        // 跟随说话人切换小头像, 并高亮全部说话人
+        self.update_mini_avatar(characters.first().copied());
+        self.highlight_speakers(characters);
+
+        // 转换说话人名称
+        let name = match self.config.speaker_names.get(name.trim()) {
+            Some(mapped) => mapped.clone(),
+            None => name,
+        };
+
+        // 执行文本过滤器
+        let (name, text) = self
+            .filters
+            .iter()
+            .fold((name, text.clone()), |(name, text), filter| {
+                (filter.filter_name(&name), filter.filter_text(&text))
+            });
+
+        // 处理特殊字符
+        let (name, name_count) = self.filter_special_chars(&name);
+        let (text, text_count) = self.filter_special_chars(&text);
+
+        if name_count + text_count > 0 && self.config.special_chars != SpecialCharPolicy::Keep {
+            self.warn(WarningKind::SpecialChar(name_count + text_count));
+        }
+
         // 执行对话
-        self.push_action(
-            SayAction {
-                name: name.clone(),
-                text: text.trim().to_string(),
-                next: !wait,
-                character: characters.first().cloned(),
-            }
-            .into(),
-        );
+        let text = text.trim();
+        let lines = match self.config.max_text_length {
+            Some(max) if text.chars().count() > max => split_text(text, max),
+            _ => vec![text.to_string()],
+        };
+
+        if lines.len() > 1 {
+            self.warn(WarningKind::LongText(lines.len()));
+        }
+
+        // 过长对话拆分为连续的多行
+        let count = lines.len();
+        for (k, text) in lines.into_iter().enumerate() {
+            self.push_action(
+                SayAction {
+                    name: name.clone(),
+                    text,
+                    next: k + 1 < count || !wait,
+                    concat: k > 0,
+                    character: characters.first().cloned(),
+                    vocal: if k == 0 { vocal.clone() } else { None },
+                }
+                .into(),
+            );
+        }
 
         res
     }
 
     fn transpile_sound(&mut self, action: &bestdori::SoundAction) -> PreResult<()> {
-        let bestdori::SoundAction { bgm, se, .. } = action;
+        let bestdori::SoundAction {
+            bgm,
+            se,
+            bgm_volume,
+            se_volume,
+            se_loop,
+            ..
+        } = action;
 
         Ok(())
             // 执行 bgm
-            .and(bgm.as_ref().map_or(Ok(()), |bgm| self.transpile_bgm(bgm)))
+            .and(bgm.as_ref().map_or(Ok(()), |bgm| {
+                self.transpile_bgm(bgm, bgm_volume.map(to_volume))
+            }))
             // 执行 se
-            .and(se.as_ref().map_or(Ok(()), |se| self.transpile_se(se)))
+            .and(se.as_ref().map_or(Ok(()), |se| {
+                self.transpile_se(se, se_volume.map(to_volume), *se_loop)
+            }))
     }
 
     fn transpile_effect(&mut self, action: &bestdori::EffectAction, wait: bool) -> PreResult<()> {
         use bestdori::Effect;
 
         match &action.effect {
-            // 入场
-            Effect::BlackIn | Effect::WhiteIn => self.display_transition("enter", !wait),
-
-            // 退场
-            Effect::BlackOut | Effect::WhiteOut => self.display_transition("exit", !wait),
+            // 转场
+            effect @ (Effect::BlackIn | Effect::BlackOut | Effect::WhiteIn | Effect::WhiteOut) => {
+                self.display_color_transition(effect, action.delay, !wait)
+            }
 
             // 呈现字幕
             Effect::Telop { text } => self.display_telop(text),
@@ -224,7 +530,24 @@ impl<R: Resolve> Transpiler<R> {
             Effect::ChangeBackground { image } => self.display_background(image, !wait)?,
 
             // 呈现卡面
-            Effect::ChangeCardStill { image } => self.display_cardstill(image, !wait)?,
+            Effect::ChangeCardStill { image } => self.display_cardstill(image)?,
+
+            // 震动
+            Effect::Shake => self.display_transition("shake", !wait),
+
+            // 清除画面特效
+            Effect::StopEffect => self.push_action(webgal::PixiAction::Init.into()),
+
+            // 画面特效
+            effect @ (Effect::Snow | Effect::Rain | Effect::Sakura) => {
+                let name = effect.screen_effect().unwrap();
+                let Some(pixi) = self.config.pixi_effects.get(name).cloned() else {
+                    self.warn(WarningKind::UnsupportedEffect(name.to_string()));
+                    return Ok(());
+                };
+
+                self.push_action(webgal::PixiAction::Perform { effect: pixi }.into());
+            }
         }
 
         Ok(())
@@ -244,66 +567,115 @@ impl<R: Resolve> Transpiler<R> {
             bestdori::LayoutType::Hide => self.remove_model(motion.character, !wait),
 
             // 执行移动
-            bestdori::LayoutType::Move => return_ok! {{
-                let model = self
-                    .context
-                    .models
-                    .get_mut(&motion.character)
-                    .ok_or(TranspileErrorKind::UninitFigure(motion.character))?;
-
-                model.side = (*to).into();
-                model.transform = Transform::new_with_x(*to_x);
-
-                self.display_motion_unwrap(motion, !wait);
-            }},
+            bestdori::LayoutType::Move => self.move_model(motion, (*to).into(), *to_x, !wait),
 
             // 执行登场
-            bestdori::LayoutType::Appear => return_ok! {{
-                let res = self.resolver.resolve_model(model);
+            bestdori::LayoutType::Appear => {
+                let res = self.resolve_model(model);
+                let motion = self.with_default_motion(motion);
 
-                self.display_motion(&res.relative_path(), (*to).into(), motion, !wait);
+                self.display_motion(&res, Some((*to).into()), &motion, !wait);
 
                 self.maybe_push_resource(res);
-            }},
+                Ok(())
+            }
         }
     }
 
-    fn transpile_motion(&mut self, action: &bestdori::MotionAction, wait: bool) {
+    fn transpile_motion(&mut self, action: &bestdori::MotionAction, wait: bool) -> PreResult<()> {
         let bestdori::MotionAction { model, motion, .. } = action;
 
-        let res = self.resolver.resolve_model(model);
+        let res = self.resolve_model(model);
 
         // 执行模型动作
-        self.display_motion(&res.relative_path(), FigureSide::default(), motion, !wait);
+        self.display_motion(&res, None, motion, !wait);
 
         self.maybe_push_resource(res);
+        Ok(())
+    }
+
+    /// 解析模型, 并将服装回退记录为警告, 每个服装只警告一次
+    fn resolve_model(&mut self, costume: &str) -> ResourceEntry {
+        let res = self.resolver.resolve_model(costume);
+
+        if let Some(CostumeFallback { costume, fallback }) = res.fallback().cloned()
+            && self.fallbacks.insert(costume.clone())
+        {
+            self.warn(WarningKind::CostumeFallback { costume, fallback });
+        }
+
+        res
+    }
+
+    /// 记录当前指令的警告
+    fn warn(&mut self, kind: WarningKind) {
+        self.pending_warnings.push(kind);
     }
 
     // ---------------- transpile ----------------
 
     /// 转译 sound/bgm
-    fn transpile_bgm(&mut self, res: &bestdori::Resource) -> PreResult<()> {
+    ///
+    /// 空资源表示停止播放.
+    fn transpile_bgm(&mut self, res: &bestdori::Resource, volume: Option<u8>) -> PreResult<()> {
+        if res.is_empty() {
+            self.stop_bgm();
+            return Ok(());
+        }
+
         let res = self.resolver.resolve_normal(res, ResourceType::Bgm)?;
 
         self.push_action(
             webgal::BgmAction {
                 sound: Some(res.relative_path()),
+                enter: None,
+                volume,
             }
             .into(),
         );
+        self.bgm = true;
 
         self.maybe_push_resource(res);
 
         Ok(())
     }
 
+    /// 停止 bgm (若正在播放)
+    fn stop_bgm(&mut self) {
+        if self.bgm {
+            self.push_action(webgal::BgmAction::new_stop(self.config.bgm_fade).into());
+            self.bgm = false;
+        }
+    }
+
     /// 转译 sound/se
-    fn transpile_se(&mut self, res: &bestdori::Resource) -> PreResult<()> {
-        let res = self.resolver.resolve_normal(res, ResourceType::Bgm)?;
+    ///
+    /// 空资源表示停止循环播放的 se.
+    fn transpile_se(
+        &mut self,
+        res: &bestdori::Resource,
+        volume: Option<u8>,
+        looped: bool,
+    ) -> PreResult<()> {
+        if res.is_empty() {
+            self.push_action(
+                webgal::PlayEffectAction {
+                    sound: None,
+                    volume: None,
+                    id: Some(LOOP_SE_ID.to_string()),
+                }
+                .into(),
+            );
+            return Ok(());
+        }
+
+        let res = self.resolver.resolve_normal(res, ResourceType::Se)?;
 
         self.push_action(
             webgal::PlayEffectAction {
                 sound: Some(res.relative_path()),
+                volume,
+                id: looped.then(|| LOOP_SE_ID.to_string()),
             }
             .into(),
         );
@@ -318,7 +690,7 @@ impl<R: Resolve> Transpiler<R> {
     /// 是否需要清空背景?
     fn display_transition(&mut self, animation: &str, next: bool) {
         self.push_action(
-            webgal::SetAnimation {
+            webgal::AnimateAction::Animation {
                 animation: animation.to_string(),
                 target: "bg-main".to_string(),
                 next,
@@ -327,17 +699,172 @@ impl<R: Resolve> Transpiler<R> {
         );
     }
 
-    /// 呈现字幕 (通过切换场景实现)
-    fn display_telop(&mut self, text: &str) {
-        self.push_action_and_change_scene(
-            webgal::ChooseAction {
-                file: self.next_scene_name(),
-                text: text.to_string(),
+    /// 执行黑 / 白转场, 以指令延迟为时长
+    ///
+    /// 映射了动画的转场播放该动画. 否则配置了临时背景时渐变到纯色背景并在入场时恢复;
+    /// 未配置时黑色转场渐隐 / 渐显整个舞台 (舞台底色为黑色), 白色转场切换到白色背景, 人物保持显示.
+    fn display_color_transition(&mut self, effect: &bestdori::Effect, delay: f32, next: bool) {
+        use bestdori::Effect;
+
+        let name = effect.transition().unwrap();
+        if let Some(animation) = self.config.transition_animations.get(name) {
+            let animation = animation.clone();
+            return self.display_transition(&animation, next);
+        }
+
+        let duration = match (delay * 1000.).round() as u32 {
+            0 => DEFAULT_TRANSITION_DURATION,
+            duration => duration,
+        };
+
+        // 渐隐 / 渐显舞台
+        if self.config.transition_backdrop.is_none()
+            && let Some(alpha) = match effect {
+                Effect::BlackOut => Some(0.),
+                Effect::BlackIn => Some(1.),
+                _ => None,
+            }
+        {
+            return self.push_action(
+                webgal::AnimateAction::Transform {
+                    transform: Transform::new_with_alpha(alpha),
+                    target: String::from("stage-main"),
+                    duration,
+                    next,
+                }
+                .into(),
+            );
+        }
+
+        let backdrop = self.config.transition_backdrop.clone().unwrap_or_default();
+        let image = match effect {
+            Effect::BlackOut => backdrop.black,
+            Effect::WhiteOut => {
+                self.generate_white_backdrop(&backdrop);
+                Some(backdrop.white)
+            }
+            _ => self.context.background.clone(),
+        };
+
+        self.push_action(
+            webgal::ChangeBgAction {
+                image,
+                duration: Some(duration),
+                next,
             }
             .into(),
         );
     }
 
+    /// 记录需要生成的白色背景
+    fn generate_white_backdrop(&mut self, backdrop: &TransitionBackdrop) {
+        let path = format!(
+            "{}/{}",
+            webgal::ResourceType::Background.dir(),
+            backdrop.white
+        );
+        if !self
+            .generated
+            .iter()
+            .any(|(generated, _)| *generated == path)
+        {
+            self.generated.push((path, WHITE_BACKDROP_PNG.to_vec()));
+        }
+    }
+
+    /// 是否以延迟作为时长的转场
+    fn is_timed_transition(&self, action: &bestdori::Action) -> bool {
+        matches!(action, bestdori::Action::Effect(a) if a.effect.transition()
+            .is_some_and(|name| !self.config.transition_animations.contains_key(name)))
+    }
+
+    /// 切换小头像 (若启用), 旁白时清除
+    fn update_mini_avatar(&mut self, speaker: Option<u8>) {
+        if self.config.mini_avatars.is_empty() {
+            return;
+        }
+
+        let avatar = speaker.and_then(|id| self.config.mini_avatars.get(&id).cloned());
+
+        if avatar != self.avatar {
+            self.avatar = avatar.clone();
+            self.push_action(webgal::MiniAvatarAction { image: avatar }.into());
+        }
+    }
+
+    /// 高亮全部说话人, 调暗其余在场人物 (若启用)
+    ///
+    /// WebGAL 对话只接受一个 figureId, 故以变换动画调整亮度; 仅输出亮度有变化的人物.
+    fn highlight_speakers(&mut self, speakers: &[u8]) {
+        if !self.config.highlight_speakers || !self.config.engine.has_transform() {
+            return;
+        }
+
+        let mut changed: Vec<u8> = self
+            .context
+            .models
+            .iter()
+            .filter(|(id, model)| model.dimmed == speakers.contains(id))
+            .map(|(&id, _)| id)
+            .collect();
+        changed.sort_unstable();
+
+        for id in changed {
+            let model = self.context.models.get_mut(&id).unwrap();
+            model.dimmed = !model.dimmed;
+            let mut transform = model.transform.clone();
+            transform.brightness = Some(if model.dimmed {
+                self.config.dimmed_brightness
+            } else {
+                1.
+            });
+
+            self.push_action(
+                webgal::AnimateAction::Transform {
+                    transform: self.adjust_transform(id, transform),
+                    target: id.to_string(),
+                    duration: HIGHLIGHT_DURATION,
+                    next: true,
+                }
+                .into(),
+            );
+        }
+    }
+
+    /// 呈现字幕
+    fn display_telop(&mut self, text: &str) {
+        match self.config.telop_style {
+            TelopStyle::Intro => self.push_action(
+                webgal::IntroAction {
+                    lines: text.lines().map(str::to_string).collect(),
+                    hold: false,
+                }
+                .into(),
+            ),
+
+            // 通过切换场景实现
+            TelopStyle::Choose => self.push_action_and_change_scene(
+                webgal::ChooseAction {
+                    file: self.next_scene_name(),
+                    text: text.to_string(),
+                }
+                .into(),
+            ),
+
+            TelopStyle::Subtitle => self.push_action(
+                SayAction {
+                    name: String::new(),
+                    text: text.to_string(),
+                    next: false,
+                    concat: false,
+                    character: None,
+                    vocal: None,
+                }
+                .into(),
+            ),
+        }
+    }
+
     /// 修改背景
     fn display_background(&mut self, res: &bestdori::Resource, next: bool) -> PreResult<()> {
         let res = self.resolver.resolve_normal(res, ResourceType::Image)?;
@@ -350,6 +877,7 @@ impl<R: Resolve> Transpiler<R> {
         self.push_action(
             webgal::ChangeBgAction {
                 image: Some(path),
+                duration: None,
                 next,
             }
             .into(),
@@ -361,23 +889,47 @@ impl<R: Resolve> Transpiler<R> {
     }
 
     /// 呈现卡面
-    fn display_cardstill(&mut self, res: &bestdori::Resource, next: bool) -> PreResult<()> {
+    ///
+    /// 隐藏文本框和人物后以背景呈现卡面, 点击后恢复原背景和人物.
+    fn display_cardstill(&mut self, res: &bestdori::Resource) -> PreResult<()> {
         let res = self.resolver.resolve_normal(res, ResourceType::Image)?;
 
-        // 记录并清空场景
-        let ctx = self.clear();
+        self.push_action(
+            webgal::SetTextboxAction {
+                visible: false,
+                next: true,
+            }
+            .into(),
+        );
+        self.hide_models();
 
-        // 显示背景
+        // 显示卡面, 等待点击
         self.push_action(
             webgal::ChangeBgAction {
                 image: Some(res.relative_path()),
-                next,
+                duration: None,
+                next: false,
             }
             .into(),
         );
 
         // 恢复场景
-        self.set_context(ctx);
+        self.push_action(
+            webgal::ChangeBgAction {
+                image: self.context.background.clone(),
+                duration: None,
+                next: true,
+            }
+            .into(),
+        );
+        self.show_models();
+        self.push_action(
+            webgal::SetTextboxAction {
+                visible: true,
+                next: true,
+            }
+            .into(),
+        );
 
         self.maybe_push_resource(res);
 
@@ -394,14 +946,69 @@ impl<R: Resolve> Transpiler<R> {
                 id,
                 next,
                 side: model.side,
-                transform: Some(model.transform),
+                transform: Some(
+                    self.adjust_brightness(
+                        self.adjust_transform(id, model.transform),
+                        model.dimmed,
+                    ),
+                ),
                 motion: model.motion,
                 expression: model.expression,
+                z_index: self.config.figure_layering.then_some(model.layer),
             }
             .into(),
         );
     }
 
+    /// 合并人物变换修正 (角色修正优先于全局修正)
+    fn adjust_transform(&self, id: u8, mut transform: Transform) -> Transform {
+        let global = &self.config.figure_transform;
+        let local = self.config.character_transforms.get(&id);
+
+        if let Some(scale) = local.and_then(|t| t.scale).or(global.scale) {
+            transform.scale = Some(webgal::Scale { x: scale, y: scale });
+        }
+
+        if let Some(y) = local.and_then(|t| t.y).or(global.y) {
+            transform.position.get_or_insert_default().y = Some(y);
+        }
+
+        transform
+    }
+
+    /// 为调暗的人物设置亮度
+    fn adjust_brightness(&self, mut transform: Transform, dimmed: bool) -> Transform {
+        if dimmed {
+            transform.brightness = Some(self.config.dimmed_brightness);
+        }
+        transform
+    }
+
+    /// 为空的动作 / 表情注入默认值 (若启用)
+    fn with_default_motion(&self, motion: &Motion) -> Motion {
+        let mut motion = motion.clone();
+
+        if !self.config.inject_default_motion {
+            return motion;
+        }
+
+        let preset = self.characters.get(motion.character);
+
+        if motion.motion.is_empty() {
+            motion.motion = preset
+                .and_then(|c| c.motion.clone())
+                .unwrap_or_else(|| self.config.default_motion.clone());
+        }
+
+        if motion.expression.is_empty()
+            && let Some(expression) = preset.and_then(|c| c.expression.clone())
+        {
+            motion.expression = expression;
+        }
+
+        motion
+    }
+
     /// 修改模型动作 (当模型存在时)
     fn try_display_motion(&mut self, motion: &Motion, next: bool) -> PreResult<()> {
         let Motion {
@@ -416,9 +1023,17 @@ impl<R: Resolve> Transpiler<R> {
             .get_mut(character)
             .ok_or(TranspileErrorKind::UninitFigure(*character))
             .map(|model| {
-                // 修改上下文
-                model.motion = Some(motion.clone());
-                model.expression = Some(expression.clone());
+                // 修改上下文 (静态立绘没有动作和表情)
+                if !model.sprite {
+                    // 转换重定向模型的动作 / 表情名称
+                    let (motion, expression) = match &model.display {
+                        Some(display) => (display.motion(motion), display.expression(expression)),
+                        None => (motion.clone(), expression.clone()),
+                    };
+
+                    model.motion = Some(motion);
+                    model.expression = Some(expression);
+                }
                 model.clone()
             })
             .map(|model| self.display_model(*character, model, next)) // 应用修改
@@ -430,24 +1045,147 @@ impl<R: Resolve> Transpiler<R> {
     }
 
     /// 修改模型动作 (不存在时插入模型)
-    fn display_motion(&mut self, model: &str, side: FigureSide, motion: &Motion, next: bool) {
-        if let Entry::Vacant(v) = self.context.models.entry(motion.character) {
-            v.insert(
-                ModelBuilder::default()
-                    .path(model.to_string())
-                    .side(side)
-                    .build()
-                    .unwrap(),
-            );
-        }
+    ///
+    /// 指定位置时将人物放到该位置, 否则保持原位置 (新模型居中).
+    fn display_motion(
+        &mut self,
+        model: &ResourceEntry,
+        side: Option<FigureSide>,
+        motion: &Motion,
+        next: bool,
+    ) {
+        let id = motion.character;
+        let side = match self.context.models.entry(id) {
+            Entry::Occupied(o) => side.unwrap_or(o.get().side),
+            Entry::Vacant(v) => {
+                v.insert(
+                    ModelBuilder::default()
+                        .path(model.relative_path())
+                        .sprite(model.kind == webgal::ResourceType::Sprite)
+                        .display(model.display().cloned())
+                        .build()
+                        .unwrap(),
+                );
+                side.unwrap_or_default()
+            }
+        };
 
+        self.occupy_slot(id, side);
         self.display_motion_unwrap(motion, next);
     }
 
+    /// 将人物放到舞台位置上 (模型一定存在)
+    ///
+    /// 人物 id 固定为角色 id, 位置与上下文保持一致. 新登场或移入已有人物的位置时分配新的层级,
+    /// 使同一位置上后到者在上.
+    fn occupy_slot(&mut self, id: u8, side: FigureSide) {
+        let Context {
+            models,
+            slots,
+            layers,
+            ..
+        } = &mut self.context;
+        let model = models.get_mut(&id).unwrap();
+        let slot = slots.entry(side).or_default();
+
+        if slot.contains(&id) {
+            return;
+        }
+
+        if model.layer == 0 || !slot.is_empty() {
+            *layers += 1;
+            model.layer = *layers;
+        }
+        model.side = side;
+        slot.push(id);
+
+        for (&other, slot) in slots.iter_mut() {
+            if other != side {
+                slot.retain(|&occupant| occupant != id);
+            }
+        }
+    }
+
+    /// 将人物移出舞台位置
+    fn leave_slot(&mut self, id: u8) {
+        for slot in self.context.slots.values_mut() {
+            slot.retain(|&occupant| occupant != id);
+        }
+    }
+
+    /// 是否为以变换动画呈现的移动
+    fn is_animated_move(&self, action: &bestdori::Action) -> bool {
+        let bestdori::Action::Layout(action) = action else {
+            return false;
+        };
+
+        self.config.animate_move
+            && self.config.engine.has_transform()
+            && action.kind == bestdori::LayoutType::Move
+            && self
+                .context
+                .models
+                .get(&action.motion.character)
+                .is_some_and(|model| model.side == action.side.to.into())
+    }
+
+    /// 移动模型
+    ///
+    /// 同侧移动时以变换动画呈现, 否则重新显示模型.
+    fn move_model(
+        &mut self,
+        motion: &Motion,
+        side: FigureSide,
+        x: i16,
+        next: bool,
+    ) -> PreResult<()> {
+        let id = motion.character;
+        let model = self
+            .context
+            .models
+            .get_mut(&id)
+            .ok_or(TranspileErrorKind::UninitFigure(id))?;
+        let transform = Transform::new_with_x(x);
+
+        if !self.config.animate_move || !self.config.engine.has_transform() || model.side != side {
+            model.transform = transform;
+            self.occupy_slot(id, side);
+            self.display_motion_unwrap(motion, next);
+            return Ok(());
+        }
+
+        // 先应用动作 / 表情, 再执行变换
+        if !motion.motion.is_empty() || !motion.expression.is_empty() {
+            self.display_motion_unwrap(motion, true);
+        }
+
+        let model = self.context.models.get_mut(&id).unwrap();
+        model.transform = transform.clone();
+        let dimmed = model.dimmed;
+
+        let delay = (motion.delay * 1000.).round() as u32;
+        self.push_action(
+            webgal::AnimateAction::Transform {
+                transform: self.adjust_brightness(self.adjust_transform(id, transform), dimmed),
+                target: id.to_string(),
+                duration: if delay > 0 {
+                    delay
+                } else {
+                    self.config.move_duration
+                },
+                next,
+            }
+            .into(),
+        );
+
+        Ok(())
+    }
+
     /// 移除模型
     fn remove_model(&mut self, id: u8, next: bool) -> PreResult<()> {
         match self.context.models.remove(&id) {
             Some(_) => {
+                self.leave_slot(id);
                 return_ok! {self.push_action(webgal::ChangeFigureAction::new_hide(id, next).into())}
             }
             None => Err(TranspileErrorKind::UninitFigure(id)),
@@ -465,9 +1203,464 @@ impl<R: Resolve> Transpile for Transpiler<R> {
     fn transpile(mut self, story: &bestdori::Story) -> TranspileResult {
         let errors = story
             .iter_with_wait()
-            .filter_map(|(a, wait)| <Self>::transpile(&mut self, a, wait).err())
+            .enumerate()
+            .filter_map(|(k, (a, wait))| {
+                <Self>::transpile(&mut self, a, story.source_index(k), wait).err()
+            })
             .collect();
 
+        if self.config.stop_bgm_at_end {
+            self.stop_bgm();
+        }
+
+        match &self.config.story_end {
+            StoryEnd::None => {}
+            StoryEnd::End => self.push_action(webgal::EndAction {}.into()),
+            StoryEnd::ChangeScene(file) => {
+                let file = file.clone();
+                self.push_action(webgal::ChangeSceneAction { file }.into())
+            }
+        }
+
         self.into_result(errors)
     }
 }
+
+#[test]
+#[cfg(test)]
+fn test_transpile_fixtures() {
+    use crate::services::resolver::Resolver;
+
+    for fixture in bd2wg_fixtures::ALL {
+        let story = bestdori::Story::from_bytes(fixture.json);
+
+        let Some(expected) = fixture.expected else {
+            assert!(story.is_err(), "{} should fail to parse", fixture.name);
+            continue;
+        };
+
+        let story = story.unwrap();
+        let talks = story
+            .iter()
+            .filter(|a| matches!(a, bestdori::Action::Talk(_)))
+            .count();
+        let result = Transpiler::<Resolver>::default().transpile(&story);
+
+        assert_eq!(
+            (
+                story.actions.len(),
+                talks,
+                result.story.len().0,
+                result.resources.len(),
+                result.errors.len(),
+            ),
+            (
+                expected.actions,
+                expected.talks,
+                expected.scenes,
+                expected.resources,
+                expected.errors,
+            ),
+            "{}: {:?}",
+            fixture.name,
+            result.errors,
+        );
+        assert_eq!(result.actions.talk, talks);
+        assert_eq!(result.actions.total(), story.actions.len());
+
+        for scene in result.story.iter() {
+            let issues = webgal::validate_scene(&scene.to_string());
+            assert!(issues.is_empty(), "{}: {issues:?}", scene.path);
+        }
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_scene_split() {
+    use crate::models::config::TranspilerConfigBuilder;
+    use test_util::*;
+
+    let story = bestdori::Story::from_bytes(bd2wg_fixtures::LONG.json).unwrap();
+    let scenes = |scene_split| {
+        let config = TranspilerConfigBuilder::default()
+            .scene_split(scene_split)
+            .build()
+            .unwrap();
+
+        transpile(&story, config).story.len().0
+    };
+
+    // LONG 共 130 条指令, 不含字幕
+    assert_eq!(scenes(SceneSplit::Single), 2);
+    assert_eq!(scenes(SceneSplit::Every(50)), 4);
+}
+
+#[cfg(test)]
+mod test_util {
+    use super::*;
+    use crate::services::resolver::Resolver;
+
+    /// 由指令构造故事
+    pub fn story_with(actions: Vec<bestdori::Action>) -> bestdori::Story {
+        bestdori::Story::new(actions, Default::default())
+    }
+
+    /// 按配置转译
+    pub fn transpile(story: &bestdori::Story, config: TranspilerConfig) -> TranspileResult {
+        Transpiler::with_config(Resolver::default(), config).transpile(story)
+    }
+
+    /// 全部场景的文本
+    pub fn scene_text(result: &TranspileResult) -> String {
+        result.story.iter().map(ToString::to_string).collect()
+    }
+
+    /// 全部场景的各行
+    pub fn scene_lines(result: &TranspileResult) -> Vec<String> {
+        scene_text(result).lines().map(str::to_string).collect()
+    }
+
+    /// 角色动作
+    pub fn motion(character: u8, motion: &str, delay: f32) -> Motion {
+        Motion {
+            delay,
+            character,
+            motion: motion.to_string(),
+            expression: String::new(),
+        }
+    }
+
+    /// 角色以默认服装在指定位置登场, 移动或退场
+    pub fn layout(
+        kind: bestdori::LayoutType,
+        side: bestdori::LayoutSideType,
+        motion: Motion,
+    ) -> bestdori::Action {
+        bestdori::Action::Layout(bestdori::LayoutAction {
+            wait: false,
+            kind,
+            model: format!("{:03}_casual-2023", motion.character),
+            motion,
+            side: bestdori::LayoutSide {
+                from: side,
+                to: side,
+                from_x: 0,
+                to_x: 0,
+            },
+        })
+    }
+
+    /// 无动作和语音的对话
+    pub fn talk(name: &str, text: &str, characters: Vec<u8>) -> bestdori::TalkAction {
+        bestdori::TalkAction {
+            wait: true,
+            delay: 0.,
+            name: name.to_string(),
+            text: text.to_string(),
+            motions: Vec::new(),
+            characters,
+            voices: Vec::new(),
+        }
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_color_transition() {
+    use crate::models::config::TranspilerConfigBuilder;
+    use test_util::*;
+
+    let effect = |effect, delay| {
+        bestdori::Action::Effect(bestdori::EffectAction {
+            wait: true,
+            delay,
+            effect,
+        })
+    };
+    let story = story_with(vec![
+        effect(bestdori::Effect::BlackOut, 0.8),
+        effect(bestdori::Effect::BlackIn, 0.),
+        effect(bestdori::Effect::WhiteOut, 1.),
+        effect(bestdori::Effect::WhiteIn, 1.),
+    ]);
+
+    // 黑色渐变舞台, 白色切换到生成的白色背景, 延迟作为时长
+    let result = transpile(&story, TranspilerConfig::default());
+    let text = scene_text(&result);
+    assert!(text.contains(r#"setTransform:{"alpha":0.0} -target=stage-main -duration=800;"#));
+    assert!(text.contains(r#"setTransform:{"alpha":1.0} -target=stage-main -duration=500;"#));
+    assert!(text.contains("changeBg:white.png -duration=1000;"));
+    assert!(!text.contains("wait:"));
+    assert_eq!(result.generated.len(), 1);
+    assert_eq!(result.generated[0].0, "background/white.png");
+
+    // 映射了动画的转场播放动画, 延迟仍为等待
+    let config = TranspilerConfigBuilder::default()
+        .transition_animations(HashMap::from([(
+            String::from("black_out"),
+            String::from("exit"),
+        )]))
+        .honor_delay(true)
+        .build()
+        .unwrap();
+    let text = scene_text(&transpile(&story, config));
+    assert!(text.contains("wait:800;\nsetAnimation:exit -target=bg-main;"));
+}
+
+#[test]
+#[cfg(test)]
+fn test_figure_layering() {
+    use bestdori::{LayoutSideType::*, LayoutType::*};
+    use test_util::*;
+
+    let layout = |kind, character, side| layout(kind, side, motion(character, "", 0.));
+    let story = story_with(vec![
+        layout(Appear, 1, LeftInside),
+        layout(Appear, 2, Center),
+        layout(Appear, 3, RightInside),
+        layout(Appear, 4, LeftOver),
+        layout(Move, 1, RightInside),
+        layout(Hide, 3, RightInside),
+        layout(Appear, 2, LeftInside),
+        layout(Move, 4, Center),
+    ]);
+    let figures: Vec<String> = scene_lines(&transpile(&story, TranspilerConfig::default()))
+        .into_iter()
+        .filter(|line| line.starts_with("changeFigure:0"))
+        .map(|line| {
+            let args: Vec<&str> = line.trim_end_matches(';').split(' ').collect();
+            let arg = |key: &str| args.iter().find_map(|a| a.strip_prefix(key)).unwrap();
+            let side = ["-left", "-right"]
+                .into_iter()
+                .find(|side| args.contains(side))
+                .unwrap_or("-center");
+            format!("{} {} {side}", arg("-id="), arg("-zIndex="))
+        })
+        .collect();
+
+    // 同一位置上后到者在上, 移入空位置时保持层级
+    assert_eq!(
+        figures,
+        [
+            "1 1 -left",
+            "2 2 -center",
+            "3 3 -right",
+            "4 4 -left",
+            "1 5 -right",
+            "2 6 -left",
+            "4 4 -center",
+        ]
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_motion_interleave() {
+    use crate::models::config::TranspilerConfigBuilder;
+    use bestdori::{LayoutSideType::Center, LayoutType::Appear};
+    use test_util::*;
+
+    let delayed =
+        |character, delay: f32| motion(character, &format!("m{}", (delay * 1000.).round()), delay);
+    let story = story_with(vec![
+        layout(Appear, Center, delayed(1, 0.)),
+        layout(Appear, Center, delayed(2, 0.)),
+        bestdori::Action::Talk(bestdori::TalkAction {
+            motions: vec![
+                delayed(1, 0.5),
+                delayed(2, 0.2004),
+                delayed(1, 0.2),
+                delayed(2, 0.0004),
+                delayed(1, 1.0006),
+                delayed(2, 0.7503),
+            ],
+            ..talk("香澄", "せーの", vec![1])
+        }),
+    ]);
+    let config = TranspilerConfigBuilder::default()
+        .motion_order(MotionOrder::Interleave)
+        .build()
+        .unwrap();
+    let lines: Vec<String> = scene_lines(&transpile(&story, config))
+        .into_iter()
+        .skip_while(|line| !line.contains("-motion=m0 "))
+        .skip(2)
+        .map(|line| match line.split_once(" -motion=") {
+            Some((_, rest)) => rest.split(' ').next().unwrap().to_string(),
+            None => line,
+        })
+        .collect();
+
+    // 按延迟排序, 等待时长之和等于最大延迟, 不因舍入累积误差
+    assert_eq!(
+        lines[..11],
+        [
+            "m0",
+            "wait:200;",
+            "m200",
+            "m200",
+            "wait:300;",
+            "m500",
+            "wait:250;",
+            "m750",
+            "wait:251;",
+            "m1001",
+            "香澄:せーの -notend -id -figureId=1;",
+        ]
+    );
+}
+
+#[test]
+#[cfg(test)]
+fn test_mini_avatar() {
+    use crate::models::config::TranspilerConfigBuilder;
+    use test_util::*;
+
+    let talk = |characters| bestdori::Action::Talk(talk("name", "text", characters));
+    let story = story_with(vec![
+        talk(vec![1]),
+        talk(vec![1]),
+        talk(vec![]),
+        talk(vec![2]),
+        talk(vec![1]),
+    ]);
+    let avatars = |mini_avatars| {
+        let config = TranspilerConfigBuilder::default()
+            .mini_avatars(mini_avatars)
+            .build()
+            .unwrap();
+        scene_lines(&transpile(&story, config))
+            .into_iter()
+            .filter(|line| line.starts_with("miniAvatar:"))
+            .collect::<Vec<_>>()
+    };
+
+    // 说话人变化时切换, 旁白和未配置的角色清除, 不重复输出
+    assert_eq!(
+        avatars(HashMap::from([(1, String::from("kasumi.png"))])),
+        [
+            "miniAvatar:kasumi.png;",
+            "miniAvatar:none;",
+            "miniAvatar:kasumi.png;"
+        ]
+    );
+    assert!(avatars(HashMap::new()).is_empty());
+}
+
+#[test]
+#[cfg(test)]
+fn test_talk_voices() {
+    use crate::models::config::TranspilerConfigBuilder;
+    use test_util::*;
+
+    let story = story_with(vec![bestdori::Action::Talk(bestdori::TalkAction {
+        voices: vec![bestdori::Voice {
+            voice: bestdori::Resource {
+                kind: bestdori::ResourceType::Bandori,
+                path: bestdori::ResourcePath::File {
+                    file: String::from("event1-01-001"),
+                    bundle: Some(String::from("sound/voice/scenario/eventstory1_0")),
+                },
+            },
+            volume: Some(1.),
+        }],
+        ..talk("香澄", "text", vec![1])
+    })]);
+    let transpile = |talk_voices| {
+        let config = TranspilerConfigBuilder::default()
+            .talk_voices(talk_voices)
+            .build()
+            .unwrap();
+        let result = transpile(&story, config);
+        (scene_text(&result), result.resources.len())
+    };
+
+    // 默认忽略语音
+    let (text, resources) = transpile(false);
+    assert!(!text.contains("-vocal="));
+    assert_eq!(resources, 0);
+
+    let (text, resources) = transpile(true);
+    assert!(text.contains("-vocal=sound_voice_scenario_eventstory1_0-event1-01-001.mp3"));
+    assert_eq!(resources, 1);
+}
+
+#[test]
+#[cfg(test)]
+fn test_multi_speaker() {
+    use bestdori::{LayoutSideType::Center, LayoutType::Appear};
+    use test_util::*;
+
+    let story = story_with(vec![
+        layout(Appear, Center, motion(1, "smile01", 0.)),
+        layout(Appear, Center, motion(2, "smile01", 0.)),
+        bestdori::Action::Talk(bestdori::TalkAction {
+            motions: vec![motion(1, "smile01", 0.), motion(2, "smile01", 0.)],
+            ..talk("", "せーの", vec![1, 2])
+        }),
+    ]);
+    let lines = scene_lines(&transpile(&story, TranspilerConfig::default()));
+    let lines: Vec<&str> = lines.iter().rev().take(3).map(String::as_str).collect();
+
+    // 两人的动作各自执行, 对话只带首个角色的 figureId, 名称以 "・" 连接
+    let say = lines[0];
+    assert!(say.contains("・") && say.ends_with(":せーの -notend -id -figureId=1;"));
+    assert!(lines[1].starts_with("changeFigure:") && lines[1].contains("-id=2 "));
+    assert!(lines[2].starts_with("changeFigure:") && lines[2].contains("-id=1 "));
+}
+
+#[test]
+#[cfg(test)]
+fn test_highlight_speakers() {
+    use bestdori::{LayoutSideType::Center, LayoutType::Appear};
+    use test_util::*;
+
+    let story = story_with(vec![
+        layout(Appear, Center, motion(1, "smile01", 0.)),
+        layout(Appear, Center, motion(2, "smile01", 0.)),
+        layout(Appear, Center, motion(3, "smile01", 0.)),
+        bestdori::Action::Talk(talk("", "せーの", vec![1, 2])),
+        bestdori::Action::Talk(talk("", "うん", vec![3])),
+    ]);
+    let config = TranspilerConfig {
+        highlight_speakers: true,
+        ..Default::default()
+    };
+    let lines = scene_lines(&transpile(&story, config));
+    let transforms = |text: &str| {
+        let at = lines.iter().position(|line| line.contains(text)).unwrap();
+        lines[..at]
+            .iter()
+            .rev()
+            .take_while(|line| line.starts_with("setTransform:"))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    // 两人同时说话时均保持高亮, 仅调暗第三人
+    let first = transforms(":せーの");
+    assert_eq!(first.len(), 1);
+    assert!(first[0].contains(r#""brightness":0.6"#) && first[0].contains("-target=3 "));
+
+    // 说话人切换后, 恢复第三人并调暗前两人
+    let second = transforms(":うん");
+    assert_eq!(second.len(), 3);
+    assert!(
+        second
+            .iter()
+            .any(|l| l.contains(r#""brightness":1.0"#) && l.contains("-target=3 "))
+    );
+    for id in [1, 2] {
+        let target = format!("-target={id} ");
+        assert!(
+            second
+                .iter()
+                .any(|l| l.contains(r#""brightness":0.6"#) && l.contains(&target))
+        );
+    }
+
+    // 未启用时不输出亮度
+    let lines = scene_lines(&transpile(&story, TranspilerConfig::default()));
+    assert!(lines.iter().all(|line| !line.contains("brightness")));
+}
@@ -0,0 +1,140 @@
+//! 文件监听
+//!
+//! 监听故事脚本和配置文件, 变更时通知调用方重新转换.
+
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, channel},
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::error::*;
+
+/// 合并连续变更的间隔
+///
+/// 编辑器保存时常先截断再写入, 或写入临时文件后重命名.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 文件监听器
+///
+/// 监听文件所在目录, 以兼容重命名替换的保存方式.
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    paths: BTreeSet<PathBuf>,
+}
+
+impl FileWatcher {
+    /// 监听指定文件
+    pub fn new<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<Self> {
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(tx).map_err(watch_error)?;
+
+        let paths = paths
+            .into_iter()
+            .map(|path| absolute(path.as_ref()))
+            .collect::<io::Result<BTreeSet<_>>>()
+            .map_err(|e| Error::File(e.into()))?;
+
+        let dirs: BTreeSet<_> = paths.iter().filter_map(|path| path.parent()).collect();
+        for dir in dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(watch_error)?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            paths,
+        })
+    }
+
+    /// 阻塞等待下一次变更, 返回变更的文件
+    ///
+    /// 间隔内的连续变更合并为一次. 监听中断时返回错误.
+    pub fn wait(&self) -> Result<Vec<PathBuf>> {
+        let mut changed = BTreeSet::new();
+
+        // 等待首个相关变更
+        while changed.is_empty() {
+            let event = self.events.recv().map_err(|e| watch_error(e.into()))?;
+            self.collect(event.map_err(watch_error)?, &mut changed);
+        }
+
+        // 合并后续变更, 断开时先返回已收集的变更
+        while let Ok(event) = self.events.recv_timeout(WATCH_DEBOUNCE) {
+            self.collect(event.map_err(watch_error)?, &mut changed);
+        }
+
+        Ok(changed.into_iter().collect())
+    }
+
+    /// 记录事件中被监听的文件
+    fn collect(&self, event: Event, changed: &mut BTreeSet<PathBuf>) {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+
+        changed.extend(
+            event
+                .paths
+                .into_iter()
+                .filter(|path| self.paths.contains(path)),
+        );
+    }
+}
+
+/// 绝对路径, 文件所在目录需已存在
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other(format!("not a file: {}", path.display())))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    Ok(dir.canonicalize()?.join(name))
+}
+
+/// 监听错误视为文件操作错误
+fn watch_error(e: notify::Error) -> Error {
+    let e = match e.kind {
+        notify::ErrorKind::Io(e) => e,
+        _ => io::Error::other(e),
+    };
+    Error::File(e.into())
+}
+
+#[test]
+#[cfg(test)]
+fn test_file_watcher() {
+    use std::{fs, thread};
+
+    let dir = std::env::temp_dir().join(format!("bd2wg-test-watch-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let story = dir.join("story.json");
+    fs::write(&story, "[]").unwrap();
+
+    let watcher = FileWatcher::new([&story]).unwrap();
+
+    let writer = {
+        let dir = dir.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            fs::write(dir.join("other.json"), "[]").unwrap();
+            fs::write(dir.join("story.json"), "[{}]").unwrap();
+        })
+    };
+
+    let changed = watcher.wait().unwrap();
+    writer.join().unwrap();
+    assert_eq!(changed, vec![story.canonicalize().unwrap()]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -2,7 +2,11 @@
 
 pub mod asset;
 pub mod download;
+pub mod filter;
 pub mod handle;
+pub mod hook;
+pub mod output;
 pub mod pipeline;
+pub mod recover;
 pub mod resolve;
 pub mod transpile;
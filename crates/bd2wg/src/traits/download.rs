@@ -14,8 +14,32 @@ use super::handle::Handle;
 /// 建议下载器内部管理基础下载任务池, 接受每个任务句柄的调用.
 pub trait Download: Handle<Result = ()> {
     /// 启动下载任务
-    fn download(
-        &mut self,
-        res: impl AsRef<Resource>,
-    ) -> Box<dyn Handle<Result = Result<(), Vec<Error>>>>;
+    fn download(&mut self, res: impl AsRef<Resource>) -> Box<dyn DownloadTask>;
+}
+
+/// 资源下载任务句柄
+pub trait DownloadTask: Handle<Result = Result<(), Vec<Error>>> {
+    /// 当前进度
+    fn progress(&self) -> Progress;
+}
+
+/// 下载进度
+///
+/// 普通资源以字节计, Live2D 模型以文件计.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// 是否已开始下载, 否则仍在排队
+    pub started: bool,
+    pub done: u64,
+    /// 总量, 未知时为空
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// 完成比例, 总量未知时为空
+    pub fn ratio(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total != 0)
+            .map(|total| (self.done as f64 / total as f64).min(1.0))
+    }
 }
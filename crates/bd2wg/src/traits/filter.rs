@@ -0,0 +1,16 @@
+//! 文本后处理
+
+use std::fmt::Debug;
+
+/// 文本过滤器
+///
+/// 在生成对话前依次作用于说话人和对话文本.
+pub trait TextFilter: Debug + Send + Sync {
+    /// 处理说话人名称
+    fn filter_name(&self, name: &str) -> String {
+        name.to_string()
+    }
+
+    /// 处理对话文本
+    fn filter_text(&self, text: &str) -> String;
+}
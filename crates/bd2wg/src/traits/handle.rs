@@ -9,6 +9,8 @@ pub trait Handle {
 
     /// 中断执行
     ///
+    /// 中断后仍可 join, 结果中包含 Cancelled 错误.
+    ///
     /// 建议此类型的实现在 Drop 中采用 cancel 方法
     fn cancel(&mut self);
 
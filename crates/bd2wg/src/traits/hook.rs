@@ -0,0 +1,11 @@
+//! 场景后处理
+
+use std::{fmt::Debug, io};
+
+/// 场景后处理钩子
+///
+/// 在场景渲染完成, 写入之前依次调用, 可插入或改写指令.
+pub trait SceneHook: Debug + Send + Sync {
+    /// 处理场景文本, path 为相对游戏目录的场景路径 (如 `scene/start.txt`)
+    fn process(&self, path: &str, text: String) -> io::Result<String>;
+}
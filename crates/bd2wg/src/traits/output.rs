@@ -0,0 +1,30 @@
+//! 输出目标
+
+use std::{io, path::Path};
+
+/// 输出目标
+///
+/// 路径为拼接输出目录后的完整路径, 与直接写入文件系统时一致,
+/// 实现可以据此将场景和资源重定向到压缩包, 内存或远程存储.
+pub trait OutputSink: Send + Sync {
+    /// 读取文件, 不存在时返回 None
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>>;
+
+    /// 写入文件, 需要时创建上级目录
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()>;
+
+    /// 删除文件, 不存在时忽略
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// 文件是否存在
+    fn exists(&self, path: &Path) -> bool {
+        matches!(self.read(path), Ok(Some(_)))
+    }
+
+    /// 全部写入完成后调用, 如写出压缩包
+    fn finish(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
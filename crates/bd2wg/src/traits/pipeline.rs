@@ -1,6 +1,16 @@
 //! 工作管线
 
-use crate::error::*;
+use std::{
+    fmt::{self, Display},
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{
+    error::*,
+    models::webgal::Resource,
+    traits::{download::Progress, resolve::ResolveStats, transpile::ActionStats},
+};
 
 use super::handle::Handle;
 
@@ -9,6 +19,38 @@ use super::handle::Handle;
 pub struct TranspileState {
     pub scene: usize,
     pub action: usize,
+    /// 按类型统计的原始指令
+    pub actions: ActionStats,
+    /// 内容相同而未重写的场景文件数
+    pub unchanged: usize,
+    /// 按配置保留的已有场景文件, 未写入新内容
+    pub kept: Vec<String>,
+    /// 写入或合并的场景文件 (相对游戏目录)
+    pub written: Vec<String>,
+    /// 合并冲突的场景文件, 新内容另存为 `.new` 文件
+    pub conflicts: Vec<String>,
+    /// 各阶段耗时
+    pub timings: TranspileTimings,
+}
+
+/// 转译各阶段耗时
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TranspileTimings {
+    /// 读取并解析脚本
+    pub parse: Duration,
+    pub transpile: Duration,
+    /// 渲染, 后处理并写入场景及工程文件
+    pub write: Duration,
+}
+
+impl Display for TranspileTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse {:.2?}, transpile {:.2?}, write {:.2?}",
+            self.parse, self.transpile, self.write
+        )
+    }
 }
 
 /// 转译结果
@@ -16,6 +58,10 @@ pub struct TranspileState {
 pub struct TranspileResult {
     pub state: TranspileState,
     pub errors: Vec<Error>,
+    pub warnings: Vec<Warning>,
+    pub stats: ResolveStats,
+    /// 需要下载的资源, 试运行时不会下载
+    pub resources: Vec<Resource>,
 }
 
 /// 下载状态
@@ -24,6 +70,51 @@ pub struct DownloadState {
     pub success: usize,
     pub failed: usize,
     pub total: usize,
+    pub timings: DownloadTimings,
+}
+
+/// 下载耗时统计
+///
+/// 单个资源的耗时自开始传输起计, 不含在下载池中排队的时间 (传输极快时除外).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadTimings {
+    /// 下载阶段总耗时
+    pub total: Duration,
+    /// 已完成资源的平均耗时
+    pub average: Duration,
+    /// 耗时最长的资源路径 (相对游戏目录) 及耗时
+    pub slowest: Option<(String, Duration)>,
+}
+
+impl Display for DownloadTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "total {:.2?}, average {:.2?}", self.total, self.average)?;
+
+        if let Some((path, elapsed)) = &self.slowest {
+            write!(f, ", slowest {path} ({elapsed:.2?})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 单个资源的下载状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceStatus {
+    Queued,
+    Downloading(Progress),
+    Done,
+    /// 失败或被取消, 附带错误信息
+    Failed(String),
+}
+
+/// 单个资源的下载任务
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceTask {
+    /// 相对游戏目录的路径
+    pub path: String,
+    pub url: String,
+    pub status: ResourceStatus,
 }
 
 /// 下载结果
@@ -33,6 +124,51 @@ pub struct DownloadResult {
     pub errors: Vec<Error>,
 }
 
+/// 完整管线阶段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PipelineStage {
+    #[default]
+    Transpile,
+    Download,
+    Finished,
+}
+
+/// 完整管线状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineState<P = TranspileState> {
+    pub stage: PipelineStage,
+    /// 转译进度, 见 [`TranspileStage::progress`]
+    pub transpile: P,
+    pub download: DownloadState,
+}
+
+/// 批量转译状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchState {
+    /// 已完成的故事数
+    pub finished: usize,
+    pub total: usize,
+}
+
+/// 批量转译中单个故事的状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoryStatus {
+    Queued,
+    /// 正在转译, 附带当前的转译状态
+    Running(TranspileState),
+    /// 已结束, 附带错误数
+    Done {
+        errors: usize,
+    },
+}
+
+/// 批量转译中的单个故事
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoryTask {
+    pub story: PathBuf,
+    pub status: StoryStatus,
+}
+
 /// 转译管线
 ///
 /// 非阻塞运行, 转移脚本并写入场景文件
@@ -47,6 +183,83 @@ pub trait TranspilePipeline:
 /// 非阻塞运行, 下载所需的资源
 pub trait DownloadPipeline: Handle<Result = DownloadResult> {
     fn state(&self) -> DownloadState;
+
+    /// 按提交顺序列出全部资源及其当前状态
+    fn tasks(&self) -> Vec<ResourceTask>;
+}
+
+/// 批量转译管线
+///
+/// 非阻塞运行, 并行转译多个故事, 按输入顺序返回各自的结果
+pub trait BatchPipeline:
+    Handle<
+    Result = (
+        Vec<(PathBuf, TranspileResult)>,
+        Result<Box<dyn DownloadPipeline>>,
+    ),
+>
+{
+    fn state(&self) -> BatchState;
+
+    /// 按输入顺序列出全部故事及其当前状态
+    fn stories(&self) -> Vec<StoryTask>;
+}
+
+/// 完整管线的转译阶段, 结束时启动下载管线
+pub trait TranspileStage:
+    Handle<Result = (Self::Output, Result<Box<dyn DownloadPipeline>>)> + Send + 'static
+{
+    /// 转译结果
+    type Output: Send + 'static;
+    /// 转译进度
+    type Progress: Clone + Default + Send + Sync + 'static;
+
+    fn progress(&self) -> Self::Progress;
+
+    /// 转译线程 panic 等未能返回结果时的转译结果
+    fn failed(error: Error) -> Self::Output;
+}
+
+impl TranspileStage for dyn TranspilePipeline + Send {
+    type Output = TranspileResult;
+    type Progress = TranspileState;
+
+    fn progress(&self) -> TranspileState {
+        self.state()
+    }
+
+    fn failed(error: Error) -> TranspileResult {
+        TranspileResult {
+            errors: vec![error],
+            ..Default::default()
+        }
+    }
+}
+
+/// 批量转译的进度为各故事的状态
+impl TranspileStage for dyn BatchPipeline + Send {
+    type Output = Vec<(PathBuf, TranspileResult)>;
+    type Progress = Vec<StoryTask>;
+
+    fn progress(&self) -> Vec<StoryTask> {
+        self.stories()
+    }
+
+    fn failed(_: Error) -> Self::Output {
+        Vec::new()
+    }
+}
+
+/// 完整管线
+///
+/// 非阻塞运行, 依次执行转译和下载
+pub trait FullPipeline<S: TranspileStage + ?Sized = dyn TranspilePipeline + Send>:
+    Handle<Result = (S::Output, Result<DownloadResult>)>
+{
+    fn state(&self) -> PipelineState<S::Progress>;
+
+    /// 下载阶段的全部资源及其当前状态, 转译阶段为空
+    fn tasks(&self) -> Vec<ResourceTask>;
 }
 
 /// 阻塞执行转译
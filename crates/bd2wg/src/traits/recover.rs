@@ -0,0 +1,47 @@
+//! 失败资源的恢复
+
+use std::{fmt::Debug, path::PathBuf};
+
+use crate::{
+    error::{Error, ResolveError},
+    models::webgal::LOCAL_URL_SCHEME,
+};
+
+use super::pipeline::ResourceTask;
+
+/// 用户提供的替代资源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replacement {
+    Url(String),
+    /// 本地文件, 复制到资源路径
+    File(PathBuf),
+}
+
+impl Replacement {
+    /// 作为资源链接, 本地文件以 `file://` 开头
+    pub fn into_url(self) -> String {
+        match self {
+            Self::Url(url) => url,
+            Self::File(path) => format!("{LOCAL_URL_SCHEME}{}", path.display()),
+        }
+    }
+}
+
+/// 下载失败后的处理方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    Replace(Replacement),
+    Retry,
+    Skip,
+}
+
+/// 失败资源的恢复
+///
+/// 在管线线程中调用, 实现可以阻塞等待用户输入.
+pub trait Recover: Debug + Send + Sync {
+    /// 资源解析失败时提供替代资源, 返回 None 时跳过
+    fn resolve(&self, error: &ResolveError) -> Option<Replacement>;
+
+    /// 资源下载失败时选择处理方式
+    fn download(&self, task: &ResourceTask, errors: &[Error]) -> Recovery;
+}
@@ -1,6 +1,11 @@
 //! 资源解析
 
-use std::{ops::Deref, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display},
+    ops::Deref,
+    sync::Arc,
+};
 
 use crate::{
     error::ResolveError,
@@ -16,32 +21,172 @@ pub enum ResourceType {
     Image,
     Bgm,
     Se,
+    Voice,
+}
+
+impl ResourceType {
+    /// 解析结果的 WebGAL 资源类型
+    pub fn webgal(&self) -> webgal::ResourceType {
+        match self {
+            Self::Image => webgal::ResourceType::Background,
+            Self::Bgm => webgal::ResourceType::Bgm,
+            Self::Se | Self::Voice => webgal::ResourceType::Vocal,
+        }
+    }
+}
+
+/// 模型动作 / 表情名称转换
+///
+/// 模型被重定向或复用时, 原脚本中的名称可能不适用于实际模型.
+pub trait ModelDisplayResolver: Debug + Send + Sync {
+    /// 转换动作名称
+    fn motion(&self, motion: &str) -> String;
+
+    /// 转换表情名称
+    fn expression(&self, expression: &str) -> String;
 }
 
 /// 资源解析结果
-pub enum ResourceEntry {
-    Vacant(Arc<webgal::Resource>),
-    Occupied(*const webgal::Resource),
+///
+/// 持有解析结果的共享指针, 并记录是否为首次解析.
+#[derive(Debug, Clone)]
+pub struct ResourceEntry {
+    resource: Arc<webgal::Resource>,
+    vacant: bool,
+    display: Option<Arc<dyn ModelDisplayResolver>>,
+    fallback: Option<CostumeFallback>,
 }
 
 impl ResourceEntry {
+    /// 首次解析的资源
+    pub fn vacant(resource: Arc<webgal::Resource>) -> Self {
+        Self {
+            resource,
+            vacant: true,
+            display: None,
+            fallback: None,
+        }
+    }
+
+    /// 已解析过的资源
+    pub fn occupied(resource: Arc<webgal::Resource>) -> Self {
+        Self {
+            resource,
+            vacant: false,
+            display: None,
+            fallback: None,
+        }
+    }
+
+    /// 附加模型名称转换
+    pub fn with_display(mut self, display: Option<Arc<dyn ModelDisplayResolver>>) -> Self {
+        self.display = display;
+        self
+    }
+
+    /// 模型名称转换 (若存在)
+    pub fn display(&self) -> Option<&Arc<dyn ModelDisplayResolver>> {
+        self.display.as_ref()
+    }
+
+    /// 附加服装回退
+    pub fn with_fallback(mut self, fallback: Option<CostumeFallback>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// 服装回退 (若发生), 每次解析该服装均会返回
+    pub fn fallback(&self) -> Option<&CostumeFallback> {
+        self.fallback.as_ref()
+    }
+
     /// 是否为新值
     pub fn is_vacant(&self) -> bool {
-        matches!(self, Self::Vacant(_))
+        self.vacant
+    }
+
+    /// 取出新值 (若为新值)
+    pub fn into_vacant(self) -> Option<Arc<webgal::Resource>> {
+        self.vacant.then_some(self.resource)
     }
 }
 
 impl AsRef<webgal::Resource> for ResourceEntry {
     fn as_ref(&self) -> &webgal::Resource {
-        match self {
-            Self::Vacant(v) => v.as_ref(),
-            Self::Occupied(o) => unsafe { o.as_ref().unwrap() },
-        }
+        &self.resource
     }
 }
 
 impl_deref_for_asref! {ResourceEntry, webgal::Resource}
 
+/// 服装回退记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostumeFallback {
+    pub costume: String,
+    pub fallback: String,
+}
+
+/// 资源解析统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolveStats {
+    /// 各类型的唯一资源数
+    pub unique: HashMap<webgal::ResourceType, usize>,
+    /// 命中已解析资源的次数
+    pub reused: usize,
+    /// 预计下载大小 (字节), 未探测时为 None
+    pub size: Option<u64>,
+    /// 经重试阶段解析的资源数
+    pub retried: usize,
+}
+
+impl ResolveStats {
+    /// 唯一资源总数
+    pub fn total(&self) -> usize {
+        self.unique.values().sum()
+    }
+
+    /// 自 `before` 以来的增量
+    pub fn since(&self, before: &Self) -> Self {
+        Self {
+            unique: (self.unique.iter())
+                .map(|(kind, n)| (*kind, n - before.unique.get(kind).unwrap_or(&0)))
+                .filter(|(_, n)| *n != 0)
+                .collect(),
+            reused: self.reused - before.reused,
+            size: self.size.map(|size| size - before.size.unwrap_or(0)),
+            retried: self.retried - before.retried,
+        }
+    }
+
+    /// 累加另一份统计
+    pub fn add(&mut self, other: &Self) {
+        for (kind, n) in &other.unique {
+            *self.unique.entry(*kind).or_default() += n;
+        }
+        self.reused += other.reused;
+        if let Some(size) = other.size {
+            *self.size.get_or_insert(0) += size;
+        }
+        self.retried += other.retried;
+    }
+}
+
+impl Display for ResolveStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} unique assets ({} reused)", self.total(), self.reused)?;
+
+        if self.retried != 0 {
+            write!(f, ", {} retried", self.retried)?;
+        }
+
+        if let Some(size) = self.size {
+            write!(f, ", ~{} MB", size.div_ceil(1 << 20))?;
+        }
+
+        Ok(())
+    }
+}
+
 /// 资源解析器
 ///
 /// 解析 Bestdori 资源为 WebGAL 资源 + 下载链接.
@@ -56,5 +201,17 @@ pub trait Resolve {
     ) -> ResolveResult<ResourceEntry>;
 
     /// 解析 Live2D 资源
+    ///
+    /// 模型被重定向时, 返回的资源附带动作 / 表情名称转换; 服装回退时附带回退记录.
     fn resolve_model(&mut self, costume: &str) -> ResourceEntry;
+
+    /// 解析统计
+    fn stats(&self) -> ResolveStats {
+        ResolveStats::default()
+    }
+
+    /// 角色表, 转译器据此填充说话人和注入默认动作
+    fn characters(&self) -> bestdori::CharacterTable {
+        bestdori::CharacterTable::builtin().clone()
+    }
 }
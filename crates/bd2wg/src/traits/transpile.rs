@@ -1,6 +1,9 @@
 //! 脚本转译
 
-use std::sync::Arc;
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
 
 use crate::{
     error::*,
@@ -8,13 +11,64 @@ use crate::{
         bestdori,
         webgal::{self, Resource},
     },
+    traits::resolve::ResolveStats,
 };
 
+/// 各类指令的转译计数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionStats {
+    pub talk: usize,
+    pub sound: usize,
+    pub effect: usize,
+    pub layout: usize,
+    pub motion: usize,
+    pub wait: usize,
+    /// 无法识别而跳过的指令
+    pub skipped: usize,
+}
+
+impl ActionStats {
+    /// 记录一条指令
+    pub fn record(&mut self, action: &bestdori::Action) {
+        use bestdori::Action;
+
+        *match action {
+            Action::Talk(_) => &mut self.talk,
+            Action::Sound(_) => &mut self.sound,
+            Action::Effect(_) => &mut self.effect,
+            Action::Layout(_) => &mut self.layout,
+            Action::Motion(_) => &mut self.motion,
+            Action::Wait(_) => &mut self.wait,
+            Action::Unknown(_) => &mut self.skipped,
+        } += 1;
+    }
+
+    /// 指令总数
+    pub fn total(&self) -> usize {
+        self.talk + self.sound + self.effect + self.layout + self.motion + self.wait + self.skipped
+    }
+}
+
+impl Display for ActionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} talks, {} sounds, {} effects, {} layouts, {} motions, {} waits, {} skipped",
+            self.talk, self.sound, self.effect, self.layout, self.motion, self.wait, self.skipped
+        )
+    }
+}
+
 /// 转译结果
 pub struct TranspileResult {
     pub story: webgal::Story,
     pub resources: Vec<Arc<Resource>>,
     pub errors: Vec<Error>,
+    pub warnings: Vec<Warning>,
+    pub stats: ResolveStats,
+    pub actions: ActionStats,
+    /// 转译器生成的文件 (相对游戏目录) 及其内容, 如白色转场背景
+    pub generated: Vec<(String, Vec<u8>)>,
 }
 
 /// 脚本转译器
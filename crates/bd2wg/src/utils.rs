@@ -1,17 +1,20 @@
 //! 辅助工具
 
-use std::{fs, path::Path};
+use std::{collections::BTreeMap, fs, path::Path};
 
 use reqwest::{
     blocking::Client,
     header::{HeaderMap, HeaderName, HeaderValue},
 };
+use serde::Deserialize;
 use serde_json::Value;
 
 // /// 默认请求头路径
 // pub const DEFAULT_HEADER_PATH: &str = "./assets/header.json";
 
 /// 为 Handle 实现 cancel Drop
+///
+/// 指定 DropPolicy 字段时, 仅在其为 Cancel 时中断.
 #[macro_export]
 macro_rules! impl_drop_for_handle {
     ($t:ty) => {
@@ -23,6 +26,17 @@ macro_rules! impl_drop_for_handle {
             }
         }
     };
+
+    ($t:ty, $policy:ident) => {
+        impl Drop for $t {
+            fn drop(&mut self) {
+                if self.$policy == $crate::models::config::DropPolicy::Cancel && !self.is_finished()
+                {
+                    self.cancel();
+                }
+            }
+        }
+    };
 }
 
 /// 为 Serialize 实现 Display
@@ -39,6 +53,20 @@ macro_rules! impl_display_for_serde {
     };
 }
 
+/// 为 Deserialize 实现 FromStr
+#[macro_export]
+macro_rules! impl_fromstr_for_serde {
+    ($t:ty) => {
+        impl std::str::FromStr for $t {
+            type Err = serde_json::Error;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                serde_json::from_str(s)
+            }
+        }
+    };
+}
+
 /// 为 AsRef 实现 Deref
 #[macro_export]
 macro_rules! impl_deref_for_asref {
@@ -88,24 +116,26 @@ macro_rules! return_ok {
     }};
 }
 
-/// 当原子量为 true 时 panic
+/// 获取锁, 忽略中毒
+///
+/// 持有锁的线程 panic 时锁会中毒, 此处保护的均为状态计数, 缓存等数据, 可继续使用,
+/// 以免一个线程的 panic 蔓延到其他线程.
 #[macro_export]
-macro_rules! false_or_panic {
-    ($atom:expr) => {
-        false_or_panic! {$atom, "canceled."}
-    };
-    ($atom:expr, $text:expr) => {
-        if $atom.load(std::sync::atomic::Ordering::Relaxed) {
-            panic!($text)
-        }
+macro_rules! safe_unwrap_lock {
+    ($lock:expr) => {
+        $lock.unwrap_or_else(std::sync::PoisonError::into_inner)
     };
+}
 
-    ($atom:ident) => {
-        false_or_panic! {$atom, "canceled."}
+/// 当原子量为 true 时返回给定值
+#[macro_export]
+macro_rules! false_or_return {
+    ($atom:expr) => {
+        false_or_return! {$atom, ()}
     };
-    ($atom:ident, $text:literal) => {
+    ($atom:expr, $ret:expr) => {
         if $atom.load(std::sync::atomic::Ordering::Relaxed) {
-            panic!($text)
+            return $ret;
         }
     };
 }
@@ -125,6 +155,32 @@ pub fn new_client_with_header(header: HeaderMap) -> reqwest::Result<Client> {
     }
 }
 
+/// 创建通过 HEAD 请求检查链接是否可用的探测函数
+pub fn new_url_probe(header: HeaderMap) -> reqwest::Result<impl Fn(&str) -> bool + Send + Sync> {
+    let client = new_client_with_header(header)?;
+    Ok(move |url: &str| {
+        client
+            .head(url)
+            .send()
+            .is_ok_and(|resp| resp.status().is_success())
+    })
+}
+
+/// 创建通过 HEAD 请求获取资源大小的探测函数
+pub fn new_size_probe(
+    header: HeaderMap,
+) -> reqwest::Result<impl Fn(&str) -> Option<u64> + Send + Sync> {
+    let client = new_client_with_header(header)?;
+    Ok(move |url: &str| {
+        client
+            .head(url)
+            .send()
+            .ok()
+            .filter(|resp| resp.status().is_success())?
+            .content_length()
+    })
+}
+
 /// 创建完整路径, 将字节写入文件
 pub fn create_and_write(bytes: impl AsRef<[u8]>, path: &Path) -> std::io::Result<()> {
     if let Some(dir) = path.parent() {
@@ -152,6 +208,104 @@ pub fn gen_name_from_url(url: &str, extend: &str) -> String {
         .collect()
 }
 
+/// 从 url 生成可读的唯一路径
+///
+/// 保留 url 中的原始文件名 (去除扩展名), 追加 url 的短哈希以避免重名.
+/// 用户提供的本地文件链接同样适用, 包括 Windows 路径.
+pub fn gen_readable_name_from_url(url: &str, extend: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let base = path
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or("");
+    let stem = base.rsplit_once('.').map_or(base, |(stem, _)| stem);
+
+    let stem: String = stem
+        .chars()
+        .map(|c| match c {
+            ':' | '?' | '*' | '"' | '<' | '>' | '|' | '\\' | '/' | ' ' => '_',
+            c => c,
+        })
+        .collect();
+
+    if stem.is_empty() {
+        format!("{}{extend}", short_hash(url))
+    } else {
+        format!("{stem}-{}{extend}", short_hash(url))
+    }
+}
+
+/// 计算字符串的短哈希 (8 位十六进制)
+///
+/// 采用 FNV-1a, 保证不同平台和多次运行间结果一致.
+pub fn short_hash(s: &str) -> String {
+    let hash = fnv1a(s.as_bytes());
+    format!("{:08x}", (hash >> 32) as u32 ^ hash as u32)
+}
+
+/// 计算字节流的哈希 (16 位十六进制, FNV-1a)
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a(bytes))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325_u64, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 渲染 `${name}` 形式的模板, 未知变量保留原样
+pub fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    vars.iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("${{{name}}}"), value)
+        })
+}
+
+/// 按最大字符数拆分文本
+///
+/// 优先在标点或空白之后断开, 找不到断点时强制截断.
+pub fn split_text(text: &str, max: usize) -> Vec<String> {
+    let max = max.max(1);
+    let mut chars: Vec<char> = text.chars().collect();
+    let mut parts = Vec::new();
+
+    while chars.len() > max {
+        let at = chars[..max]
+            .iter()
+            .rposition(|c| c.is_whitespace() || "。！？、…，,.!?".contains(*c))
+            .map_or(max, |k| k + 1);
+
+        parts.push(chars.drain(..at).collect());
+    }
+
+    if !chars.is_empty() || parts.is_empty() {
+        parts.push(chars.into_iter().collect());
+    }
+
+    parts
+}
+
+/// 是否为默认字体无法显示的字符 (emoji 或控制字符, 不含换行)
+///
+/// 杂项符号与装饰符号区段只包含默认以 emoji 呈现的字符, 台词中常见的 ♪, ★, ♡ 等予以保留.
+pub fn is_special_char(c: char) -> bool {
+    (c.is_control() && c != '\n')
+        || matches!(
+            c as u32,
+            0x1F000..=0x1FAFF // emoji, 符号与象形文字
+                | 0x2614..=0x2615 | 0x2648..=0x2653 | 0x267F | 0x2693 | 0x26A1 // 杂项符号中的 emoji
+                | 0x26AA..=0x26AB | 0x26BD..=0x26BE | 0x26C4..=0x26C5 | 0x26CE | 0x26D4
+                | 0x26EA | 0x26F2..=0x26F3 | 0x26F5 | 0x26FA | 0x26FD
+                | 0x2705 | 0x270A..=0x270B | 0x2728 | 0x274C | 0x274E // 装饰符号中的 emoji
+                | 0x2753..=0x2755 | 0x2757 | 0x2795..=0x2797 | 0x27B0 | 0x27BF
+                | 0xFE00..=0xFE0F // 变体选择符
+                | 0x200D // 零宽连接符
+                | 0xE0000..=0xE007F // 标签字符
+        )
+}
+
 /// 将第一个英文字母变为小写
 pub fn lower_first_alphabetic(s: &str) -> String {
     let mut find = false;
@@ -234,3 +388,277 @@ const HEADER_JSON: &[u8] = include_bytes!("../assets/header.json");
 pub fn default_header() -> anyhow::Result<HeaderMap> {
     new_header_from_bytes(HEADER_JSON)
 }
+
+/// 不写入请求头文件的请求头, 由客户端按请求生成
+const TRANSIENT_HEADERS: &[&str] = &["host", "content-length", "connection"];
+
+/// 记录一个请求头, 名称转为小写, 忽略 HTTP/2 伪请求头和随请求变化的请求头
+fn push_header(headers: &mut BTreeMap<String, String>, name: &str, value: &str) {
+    let name = name.trim().to_ascii_lowercase();
+    if name.is_empty() || name.starts_with(':') || TRANSIENT_HEADERS.contains(&name.as_str()) {
+        return;
+    }
+    headers.insert(name, value.trim().to_string());
+}
+
+/// 从浏览器 "复制为 cURL (bash)" 得到的命令中提取请求头
+///
+/// 读取 `-H`, `-b`, `-A`, `-e` 及其长选项, 其余参数忽略.
+pub fn header_from_curl(command: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let words = split_shell_words(command)?;
+    let mut headers = BTreeMap::new();
+
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        let name = match word.as_str() {
+            "-H" | "--header" => None,
+            "-b" | "--cookie" => Some("cookie"),
+            "-A" | "--user-agent" => Some("user-agent"),
+            "-e" | "--referer" => Some("referer"),
+            _ => continue,
+        };
+        let Some(value) = words.next() else {
+            anyhow::bail!("missing value for {word}");
+        };
+
+        match name {
+            Some(name) => push_header(&mut headers, name, value),
+            None => match value.split_once(':') {
+                Some((name, value)) => push_header(&mut headers, name, value),
+                None => anyhow::bail!("invalid header: {value}"),
+            },
+        }
+    }
+
+    if headers.is_empty() {
+        anyhow::bail!("no header found in the curl command");
+    }
+    Ok(headers)
+}
+
+/// 按 POSIX shell 规则切分命令行, 支持单双引号, `$'...'`, 反斜杠转义和续行
+fn split_shell_words(command: &str) -> anyhow::Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '\\' => match chars.next() {
+                Some('\n') | None => {}
+                Some(c) => word.get_or_insert_default().push(c),
+            },
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => anyhow::bail!("unterminated quote in command"),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => word.extend(['\\', c]),
+                            None => anyhow::bail!("unterminated quote in command"),
+                        },
+                        Some(c) => word.push(c),
+                        None => anyhow::bail!("unterminated quote in command"),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some('t') => word.push('\t'),
+                            Some(c) => word.push(c),
+                            None => anyhow::bail!("unterminated quote in command"),
+                        },
+                        Some(c) => word.push(c),
+                        None => anyhow::bail!("unterminated quote in command"),
+                    }
+                }
+            }
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+
+    words.extend(word);
+    Ok(words)
+}
+
+/// HAR 导出文件, 只保留请求头相关字段
+#[derive(Debug, Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarLog {
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarRequest {
+    url: String,
+    headers: Vec<HarHeader>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+/// HAR 中未指定链接时优先选取的请求
+const HAR_DEFAULT_HOST: &str = "bestdori.com";
+
+/// 从浏览器导出的 HAR 文件中提取请求头
+///
+/// 选取首个链接包含 `url` 的请求, 未指定时优先选取 Bestdori 的请求, 否则选取首个请求.
+pub fn header_from_har(
+    bytes: &[u8],
+    url: Option<&str>,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let har: Har = serde_json::from_slice(bytes)?;
+    let entries = &har.log.entries;
+
+    let entry = match url {
+        Some(url) => entries.iter().find(|entry| entry.request.url.contains(url)),
+        None => entries
+            .iter()
+            .find(|entry| entry.request.url.contains(HAR_DEFAULT_HOST))
+            .or(entries.first()),
+    };
+    let Some(entry) = entry else {
+        anyhow::bail!("no matching request found in the HAR file");
+    };
+
+    let mut headers = BTreeMap::new();
+    for header in &entry.request.headers {
+        push_header(&mut headers, &header.name, &header.value);
+    }
+    if headers.is_empty() {
+        anyhow::bail!("no header found in request {}", entry.request.url);
+    }
+    Ok(headers)
+}
+
+#[test]
+#[cfg(test)]
+fn test_gen_readable_name_from_url() {
+    let url = "https://example.com/uploads/bg/Classroom%20A.jpg?v=2";
+    let name = gen_readable_name_from_url(url, ".png");
+
+    assert!(name.starts_with("Classroom%20A-"));
+    assert!(name.ends_with(".png"));
+    assert_eq!(name, gen_readable_name_from_url(url, ".png"));
+    assert_ne!(
+        name,
+        gen_readable_name_from_url("https://example.com/other/Classroom%20A.jpg", ".png")
+    );
+    assert!(gen_readable_name_from_url("file://C:\\story\\bg.png", ".png").starts_with("bg-"));
+}
+
+#[test]
+#[cfg(test)]
+fn test_is_special_char() {
+    assert!("♪★☆♡♥…「」".chars().all(|c| !is_special_char(c)));
+    assert!("😀✨⚡❌\u{200D}\u{FE0F}\u{7}".chars().all(is_special_char));
+    assert!(!is_special_char('\n'));
+}
+
+#[test]
+#[cfg(test)]
+fn test_split_text() {
+    assert_eq!(split_text("", 4), vec![String::new()]);
+    assert_eq!(split_text("abcdefgh", 4), vec!["abcd", "efgh"]);
+    assert_eq!(split_text("ええ。そうね", 4), vec!["ええ。", "そうね"]);
+}
+
+#[test]
+#[cfg(test)]
+fn test_render_template() {
+    assert_eq!(
+        render_template("${story}-${n}.txt", &[("story", "mygo-1"), ("n", "2")]),
+        "mygo-1-2.txt"
+    );
+    assert_eq!(render_template("${x}-${n}", &[("n", "1")]), "${x}-1");
+}
+
+#[test]
+#[cfg(test)]
+fn test_safe_unwrap_lock() {
+    use std::sync::{Arc, Mutex};
+
+    let lock = Arc::new(Mutex::new(0));
+    let poisoned = lock.clone();
+    let result = std::thread::spawn(move || {
+        let mut count = poisoned.lock().unwrap();
+        *count += 1;
+        panic!("worker failed");
+    })
+    .join();
+
+    assert!(lock.is_poisoned());
+    assert_eq!(*safe_unwrap_lock!(lock.lock()), 1);
+    assert!(matches!(
+        crate::error::Error::from_panic(result.unwrap_err()),
+        crate::error::Error::Panicked(message) if message == "worker failed"
+    ));
+}
+
+#[test]
+#[cfg(test)]
+fn test_header_from_curl_and_har() {
+    let curl = r#"curl 'https://bestdori.com/api/post/details?id=1' \
+  -H 'accept: application/json' \
+  -H $'referer: https://bestdori.com/community/stories/1/it\'s' \
+  -H "Host: bestdori.com" \
+  -b 'a=1; b=2' \
+  -A "Mozilla/5.0 \"test\"""#;
+    let headers = header_from_curl(curl).unwrap();
+    assert_eq!(headers["accept"], "application/json");
+    assert_eq!(
+        headers["referer"],
+        "https://bestdori.com/community/stories/1/it's"
+    );
+    assert_eq!(headers["cookie"], "a=1; b=2");
+    assert_eq!(headers["user-agent"], "Mozilla/5.0 \"test\"");
+    assert!(!headers.contains_key("host"));
+    assert!(header_from_curl("curl 'https://bestdori.com/").is_err());
+
+    let har = br#"{"log": {"entries": [
+        {"request": {"url": "https://example.com/", "headers": [{"name": "a", "value": "1"}]}},
+        {"request": {"url": "https://bestdori.com/api", "headers": [
+            {"name": ":authority", "value": "bestdori.com"},
+            {"name": "User-Agent", "value": "test"}
+        ]}}
+    ]}}"#;
+    let headers = header_from_har(har, None).unwrap();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers["user-agent"], "test");
+    assert_eq!(header_from_har(har, Some("example")).unwrap()["a"], "1");
+    assert!(header_from_har(har, Some("missing")).is_err());
+    new_header_from_json(&serde_json::to_value(&headers).unwrap()).unwrap();
+}
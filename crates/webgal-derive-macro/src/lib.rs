@@ -1,112 +1,367 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{
-    Attribute, Data, DeriveInput, Fields, Ident, Lit, Meta, NestedMeta, Type, parse_macro_input,
+    Attribute, Data, DeriveInput, Fields, Generics, Ident, Lit, LitStr, Meta, MetaNameValue,
+    NestedMeta, Type, parse_macro_input, parse_quote,
 };
 
-/// 为具名结构体派生 Actionable trait
+/// 为具名结构体或枚举派生 Actionable trait
 ///
 /// 生成:
 /// - `Display`: 格式化为 WebGAL 命令字符串 (head + main + args)
 /// - `Into<Action>`: 装箱为通用 Action
 /// - `Actionable`: 标记实现
 /// - `ActionCustom`: 空实现 (除非标注 #[action(custom)])
+/// - `FromStr`: 按相同属性解析命令, 仅在未标注 custom 且均有静态 head 时生成.
+///   转义的分隔字符不会还原.
+/// - `ActionArgs`: 仅没有 main 字段的结构体, 供其他指令以 flatten 合并参数
+/// - `Serialize`: 仅标注 #[action(serde)] 时
 ///
 /// 结构体属性:
 /// - `#[action(head = "...")]`: 静态 head 前缀
 /// - `#[action(main = "single"|"list")]`: main 序列化方式
+/// - `#[action(sep = "...")]`: list 的分隔符, 默认为 `|`
 /// - `#[action(custom)]`: 用户自定义 ActionCustom
+/// - `#[action(serde)]`: 同时生成 `Serialize`, 输出 `webgal_derive::ActionForm` 形式的
+///   head, main 和参数, 需启用 webgal-derive 的 `serde` feature
+///
+/// 枚举的每个变体 (具名字段或单元) 各自对应一种命令, 变体上的 `head`, `main`, `sep`
+/// 覆盖枚举上的同名属性, `custom` 和 `serde` 只能标注在枚举上.
 ///
 /// 字段属性:
 /// - `#[action(main)]`: 标记 main 字段
 /// - `#[action(nullable)]`: 字段可为空 (通常 Option<T>)
 /// - `#[action(none)]`: None 时输出 "none"
-/// - `#[action(arg = "tag"|"pair"|"value")]`: 参数格式
+/// - `#[action(arg = "tag"|"pair"|"value")]`: 参数格式, `Vec<T>` 字段每个元素输出一个 pair 或 value 参数
 /// - `#[action(rename = "...")]`: 参数重命名
 /// - `#[action(tie = "...")]`: 关联开关
+/// - `#[action(when = "...")]`: 以 `self` 上的布尔表达式决定是否输出参数, 解析时缺省取默认值
+/// - `#[action(raw)]`: 不转义分隔字符 (如 JSON 值)
+/// - `#[action(keep = ":|")]`: 转义时保留列出的分隔字符, 可选 `:`, `|`, `-`
+/// - `#[action(bool = "on|off")]`: bool 的 main 或参数值以文本表示, 依次为 true, false
+/// - `#[action(format = "{:.2}")]`: 以格式字符串输出 main 或参数值, 如控制浮点数精度
+/// - `#[action(skip)]`: 不参与序列化, 解析时取默认值
+/// - `#[action(flatten)]`: 将字段 (实现 ActionArgs) 的参数合并到本指令
+///
+/// main 和参数值默认以 `webgal_derive::escape` 替换 WebGAL 分隔字符.
+///
+/// 支持泛型和生命周期参数, 字段类型所需的 `Display` 约束由使用者声明.
+/// `FromStr` 和 `ActionArgs` 为每个类型参数追加 `FromStr` 约束, 带生命周期参数时不生成.
+///
+/// 未知或重复的属性, 多个 main 字段及冲突的属性组合均以指向出错位置的编译错误报告.
 #[proc_macro_derive(Actionable, attributes(action))]
 pub fn derive_actionable(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = input.ident;
+    let generics = input.generics;
 
-    let struct_attrs = parse_struct_attrs(&input.attrs);
+    let struct_attrs = parse_struct_attrs(&input.attrs)?;
 
-    let fields = match input.data {
-        Data::Struct(data) => match data.fields {
-            Fields::Named(fields) => fields.named,
-            _ => panic!("Only named-field structs are supported"),
-        },
-        _ => panic!("Only structs are supported"),
+    let (parts, args_impl, parse_arms) = match input.data {
+        Data::Struct(data) => {
+            let fields = match data.fields {
+                Fields::Named(fields) => fields.named,
+                fields => {
+                    return Err(syn::Error::new_spanned(
+                        fields,
+                        "Actionable only supports named-field structs",
+                    ));
+                }
+            };
+            let field_infos = fields
+                .into_iter()
+                .map(parse_field_attrs)
+                .collect::<syn::Result<Vec<_>>>()?;
+            validate_fields(&struct_attrs, &field_infos, &name)?;
+
+            let parts = gen_parts(&struct_attrs, &field_infos, |ident| {
+                quote! { self.#ident }
+            });
+            let parse_arm = gen_parse_arm(&struct_attrs, &field_infos, quote! { Self });
+            let args_impl = gen_action_args_impl(&name, &generics, &field_infos);
+            (parts, args_impl, vec![parse_arm])
+        }
+        Data::Enum(data) => {
+            let (arms, parse_arms): (Vec<_>, Vec<_>) = data
+                .variants
+                .into_iter()
+                .map(|variant| gen_variant_arm(&struct_attrs, variant))
+                .collect::<syn::Result<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+            (quote! { match self { #(#arms)* } }, quote! {}, parse_arms)
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "Actionable only supports structs and enums",
+            ));
+        }
     };
 
-    let field_infos: Vec<_> = fields.into_iter().map(parse_field_attrs).collect();
+    let serialize_impl = if struct_attrs.serde {
+        gen_serialize_impl(&name, &generics, parts.clone())
+    } else {
+        quote! {}
+    };
+    let display_impl = gen_display_impl(&name, &generics, parts);
+
+    let parse_arms: Option<Vec<_>> = parse_arms.into_iter().collect();
+    let fromstr_impl = match parse_arms {
+        Some(arms) if !struct_attrs.custom => gen_fromstr_impl(&name, &generics, &arms),
+        _ => quote! {},
+    };
 
     let custom_impl = if struct_attrs.custom {
         quote! {}
     } else {
-        gen_action_custom_impl(&name)
+        gen_action_custom_impl(&name, &generics)
     };
 
-    let actionable_impl = gen_actionable_impl(&name);
-    let into_action_impl = gen_into_action_impl(&name);
-    let display_impl = gen_display_impl(&struct_attrs, &field_infos, &name);
+    let actionable_impl = gen_actionable_impl(&name, &generics);
+    let into_action_impl = gen_into_action_impl(&name, &generics);
 
-    TokenStream::from(quote! {
+    Ok(quote! {
         #custom_impl
         #display_impl
+        #args_impl
+        #serialize_impl
+        #fromstr_impl
         #actionable_impl
         #into_action_impl
     })
 }
 
+/// list 的默认分隔符
+const DEFAULT_SEP: &str = "|";
+
 struct StructAttrs {
     head: Option<String>,
     main: Option<String>,
+    sep: Option<String>,
     custom: bool,
+    serde: bool,
+}
+
+/// 展开全部 `#[action(...)]` 中的条目
+fn action_metas(attrs: &[Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("action")) {
+        let Meta::List(meta_list) = attr.parse_meta()? else {
+            return Err(syn::Error::new_spanned(attr, "expected #[action(...)]"));
+        };
+        for nested in meta_list.nested {
+            match nested {
+                NestedMeta::Meta(meta) => metas.push(meta),
+                NestedMeta::Lit(lit) => {
+                    return Err(syn::Error::new_spanned(lit, "expected an action attribute"));
+                }
+            }
+        }
+    }
+    Ok(metas)
+}
+
+/// 属性名
+fn meta_key(path: &syn::Path) -> String {
+    path.get_ident().map(Ident::to_string).unwrap_or_default()
+}
+
+fn unknown_attr(meta: &Meta, expected: &str) -> syn::Error {
+    let key = meta_key(meta.path());
+    syn::Error::new_spanned(
+        meta,
+        format!("unknown action attribute `{key}`, expected one of: {expected}"),
+    )
+}
+
+fn lit_str(nv: &MetaNameValue) -> syn::Result<LitStr> {
+    match &nv.lit {
+        Lit::Str(lit) => Ok(lit.clone()),
+        lit => Err(syn::Error::new_spanned(lit, "expected a string literal")),
+    }
+}
+
+/// 写入只允许出现一次的属性
+fn set_once<T>(slot: &mut Option<T>, value: T, path: &syn::Path) -> syn::Result<()> {
+    if slot.is_some() {
+        let key = meta_key(path);
+        return Err(syn::Error::new_spanned(
+            path,
+            format!("duplicate action attribute `{key}`"),
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn set_flag(flag: &mut bool, path: &syn::Path) -> syn::Result<()> {
+    if *flag {
+        let key = meta_key(path);
+        return Err(syn::Error::new_spanned(
+            path,
+            format!("duplicate action attribute `{key}`"),
+        ));
+    }
+    *flag = true;
+    Ok(())
 }
 
-fn parse_struct_attrs(attrs: &[Attribute]) -> StructAttrs {
+fn parse_struct_attrs(attrs: &[Attribute]) -> syn::Result<StructAttrs> {
+    const EXPECTED: &str = "head, main, sep, custom, serde";
+
     let mut head = None;
     let mut main = None;
+    let mut sep = None;
     let mut custom = false;
+    let mut serde = false;
 
-    for attr in attrs {
-        if !attr.path.is_ident("action") {
-            continue;
+    for meta in action_metas(attrs)? {
+        match &meta {
+            Meta::NameValue(nv) => match meta_key(&nv.path).as_str() {
+                "head" => set_once(&mut head, lit_str(nv)?.value(), &nv.path)?,
+                "main" => {
+                    let lit = lit_str(nv)?;
+                    if !matches!(lit.value().as_str(), "single" | "list") {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "expected main = \"single\" or \"list\"",
+                        ));
+                    }
+                    set_once(&mut main, lit.value(), &nv.path)?;
+                }
+                "sep" => {
+                    let lit = lit_str(nv)?;
+                    if lit.value().is_empty() {
+                        return Err(syn::Error::new_spanned(lit, "sep must not be empty"));
+                    }
+                    set_once(&mut sep, lit.value(), &nv.path)?;
+                }
+                _ => return Err(unknown_attr(&meta, EXPECTED)),
+            },
+            Meta::Path(path) if path.is_ident("custom") => set_flag(&mut custom, path)?,
+            Meta::Path(path) if path.is_ident("serde") => set_flag(&mut serde, path)?,
+            _ => return Err(unknown_attr(&meta, EXPECTED)),
         }
+    }
 
-        let Ok(Meta::List(meta_list)) = attr.parse_meta() else {
-            continue;
-        };
+    Ok(StructAttrs {
+        head,
+        main,
+        sep,
+        custom,
+        serde,
+    })
+}
 
-        for nested in meta_list.nested {
-            let NestedMeta::Meta(meta) = nested else {
-                continue;
-            };
+/// 校验结构体或变体的字段组合, 错误指向 `target` 或相关字段
+fn validate_fields(
+    attrs: &StructAttrs,
+    field_infos: &[FieldInfo],
+    target: &Ident,
+) -> syn::Result<()> {
+    let mut mains = field_infos.iter().filter(|info| info.main);
+    let first_main = mains.next();
+    if let Some(duplicate) = mains.next() {
+        let first = &first_main.unwrap().ident;
+        return Err(syn::Error::new_spanned(
+            &duplicate.ident,
+            format!("duplicate #[action(main)], already set on `{first}`"),
+        ));
+    }
 
-            match meta {
-                Meta::NameValue(nv) => {
-                    if nv.path.is_ident("head") {
-                        if let Lit::Str(lit) = nv.lit {
-                            head = Some(lit.value());
-                        }
-                    } else if nv.path.is_ident("main") {
-                        if let Lit::Str(lit) = nv.lit {
-                            main = Some(lit.value());
-                        }
-                    }
-                }
-                Meta::Path(path) if path.is_ident("custom") => {
-                    custom = true;
-                }
-                _ => {}
-            }
+    match (&attrs.main, first_main) {
+        (Some(main_type), None) => {
+            return Err(syn::Error::new_spanned(
+                target,
+                format!("main = \"{main_type}\" requires a field marked #[action(main)]"),
+            ));
+        }
+        (None, Some(info)) => {
+            return Err(syn::Error::new_spanned(
+                &info.ident,
+                "#[action(main)] requires #[action(main = \"single\"|\"list\")] on the type",
+            ));
         }
+        _ => {}
     }
 
-    StructAttrs { head, main, custom }
+    if attrs.sep.is_some() && attrs.main.as_deref() != Some("list") {
+        return Err(syn::Error::new_spanned(
+            target,
+            "#[action(sep = \"...\")] requires #[action(main = \"list\")]",
+        ));
+    }
+
+    Ok(())
+}
+
+/// 生成枚举变体的 Display 和 FromStr 的 match 分支, 变体属性覆盖枚举属性
+fn gen_variant_arm(
+    enum_attrs: &StructAttrs,
+    variant: syn::Variant,
+) -> syn::Result<(proc_macro2::TokenStream, Option<proc_macro2::TokenStream>)> {
+    let ident = variant.ident;
+    let variant_attrs = parse_struct_attrs(&variant.attrs)?;
+    if variant_attrs.custom || variant_attrs.serde {
+        let attr = (variant.attrs.iter())
+            .find(|attr| attr.path.is_ident("action"))
+            .expect("custom and serde are parsed from an action attribute");
+        return Err(syn::Error::new_spanned(
+            attr,
+            "#[action(custom)] and #[action(serde)] must be set on the enum, not on a variant",
+        ));
+    }
+    let attrs = StructAttrs {
+        head: variant_attrs.head.or_else(|| enum_attrs.head.clone()),
+        main: variant_attrs.main.or_else(|| enum_attrs.main.clone()),
+        sep: variant_attrs.sep.or_else(|| enum_attrs.sep.clone()),
+        custom: enum_attrs.custom,
+        serde: enum_attrs.serde,
+    };
+
+    let field_infos = match variant.fields {
+        Fields::Named(fields) => (fields.named.into_iter())
+            .map(parse_field_attrs)
+            .collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unit => Vec::new(),
+        fields @ Fields::Unnamed(_) => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "Actionable only supports named-field and unit variants",
+            ));
+        }
+    };
+    validate_fields(&attrs, &field_infos, &ident)?;
+
+    // 只绑定参与序列化的字段, 以前缀避免与生成代码中的局部变量重名
+    let bindings = field_infos
+        .iter()
+        .filter(|info| info.main || info.arg.is_some() || info.flatten)
+        .map(|info| {
+            let field = &info.ident;
+            let binding = format_ident!("__{}", field);
+            quote! { #field: #binding, }
+        });
+    let parts = gen_parts(&attrs, &field_infos, |field| {
+        let binding = format_ident!("__{}", field);
+        quote! { (*#binding) }
+    });
+
+    let parse_arm = gen_parse_arm(&attrs, &field_infos, quote! { Self::#ident });
+
+    let display_arm = quote! {
+        Self::#ident { #(#bindings)* .. } => #parts,
+    };
+    Ok((display_arm, parse_arm))
 }
 
 struct FieldInfo {
@@ -118,10 +373,19 @@ struct FieldInfo {
     tie: Option<String>,
     none: bool,
     nullable: bool,
+    raw: bool,
+    flatten: bool,
+    when: Option<syn::Expr>,
+    bool_text: Option<(String, String)>,
+    format: Option<String>,
+    keep: Option<String>,
 }
 
-fn parse_field_attrs(field: syn::Field) -> FieldInfo {
-    let ident = field.ident.expect("Field must have an identifier");
+fn parse_field_attrs(field: syn::Field) -> syn::Result<FieldInfo> {
+    const EXPECTED: &str = "main, nullable, none, raw, skip, flatten, \
+                            arg, rename, tie, when, bool, format, keep";
+
+    let ident = field.ident.expect("named fields have an identifier");
     let ty = field.ty;
     let mut main = false;
     let mut arg = None;
@@ -129,56 +393,120 @@ fn parse_field_attrs(field: syn::Field) -> FieldInfo {
     let mut tie = None;
     let mut none = false;
     let mut nullable = false;
+    let mut raw = false;
+    let mut skip = false;
+    let mut flatten = false;
+    let mut when = None;
+    let mut bool_text = None;
+    let mut format = None;
+    let mut keep = None;
 
-    for attr in field.attrs {
-        if !attr.path.is_ident("action") {
-            continue;
-        }
-
-        let Ok(Meta::List(meta_list)) = attr.parse_meta() else {
-            continue;
-        };
-
-        for nested in meta_list.nested {
-            let NestedMeta::Meta(meta) = nested else {
-                continue;
-            };
+    let error = |message: &str| Err(syn::Error::new_spanned(&ident, message));
 
-            match meta {
-                Meta::Path(path) => {
-                    if path.is_ident("main") {
-                        main = true;
-                    } else if path.is_ident("nullable") {
-                        nullable = true;
-                    } else if path.is_ident("none") {
-                        none = true;
+    for meta in action_metas(&field.attrs)? {
+        match &meta {
+            Meta::Path(path) => match meta_key(path).as_str() {
+                "main" => set_flag(&mut main, path)?,
+                "nullable" => set_flag(&mut nullable, path)?,
+                "none" => set_flag(&mut none, path)?,
+                "raw" => set_flag(&mut raw, path)?,
+                "skip" => set_flag(&mut skip, path)?,
+                "flatten" => set_flag(&mut flatten, path)?,
+                _ => return Err(unknown_attr(&meta, EXPECTED)),
+            },
+            Meta::NameValue(nv) => match meta_key(&nv.path).as_str() {
+                "arg" => {
+                    let lit = lit_str(nv)?;
+                    if !matches!(lit.value().as_str(), "tag" | "pair" | "value") {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "expected arg = \"tag\", \"pair\" or \"value\"",
+                        ));
                     }
+                    set_once(&mut arg, lit.value(), &nv.path)?;
                 }
-                Meta::NameValue(nv) => {
-                    if nv.path.is_ident("arg") {
-                        if let Lit::Str(lit) = nv.lit {
-                            arg = Some(lit.value());
-                        }
-                    } else if nv.path.is_ident("rename") {
-                        if let Lit::Str(lit) = nv.lit {
-                            rename = Some(lit.value());
-                        }
-                    } else if nv.path.is_ident("tie") {
-                        if let Lit::Str(lit) = nv.lit {
-                            tie = Some(lit.value());
-                        }
+                "rename" => set_once(&mut rename, lit_str(nv)?.value(), &nv.path)?,
+                "tie" => set_once(&mut tie, lit_str(nv)?.value(), &nv.path)?,
+                "format" => {
+                    let lit = lit_str(nv)?;
+                    let placeholders = (lit.value().replace("{{", "").replace("}}", ""))
+                        .matches('{')
+                        .count();
+                    if placeholders != 1 {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "format must contain exactly one placeholder",
+                        ));
                     }
+                    set_once(&mut format, lit.value(), &nv.path)?;
                 }
-                _ => {}
-            }
+                "bool" => {
+                    let lit = lit_str(nv)?;
+                    let value = lit.value();
+                    let Some((on, off)) = value.split_once('|') else {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "expected bool = \"<true text>|<false text>\"",
+                        ));
+                    };
+                    set_once(&mut bool_text, (on.to_string(), off.to_string()), &nv.path)?;
+                }
+                "keep" => {
+                    let lit = lit_str(nv)?;
+                    if lit.value().is_empty() || !lit.value().chars().all(|c| ":|-".contains(c)) {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "keep may only list the separators `:`, `|` and `-`",
+                        ));
+                    }
+                    set_once(&mut keep, lit.value(), &nv.path)?;
+                }
+                "when" => {
+                    let expr = lit_str(nv)?.parse::<syn::Expr>()?;
+                    set_once(&mut when, expr, &nv.path)?;
+                }
+                _ => return Err(unknown_attr(&meta, EXPECTED)),
+            },
+            Meta::List(_) => return Err(unknown_attr(&meta, EXPECTED)),
         }
     }
 
-    if none && arg.as_deref() == Some("tag") {
-        panic!("#[action(none)] cannot be used with #[action(arg = \"tag\")]");
+    let tag = arg.as_deref() == Some("tag");
+    if main && arg.is_some() {
+        return error("#[action(main)] cannot be used with #[action(arg = \"...\")]");
+    }
+    if none && tag {
+        return error("#[action(none)] cannot be used with #[action(arg = \"tag\")]");
+    }
+    if (none || nullable) && !main && arg.is_none() {
+        return error("#[action(none)] and #[action(nullable)] require main or arg");
+    }
+    if (rename.is_some() || tie.is_some() || when.is_some()) && arg.is_none() {
+        return error("#[action(rename, tie, when)] require #[action(arg = \"...\")]");
+    }
+    if raw && !main && arg.is_none() {
+        return error("#[action(raw)] requires main or arg");
+    }
+    if keep.is_some() && (raw || bool_text.is_some() || (!main && arg.is_none())) {
+        return error("#[action(keep = \"...\")] requires an escaped main or arg");
+    }
+    if skip && flatten {
+        return error("#[action(skip)] cannot be used with #[action(flatten)]");
+    }
+    if (skip || flatten) && (main || arg.is_some()) {
+        return error("#[action(skip)] and #[action(flatten)] cannot be used with main or arg");
+    }
+    if is_vec_type(&ty) && arg.is_some() && (none || tag) {
+        return error("Vec fields only support #[action(arg = \"pair\"|\"value\")] without none");
+    }
+    if bool_text.is_some() && (tag || (!main && arg.is_none())) {
+        return error("#[action(bool = \"...\")] requires main or a pair / value arg");
+    }
+    if format.is_some() && bool_text.is_some() {
+        return error("#[action(format = \"...\")] cannot be used with #[action(bool = \"...\")]");
     }
 
-    FieldInfo {
+    Ok(FieldInfo {
         ident,
         ty,
         main,
@@ -187,18 +515,34 @@ fn parse_field_attrs(field: syn::Field) -> FieldInfo {
         tie,
         none,
         nullable,
-    }
+        raw,
+        flatten,
+        when,
+        bool_text,
+        format,
+        keep,
+    })
 }
 
-fn gen_action_custom_impl(name: &Ident) -> proc_macro2::TokenStream {
+fn gen_action_custom_impl(name: &Ident, generics: &Generics) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     quote! {
-        impl webgal_derive::ActionCustom for #name {}
+        impl #impl_generics webgal_derive::ActionCustom for #name #ty_generics #where_clause {}
     }
 }
 
-fn gen_into_action_impl(name: &Ident) -> proc_macro2::TokenStream {
+/// 装箱为 Action 要求 `Send + Sync + 'static`
+fn gen_into_action_impl(name: &Ident, generics: &Generics) -> proc_macro2::TokenStream {
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let mut bounded = generics.clone();
+    bounded
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { #name #ty_generics: Send + Sync + 'static });
+    let (impl_generics, _, where_clause) = bounded.split_for_impl();
+
     quote! {
-        impl Into<Action> for #name {
+        impl #impl_generics Into<Action> for #name #ty_generics #where_clause {
             fn into(self) -> Action {
                 Action(Box::new(self))
             }
@@ -206,43 +550,80 @@ fn gen_into_action_impl(name: &Ident) -> proc_macro2::TokenStream {
     }
 }
 
-fn gen_actionable_impl(name: &Ident) -> proc_macro2::TokenStream {
+fn gen_actionable_impl(name: &Ident, generics: &Generics) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     quote! {
-        impl webgal_derive::Actionable for #name {}
+        impl #impl_generics webgal_derive::Actionable for #name #ty_generics #where_clause {}
     }
 }
 
-fn gen_display_impl(
-    struct_attrs: &StructAttrs,
+/// 为解析相关的实现追加类型参数的 FromStr 约束, 带生命周期参数时返回 None
+fn parse_generics(generics: &Generics) -> Option<Generics> {
+    if generics.lifetimes().next().is_some() {
+        return None;
+    }
+
+    let mut bounded = generics.clone();
+    let params: Vec<_> = generics.type_params().map(|param| &param.ident).collect();
+    let where_clause = bounded.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote! {
+            #param: std::str::FromStr
+        });
+        where_clause.predicates.push(parse_quote! {
+            <#param as std::str::FromStr>::Err: std::fmt::Display
+        });
+    }
+    Some(bounded)
+}
+
+/// 生成计算 `(head, main, args)` 的代码块, `access` 给出字段的访问表达式
+fn gen_parts(
+    attrs: &StructAttrs,
     field_infos: &[FieldInfo],
-    name: &Ident,
+    access: impl Fn(&Ident) -> proc_macro2::TokenStream,
 ) -> proc_macro2::TokenStream {
-    let head_part = if let Some(head) = &struct_attrs.head {
-        quote! { concat!(#head, ":") }
+    let head_part = if let Some(head) = &attrs.head {
+        // 无主参数的命令 (如 `pixiInit;`) 不带冒号
+        if attrs.main.is_none() && !attrs.custom {
+            quote! { String::from(#head) }
+        } else {
+            quote! { String::from(concat!(#head, ":")) }
+        }
     } else {
         quote! { self.get_head() }
     };
 
-    let main_part = gen_main_part(struct_attrs, field_infos, name);
-    let arg_parts = gen_arg_parts(field_infos);
+    let main_part = gen_main_part(attrs, field_infos, &access);
+    let arg_parts = gen_arg_parts(field_infos, &access);
 
     quote! {
-        impl std::fmt::Display for #name {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                let head = #head_part;
-                let main = #main_part;
+        {
+            let head = #head_part;
+            let main = #main_part;
 
-                let mut args = Vec::new();
+            let mut args: Vec<String> = Vec::new();
+            {
+                let args = &mut args;
                 #(#arg_parts)*
+            }
 
-                if let Some(other_args) = self.get_other_args() {
-                    for (key, value) in other_args {
-                        match value {
-                            Some(val) => args.push(format!("-{}={}", key, val)),
-                            None => args.push(format!("-{}", key)),
-                        }
-                    }
-                }
+            (head, main, args)
+        }
+    }
+}
+
+fn gen_display_impl(
+    name: &Ident,
+    generics: &Generics,
+    parts: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics std::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let (head, main, mut args) = #parts;
+                webgal_derive::push_other_args(&mut args, self.get_other_args());
 
                 let s = if args.is_empty() {
                     format!("{}{}", head, main)
@@ -256,46 +637,71 @@ fn gen_display_impl(
     }
 }
 
+/// 生成以 `webgal_derive::ActionForm` 表示的 Serialize
+fn gen_serialize_impl(
+    name: &Ident,
+    generics: &Generics,
+    parts: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics webgal_derive::serde::Serialize for #name #ty_generics #where_clause {
+            fn serialize<__S>(&self, serializer: __S) -> Result<__S::Ok, __S::Error>
+            where
+                __S: webgal_derive::serde::Serializer,
+            {
+                let (head, main, mut args) = #parts;
+                webgal_derive::push_other_args(&mut args, self.get_other_args());
+                webgal_derive::serde::Serialize::serialize(
+                    &webgal_derive::ActionForm::new(head, main, args),
+                    serializer,
+                )
+            }
+        }
+    }
+}
+
 fn gen_main_part(
     struct_attrs: &StructAttrs,
     field_infos: &[FieldInfo],
-    name: &Ident,
+    access: &impl Fn(&Ident) -> proc_macro2::TokenStream,
 ) -> proc_macro2::TokenStream {
     let Some(main_type) = &struct_attrs.main else {
         return quote! { self.get_main() };
     };
 
-    let main_field = field_infos.iter().find(|info| info.main);
-    let Some(main_field) = main_field else {
-        panic!(
-            "Struct {name} sets main = \"{main_type}\" but has no field marked with #[action(main)]"
-        );
-    };
+    let main_field = (field_infos.iter())
+        .find(|info| info.main)
+        .expect("main field is validated");
 
-    let field_ident = &main_field.ident;
+    let field = access(&main_field.ident);
     let is_option = is_option_type(&main_field.ty);
+    let sep = struct_attrs.sep.as_deref().unwrap_or(DEFAULT_SEP);
     let none_flag = main_field.none;
+    let v_str = gen_format(main_field, quote! { v });
+    let item_str = gen_format(main_field, quote! { item });
+    let field_str = gen_format(main_field, field.clone());
 
     match main_type.as_str() {
         "single" => {
             if is_option {
                 if none_flag {
                     quote! {
-                        match &self.#field_ident {
-                            Some(v) => format!("{}", v),
+                        match &#field {
+                            Some(v) => #v_str,
                             None => String::from("none"),
                         }
                     }
                 } else {
                     quote! {
-                        match &self.#field_ident {
-                            Some(v) => format!("{}", v),
+                        match &#field {
+                            Some(v) => #v_str,
                             None => String::new(),
                         }
                     }
                 }
             } else {
-                quote! { format!("{}", self.#field_ident) }
+                quote! { #field_str }
             }
         }
         "list" => {
@@ -303,48 +709,59 @@ fn gen_main_part(
                 if none_flag {
                     quote! {
                         {
-                            let items: Vec<String> = self.#field_ident
+                            let items: Vec<String> = #field
                                 .as_ref()
-                                .map(|arr| arr.iter().map(|item| format!("{}", item)).collect())
+                                .map(|arr| arr.iter().map(|item| #item_str).collect())
                                 .unwrap_or_default();
                             if items.is_empty() {
                                 String::from("none")
                             } else {
-                                items.join("|")
+                                items.join(#sep)
                             }
                         }
                     }
                 } else {
                     quote! {
                         {
-                            let items: Vec<String> = self.#field_ident
+                            let items: Vec<String> = #field
                                 .as_ref()
-                                .map(|arr| arr.iter().map(|item| format!("{}", item)).collect())
+                                .map(|arr| arr.iter().map(|item| #item_str).collect())
                                 .unwrap_or_default();
-                            items.join("|")
+                            items.join(#sep)
                         }
                     }
                 }
             } else {
                 quote! {
                     {
-                        let items: Vec<String> = self.#field_ident
+                        let items: Vec<String> = #field
                             .iter()
-                            .map(|item| format!("{}", item))
+                            .map(|item| #item_str)
                             .collect();
-                        items.join("|")
+                        items.join(#sep)
                     }
                 }
             }
         }
-        _ => panic!("Invalid main type: {main_type}"),
+        _ => unreachable!("validated in parse_struct_attrs"),
     }
 }
 
-fn gen_arg_parts(field_infos: &[FieldInfo]) -> Vec<proc_macro2::TokenStream> {
+fn gen_arg_parts(
+    field_infos: &[FieldInfo],
+    access: &impl Fn(&Ident) -> proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
     let mut parts = Vec::new();
 
     for info in field_infos {
+        if info.flatten {
+            let field = access(&info.ident);
+            parts.push(quote! {
+                webgal_derive::ActionArgs::write_args(&#field, args);
+            });
+            continue;
+        }
+
         let Some(arg_type) = &info.arg else {
             continue;
         };
@@ -354,10 +771,21 @@ fn gen_arg_parts(field_infos: &[FieldInfo]) -> Vec<proc_macro2::TokenStream> {
         let field_name = info.rename.as_deref().unwrap_or(&field_ident_string);
         let is_option = is_option_type(&info.ty);
 
-        let part = if info.nullable || is_option {
-            gen_nullable_arg(arg_type, info, field_ident, field_name)
+        let field = access(field_ident);
+        let part = if is_vec_type(&info.ty) {
+            gen_repeated_arg(arg_type, info, &field, field_name)
+        } else if info.nullable || is_option {
+            gen_nullable_arg(arg_type, info, &field, field_name)
         } else {
-            gen_non_nullable_arg(arg_type, info, field_ident, field_name)
+            gen_non_nullable_arg(arg_type, info, &field, field_name)
+        };
+        let part = match &info.when {
+            Some(when) => quote! {
+                if #when {
+                    #part
+                }
+            },
+            None => part,
         };
 
         parts.push(part);
@@ -366,21 +794,52 @@ fn gen_arg_parts(field_infos: &[FieldInfo]) -> Vec<proc_macro2::TokenStream> {
     parts
 }
 
+/// Vec 字段, 每个元素一个参数, 非空时输出一次关联开关
+fn gen_repeated_arg(
+    arg_type: &str,
+    info: &FieldInfo,
+    field: &proc_macro2::TokenStream,
+    field_name: &str,
+) -> proc_macro2::TokenStream {
+    let value_str = gen_format(info, quote! { value });
+    let tie = match &info.tie {
+        Some(tn) => quote! {
+            if !#field.is_empty() {
+                args.push(format!("-{}", #tn));
+            }
+        },
+        None => quote! {},
+    };
+    let push = match arg_type {
+        "pair" => quote! { args.push(format!("-{}={}", #field_name, #value_str)); },
+        "value" => quote! { args.push(format!("-{}", #value_str)); },
+        _ => unreachable!("validated in parse_field_attrs"),
+    };
+
+    quote! {
+        #tie
+        for value in #field.iter() {
+            #push
+        }
+    }
+}
+
 fn gen_nullable_arg(
     arg_type: &str,
     info: &FieldInfo,
-    field_ident: &Ident,
+    field: &proc_macro2::TokenStream,
     field_name: &str,
 ) -> proc_macro2::TokenStream {
     let tie_name = &info.tie;
     let none_flag = info.none;
+    let value_str = gen_format(info, quote! { value });
 
     match arg_type {
         "tag" => {
             if none_flag {
                 match tie_name {
                     Some(tn) => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             if *value {
                                 args.push(format!("-{}", #tn));
                                 args.push(format!("-{}", #field_name));
@@ -391,7 +850,7 @@ fn gen_nullable_arg(
                         }
                     },
                     None => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             if *value {
                                 args.push(format!("-{}", #field_name));
                             }
@@ -403,7 +862,7 @@ fn gen_nullable_arg(
             } else {
                 match tie_name {
                     Some(tn) => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             if *value {
                                 args.push(format!("-{}", #tn));
                                 args.push(format!("-{}", #field_name));
@@ -411,7 +870,7 @@ fn gen_nullable_arg(
                         }
                     },
                     None => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             if *value {
                                 args.push(format!("-{}", #field_name));
                             }
@@ -424,17 +883,17 @@ fn gen_nullable_arg(
             if none_flag {
                 match tie_name {
                     Some(tn) => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             args.push(format!("-{}", #tn));
-                            args.push(format!("-{}={}", #field_name, format!("{}", value)));
+                            args.push(format!("-{}={}", #field_name, #value_str));
                         } else {
                             args.push(format!("-{}", #tn));
                             args.push(format!("-{}=none", #field_name));
                         }
                     },
                     None => quote! {
-                        if let Some(value) = &self.#field_ident {
-                            args.push(format!("-{}={}", #field_name, format!("{}", value)));
+                        if let Some(value) = &#field {
+                            args.push(format!("-{}={}", #field_name, #value_str));
                         } else {
                             args.push(format!("-{}=none", #field_name));
                         }
@@ -443,14 +902,14 @@ fn gen_nullable_arg(
             } else {
                 match tie_name {
                     Some(tn) => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             args.push(format!("-{}", #tn));
-                            args.push(format!("-{}={}", #field_name, format!("{}", value)));
+                            args.push(format!("-{}={}", #field_name, #value_str));
                         }
                     },
                     None => quote! {
-                        if let Some(value) = &self.#field_ident {
-                            args.push(format!("-{}={}", #field_name, format!("{}", value)));
+                        if let Some(value) = &#field {
+                            args.push(format!("-{}={}", #field_name, #value_str));
                         }
                     },
                 }
@@ -460,17 +919,17 @@ fn gen_nullable_arg(
             if none_flag {
                 match tie_name {
                     Some(tn) => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             args.push(format!("-{}", #tn));
-                            args.push(format!("-{}", format!("{}", value)));
+                            args.push(format!("-{}", #value_str));
                         } else {
                             args.push(format!("-{}", #tn));
                             args.push(format!("-none"));
                         }
                     },
                     None => quote! {
-                        if let Some(value) = &self.#field_ident {
-                            args.push(format!("-{}", format!("{}", value)));
+                        if let Some(value) = &#field {
+                            args.push(format!("-{}", #value_str));
                         } else {
                             args.push(format!("-none"));
                         }
@@ -479,41 +938,42 @@ fn gen_nullable_arg(
             } else {
                 match tie_name {
                     Some(tn) => quote! {
-                        if let Some(value) = &self.#field_ident {
+                        if let Some(value) = &#field {
                             args.push(format!("-{}", #tn));
-                            args.push(format!("-{}", format!("{}", value)));
+                            args.push(format!("-{}", #value_str));
                         }
                     },
                     None => quote! {
-                        if let Some(value) = &self.#field_ident {
-                            args.push(format!("-{}", format!("{}", value)));
+                        if let Some(value) = &#field {
+                            args.push(format!("-{}", #value_str));
                         }
                     },
                 }
             }
         }
-        _ => panic!("Invalid arg type: {arg_type}"),
+        _ => unreachable!("validated in parse_field_attrs"),
     }
 }
 
 fn gen_non_nullable_arg(
     arg_type: &str,
     info: &FieldInfo,
-    field_ident: &Ident,
+    field: &proc_macro2::TokenStream,
     field_name: &str,
 ) -> proc_macro2::TokenStream {
     let tie_name = &info.tie;
+    let field_str = gen_format(info, field.clone());
 
     match arg_type {
         "tag" => match tie_name {
             Some(tn) => quote! {
-                if self.#field_ident {
+                if #field {
                     args.push(format!("-{}", #tn));
                     args.push(format!("-{}", #field_name));
                 }
             },
             None => quote! {
-                if self.#field_ident {
+                if #field {
                     args.push(format!("-{}", #field_name));
                 }
             },
@@ -521,22 +981,352 @@ fn gen_non_nullable_arg(
         "pair" => match tie_name {
             Some(tn) => quote! {
                 args.push(format!("-{}", #tn));
-                args.push(format!("-{}={}", #field_name, format!("{}", self.#field_ident)));
+                args.push(format!("-{}={}", #field_name, #field_str));
             },
             None => quote! {
-                args.push(format!("-{}={}", #field_name, format!("{}", self.#field_ident)));
+                args.push(format!("-{}={}", #field_name, #field_str));
             },
         },
         "value" => match tie_name {
             Some(tn) => quote! {
                 args.push(format!("-{}", #tn));
-                args.push(format!("-{}", format!("{}", self.#field_ident)));
+                args.push(format!("-{}", #field_str));
             },
             None => quote! {
-                args.push(format!("-{}", format!("{}", self.#field_ident)));
+                args.push(format!("-{}", #field_str));
             },
         },
-        _ => panic!("无效的 arg 类型: {arg_type}"),
+        _ => unreachable!("validated in parse_field_attrs"),
+    }
+}
+
+fn gen_fromstr_impl(
+    name: &Ident,
+    generics: &Generics,
+    arms: &[proc_macro2::TokenStream],
+) -> proc_macro2::TokenStream {
+    let Some(generics) = parse_generics(generics) else {
+        return quote! {};
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics std::str::FromStr for #name #ty_generics #where_clause {
+            type Err = webgal_derive::ParseError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut line = webgal_derive::split_line(s)?;
+                let head = line.head;
+                let __line_main = line.main;
+                let __line_args = &mut line.args;
+
+                let value = match head {
+                    #(#arms)*
+                    head => return Err(webgal_derive::ParseError::Head(head.to_string())),
+                };
+
+                __line_args.finish()?;
+                Ok(value)
+            }
+        }
+    }
+}
+
+/// 生成按 head 匹配的解析分支, 没有静态 head 时返回 None
+fn gen_parse_arm(
+    attrs: &StructAttrs,
+    field_infos: &[FieldInfo],
+    path: proc_macro2::TokenStream,
+) -> Option<proc_macro2::TokenStream> {
+    let head = attrs.head.as_ref()?;
+    let fields = gen_parse_fields(Some(attrs), field_infos, path);
+
+    Some(quote! {
+        #head => { #fields }
+    })
+}
+
+/// 生成从 `__line_main` 和 `__line_args` 构造指令的代码
+///
+/// 先读取 main 和具名参数, 再按顺序读取值参数. `attrs` 为空时不读取 main.
+fn gen_parse_fields(
+    attrs: Option<&StructAttrs>,
+    field_infos: &[FieldInfo],
+    path: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let main_field = field_infos.iter().position(|info| info.main);
+
+    let mut named = Vec::new();
+    let mut values = Vec::new();
+    for (index, info) in field_infos.iter().enumerate() {
+        let binding = format_ident!("__{}", info.ident);
+        let main = attrs.and_then(|attrs| Some((attrs.main.as_deref()?, attrs)));
+        let value = match main {
+            Some((main_type, attrs)) if main_field == Some(index) => {
+                let sep = attrs.sep.as_deref().unwrap_or(DEFAULT_SEP);
+                gen_parse_main(main_type, sep, info)
+            }
+            _ => gen_parse_arg(info),
+        };
+
+        let stmt = quote! { let #binding = #value; };
+        match info.arg.as_deref() {
+            Some("value") => values.push(stmt),
+            _ => named.push(stmt),
+        }
+    }
+
+    let fields = field_infos.iter().map(|info| {
+        let field = &info.ident;
+        let binding = format_ident!("__{}", field);
+        quote! { #field: #binding }
+    });
+
+    quote! {
+        #(#named)*
+        #(#values)*
+        #path { #(#fields),* }
+    }
+}
+
+/// 生成参数组结构体的 ActionArgs, 有 main 字段时不生成
+fn gen_action_args_impl(
+    name: &Ident,
+    generics: &Generics,
+    field_infos: &[FieldInfo],
+) -> proc_macro2::TokenStream {
+    if field_infos.iter().any(|info| info.main) {
+        return quote! {};
+    }
+    let Some(generics) = parse_generics(generics) else {
+        return quote! {};
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let arg_parts = gen_arg_parts(field_infos, &|ident| quote! { self.#ident });
+    let fields = gen_parse_fields(None, field_infos, quote! { Self });
+
+    quote! {
+        impl #impl_generics webgal_derive::ActionArgs for #name #ty_generics #where_clause {
+            fn write_args(&self, args: &mut Vec<String>) {
+                #(#arg_parts)*
+            }
+
+            fn read_args(
+                __line_args: &mut webgal_derive::Args<'_>,
+            ) -> Result<Self, webgal_derive::ParseError> {
+                Ok({ #fields })
+            }
+        }
+    }
+}
+
+fn gen_parse_main(main_type: &str, sep: &str, info: &FieldInfo) -> proc_macro2::TokenStream {
+    let parse = gen_parse_fn(info);
+    let field_name = info.ident.to_string();
+    let none_arm = if info.none {
+        quote! { "none" => None, }
+    } else {
+        quote! {}
+    };
+
+    let value = match main_type {
+        "single" => quote! { #parse(#field_name, main)? },
+        "list" => quote! {
+            main.split(#sep)
+                .map(|item| #parse(#field_name, item))
+                .collect::<Result<_, _>>()?
+        },
+        _ => unreachable!("validated in parse_struct_attrs"),
+    };
+
+    if is_option_type(&info.ty) {
+        quote! {
+            match __line_main {
+                "" => None,
+                #none_arm
+                main => Some(#value),
+            }
+        }
+    } else if main_type == "list" {
+        quote! {
+            match __line_main {
+                "" => Default::default(),
+                main => #value,
+            }
+        }
+    } else {
+        quote! {
+            {
+                let main = __line_main;
+                #value
+            }
+        }
+    }
+}
+
+fn gen_parse_arg(info: &FieldInfo) -> proc_macro2::TokenStream {
+    if info.flatten {
+        let ty = &info.ty;
+        return quote! {
+            <#ty as webgal_derive::ActionArgs>::read_args(__line_args)?
+        };
+    }
+
+    let Some(arg_type) = &info.arg else {
+        return quote! { Default::default() };
+    };
+    if is_vec_type(&info.ty) {
+        return gen_parse_repeated_arg(arg_type, info);
+    }
+
+    let field_ident_string = info.ident.to_string();
+    let field_name = info.rename.as_deref().unwrap_or(&field_ident_string);
+    let is_option = info.nullable || is_option_type(&info.ty);
+    let parse = gen_parse_fn(info);
+
+    // 读取参数, 存在时一并读取关联开关
+    let take = match arg_type.as_str() {
+        "value" => quote! { __line_args.take_value() },
+        _ => quote! { __line_args.take(#field_name) },
+    };
+    let take = match &info.tie {
+        Some(tn) => quote! {
+            {
+                let value = #take;
+                if value.is_some() {
+                    __line_args.take(#tn);
+                }
+                value
+            }
+        },
+        None => take,
+    };
+    let missing_value = quote! {
+        return Err(webgal_derive::ParseError::MissingValue(#field_name))
+    };
+    let missing_arg = match info.when {
+        Some(_) => quote! { Default::default() },
+        None => quote! {
+            return Err(webgal_derive::ParseError::MissingArg(#field_name))
+        },
+    };
+
+    match (arg_type.as_str(), is_option) {
+        ("tag", true) if info.none => quote! {
+            match #take {
+                Some(Some(_)) => None,
+                Some(None) => Some(true),
+                None => Some(false),
+            }
+        },
+        ("tag", true) => quote! { #take.map(|_| true) },
+        ("tag", false) => quote! { #take.is_some() },
+        ("pair", true) => {
+            let none_arm = match info.none {
+                true => quote! { Some(Some("none")) => None, },
+                false => quote! {},
+            };
+            quote! {
+                match #take {
+                    #none_arm
+                    Some(Some(value)) => Some(#parse(#field_name, value)?),
+                    Some(None) => #missing_value,
+                    None => None,
+                }
+            }
+        }
+        ("pair", false) => quote! {
+            match #take {
+                Some(Some(value)) => #parse(#field_name, value)?,
+                Some(None) => #missing_value,
+                None => #missing_arg,
+            }
+        },
+        ("value", true) => {
+            let none_arm = match info.none {
+                true => quote! { Some("none") => None, },
+                false => quote! {},
+            };
+            quote! {
+                match #take {
+                    #none_arm
+                    Some(value) => Some(#parse(#field_name, value)?),
+                    None => None,
+                }
+            }
+        }
+        ("value", false) => quote! {
+            match #take {
+                Some(value) => #parse(#field_name, value)?,
+                None => #missing_arg,
+            }
+        },
+        _ => unreachable!("validated in parse_field_attrs"),
+    }
+}
+
+/// 读取 Vec 字段的全部同名参数, 或剩余的全部值参数
+fn gen_parse_repeated_arg(arg_type: &str, info: &FieldInfo) -> proc_macro2::TokenStream {
+    let parse = gen_parse_fn(info);
+    let field_ident_string = info.ident.to_string();
+    let field_name = info.rename.as_deref().unwrap_or(&field_ident_string);
+
+    let take = match arg_type {
+        "pair" => quote! {
+            while let Some(value) = __line_args.take(#field_name) {
+                let Some(value) = value else {
+                    return Err(webgal_derive::ParseError::MissingValue(#field_name));
+                };
+                values.push(#parse(#field_name, value)?);
+            }
+        },
+        _ => quote! {
+            while let Some(value) = __line_args.take_value() {
+                values.push(#parse(#field_name, value)?);
+            }
+        },
+    };
+    let tie = match &info.tie {
+        Some(tn) => quote! {
+            if !values.is_empty() {
+                __line_args.take(#tn);
+            }
+        },
+        None => quote! {},
+    };
+
+    quote! {
+        {
+            let mut values = Vec::new();
+            #take
+            #tie
+            values
+        }
+    }
+}
+
+/// 格式化字段值, 非 raw 时转义分隔字符
+fn gen_format(info: &FieldInfo, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if let Some((on, off)) = &info.bool_text {
+        return quote! { webgal_derive::BoolText(#on, #off).render(#value) };
+    }
+
+    let format = info.format.as_deref().unwrap_or("{}");
+    match (info.raw, &info.keep) {
+        (true, _) => quote! { format!(#format, #value) },
+        (false, Some(keep)) => {
+            quote! { webgal_derive::escape_keep(&format!(#format, #value), #keep) }
+        }
+        (false, None) => quote! { webgal_derive::escape(&format!(#format, #value)) },
+    }
+}
+
+/// 字段值的解析函数, 以 `(字段名, 文本)` 调用
+fn gen_parse_fn(info: &FieldInfo) -> proc_macro2::TokenStream {
+    match &info.bool_text {
+        Some((on, off)) => quote! { webgal_derive::BoolText(#on, #off).parse },
+        None => quote! { webgal_derive::parse_value },
     }
 }
 
@@ -552,3 +1342,16 @@ fn is_option_type(ty: &syn::Type) -> bool {
         .map(|segment| segment.ident == "Option")
         .unwrap_or(false)
 }
+
+fn is_vec_type(ty: &syn::Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+
+    type_path
+        .path
+        .segments
+        .last()
+        .map(|segment| segment.ident == "Vec")
+        .unwrap_or(false)
+}
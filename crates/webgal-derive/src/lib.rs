@@ -1,12 +1,118 @@
 //! WebGAL 脚本序列化
-//! 
-//! 使用 #[derive(webgal_derive::Actionable)] 为结构体添加序列化功能.
+//!
+//! 使用 #[derive(webgal_derive::Actionable)] 为结构体或枚举添加序列化功能, 以及对应的 FromStr 解析.
+//!
+//! 解析是有损的: 序列化时转义的分隔字符 (见 [`escape`]) 解析后仍为全角字符, 不会还原;
+//! `format` 指定的精度和 `get_other_args` 追加的参数同样无法还原.
+//! 因此 `parse` 后再序列化得到相同的命令行, 但字段值不一定与原值相同.
+//!
+//! 派生要求作用域内有 `ActionCustom` 和 `Action(Box<dyn Actionable + Send + Sync>)`:
+//!
+//! ```
+//! use webgal_derive::{ActionCustom, Actionable};
+//!
+//! struct Action(Box<dyn Actionable + Send + Sync + 'static>);
+//!
+//! #[derive(Actionable)]
+//! #[action(head = "say", main = "single")]
+//! struct Say {
+//!     #[action(main)]
+//!     text: String,
+//!     #[action(arg = "pair")]
+//!     speaker: Option<String>,
+//! }
+//!
+//! let say = Say { text: String::from("hi"), speaker: Some(String::from("me")) };
+//! assert_eq!(say.to_string(), "say:hi -speaker=me;");
+//! ```
+//!
+//! 属性在编译期校验, 以下写法均无法通过编译.
+//!
+//! 重复的主参数字段:
+//!
+//! ```compile_fail
+//! # use webgal_derive::{ActionCustom, Actionable};
+//! # struct Action(Box<dyn Actionable + Send + Sync + 'static>);
+//! #[derive(Actionable)]
+//! #[action(head = "say", main = "single")]
+//! struct Say {
+//!     #[action(main)]
+//!     text: String,
+//!     #[action(main)]
+//!     other: String,
+//! }
+//! ```
+//!
+//! 未知的属性键:
+//!
+//! ```compile_fail
+//! # use webgal_derive::{ActionCustom, Actionable};
+//! # struct Action(Box<dyn Actionable + Send + Sync + 'static>);
+//! #[derive(Actionable)]
+//! #[action(head = "say", main = "single")]
+//! struct Say {
+//!     #[action(main, argument = "text")]
+//!     text: String,
+//! }
+//! ```
+//!
+//! 互相冲突的组合, 如 `sep` 只能与 `main = "list"` 一同使用:
+//!
+//! ```compile_fail
+//! # use webgal_derive::{ActionCustom, Actionable};
+//! # struct Action(Box<dyn Actionable + Send + Sync + 'static>);
+//! #[derive(Actionable)]
+//! #[action(head = "say", main = "single", sep = ",")]
+//! struct Say {
+//!     #[action(main)]
+//!     text: String,
+//! }
+//! ```
 
-use std::fmt::Display;
+use std::{
+    borrow::Borrow,
+    fmt::{self, Display},
+    str::FromStr,
+};
 
 // 重新导出派生宏
 pub use webgal_derive_macro::Actionable;
 
+// 供 #[action(serde)] 生成的代码使用
+#[cfg(feature = "serde")]
+pub use serde;
+
+/// 分隔字符及其替换, 依次为命令结束, 命令名分隔, 列表项分隔
+const SEPARATORS: &[(char, char)] = &[(';', '；'), (':', '：'), ('|', '｜')];
+
+/// 将文本中的 WebGAL 分隔字符替换为全角字符
+///
+/// `;`, `:`, `|` 直接替换; `-` 仅在空白之后 (会被视为参数) 时替换.
+/// 自定义的 `get_head`, `get_main` 应对其中的文本调用.
+pub fn escape(text: &str) -> String {
+    escape_keep(text, "")
+}
+
+/// 同 [`escape`], 但保留 `keep` 中的分隔字符
+///
+/// 如对话文本中的 `|` 为换行, 且 WebGAL 只以首个 `:` 分隔命令名, 二者均可保留.
+pub fn escape_keep(text: &str, keep: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut after_space = false;
+
+    for c in text.chars() {
+        match SEPARATORS.iter().find(|(sep, _)| *sep == c) {
+            _ if keep.contains(c) => out.push(c),
+            Some((_, sub)) => out.push(*sub),
+            None if c == '-' && after_space => out.push('－'),
+            None => out.push(c),
+        }
+        after_space = c.is_whitespace();
+    }
+
+    out
+}
+
 /// WebGAL 命令标记特型
 pub trait Actionable: Display {}
 
@@ -24,3 +130,241 @@ pub trait ActionCustom {
         None
     }
 }
+
+/// 追加 `get_other_args` 返回的参数
+pub fn push_other_args(args: &mut Vec<String>, other_args: Option<Vec<(String, Option<String>)>>) {
+    for (key, value) in other_args.into_iter().flatten() {
+        match value {
+            Some(value) => args.push(format!("-{key}={value}")),
+            None => args.push(format!("-{key}")),
+        }
+    }
+}
+
+/// 指令参数组, 由派生宏为结构体生成, 以 `#[action(flatten)]` 合并到其他指令
+pub trait ActionArgs {
+    /// 追加参数, 如 `-duration=500`
+    fn write_args(&self, args: &mut Vec<String>);
+
+    /// 读取参数
+    fn read_args(args: &mut Args<'_>) -> Result<Self, ParseError>
+    where
+        Self: Sized;
+}
+
+/// 命令解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// 缺少结尾的 `;`
+    Unterminated,
+    /// 命令中间出现 `;`
+    StraySemicolon,
+    /// 命令名后缺少 `:`
+    MissingColon(String),
+    /// 参数名为空或包含字母数字以外的字符
+    MalformedArg(String),
+    /// 命令名不匹配
+    Head(String),
+    /// 缺少必需的参数
+    MissingArg(&'static str),
+    /// 参数缺少值
+    MissingValue(&'static str),
+    /// 无法识别的参数
+    UnknownArg(String),
+    /// 值无法解析
+    InvalidValue {
+        field: &'static str,
+        value: String,
+        message: String,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unterminated => write!(f, "missing terminating ';'"),
+            Self::StraySemicolon => write!(f, "unexpected ';' inside command"),
+            Self::MissingColon(command) => write!(f, "missing ':' after command {command}"),
+            Self::MalformedArg(arg) => write!(f, "malformed argument: -{arg}"),
+            Self::Head(head) => write!(f, "unexpected command: {head}"),
+            Self::MissingArg(name) => write!(f, "missing argument: -{name}"),
+            Self::MissingValue(name) => write!(f, "missing value for argument: -{name}"),
+            Self::UnknownArg(arg) => write!(f, "unknown argument: -{arg}"),
+            Self::InvalidValue {
+                field,
+                value,
+                message,
+            } => write!(f, "invalid value for {field}: {value} ({message})"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 切分后的命令行
+#[derive(Debug, Clone)]
+pub struct Line<'a> {
+    pub head: &'a str,
+    pub main: &'a str,
+    pub args: Args<'a>,
+}
+
+/// 切分命令行: `head:main -key=value -tag;`, 没有 `:` 时 main 为空
+///
+/// 派生的 FromStr 与场景校验共用此函数.
+pub fn split_line(line: &str) -> Result<Line<'_>, ParseError> {
+    let body = line
+        .trim()
+        .strip_suffix(';')
+        .ok_or(ParseError::Unterminated)?;
+    if body.contains(';') {
+        return Err(ParseError::StraySemicolon);
+    }
+
+    let mut parts = body.split(" -");
+    let command = parts.next().unwrap_or_default();
+    let (head, main) = command.split_once(':').unwrap_or((command, ""));
+
+    let args = parts
+        .map(|arg| {
+            let (key, value) = match arg.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (arg, None),
+            };
+
+            match !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric()) {
+                true => Ok((key, value)),
+                false => Err(ParseError::MalformedArg(arg.to_string())),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Line {
+        head,
+        main,
+        args: Args {
+            args,
+            used: Vec::new(),
+        },
+    })
+}
+
+/// 命令参数, 记录已读取的参数以发现多余参数
+#[derive(Debug, Clone)]
+pub struct Args<'a> {
+    args: Vec<(&'a str, Option<&'a str>)>,
+    used: Vec<usize>,
+}
+
+impl<'a> Args<'a> {
+    /// 读取首个未读取的同名参数, 返回其值
+    pub fn take(&mut self, name: &str) -> Option<Option<&'a str>> {
+        let index =
+            (0..self.args.len()).find(|i| !self.used.contains(i) && self.args[*i].0 == name)?;
+        self.used.push(index);
+        Some(self.args[index].1)
+    }
+
+    /// 读取首个未读取的无值参数作为值 (`-value`)
+    pub fn take_value(&mut self) -> Option<&'a str> {
+        let index =
+            (0..self.args.len()).find(|i| !self.used.contains(i) && self.args[*i].1.is_none())?;
+        self.used.push(index);
+        Some(self.args[index].0)
+    }
+
+    /// 全部参数, 含已读取的参数
+    pub fn all(&self) -> impl Iterator<Item = (&'a str, Option<&'a str>)> + '_ {
+        self.args.iter().copied()
+    }
+
+    /// 确认全部参数均已读取
+    pub fn finish(&self) -> Result<(), ParseError> {
+        match (0..self.args.len()).find(|i| !self.used.contains(i)) {
+            Some(index) => Err(ParseError::UnknownArg(self.args[index].0.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// bool 值的文本表示, 依次为 true, false
+#[derive(Debug, Clone, Copy)]
+pub struct BoolText(pub &'static str, pub &'static str);
+
+impl BoolText {
+    pub fn render(self, value: impl Borrow<bool>) -> String {
+        String::from(if *value.borrow() { self.0 } else { self.1 })
+    }
+
+    pub fn parse(self, field: &'static str, value: &str) -> Result<bool, ParseError> {
+        match value {
+            v if v == self.0 => Ok(true),
+            v if v == self.1 => Ok(false),
+            _ => Err(ParseError::InvalidValue {
+                field,
+                value: value.to_string(),
+                message: format!("expected {} or {}", self.0, self.1),
+            }),
+        }
+    }
+}
+
+/// 解析字段值
+pub fn parse_value<T>(field: &'static str, value: &str) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|e: T::Err| ParseError::InvalidValue {
+        field,
+        value: value.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// 指令的结构化形式, 由 `#[action(serde)]` 生成的 `Serialize` 输出
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ActionForm {
+    pub head: String,
+    pub main: String,
+    pub args: Vec<FormArg>,
+}
+
+/// 结构化参数, `-value` 形式的参数没有值
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FormArg {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl ActionForm {
+    /// 以渲染命令行所用的 head, main 和参数构造, head 不含结尾的 `:`
+    pub fn new(head: String, main: String, args: Vec<String>) -> Self {
+        let head = match head.strip_suffix(':') {
+            Some(head) => head.to_string(),
+            None => head,
+        };
+        let args = args
+            .iter()
+            .map(|arg| {
+                let arg = arg.strip_prefix('-').unwrap_or(arg);
+                match arg.split_once('=') {
+                    Some((key, value)) => FormArg {
+                        key: key.to_string(),
+                        value: Some(value.to_string()),
+                    },
+                    None => FormArg {
+                        key: arg.to_string(),
+                        value: None,
+                    },
+                }
+            })
+            .collect();
+
+        Self { head, main, args }
+    }
+}